@@ -0,0 +1,74 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use schroedinger_approx::airy_wave_func::AiryWaveFunction;
+use schroedinger_approx::wkb_wave_func::Phase;
+use schroedinger_approx::*;
+use std::sync::Arc;
+
+fn airy_parts(c: &mut Criterion) {
+    let potential = &potentials::square;
+    let mass = 1.0;
+    for n in 1..=9 {
+        c.bench_function(&format!("turning_point_square_nenergy_{}", n), |b| {
+            let energy = energy::nth_energy(n, mass, 1.0, potential, APPROX_INF);
+            let lower_bound = newtons_method::newtons_method(
+                &|x| potential(x) - energy,
+                APPROX_INF.0,
+                TURNING_POINT_TOLERANCE,
+            );
+            let upper_bound = newtons_method::newtons_method(
+                &|x| potential(x) - energy,
+                APPROX_INF.1,
+                TURNING_POINT_TOLERANCE,
+            );
+            let phase = Arc::new(Phase::new(energy, mass, UNITS.hbar, Arc::new(potential)));
+            let view = (
+                lower_bound - (upper_bound - lower_bound) * VIEW_FACTOR,
+                upper_bound + (upper_bound - lower_bound) * VIEW_FACTOR,
+            );
+            b.iter(|| AiryWaveFunction::new(phase.clone(), black_box(view)))
+        });
+    }
+}
+
+// stands in for a potential that is expensive to evaluate, e.g. one given by a numerical integral
+fn expensive_potential(x: f64) -> f64 {
+    (1..=200)
+        .map(|k| (x / k as f64).powi(2) / k as f64)
+        .sum::<f64>()
+        / 1.2
+}
+
+fn potential_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("expensive_potential");
+    group.sample_size(10);
+    group.bench_function("energy_uncached", |b| {
+        b.iter(|| energy::nth_energy(3, 1.0, 1.0, &expensive_potential, black_box(APPROX_INF)))
+    });
+    group.bench_function("energy_cached", |b| {
+        b.iter(|| {
+            let cached = potential_cache::cached(expensive_potential);
+            energy::nth_energy(3, 1.0, 1.0, &cached, black_box(APPROX_INF))
+        })
+    });
+    group.bench_function("turning_points_uncached", |b| {
+        b.iter(|| {
+            let phase = Phase::new(5.0, 1.0, UNITS.hbar, Arc::new(expensive_potential));
+            turning_points::calc_ts(&phase, black_box((-10.0, 10.0)))
+        })
+    });
+    group.bench_function("turning_points_cached", |b| {
+        b.iter(|| {
+            let phase = Phase {
+                energy: 5.0,
+                mass: 1.0,
+                hbar: UNITS.hbar,
+                potential: Arc::new(potential_cache::cached(expensive_potential)),
+            };
+            turning_points::calc_ts(&phase, black_box((-10.0, 10.0)))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, airy_parts, potential_cache);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use schroedinger_approx::integrals::evaluate_function_between;
+use schroedinger_approx::utils::{complex, Function};
+use schroedinger_approx::wave_function_builder::{renormalize_factor, ScalingType, WaveFunction};
+use schroedinger_approx::*;
+use std::f64;
+use std::sync::Arc;
+
+// the setup is inside the closures, so only the benchmarks matching the filter compute it
+fn evaluate(c: &mut Criterion) {
+    for n in 1..=9 {
+        c.bench_function(&format!("evaluate_nenergy_{}", n), |b| {
+            let wave_function = WaveFunction::new(
+                Arc::new(potentials::square),
+                1.0, // mass
+                n,   // nth energy
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::Renormalize(complex(0.0, f64::consts::PI / 4.0).exp()),
+            );
+            b.iter(|| evaluate_function_between(&wave_function, -10.0, black_box(10.0), 100))
+        });
+    }
+}
+
+// every iteration solves for the energy and builds all parts
+fn construct(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construct");
+    group.sample_size(10);
+    for n in 1..=9 {
+        group.bench_function(format!("nenergy_{}", n), |b| {
+            b.iter(|| {
+                WaveFunction::new(
+                    Arc::new(potentials::square),
+                    1.0,
+                    black_box(n),
+                    APPROX_INF,
+                    VIEW_FACTOR,
+                    ScalingType::None,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn renormalize(c: &mut Criterion) {
+    let square = Function::new(|x: f64| complex(x * x, 0.0));
+    c.bench_function("renormalize_square", |b| {
+        b.iter(|| renormalize_factor(&square, black_box((-10.0, 10.0))))
+    });
+}
+
+criterion_group!(benches, evaluate, construct, renormalize);
+criterion_main!(benches);
@@ -3,6 +3,23 @@ use std::path::PathBuf;
 use std::process::Command;
 
 fn main() {
+    // the Go library can't be linked into wasm, airy_series is used instead
+    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "wasm32" {
+        return;
+    }
+
+    // with any rerun-if-changed cargo only reruns the script for the listed files, the Go library
+    // has to be rebuilt when its sources change
+    for source in [
+        "build.rs",
+        "lib/build.sh",
+        "lib/main.go",
+        "lib/go.mod",
+        "lib/go.sum",
+    ] {
+        println!("cargo:rerun-if-changed={}", source);
+    }
+
     Command::new("sh")
         .arg("build.sh")
         .current_dir("./lib/")
@@ -35,4 +52,14 @@ fn main() {
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
+
+    // Header for the C interface in src/capi.rs, next to the bindings instead of in the source tree
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    cbindgen::Builder::new()
+        .with_src("src/capi.rs")
+        .with_language(cbindgen::Language::C)
+        .with_include_guard("SCHROEDINGER_APPROX_H")
+        .generate()
+        .expect("Unable to generate C header")
+        .write_to_file(out_path.join("schroedinger_approx.h"));
 }
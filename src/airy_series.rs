@@ -0,0 +1,480 @@
+use crate::*;
+
+// Pure Rust Airy functions, Ai for targets where the Go library can't be linked (e.g. wasm32), Bi
+// and the derivatives everywhere. Uses the Maclaurin series close to the origin and the asymptotic
+// expansions further out.
+
+const AI_0: f64 = 0.355_028_053_887_817_2;
+const AI_PRIME_0: f64 = -0.258_819_403_792_806_8;
+// beyond this |z| the asymptotic expansions are used
+pub const SERIES_RADIUS: f64 = 6.0;
+const MAX_TERMS: usize = 200;
+
+// Ai, Bi and their derivatives at one point
+#[derive(Clone, Copy, Debug)]
+pub struct AiryValues {
+    pub ai: Complex64,
+    pub ai_prime: Complex64,
+    pub bi: Complex64,
+    pub bi_prime: Complex64,
+}
+
+impl AiryValues {
+    fn scale(self, ai_factor: f64, bi_factor: f64) -> AiryValues {
+        AiryValues {
+            ai: self.ai * ai_factor,
+            ai_prime: self.ai_prime * ai_factor,
+            bi: self.bi * bi_factor,
+            bi_prime: self.bi_prime * bi_factor,
+        }
+    }
+}
+
+// zeta = 2/3 z^(3/2), the exponent of the asymptotic expansions
+fn zeta(z: Complex64) -> Complex64 {
+    2.0 / 3.0 * z.powf(1.5)
+}
+
+fn maclaurin(z: Complex64) -> AiryValues {
+    let z2 = z * z;
+    let z3 = z2 * z;
+    let mut f_term = complex(1.0, 0.0);
+    let mut g_term = z;
+    let (mut f, mut g) = (f_term, g_term);
+    let (mut f_prime, mut g_prime) = (complex(0.0, 0.0), complex(1.0, 0.0));
+
+    for k in 1..MAX_TERMS {
+        let k = k as f64;
+        // the terms of the derivatives follow from the previous terms of f and g
+        let f_prime_term = f_term * z2 / (3.0 * k - 1.0);
+        let g_prime_term = g_term * z2 / (3.0 * k);
+        f_term = f_term * z3 / ((3.0 * k - 1.0) * (3.0 * k));
+        g_term = g_term * z3 / ((3.0 * k) * (3.0 * k + 1.0));
+        f += f_term;
+        g += g_term;
+        f_prime += f_prime_term;
+        g_prime += g_prime_term;
+
+        if f_term.norm() <= f64::EPSILON * f.norm()
+            && g_term.norm() <= f64::EPSILON * g.norm()
+            && f_prime_term.norm() <= f64::EPSILON * f_prime.norm()
+            && g_prime_term.norm() <= f64::EPSILON * g_prime.norm()
+        {
+            break;
+        }
+    }
+
+    let sqrt_3 = 3.0f64.sqrt();
+    AiryValues {
+        ai: AI_0 * f + AI_PRIME_0 * g,
+        ai_prime: AI_0 * f_prime + AI_PRIME_0 * g_prime,
+        bi: sqrt_3 * (AI_0 * f - AI_PRIME_0 * g),
+        bi_prime: sqrt_3 * (AI_0 * f_prime - AI_PRIME_0 * g_prime),
+    }
+}
+
+// u_k coefficients of the asymptotic expansion, or v_k = -(6k + 1) / (6k - 1) u_k of the
+// derivatives, summed as sum_k sign(k) * u_k / zeta^k until the terms stop decreasing
+fn asymptotic_sum(
+    zeta: Complex64,
+    sign: fn(usize) -> f64,
+    parity: Option<usize>,
+    derivative: bool,
+) -> Complex64 {
+    let mut u = 1.0;
+    let mut zeta_pow = complex(1.0, 0.0);
+    let mut sum = complex(0.0, 0.0);
+    let mut last_norm = f64::INFINITY;
+
+    for k in 0..MAX_TERMS {
+        let kf = k as f64;
+        if k > 0 {
+            u *= (6.0 * kf - 5.0) * (6.0 * kf - 3.0) * (6.0 * kf - 1.0)
+                / ((2.0 * kf - 1.0) * 216.0 * kf);
+            zeta_pow *= zeta;
+        }
+
+        if parity.map(|p| k % 2 != p).unwrap_or(false) {
+            continue;
+        }
+
+        let coefficient = if derivative && k > 0 {
+            -(6.0 * kf + 1.0) / (6.0 * kf - 1.0) * u
+        } else {
+            u
+        };
+        let term = sign(k) * coefficient / zeta_pow;
+        if term.norm() > last_norm {
+            break;
+        }
+        last_norm = term.norm();
+        sum += term;
+
+        if term.norm() <= f64::EPSILON * sum.norm() {
+            break;
+        }
+    }
+
+    sum
+}
+
+fn alternating(k: usize) -> f64 {
+    if k.is_multiple_of(2) {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+// unscaled around the negative real axis, |arg(z)| > 2 pi / 3
+fn oscillating(z: Complex64) -> AiryValues {
+    let w = -z;
+    let xi = 2.0 / 3.0 * w.powf(1.5);
+    let signs = |k: usize| if (k / 2).is_multiple_of(2) { 1.0 } else { -1.0 };
+    let p = asymptotic_sum(xi, signs, Some(0), false);
+    let q = asymptotic_sum(xi, signs, Some(1), false);
+    let r = asymptotic_sum(xi, signs, Some(0), true);
+    let s = asymptotic_sum(xi, signs, Some(1), true);
+    let angle = xi - f64::consts::PI / 4.0;
+    let (sin, cos) = (angle.sin(), angle.cos());
+    let sqrt_pi = f64::consts::PI.sqrt();
+    let root = w.powf(0.25);
+
+    AiryValues {
+        ai: (cos * p + sin * q) / (sqrt_pi * root),
+        ai_prime: root / sqrt_pi * (sin * r - cos * s),
+        bi: (-sin * p + cos * q) / (sqrt_pi * root),
+        bi_prime: root / sqrt_pi * (cos * r + sin * s),
+    }
+}
+
+// Ai and Ai' times e^(Re zeta) where Ai decays, |arg(z)| <= 2 pi / 3, e^(-zeta) e^(Re zeta) is a
+// phase
+fn decaying_scaled(z: Complex64) -> (Complex64, Complex64) {
+    let zeta = zeta(z);
+    let phase = complex(0.0, -zeta.im).exp();
+    let root = z.powf(0.25);
+    let sqrt_pi = f64::consts::PI.sqrt();
+    (
+        phase / (2.0 * sqrt_pi * root) * asymptotic_sum(zeta, alternating, None, false),
+        -phase * root / (2.0 * sqrt_pi) * asymptotic_sum(zeta, alternating, None, true),
+    )
+}
+
+// unscaled Ai and Ai' for the connection formula of Bi
+fn ai_unscaled(z: Complex64) -> (Complex64, Complex64) {
+    if z.norm() < SERIES_RADIUS {
+        let values = maclaurin(z);
+        (values.ai, values.ai_prime)
+    } else if z.arg().abs() > 2.0 * f64::consts::PI / 3.0 {
+        let values = oscillating(z);
+        (values.ai, values.ai_prime)
+    } else {
+        let (ai, ai_prime) = decaying_scaled(z);
+        let factor = (-zeta(z).re).exp();
+        (ai * factor, ai_prime * factor)
+    }
+}
+
+// asymptotic expansions for |z| >= SERIES_RADIUS, scaled as in airy_scaled
+fn asymptotic_scaled(z: Complex64) -> AiryValues {
+    let zeta = zeta(z);
+    let bi_factor = (-zeta.re.abs()).exp();
+    if z.arg().abs() > 2.0 * f64::consts::PI / 3.0 {
+        return oscillating(z).scale(zeta.re.exp(), bi_factor);
+    }
+
+    let (ai, ai_prime) = decaying_scaled(z);
+    let (bi, bi_prime) = if z.arg().abs() < f64::consts::PI / 6.0 {
+        // Bi grows with e^zeta, the part decaying like Ai is below the rounding error
+        let phase = complex(0.0, zeta.im).exp();
+        let root = z.powf(0.25);
+        let sqrt_pi = f64::consts::PI.sqrt();
+        (
+            phase / (sqrt_pi * root) * asymptotic_sum(zeta, |_| 1.0, None, false),
+            phase * root / sqrt_pi * asymptotic_sum(zeta, |_| 1.0, None, true),
+        )
+    } else {
+        // Bi(z) = e^(i pi / 6) Ai(z e^(2 i pi / 3)) + e^(-i pi / 6) Ai(z e^(-2 i pi / 3))
+        let rotation = complex(0.0, 2.0 * f64::consts::PI / 3.0).exp();
+        let (plus, plus_prime) = ai_unscaled(z * rotation);
+        let (minus, minus_prime) = ai_unscaled(z * rotation.conj());
+        let e = complex(0.0, f64::consts::PI / 6.0).exp();
+        (
+            (e * plus + e.conj() * minus) * bi_factor,
+            (e * rotation * plus_prime + (e * rotation).conj() * minus_prime) * bi_factor,
+        )
+    };
+
+    AiryValues {
+        ai,
+        ai_prime,
+        bi,
+        bi_prime,
+    }
+}
+
+// Ai, Ai' times e^(Re zeta) and Bi, Bi' times e^(-|Re zeta|) with zeta = 2/3 z^(3/2), they stay
+// representable far into the forbidden region where Ai underflows and Bi overflows
+pub fn airy_scaled(z: Complex64) -> AiryValues {
+    if z.norm() < SERIES_RADIUS {
+        let zeta = zeta(z);
+        return maclaurin(z).scale(zeta.re.exp(), (-zeta.re.abs()).exp());
+    }
+    asymptotic_scaled(z)
+}
+
+pub fn airy(z: Complex64) -> AiryValues {
+    if z.norm() < SERIES_RADIUS {
+        return maclaurin(z);
+    }
+    let zeta = zeta(z);
+    asymptotic_scaled(z).scale((-zeta.re).exp(), zeta.re.abs().exp())
+}
+
+pub fn airy_ai(z: Complex64) -> Complex64 {
+    airy(z).ai
+}
+
+pub fn airy_bi(z: Complex64) -> Complex64 {
+    airy(z).bi
+}
+
+pub fn airy_ai_prime(z: Complex64) -> Complex64 {
+    airy(z).ai_prime
+}
+
+pub fn airy_bi_prime(z: Complex64) -> Complex64 {
+    airy(z).bi_prime
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // reference values from mpmath
+    const REFERENCE: [((f64, f64), (f64, f64)); 12] = [
+        ((0.0, 0.0), (0.355028053887817, 0.0)),
+        ((1.0, 0.0), (0.135292416312881, 0.0)),
+        ((-1.0, 0.0), (0.535560883292352, 0.0)),
+        ((5.0, 0.0), (1.08344428136074e-4, 0.0)),
+        ((-5.0, 0.0), (0.350761009024114, 0.0)),
+        ((7.5, 0.0), (1.91725606751343e-7, 0.0)),
+        ((-7.5, 0.0), (0.321775716380648, 0.0)),
+        ((10.0, 0.0), (1.10475325528987e-10, 0.0)),
+        ((-20.0, 0.0), (-0.176406127077985, 0.0)),
+        ((1.0, 1.0), (0.0604583083718381, -0.151889565877181)),
+        ((-4.0, 6.0), (50174.2665971029, 42686.1514113197)),
+        ((3.0, -7.0), (0.123527084435413, 2.02889833582226)),
+    ];
+
+    // x, Bi, Ai' and Bi' from mpmath
+    const REFERENCE_REAL: [(f64, f64, f64, f64); 11] = [
+        (
+            0.0,
+            0.6149266274460007,
+            -0.2588194037928068,
+            0.4482883573538264,
+        ),
+        (
+            1.0,
+            1.207423594952871,
+            -0.1591474412967932,
+            0.9324359333927756,
+        ),
+        (
+            -1.0,
+            0.1039973894969446,
+            -0.01016056711664521,
+            0.5923756264227924,
+        ),
+        (
+            2.5,
+            6.481660738460579,
+            -0.02625088103590323,
+            9.421423317334302,
+        ),
+        (
+            5.0,
+            657.7920441711712,
+            -2.474138908684625e-4,
+            1435.819080217983,
+        ),
+        (
+            -5.0,
+            -0.1383691349016006,
+            0.3271928185544431,
+            0.7784117730018992,
+        ),
+        (
+            7.5,
+            303229.6151125334,
+            -5.312713959720545e-7,
+            819987.8353587996,
+        ),
+        (
+            -7.5,
+            -0.1124634850764908,
+            0.3188095066985546,
+            0.8778022815457609,
+        ),
+        (
+            10.0,
+            455641153.5482251,
+            -3.520633676738924e-10,
+            1429236134.482866,
+        ),
+        (
+            -20.0,
+            -0.2001393093226513,
+            0.8928628567364712,
+            -0.7914290338395365,
+        ),
+        (
+            30.0,
+            9.057288512151307e46,
+            -1.759876581432726e-48,
+            4.953304512891299e47,
+        ),
+    ];
+
+    // z, Bi, Ai' and Bi' as (re, im) from mpmath
+    const REFERENCE_COMPLEX: [[(f64, f64); 4]; 3] = [
+        [
+            (1.0, 1.0),
+            (0.7166580733827684, 0.6198892904008448),
+            (-0.1306279534996475, 0.1630675964493239),
+            (0.07566284417496599, 0.7837009987854553),
+        ],
+        [
+            (-4.0, 6.0),
+            (-42686.15141151053, 50174.26659622329),
+            (37220.58915109669, -170532.1827494003),
+            (170532.1827512622, 37220.58914950867),
+        ],
+        [
+            (8.0, 3.0),
+            (-297016.0531185619, 445697.6584254899),
+            (1.674762067452836e-7, 2.485534123454018e-7),
+            (-1083279.778960837, 1111317.904024112),
+        ],
+    ];
+
+    // e^zeta Ai, e^zeta Ai', e^-zeta Bi and e^-zeta Bi' with zeta = 2/3 x^(3/2), from mpmath
+    const REFERENCE_SCALED: [(f64, [f64; 4]); 3] = [
+        (
+            20.0,
+            [
+                0.1332404073518136,
+                -0.5975232737163193,
+                0.2671023248341684,
+                1.191155399372008,
+            ],
+        ),
+        (
+            50.0,
+            [
+                0.106053469759168,
+                -0.7504406102617342,
+                0.2122319627140653,
+                1.499643556488666,
+            ],
+        ),
+        (
+            100.0,
+            [
+                0.08919692093633041,
+                -0.8921920625040315,
+                0.1784310111708354,
+                1.783863754962809,
+            ],
+        ),
+    ];
+
+    fn assert_close(actual: Complex64, expected: Complex64, tolerance: f64, what: &str) {
+        assert!(
+            (actual - expected).norm() <= tolerance * expected.norm(),
+            "{} = {}, expected {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn bi_and_derivatives() {
+        let references = REFERENCE_REAL
+            .iter()
+            .map(|(x, bi, ai_prime, bi_prime)| {
+                [(*x, 0.0), (*bi, 0.0), (*ai_prime, 0.0), (*bi_prime, 0.0)]
+            })
+            .chain(REFERENCE_COMPLEX);
+        for [z, bi, ai_prime, bi_prime] in references {
+            let z = complex(z.0, z.1);
+            let values = airy(z);
+            let expected = |(re, im): (f64, f64)| complex(re, im);
+            assert_close(values.bi, expected(bi), 1e-9, &format!("Bi({})", z));
+            assert_close(
+                values.ai_prime,
+                expected(ai_prime),
+                1e-9,
+                &format!("Ai'({})", z),
+            );
+            assert_close(
+                values.bi_prime,
+                expected(bi_prime),
+                1e-9,
+                &format!("Bi'({})", z),
+            );
+        }
+    }
+
+    #[test]
+    fn wronskian() {
+        // Ai Bi' - Ai' Bi = 1 / pi
+        for x in [-30.0, -12.0, -6.5, -3.0, 0.5, 4.0, 6.5, 12.0, 25.0] {
+            let values = airy_scaled(complex(x, 0.0));
+            let w = values.ai * values.bi_prime - values.ai_prime * values.bi;
+            assert_close(
+                w,
+                complex(1.0 / f64::consts::PI, 0.0),
+                1e-10,
+                &format!("W({})", x),
+            );
+        }
+    }
+
+    #[test]
+    fn scaled_beyond_overflow() {
+        for (x, expected) in REFERENCE_SCALED {
+            let values = airy_scaled(complex(x, 0.0));
+            let actual = [values.ai, values.ai_prime, values.bi, values.bi_prime];
+            for (a, e) in actual.iter().zip(expected) {
+                assert_close(*a, complex(e, 0.0), 1e-10, &format!("scaled at {}", x));
+            }
+        }
+
+        // Bi overflows at 200, its scaled form doesn't
+        assert!(!airy(complex(200.0, 0.0)).bi.is_finite());
+        assert!(airy_scaled(complex(200.0, 0.0)).bi.is_finite());
+        // on the oscillating side there is nothing to scale
+        let (scaled, unscaled) = (airy_scaled(complex(-9.0, 0.0)), airy(complex(-9.0, 0.0)));
+        assert_close(scaled.bi, unscaled.bi, 1e-14, "Bi(-9)");
+    }
+
+    #[test]
+    fn matches_reference() {
+        for ((re, im), (ai_re, ai_im)) in REFERENCE {
+            let actual = airy_ai(complex(re, im));
+            let expected = complex(ai_re, ai_im);
+            assert!(
+                (actual - expected).norm() <= 1e-6 * expected.norm(),
+                "Ai({} + {}i) = {}, expected {}",
+                re,
+                im,
+                actual,
+                expected
+            );
+        }
+    }
+}
@@ -3,32 +3,73 @@ use crate::turning_points::*;
 use crate::wkb_wave_func::Phase;
 use crate::*;
 use num::signum;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+// calls of Ai where the Go library returned something the Rust series didn't
+static AI_FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+// number of non finite values of the Go airy_ai replaced by airy_series since the start
+pub fn ai_failures() -> usize {
+    AI_FAILURES.load(Ordering::Relaxed)
+}
+
+// value of the Go airy_ai at x if it is finite or Ai overflows anyway, airy_series otherwise. The
+// first failure is reported, a broken build of lib/libairy.a would otherwise only show up as NaNs
+// scattered through the output.
+fn checked_ai(x: Complex64, value: Complex64) -> Complex64 {
+    if value.is_finite() {
+        return value;
+    }
+    let fallback = airy_series::airy_ai(x);
+    if !fallback.is_finite() {
+        return value;
+    }
+    if AI_FAILURES.fetch_add(1, Ordering::Relaxed) == 0 {
+        eprintln!(
+            "airy_ai of the Go library returned {} for Ai({}), airy_series is used instead. \
+             Check that lib/libairy.a was built by build.rs, further failures are counted in \
+             airy_wave_func::ai_failures().",
+            value, x
+        );
+    }
+    fallback
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(non_snake_case)]
 fn Ai(x: Complex64) -> Complex64 {
     let go_return;
     unsafe {
         go_return = airy_ai(x.re, x.im);
     }
-    return complex(go_return.r0, go_return.r1);
+    checked_ai(x, complex(go_return.r0, go_return.r1))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[allow(non_snake_case)]
+fn Ai(x: Complex64) -> Complex64 {
+    airy_series::airy_ai(x)
 }
 
 #[allow(non_snake_case)]
 fn Bi(x: Complex64) -> Complex64 {
-    return -complex(0.0, 1.0) * Ai(x)
-        + 2.0 * Ai(x * complex(-0.5, 3.0_f64.sqrt() / 2.0)) * complex(3_f64.sqrt() / 2.0, 0.5);
+    airy_series::airy_bi(x)
 }
 
 #[derive(Clone)]
 pub struct AiryWaveFunction {
     c: Complex64,
+    // coefficient of Bi, only nonzero after matching the derivatives
+    c_bi: Complex64,
     u_1: f64,
     pub turning_point: f64,
     phase: Arc<Phase>,
     pub ts: (f64, f64),
     op: fn(Complex64) -> Complex64,
     phase_off: f64,
+    // action of langer_mapping by u with x = turning_point + u |u|, see with_action_table
+    action: Option<Arc<IntegralTable>>,
 }
 
 impl AiryWaveFunction {
@@ -40,15 +81,19 @@ impl AiryWaveFunction {
         signum(u_1) * u_1.abs().pow(1.0 / 3.0)
     }
 
-    pub fn new<'a>(phase: Arc<Phase>, view: (f64, f64)) -> (Vec<AiryWaveFunction>, TGroup) {
+    pub fn new(
+        phase: Arc<Phase>,
+        view: (f64, f64),
+    ) -> Result<(Vec<AiryWaveFunction>, TGroup), String> {
         let phase = phase;
-        let turning_point_boundaries = turning_points::calc_ts(phase.as_ref(), view);
+        let turning_point_boundaries = turning_points::calc_ts(phase.as_ref(), view)?;
 
         let funcs: Vec<AiryWaveFunction> = turning_point_boundaries
             .ts
             .iter()
             .map(|((tb1, tb2), t)| {
-                let u_1 = 2.0 * phase.mass * -derivative(phase.potential.as_ref(), *t);
+                let u_1 = 2.0 * phase.mass * -derivative(phase.potential.as_ref(), *t)
+                    / phase.hbar.powi(2);
 
                 AiryWaveFunction {
                     u_1,
@@ -57,11 +102,13 @@ impl AiryWaveFunction {
                     ts: (*tb1, *tb2),
                     op: identity,
                     c: 1.0.into(),
+                    c_bi: 0.0.into(),
                     phase_off: 0.0,
+                    action: None,
                 }
             })
             .collect::<Vec<AiryWaveFunction>>();
-        return (funcs, turning_point_boundaries);
+        Ok((funcs, turning_point_boundaries))
     }
 
     pub fn with_op(&self, op: fn(Complex64) -> Complex64) -> AiryWaveFunction {
@@ -72,7 +119,9 @@ impl AiryWaveFunction {
             ts: self.ts,
             op,
             c: self.c,
+            c_bi: self.c_bi,
             phase_off: self.phase_off,
+            action: self.action.clone(),
         }
     }
 
@@ -84,7 +133,16 @@ impl AiryWaveFunction {
             ts: self.ts,
             op: self.op,
             c,
+            c_bi: self.c_bi,
             phase_off: self.phase_off,
+            action: self.action.clone(),
+        }
+    }
+
+    pub fn with_c_bi(&self, c_bi: Complex64) -> AiryWaveFunction {
+        AiryWaveFunction {
+            c_bi,
+            ..self.clone()
         }
     }
 
@@ -96,19 +154,142 @@ impl AiryWaveFunction {
             ts: self.ts,
             op: self.op,
             c: self.c,
+            c_bi: self.c_bi,
             phase_off,
+            action: self.action.clone(),
+        }
+    }
+
+    pub fn get_c(&self) -> Complex64 {
+        self.c
+    }
+
+    pub fn get_c_bi(&self) -> Complex64 {
+        self.c_bi
+    }
+
+    // argument of Ai and Bi at x, positive in the forbidden region
+    fn argument(&self, x: f64) -> f64 {
+        Self::get_u_1_cube_root(self.u_1) * (self.turning_point - x)
+    }
+
+    fn prefactor(&self) -> f64 {
+        std::f64::consts::PI.sqrt() / ((self.u_1).abs().pow(1.0 / 6.0) * self.phase.hbar.sqrt())
+    }
+
+    // Ai and Bi at x with the prefactor of the wave function, and their derivatives by x
+    pub fn basis(&self, x: f64) -> airy_series::AiryValues {
+        let values = airy_series::airy(complex(self.argument(x), 0.0));
+        let prefactor = self.prefactor();
+        let chain = -Self::get_u_1_cube_root(self.u_1);
+        airy_series::AiryValues {
+            ai: values.ai * prefactor,
+            ai_prime: values.ai_prime * prefactor * chain,
+            bi: values.bi * prefactor,
+            bi_prime: values.bi_prime * prefactor * chain,
+        }
+    }
+
+    pub fn derivative(&self, x: f64) -> Complex64 {
+        let basis = self.basis(x);
+        (self.op)(self.c * basis.ai_prime + self.c_bi * basis.bi_prime)
+    }
+
+    // square root of the distance to the turning point with its sign
+    fn u(&self, x: f64) -> f64 {
+        (x - self.turning_point).signum() * (x - self.turning_point).abs().sqrt()
+    }
+
+    // The action of langer_mapping tabulated over range, where it is then interpolated instead of
+    // integrated for every x. The integrand 2 |u| sqrt|Q| by u is smooth through the turning point,
+    // which is one of the nodes.
+    pub fn with_action_table(&self, range: (f64, f64)) -> AiryWaveFunction {
+        let phase = self.phase.as_ref();
+        let turning_point = self.turning_point;
+        let table = IntegralTable::through(
+            |u| 2.0 * u.abs() * phase.eval(turning_point + u * u.abs()),
+            0.0,
+            (self.u(range.0), self.u(range.1)),
+            INTEG_STEPS,
+        );
+        AiryWaveFunction {
+            action: Some(Arc::new(table)),
+            ..self.clone()
         }
     }
+
+    // Langer's mapping onto the Airy equation, zeta with 2/3 |zeta|^(3/2) the action between the
+    // turning point and x, positive in the forbidden region, and (zeta / k^2)^(1/4) with
+    // k^2 = 2m (V - E) / hbar^2. At the turning point they are the linear argument and |u_1|^(-1/6).
+    pub fn langer_mapping(&self, x: f64) -> (f64, f64) {
+        let k_squared = 2.0 * self.phase.mass * ((self.phase.potential)(x) - self.phase.energy)
+            / self.phase.hbar.powi(2);
+        // NaN outside of the table
+        let tabulated = self.action.as_ref().map_or(f64::NAN, |table| {
+            (table.integral(self.u(x)) - table.integral(0.0)).abs()
+        });
+        let action = if tabulated.is_finite() {
+            tabulated
+        } else {
+            integrate(
+                evaluate_function_between(self.phase.as_ref(), self.turning_point, x, INTEG_STEPS),
+                TRAPEZE_PER_THREAD,
+            )
+            .abs()
+        };
+        if action == 0.0 || k_squared == 0.0 {
+            return (self.argument(x), self.u_1.abs().powf(-1.0 / 6.0));
+        }
+        let zeta = k_squared.signum() * (1.5 * action).powf(2.0 / 3.0);
+        (zeta, (zeta / k_squared).powf(0.25))
+    }
+
+    // c Ai(zeta) with the prefactor of the mapping, one formula through the turning point that
+    // tends to the WKB function on both sides. Only the Ai part, c_bi is left out.
+    pub fn eval_uniform(&self, x: f64) -> Complex64 {
+        let (zeta, factor) = self.langer_mapping(x);
+        let prefactor = std::f64::consts::PI.sqrt() * factor / self.phase.hbar.sqrt();
+        let value = if zeta > airy_series::SERIES_RADIUS {
+            // Ai underflows before c is applied, as in eval
+            let scaled = airy_series::airy_scaled(complex(zeta, 0.0)).ai;
+            if self.c.norm() > 0.0 {
+                self.c / self.c.norm()
+                    * scaled
+                    * ((self.c.norm() * prefactor).ln() - 2.0 / 3.0 * zeta.powf(1.5)).exp()
+            } else {
+                complex(0.0, 0.0)
+            }
+        } else {
+            self.c * prefactor * Ai(complex(zeta, 0.0))
+        };
+        (self.op)(value)
+    }
 }
 
 impl Func<f64, Complex64> for AiryWaveFunction {
     fn eval(&self, x: f64) -> Complex64 {
-        let u_1_cube_root = Self::get_u_1_cube_root(self.u_1);
+        let argument = self.argument(x);
+        let prefactor = self.prefactor();
 
-        let value = self.c
-            * ((std::f64::consts::PI.sqrt() / (self.u_1).abs().pow(1.0 / 6.0))
-                * Ai(complex(u_1_cube_root * (self.turning_point - x), 0.0)))
-                as Complex64;
+        let value = if argument > airy_series::SERIES_RADIUS {
+            // deep in the forbidden region Ai underflows and Bi overflows before the coefficients
+            // are applied, the exponents of the scaled functions are added to their logs instead
+            let scaled = airy_series::airy_scaled(complex(argument, 0.0));
+            let zeta = 2.0 / 3.0 * argument.powf(1.5);
+            let term = |c: Complex64, scaled: Complex64, exponent: f64| {
+                if c.norm() > 0.0 {
+                    c / c.norm() * scaled * ((c.norm() * prefactor).ln() + exponent).exp()
+                } else {
+                    complex(0.0, 0.0)
+                }
+            };
+            term(self.c, scaled.ai, -zeta) + term(self.c_bi, scaled.bi, zeta)
+        } else if self.c_bi.norm() > 0.0 {
+            prefactor
+                * (self.c * Ai(complex(argument, 0.0)) + self.c_bi * Bi(complex(argument, 0.0)))
+        } else {
+            self.c * prefactor * Ai(complex(argument, 0.0))
+        };
         return (self.op)(value);
     }
 }
@@ -131,17 +312,100 @@ mod test {
         let data_str_ai: String = values
             .par_iter()
             .map(|p| -> String { format!("{} {} {}\n", p.x, p.y.re, p.y.im) })
-            .reduce(|| String::new(), |s: String, current: String| s + &*current);
+            .collect::<Vec<String>>()
+            .concat();
 
         let values_bi = evaluate_function_between(&airy_bi, -5.0, 2.0, NUMBER_OF_POINTS);
 
         let data_str_bi: String = values_bi
             .par_iter()
             .map(|p| -> String { format!("{} {} {}\n", p.x, p.y.re, p.y.im) })
-            .reduce(|| String::new(), |s: String, current: String| s + &*current);
+            .collect::<Vec<String>>()
+            .concat();
 
         data_file
             .write_all((data_str_ai + "\n\n" + &*data_str_bi).as_ref())
             .unwrap()
     }
+
+    #[test]
+    fn failed_ai_falls_back_to_series() {
+        let x = complex(-2.0, 0.5);
+        let failures = ai_failures();
+        let value = checked_ai(x, complex(f64::NAN, 0.0));
+        assert_eq!(value, airy_series::airy_ai(x));
+        assert!(ai_failures() > failures);
+
+        // finite values are passed through
+        assert_eq!(checked_ai(x, complex(1.0, 2.0)), complex(1.0, 2.0));
+        // Ai overflows far from the positive real axis, that isn't a failure
+        let far = complex(-1e3, 1e3);
+        assert!(checked_ai(far, complex(f64::INFINITY, 0.0))
+            .re
+            .is_infinite());
+    }
+
+    #[test]
+    fn langer_mapping_of_linear_potential() {
+        // V = |x| is linear around both turning points, the mapping is the linear argument there
+        let phase = Arc::new(Phase::new(2.0, 1.0, UNITS.hbar, Arc::new(|x: f64| x.abs())));
+        let (funcs, _) = AiryWaveFunction::new(phase, (-5.0, 5.0)).unwrap();
+        let airy = funcs.last().unwrap().with_c(complex(1.0, 0.0));
+        assert!((airy.turning_point - 2.0).abs() < 1e-9);
+        for x in [0.5, 1.9, 2.0, 2.1, 4.0] {
+            let (zeta, factor) = airy.langer_mapping(x);
+            let argument = airy.argument(x);
+            assert!((zeta - argument).abs() < 1e-5 * argument.abs().max(1.0));
+            assert!((factor - airy.u_1.abs().powf(-1.0 / 6.0)).abs() < 1e-5);
+            assert!((airy.eval_uniform(x) - airy.eval(x)).norm() < 1e-5, "{}", x);
+        }
+    }
+
+    #[test]
+    fn tabulated_langer_mapping() {
+        let phase = Arc::new(Phase::new(
+            2.5 * 2.0f64.sqrt(),
+            1.0,
+            UNITS.hbar,
+            Arc::new(potentials::square),
+        ));
+        let (funcs, _) = AiryWaveFunction::new(phase, (-5.0, 5.0)).unwrap();
+        let airy = funcs.last().unwrap();
+        let tabulated = airy.with_action_table((0.0, 5.0));
+        for x in [0.0, 0.7, 1.5, airy.turning_point, 2.0, 3.3, 5.0, 6.0] {
+            let (zeta, factor) = airy.langer_mapping(x);
+            let (table_zeta, table_factor) = tabulated.langer_mapping(x);
+            assert!(
+                (table_zeta - zeta).abs() < 1e-6 * zeta.abs().max(1.0),
+                "{}",
+                x
+            );
+            assert!((table_factor - factor).abs() < 1e-6 * factor, "{}", x);
+        }
+    }
+
+    #[test]
+    fn large_amplitude_deep_in_forbidden_region() {
+        let phase = Arc::new(Phase::new(
+            2.0f64.sqrt() / 2.0,
+            1.0,
+            UNITS.hbar,
+            Arc::new(potentials::square),
+        ));
+        let (funcs, _) = AiryWaveFunction::new(phase, (-5.0, 5.0)).unwrap();
+        let airy = funcs.last().unwrap().with_c(complex(1e300, 0.0));
+        let at = |argument: f64| {
+            airy.eval(airy.turning_point - argument / AiryWaveFunction::get_u_1_cube_root(airy.u_1))
+        };
+
+        // the scaled form takes over without a jump
+        let below = at(airy_series::SERIES_RADIUS * (1.0 - 1e-12));
+        let above = at(airy_series::SERIES_RADIUS * (1.0 + 1e-12));
+        assert!((below - above).norm() < 1e-6 * below.norm());
+
+        // Ai(110) ~ e^-769 underflows on its own
+        assert_eq!(Ai(complex(110.0, 0.0)).norm(), 0.0);
+        let deep = at(110.0);
+        assert!(deep.is_finite() && deep.re > 0.0, "{}", deep);
+    }
 }
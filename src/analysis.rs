@@ -0,0 +1,691 @@
+use crate::hydrogen::HydrogenLike;
+use crate::*;
+use serde::Serialize;
+use std::fmt::Display;
+
+// bisection steps refining an extremum between two scan points, enough to reach f64 resolution
+const REFINE_STEPS: usize = 64;
+// samples of |psi|^2 per well, every evaluation of a WKB part integrates its phase so this stays
+// well below INTEG_STEPS, the assignment only needs rough probabilities
+const PROBABILITY_POINTS: usize = 2000;
+// u / r of an s state is taken at this fraction of the inner turning point and twice it
+const CONTACT_RADIUS: f64 = 1e-2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Minimum,
+    Maximum,
+}
+
+// interior minima of the potential in range, ascending in x
+pub fn find_minima<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    range: (f64, f64),
+) -> Vec<Point<f64, f64>> {
+    find_extrema(potential, range, Kind::Minimum)
+}
+
+// interior maxima of the potential in range, ascending in x, e.g. the tops of the barriers
+pub fn find_maxima<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    range: (f64, f64),
+) -> Vec<Point<f64, f64>> {
+    find_extrema(potential, range, Kind::Maximum)
+}
+
+// Scans the sign of V' on a uniform grid and refines every sign change by bisection. Where V' is
+// exactly zero over several scan points, like at the flat bottom of a well, the extremum is put in
+// the middle of the flat part.
+fn find_extrema<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    range: (f64, f64),
+    kind: Kind,
+) -> Vec<Point<f64, f64>> {
+    let grid = Grid::uniform(range, NUMBER_OF_POINTS);
+    let slopes: Vec<f64> = (0..grid.len())
+        .into_par_iter()
+        .map(|i| derivative(potential, grid.x(i)))
+        .collect();
+
+    let mut extrema = vec![];
+    // last scan point with a non zero, non NaN slope
+    let mut last: Option<usize> = None;
+    for (i, slope) in slopes.iter().enumerate() {
+        if *slope == 0.0 || slope.is_nan() {
+            continue;
+        }
+        if let Some(j) = last {
+            let found = if slopes[j] < 0.0 && *slope > 0.0 {
+                Some(Kind::Minimum)
+            } else if slopes[j] > 0.0 && *slope < 0.0 {
+                Some(Kind::Maximum)
+            } else {
+                None
+            };
+
+            if found == Some(kind) {
+                let x = if i == j + 1 {
+                    refine(potential, grid.x(j), grid.x(i), slopes[j])
+                } else {
+                    (grid.x(j + 1) + grid.x(i - 1)) / 2.0
+                };
+                extrema.push(Point { x, y: potential(x) });
+            }
+        }
+        last = Some(i);
+    }
+    extrema
+}
+
+// narrows [a, b] down to the sign change of V', the slope at a has the sign of slope_a
+fn refine<F: Fn(f64) -> f64 + ?Sized>(potential: &F, mut a: f64, mut b: f64, slope_a: f64) -> f64 {
+    for _ in 0..REFINE_STEPS {
+        let middle = (a + b) / 2.0;
+        if middle <= a || middle >= b {
+            break;
+        }
+        let slope = derivative(potential, middle);
+        if slope == 0.0 {
+            return middle;
+        }
+        if slope.signum() == slope_a.signum() {
+            a = middle;
+        } else {
+            b = middle;
+        }
+    }
+    (a + b) / 2.0
+}
+
+// the wells of the potential in range, separated at the tops of the barriers between them
+pub fn wells<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    range: (f64, f64),
+) -> Vec<(f64, f64)> {
+    let boundaries: Vec<f64> = [
+        vec![range.0],
+        find_maxima(potential, range).iter().map(|m| m.x).collect(),
+        vec![range.1],
+    ]
+    .concat();
+    boundaries.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+// fraction of |psi|^2 in every well, only the part of a well inside the view is integrated
+pub fn well_probabilities(wave_function: &WaveFunction, wells: &[(f64, f64)]) -> Vec<f64> {
+    let view = wave_function.get_view();
+    let probabilities: Vec<f64> = wells
+        .iter()
+        .map(|well| {
+            let range = (well.0.max(view.0), well.1.min(view.1));
+            if range.0 >= range.1 {
+                return 0.0;
+            }
+            let probability = Grid::uniform(range, PROBABILITY_POINTS)
+                .evaluate(wave_function)
+                .iter()
+                .map(|p| Point {
+                    x: p.x,
+                    y: p.y.norm_sqr(),
+                })
+                .collect();
+            integrate(probability, TRAPEZE_PER_THREAD)
+        })
+        .collect();
+
+    let total: f64 = probabilities.iter().sum();
+    probabilities.iter().map(|p| p / total).collect()
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LevelLabel {
+    pub n: usize,
+    pub energy: f64,
+    // index into the wells, ordered by x
+    pub well: usize,
+    // quantum number of the state counted within its well
+    pub local_n: usize,
+    pub probabilities: Vec<f64>,
+}
+
+// Assigns every state to the well holding most of its probability. The states have to be in
+// ascending energy, the first one being the n_start-th state of the potential. The amplitudes of
+// the wells follow from the connection across the barriers at the WKB energy, which is no exact
+// level of either well, so the dominant well often holds little more than its share, the
+// probabilities are kept to judge how clear an assignment is.
+pub fn label_levels(
+    states: &[WaveFunction],
+    n_start: usize,
+    wells: &[(f64, f64)],
+) -> Vec<LevelLabel> {
+    let probabilities: Vec<Vec<f64>> = states
+        .par_iter()
+        .map(|state| well_probabilities(state, wells))
+        .collect();
+
+    let mut count_per_well = vec![0; wells.len()];
+    states
+        .iter()
+        .zip(probabilities)
+        .enumerate()
+        .map(|(i, (state, probabilities))| {
+            let well = (0..wells.len())
+                .max_by(|a, b| cmp_f64(&probabilities[*a], &probabilities[*b]))
+                .unwrap();
+            let local_n = count_per_well[well];
+            count_per_well[well] += 1;
+            LevelLabel {
+                n: n_start + i,
+                energy: state.get_energy(),
+                well,
+                local_n,
+                probabilities,
+            }
+        })
+        .collect()
+}
+
+// labelled spectrum of the states n_range of a potential, the wells are searched in APPROX_INF
+pub fn labelled_spectrum(
+    potential: SharedPotential,
+    mass: f64,
+    n_range: std::ops::Range<usize>,
+) -> Vec<LevelLabel> {
+    let wells = wells(potential.as_ref(), APPROX_INF);
+    let states = eigenstates(
+        potential,
+        mass,
+        n_range.clone(),
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::None,
+    );
+    label_levels(&states, n_range.start, &wells)
+}
+
+// Spread of a superposition over the eigenstates, it sets the time scales of its dynamics: it
+// dephases after about hbar / delta_e, the fewer states take part the cleaner the revivals.
+#[derive(Serialize, Clone, Debug)]
+pub struct EnergyStatistics {
+    pub mean_energy: f64,
+    pub energy_uncertainty: f64,
+    // sum of p_n^2, 1 / ipr is the number of states taking part
+    pub inverse_participation_ratio: f64,
+    pub dephasing_time: f64,
+}
+
+impl Display for EnergyStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<E> = {:.9}, dE = {:.3e}, IPR = {:.4} ({:.2} states), hbar / dE = {:.3e}",
+            self.mean_energy,
+            self.energy_uncertainty,
+            self.inverse_participation_ratio,
+            1.0 / self.inverse_participation_ratio,
+            self.dephasing_time
+        )
+    }
+}
+
+// Statistics of p_n = |c_n|^2 / sum |c|^2, the members count as normalized eigenstates like in
+// Superposition::coherent. Members of other potentials aren't eigenstates of its hamiltonian and
+// are left out.
+pub fn energy_statistics(superposition: &Superposition) -> Result<EnergyStatistics, String> {
+    let members: Vec<(f64, f64)> = superposition
+        .get_coefficients()
+        .iter()
+        .zip(superposition.get_energies())
+        .map(|((_, c), (_, energy))| (c.norm_sqr(), energy))
+        .collect();
+    let total: f64 = members.iter().map(|(p, _)| p).sum();
+    if total == 0.0 {
+        return Err("the superposition has no eigenstate with a nonzero coefficient".to_string());
+    }
+
+    let mean_energy = members.iter().map(|(p, e)| p * e).sum::<f64>() / total;
+    let variance = members
+        .iter()
+        .map(|(p, e)| p * (e - mean_energy).powi(2))
+        .sum::<f64>()
+        / total;
+    let energy_uncertainty = variance.sqrt();
+    Ok(EnergyStatistics {
+        mean_energy,
+        energy_uncertainty,
+        inverse_participation_ratio: members.iter().map(|(p, _)| (p / total).powi(2)).sum(),
+        dephasing_time: superposition.get_hbar() / energy_uncertainty,
+    })
+}
+
+// Response of the turning points and of the norm of the unscaled psi to a change of the energy,
+// from central differences at E +- delta. A simple turning point moves by 1 / V'(t) per unit of
+// energy, the condition number max |V'(t) dt / dE| is 1 for those and grows where a turning point
+// sits close to an extremum of V, e.g. at the top of a barrier, and the state hinges on E.
+#[derive(Serialize, Clone, Debug)]
+pub struct TurningPointSensitivity {
+    pub energy: f64,
+    pub delta: f64,
+    pub turning_points: Vec<f64>,
+    // dt / dE of every turning point
+    pub turning_point_rates: Vec<f64>,
+    // d ln N / dE of N = integral of |psi|^2 over the view
+    pub normalization_rate: f64,
+    pub condition_number: f64,
+}
+
+impl Display for TurningPointSensitivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "E = {:.9} +- {:.1e}: dt / dE = {:?}, d ln N / dE = {:.3e}, condition number {:.3}",
+            self.energy,
+            self.delta,
+            self.turning_point_rates,
+            self.normalization_rate,
+            self.condition_number
+        )
+    }
+}
+
+// fails if the number of turning points changes between E - delta and E + delta, a turning point
+// appearing or disappearing has no finite rate
+pub fn turning_point_sensitivity(
+    wave_function: &WaveFunction,
+    delta: f64,
+) -> Result<TurningPointSensitivity, String> {
+    if !delta.is_finite() || delta <= 0.0 {
+        return Err(format!("delta {} isn't a finite positive number", delta));
+    }
+    let phase = wave_function.get_phase();
+    let view = wave_function.get_view();
+    let grid = Grid::uniform(view, PROBABILITY_POINTS);
+    let at = |energy: f64| -> Result<(Vec<f64>, f64), String> {
+        potentials::check_energy(phase.potential.as_ref(), energy, APPROX_INF)?;
+        let shifted = Arc::new(phase.with_energy(energy));
+        let turning_points = turning_points::calc_ts(shifted.as_ref(), view)?
+            .ts
+            .iter()
+            .map(|(_, t)| *t)
+            .collect();
+        let unscaled = EnergyStage::from_phase(shifted, APPROX_INF, VIEW_FACTOR, ScalingType::None)
+            .find_turning_points()?
+            .build_parts()?
+            .scale();
+        let norm = integrate(
+            grid.evaluate(&unscaled)
+                .iter()
+                .map(|p| Point {
+                    x: p.x,
+                    y: p.y.norm_sqr(),
+                })
+                .collect(),
+            TRAPEZE_PER_THREAD,
+        );
+        Ok((turning_points, norm))
+    };
+
+    let (turning_points, norm) = at(phase.energy)?;
+    let (lower, norm_lower) = at(phase.energy - delta)?;
+    let (upper, norm_upper) = at(phase.energy + delta)?;
+    if lower.len() != turning_points.len() || upper.len() != turning_points.len() {
+        return Err(format!(
+            "the number of turning points changes from {} at E - delta over {} to {} at E + delta",
+            lower.len(),
+            turning_points.len(),
+            upper.len()
+        ));
+    }
+
+    let turning_point_rates: Vec<f64> = lower
+        .iter()
+        .zip(upper.iter())
+        .map(|(l, u)| (u - l) / (2.0 * delta))
+        .collect();
+    let potential = |x| (phase.potential)(x);
+    let condition_number = turning_points
+        .iter()
+        .zip(turning_point_rates.iter())
+        .map(|(t, rate)| (newtons_method::derivative(&potential, *t) * rate).abs())
+        .fold(0.0, f64::max);
+    Ok(TurningPointSensitivity {
+        energy: phase.energy,
+        delta,
+        turning_points,
+        turning_point_rates,
+        normalization_rate: (norm_upper - norm_lower) / (2.0 * delta * norm),
+        condition_number,
+    })
+}
+
+// |psi(x)|^2 of the state normalized over OBSERVABLE_DOMAIN and the region psi is taken from at
+// x. psi is finite in the Airy ranges, but in a joint it is a blend of the Airy and the WKB part
+// that can kink where either of them is off, see density_sweep.
+#[derive(Serialize, Clone, Debug)]
+pub struct PointDensity {
+    pub x: f64,
+    pub density: f64,
+    pub region: Region,
+}
+
+fn observable_norm(wave_function: &WaveFunction) -> f64 {
+    let grid = Grid::uniform(OBSERVABLE_DOMAIN.range(wave_function), PROBABILITY_POINTS);
+    matrix_elements::norm(wave_function, &grid)
+}
+
+pub fn density_at(wave_function: &WaveFunction, x: f64) -> PointDensity {
+    let norm = observable_norm(wave_function);
+    PointDensity {
+        x,
+        density: wave_function.eval(x).norm_sqr() / (norm * norm),
+        region: wave_function.region(x),
+    }
+}
+
+// |psi(0)|^2 = |R(0)|^2 / (4 pi) of a hydrogen-like state from its radial function u = r R, 0
+// unless l = 0. Far inside the inner turning point u / r of an s state goes linearly to R(0), it is
+// extrapolated from two radii there, the region is the one at the inner of them.
+pub fn contact_density(hydrogen: &HydrogenLike, wave_function: &WaveFunction) -> PointDensity {
+    let phase = wave_function.get_phase();
+    let domain = wave_function.domain();
+    let inner =
+        turning_points::classical_turning_points(phase.potential.as_ref(), phase.energy, domain)
+            .map_or(domain.1, |t| t.0);
+    let r = f64::max(CONTACT_RADIUS * inner, domain.0);
+    let region = wave_function.region(r);
+    if hydrogen.l > 0 {
+        return PointDensity {
+            x: 0.0,
+            density: 0.0,
+            region,
+        };
+    }
+
+    let norm = observable_norm(wave_function);
+    let radial = |r: f64| wave_function.eval(r).norm() / (r * norm);
+    let at_origin = 2.0 * radial(r) - radial(2.0 * r);
+    PointDensity {
+        x: 0.0,
+        density: at_origin * at_origin / (4.0 * f64::consts::PI),
+        region,
+    }
+}
+
+// A density over the parameters of a family of states, e.g. the mass or a parameter of V, with its
+// derivative by the parameter from the neighbouring parameters. Where the density is taken from
+// another region of psi at a neighbour it can jump or kink in between, such neighbours aren't used
+// and the derivative is none if no neighbour is left.
+#[derive(Serialize, Clone, Debug)]
+pub struct DensitySweep {
+    pub parameters: Vec<f64>,
+    pub densities: Vec<PointDensity>,
+    pub derivatives: Vec<Option<f64>>,
+}
+
+// the parameters have to be ascending, e.g.
+//     density_sweep(&|m| density_at(&WaveFunction::new(potential.clone(), m, 0, ...), 0.0), &masses)
+pub fn density_sweep(
+    density: &(dyn Fn(f64) -> PointDensity + Sync),
+    parameters: &[f64],
+) -> DensitySweep {
+    let densities = parameters
+        .par_iter()
+        .map(|p| density(*p))
+        .collect::<Vec<PointDensity>>();
+    let values = densities.iter().map(|d| d.density).collect::<Vec<f64>>();
+    let regions = densities.iter().map(|d| d.region).collect::<Vec<Region>>();
+    DensitySweep {
+        parameters: parameters.to_vec(),
+        derivatives: sweep_derivatives(parameters, &values, &regions),
+        densities,
+    }
+}
+
+// dy / d lambda from the parabola through a parameter and both neighbours in the same region, the
+// line through it and the one neighbour otherwise
+fn sweep_derivatives<R: PartialEq>(
+    parameters: &[f64],
+    values: &[f64],
+    regions: &[R],
+) -> Vec<Option<f64>> {
+    let slope = |i: usize, j: usize| (values[j] - values[i]) / (parameters[j] - parameters[i]);
+    (0..parameters.len())
+        .map(|i| {
+            let same = |j: &usize| regions[*j] == regions[i];
+            let lower = i.checked_sub(1).filter(same);
+            let upper = Some(i + 1).filter(|j| *j < parameters.len()).filter(same);
+            match (lower, upper) {
+                (Some(l), Some(u)) => {
+                    let (h0, h1) = (parameters[i] - parameters[l], parameters[u] - parameters[i]);
+                    Some((h1 * slope(l, i) + h0 * slope(i, u)) / (h0 + h1))
+                }
+                (Some(j), None) | (None, Some(j)) => Some(slope(i, j)),
+                (None, None) => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extrema_of_double_mexican_hat() {
+        let minima = find_minima(&potentials::double_mexican_hat, (-10.0, 10.0));
+        let maxima = find_maxima(&potentials::double_mexican_hat, (-10.0, 10.0));
+
+        let expected_minima = [-4.0, 0.0, 4.0];
+        assert_eq!(minima.len(), 3);
+        for (m, x) in minima.iter().zip(expected_minima.iter()) {
+            assert!((m.x - x).abs() < 1e-6, "{} != {}", m.x, x);
+            assert!(m.y.abs() < 1e-9);
+        }
+
+        // V' = 0 at x^2 = 16 / 3 between the wells
+        let top = (16.0f64 / 3.0).sqrt();
+        assert_eq!(maxima.len(), 2);
+        assert!((maxima[0].x + top).abs() < 1e-6);
+        assert!((maxima[1].x - top).abs() < 1e-6);
+        assert!(float_compare(
+            maxima[1].y,
+            potentials::double_mexican_hat(top),
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn statistics_of_two_levels() {
+        let superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (1, complex(0.0, 1.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let energies: Vec<f64> = superposition
+            .get_energies()
+            .iter()
+            .map(|(_, e)| *e)
+            .collect();
+        let statistics = energy_statistics(&superposition).unwrap();
+        assert!(float_compare(
+            statistics.mean_energy,
+            (energies[0] + energies[1]) / 2.0,
+            1e-12
+        ));
+        assert!(float_compare(
+            statistics.energy_uncertainty,
+            (energies[1] - energies[0]) / 2.0,
+            1e-9
+        ));
+        assert!(float_compare(
+            statistics.inverse_participation_ratio,
+            0.5,
+            1e-12
+        ));
+
+        let mut ground = superposition;
+        ground.set_coefficient(1, complex(0.0, 0.0));
+        let statistics = energy_statistics(&ground).unwrap();
+        assert_eq!(statistics.energy_uncertainty, 0.0);
+        assert_eq!(statistics.inverse_participation_ratio, 1.0);
+        ground.set_coefficient(0, complex(0.0, 0.0));
+        assert!(energy_statistics(&ground).is_err());
+    }
+
+    // the right well is 4 higher than the left one, less than half the level spacing
+    fn tilted_double_well(x: f64) -> f64 {
+        potentials::mexican_hat(x) + 0.5 * x + 10.0
+    }
+
+    #[test]
+    fn levels_alternate_between_wells() {
+        let wells = wells(&tilted_double_well, APPROX_INF);
+        assert_eq!(wells.len(), 2);
+        assert!(wells[0].1.abs() < 0.1);
+
+        // built from the seeded nth_energy, labelled_spectrum scans for the energies which is slow
+        let states: Vec<WaveFunction> = (0..4)
+            .into_par_iter()
+            .map(|n| {
+                WaveFunction::new(
+                    Arc::new(tilted_double_well),
+                    1.0,
+                    n,
+                    APPROX_INF,
+                    VIEW_FACTOR,
+                    ScalingType::None,
+                )
+            })
+            .collect();
+        let labels = label_levels(&states, 0, &wells);
+        let expected = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        for (i, (label, (well, local_n))) in labels.iter().zip(expected.iter()).enumerate() {
+            assert_eq!(label.n, i);
+            assert_eq!(
+                (label.well, label.local_n),
+                (*well, *local_n),
+                "{:?}",
+                label
+            );
+            assert!(float_compare(label.probabilities.iter().sum(), 1.0, 1e-12));
+        }
+    }
+
+    #[test]
+    fn flat_bottom_is_centered() {
+        let well = |x: f64| {
+            if x.abs() < 1.0 {
+                0.0
+            } else {
+                (x.abs() - 1.0).powi(2)
+            }
+        };
+        let minima = find_minima(&well, (-5.0, 5.0));
+
+        assert_eq!(minima.len(), 1);
+        assert!(minima[0].x.abs() < 1e-3);
+        assert_eq!(minima[0].y, 0.0);
+        assert!(find_maxima(&well, (-5.0, 5.0)).is_empty());
+    }
+
+    #[test]
+    fn sensitivity_of_oscillator() {
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            1,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let sensitivity = turning_point_sensitivity(&wave_function, 1e-4).unwrap();
+        println!("{}", sensitivity);
+        // V = x^2, t = +-sqrt(E) moves by 1 / (2 t)
+        assert_eq!(sensitivity.turning_points.len(), 2);
+        for (t, rate) in sensitivity
+            .turning_points
+            .iter()
+            .zip(sensitivity.turning_point_rates.iter())
+        {
+            assert!((rate - 0.5 / t).abs() < 1e-3 * rate.abs(), "{} {}", t, rate);
+        }
+        assert!((sensitivity.condition_number - 1.0).abs() < 1e-3);
+        assert!(sensitivity.normalization_rate.is_finite());
+
+        assert!(turning_point_sensitivity(&wave_function, 0.0).is_err());
+        assert!(turning_point_sensitivity(&wave_function, 1e3).is_err());
+    }
+
+    #[test]
+    fn densities_of_oscillator() {
+        // V = x^2 with m = 1 is the oscillator of omega = sqrt(2),
+        // |psi_4(0)|^2 = sqrt(omega / pi) H_4(0)^2 / (2^4 4!) = 3 / 8 sqrt(omega / pi)
+        let state = |n| {
+            WaveFunction::new(
+                Arc::new(potentials::square),
+                1.0,
+                n,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::None,
+            )
+        };
+        let center = density_at(&state(4), 0.0);
+        let exact = 0.375 * (2.0f64.sqrt() / f64::consts::PI).sqrt();
+        assert!(
+            (center.density - exact).abs() < 0.03 * exact,
+            "{:?}",
+            center
+        );
+        assert_eq!(center.region, Region::Wkb);
+        // the node of the odd state
+        assert!(density_at(&state(1), 0.0).density < 1e-3 * exact);
+    }
+
+    #[test]
+    fn derivatives_skip_other_regions() {
+        let parameters = [0.0, 0.5, 1.5, 2.0, 3.0];
+        let values = parameters.map(|p| p * p);
+        let derivatives = sweep_derivatives(&parameters, &values, &[0; 5]);
+        for i in 1..4 {
+            assert!((derivatives[i].unwrap() - 2.0 * parameters[i]).abs() < 1e-12);
+        }
+        assert_eq!(derivatives[0], Some(0.5));
+
+        let derivatives = sweep_derivatives(&parameters, &values, &[0, 0, 1, 0, 0]);
+        assert_eq!(derivatives[1], Some(0.5));
+        assert_eq!(derivatives[2], None);
+        assert_eq!(derivatives[3], Some(5.0));
+    }
+
+    #[test]
+    fn contact_density_scales_with_charge() {
+        // psi_1s(0)^2 = Z^3 / pi, the WKB states scale exactly with Z
+        let contact = |z: f64| {
+            let hydrogen = HydrogenLike::new(z, 1.0, 1, 0);
+            contact_density(
+                &hydrogen,
+                &hydrogen.wave_function(ScalingType::None).unwrap(),
+            )
+        };
+        let sweep = density_sweep(&contact, &[1.9, 2.0, 2.1]);
+        let (at_one, at_two) = (contact(1.0).density, sweep.densities[1].density);
+        assert!(
+            at_one > 0.5 / f64::consts::PI && at_one < 2.0 / f64::consts::PI,
+            "{}",
+            at_one
+        );
+        assert!((at_two / at_one - 8.0).abs() < 0.08, "{}", at_two / at_one);
+        let rate = sweep.derivatives[1].unwrap();
+        assert!((rate - 3.0 * at_two / 2.0).abs() < 0.02 * rate, "{}", rate);
+
+        let p = HydrogenLike::new(1.0, 1.0, 2, 1);
+        assert_eq!(
+            contact_density(&p, &p.wave_function(ScalingType::None).unwrap()).density,
+            0.0
+        );
+    }
+}
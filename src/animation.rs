@@ -0,0 +1,220 @@
+use crate::*;
+use std::process::{Command, Stdio};
+
+// size of the frames in pixels, both even as mp4 needs it for the yuv420p pixel format
+pub const FRAME_WIDTH: usize = 800;
+pub const FRAME_HEIGHT: usize = 450;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const PROBABILITY: [u8; 3] = [70, 110, 200];
+const POTENTIAL: [u8; 3] = [40, 40, 40];
+// the potential is drawn up to this multiple of the highest member energy above its minimum
+const POTENTIAL_HEADROOM: f64 = 2.0;
+
+// |psi(x, t)|^2 at every time, one sample per column, rasterized one frame at a time
+struct Frames {
+    samples: Vec<Vec<Point<f64, Complex64>>>,
+    // row of the potential in every column, none where it isn't a number
+    potential_rows: Vec<Option<usize>>,
+    max: f64,
+    width: usize,
+    height: usize,
+}
+
+impl Frames {
+    fn new(
+        superposition: &mut Superposition,
+        times: &[f64],
+        width: usize,
+        height: usize,
+    ) -> Frames {
+        let grid = Grid::uniform(superposition.get_view(), width);
+        let samples = superposition.sample_evolution(&grid, times);
+        let max = samples
+            .iter()
+            .flatten()
+            .map(|p| p.y.norm_sqr())
+            .fold(0.0, f64::max);
+
+        let potential = superposition.get_potential();
+        let values: Vec<f64> = grid.points().iter().map(|x| potential(*x)).collect();
+        let v_min = values
+            .iter()
+            .cloned()
+            .filter(|v| v.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        let e_max = superposition
+            .get_member_energies()
+            .into_iter()
+            .fold(v_min, f64::max);
+        let v_max = v_min + POTENTIAL_HEADROOM * (e_max - v_min);
+        let potential_rows = values
+            .iter()
+            .map(|v| {
+                if v.is_nan() || v_max.is_nan() || v_max <= v_min {
+                    None
+                } else {
+                    // infinite walls are clipped to the top
+                    Some(Self::row((v - v_min) / (v_max - v_min), height))
+                }
+            })
+            .collect();
+
+        Frames {
+            samples,
+            potential_rows,
+            max: if max > 0.0 { max } else { 1.0 },
+            width,
+            height,
+        }
+    }
+
+    // row of a fraction of the height, counted from the top
+    fn row(fraction: f64, height: usize) -> usize {
+        ((1.0 - fraction.clamp(0.0, 1.0)) * (height - 1) as f64).round() as usize
+    }
+
+    // rgb24, row by row from the top left
+    fn render(&self, samples: &[Point<f64, Complex64>]) -> Vec<u8> {
+        let mut pixels = vec![BACKGROUND; self.width * self.height];
+        for (column, p) in samples.iter().enumerate() {
+            let top = Self::row(p.y.norm_sqr() / self.max, self.height);
+            for row in top..self.height {
+                pixels[row * self.width + column] = PROBABILITY;
+            }
+        }
+        // vertical segments to the previous column keep steep parts of the potential connected
+        for column in 0..self.width {
+            let current = match self.potential_rows[column] {
+                Some(row) => row,
+                None => continue,
+            };
+            let previous = column
+                .checked_sub(1)
+                .and_then(|c| self.potential_rows[c])
+                .unwrap_or(current);
+            for row in current.min(previous)..=current.max(previous) {
+                pixels[row * self.width + column] = POTENTIAL;
+            }
+        }
+        pixels.concat()
+    }
+}
+
+// every frame as rgb24 pixels of the given size, |psi|^2 is scaled to its largest value over all
+// times and the potential drawn on top
+pub fn render_frames(
+    superposition: &mut Superposition,
+    times: &[f64],
+    width: usize,
+    height: usize,
+) -> Vec<Vec<u8>> {
+    let frames = Frames::new(superposition, times, width, height);
+    frames.samples.iter().map(|s| frames.render(s)).collect()
+}
+
+// Renders |psi(x, t)|^2 with the potential at the times and pipes the frames to ffmpeg, which has
+// to be on the PATH. The format is taken from the extension of output, .gif or .mp4.
+pub fn write_animation(
+    superposition: &mut Superposition,
+    times: &[f64],
+    fps: u32,
+    output: &Path,
+) -> Result<(), String> {
+    let format_args: &[&str] = match output.extension().and_then(|e| e.to_str()) {
+        Some("gif") => &[],
+        Some("mp4") => &["-pix_fmt", "yuv420p"],
+        _ => {
+            return Err(format!(
+                "can't tell the format of {} from its extension, use .gif or .mp4",
+                output.display()
+            ))
+        }
+    };
+    if times.is_empty() || fps == 0 {
+        return Err("an animation needs at least one time and a frame rate above 0".to_string());
+    }
+
+    let frames = Frames::new(superposition, times, FRAME_WIDTH, FRAME_HEIGHT);
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+        ])
+        .args(["-s", &format!("{}x{}", FRAME_WIDTH, FRAME_HEIGHT)])
+        .args(["-r", &fps.to_string(), "-i", "-"])
+        .args(format_args)
+        .arg(output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("can't start ffmpeg: {}", e))?;
+
+    let mut stdin = ffmpeg.stdin.take().unwrap();
+    for samples in &frames.samples {
+        stdin
+            .write_all(&frames.render(samples))
+            .map_err(|e| format!("can't write a frame to ffmpeg: {}", e))?;
+    }
+    drop(stdin);
+
+    let status = ffmpeg
+        .wait()
+        .map_err(|e| format!("ffmpeg didn't finish: {}", e))?;
+    if status.success() {
+        plot::record_file(output);
+        Ok(())
+    } else {
+        Err(format!("ffmpeg failed with {}", status))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_of_two_levels() {
+        let mut superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (1, complex(1.0, 0.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let times = [0.0, 0.7, 1.4];
+        let (width, height) = (60, 40);
+
+        // the sampled evolution agrees with evaluating psi at each time
+        let grid = Grid::uniform(superposition.get_view(), 7);
+        let samples = superposition.sample_evolution(&grid, &times);
+        assert_eq!(superposition.get_time(), 0.0);
+        superposition.set_time(times[1]);
+        for p in &samples[1] {
+            assert!((p.y - superposition.eval(p.x)).norm() < 1e-12);
+        }
+        superposition.set_time(0.0);
+
+        let frames = render_frames(&mut superposition, &times, width, height);
+        assert_eq!(frames.len(), times.len());
+        let pixel = |frame: &[u8], row: usize, column: usize| {
+            let i = 3 * (row * width + column);
+            [frame[i], frame[i + 1], frame[i + 2]]
+        };
+        for frame in &frames {
+            assert_eq!(frame.len(), 3 * width * height);
+            // |psi|^2 is filled from the bottom next to the minimum, the walls of the well are drawn
+            assert_eq!(pixel(frame, height - 1, width / 2 + width / 8), PROBABILITY);
+            assert!((0..height).any(|row| pixel(frame, row, 0) == POTENTIAL));
+        }
+        // the packet sloshes from one side to the other
+        assert_ne!(frames[0], frames[2]);
+
+        assert!(write_animation(&mut superposition, &times, 25, Path::new("psi.png")).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::*;
+use ndarray::Array1;
+
+// Sampled grids as ndarray arrays, enabled with the ndarray feature.
+pub struct SampledGrid {
+    pub x: Array1<f64>,
+    pub psi: Array1<Complex64>,
+    pub probability: Array1<f64>,
+}
+
+pub fn x_grid(view: (f64, f64), n: usize) -> Array1<f64> {
+    Array1::from_vec(Grid::uniform(view, n).points())
+}
+
+pub fn sample(func: &dyn Func<f64, Complex64>, view: (f64, f64), n: usize) -> SampledGrid {
+    let x = x_grid(view, n);
+    let psi: Vec<Complex64> = x
+        .as_slice()
+        .unwrap()
+        .par_iter()
+        .map(|x| func.eval(*x))
+        .collect();
+    let psi = Array1::from_vec(psi);
+    let probability = psi.mapv(|c| c.norm_sqr());
+
+    SampledGrid {
+        x,
+        psi,
+        probability,
+    }
+}
+
+pub fn sample_wave_function(wave_function: &WaveFunction, n: usize) -> SampledGrid {
+    sample(wave_function, wave_function.get_view(), n)
+}
+
+pub fn sample_superposition(wave_function: &Superposition, n: usize) -> SampledGrid {
+    sample(wave_function, wave_function.get_view(), n)
+}
+
+// (x, y) columns of points from the other helpers
+pub fn from_points(values: &[Point<f64, f64>]) -> (Array1<f64>, Array1<f64>) {
+    (
+        values.iter().map(|p| p.x).collect(),
+        values.iter().map(|p| p.y).collect(),
+    )
+}
+
+pub fn from_complex_points(values: &[Point<f64, Complex64>]) -> (Array1<f64>, Array1<Complex64>) {
+    (
+        values.iter().map(|p| p.x).collect(),
+        values.iter().map(|p| p.y).collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sampled_gaussian() {
+        let gaussian = Function::<f64, Complex64>::new(|x| (-x * x).exp().into());
+        let grid = sample(&gaussian, (-2.0, 2.0), 5);
+
+        assert_eq!(grid.x, ndarray::arr1(&[-2.0, -1.0, 0.0, 1.0, 2.0]));
+        for (x, (psi, probability)) in grid
+            .x
+            .iter()
+            .zip(grid.psi.iter().zip(grid.probability.iter()))
+        {
+            assert!(complex_compare(*psi, (-x * x).exp().into(), 1e-12));
+            assert!(float_compare(*probability, (-2.0 * x * x).exp(), 1e-12));
+        }
+    }
+}
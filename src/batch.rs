@@ -0,0 +1,361 @@
+use crate::manifest::{path_safe, RunManifest, MANIFEST_FILE};
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// directory in the output directory with a marker per finished job
+pub const COMPLETED_DIR: &str = ".completed";
+
+#[derive(Clone)]
+pub struct Job {
+    pub potential_name: String,
+    pub potential: SharedPotential,
+    pub mass: f64,
+    pub n_energy: usize,
+}
+
+impl Job {
+    pub fn new(
+        potential_name: &str,
+        potential: SharedPotential,
+        mass: f64,
+        n_energy: usize,
+    ) -> Job {
+        Job {
+            potential_name: potential_name.to_string(),
+            potential,
+            mass,
+            n_energy,
+        }
+    }
+
+    pub fn for_energies(
+        potential_name: &str,
+        potential: SharedPotential,
+        mass: f64,
+        n_energies: &[usize],
+    ) -> Vec<Job> {
+        n_energies
+            .iter()
+            .map(|n| Job::new(potential_name, potential.clone(), mass, *n))
+            .collect()
+    }
+
+    fn cache_key(&self) -> (String, u64) {
+        (self.potential_name.clone(), self.mass.to_bits())
+    }
+
+    // the run directories have the start time in their name, the marker leads to the one of the job
+    fn marker(&self, output_dir: &Path) -> PathBuf {
+        output_dir.join(COMPLETED_DIR).join(format!(
+            "{}_n{}_m{}.json",
+            path_safe(&self.potential_name),
+            self.n_energy,
+            path_safe(&self.mass.to_string())
+        ))
+    }
+
+    fn config_hash(&self, output_dir: &Path) -> String {
+        RunManifest::new(
+            &self.potential_name,
+            self.mass,
+            &[self.n_energy],
+            output_dir,
+        )
+        .config_hash()
+    }
+
+    // Run directory of an earlier run of the job with the same configuration hash, none if it was
+    // interrupted, any of its outputs is missing or its manifest or any of the files listed in it.
+    pub fn completed_run(&self, output_dir: &Path) -> Option<PathBuf> {
+        let marker: Completion =
+            serde_json::from_str(&std::fs::read_to_string(self.marker(output_dir)).ok()?).ok()?;
+        let config_hash = self.config_hash(output_dir);
+        let outputs_exist = marker
+            .files
+            .iter()
+            .all(|file| marker.run_dir.join(file).exists());
+        if marker.config_hash != config_hash || !outputs_exist {
+            return None;
+        }
+        if WRITE_MANIFEST {
+            let manifest: serde_json::Value = serde_json::from_str(
+                &std::fs::read_to_string(marker.run_dir.join(MANIFEST_FILE)).ok()?,
+            )
+            .ok()?;
+            let files_exist = manifest["files"].as_array()?.iter().all(|file| {
+                file.as_str()
+                    .is_some_and(|f| marker.run_dir.join(f).exists())
+            });
+            if manifest["config_hash"] != config_hash.as_str() || !files_exist {
+                return None;
+            }
+        }
+        Some(marker.run_dir)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Completion {
+    config_hash: String,
+    run_dir: PathBuf,
+    // relative to run_dir
+    files: Vec<String>,
+}
+
+// Energies per (potential name, mass), the first k energies are computed in one scan.
+pub struct EnergyCache {
+    energies: Mutex<HashMap<(String, u64), Vec<f64>>>,
+}
+
+impl Default for EnergyCache {
+    fn default() -> EnergyCache {
+        EnergyCache::new()
+    }
+}
+
+impl EnergyCache {
+    pub fn new() -> EnergyCache {
+        EnergyCache {
+            energies: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cached(&self, key: &(String, u64), n: usize) -> Option<f64> {
+        self.energies
+            .lock()
+            .unwrap()
+            .get(key)
+            .and_then(|energies| energies.get(n).copied())
+    }
+
+    pub fn nth_energy(&self, job: &Job, approx_inf: (f64, f64)) -> Result<f64, String> {
+        let key = job.cache_key();
+        if let Some(energy) = self.cached(&key, job.n_energy) {
+            return Ok(energy);
+        }
+
+        let potential = |x| (job.potential)(x);
+        let energies = energy::first_energies(
+            job.n_energy + 1,
+            job.mass,
+            UNITS.hbar,
+            &potential,
+            approx_inf,
+        )
+        .map_err(|err| format!("{}: {}", job.potential_name, err))?;
+        let energy = energies[job.n_energy];
+
+        let mut cache = self.energies.lock().unwrap();
+        let entry = cache.entry(key).or_default();
+        if entry.len() < energies.len() {
+            *entry = energies;
+        }
+        Ok(energy)
+    }
+
+    // computes the energies of all jobs with one scan per potential and mass, the jobs of a scan
+    // that fails get its error from nth_energy again
+    pub fn fill(&self, jobs: &[Job], approx_inf: (f64, f64)) {
+        let mut highest: HashMap<(String, u64), &Job> = HashMap::new();
+        for job in jobs {
+            let entry = highest.entry(job.cache_key()).or_insert(job);
+            if entry.n_energy < job.n_energy {
+                *entry = job;
+            }
+        }
+
+        highest
+            .values()
+            .collect::<Vec<&&Job>>()
+            .par_iter()
+            .for_each(|job| {
+                let _ = self.nth_energy(job, approx_inf);
+            });
+    }
+}
+
+// fails without creating the run directory if the energy or the wave function can't be computed
+pub fn run_job(job: &Job, cache: &EnergyCache, output_dir: &Path) -> Result<RunManifest, String> {
+    let energy = cache.nth_energy(job, APPROX_INF)?;
+    let wave_function = WaveFunction::from_energy(
+        job.potential.clone(),
+        job.mass,
+        energy,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::Renormalize(1.0.into()),
+    )?;
+
+    let mut manifest = RunManifest::new(&job.potential_name, job.mass, &[job.n_energy], output_dir)
+        .in_run_dir(RUN_DIR_TEMPLATE);
+
+    plot::plot_wavefunction(
+        &wave_function,
+        manifest.get_output_dir(),
+        &manifest.resolve_template(OUTPUT_FILE_TEMPLATE),
+    );
+
+    let files = if WRITE_MANIFEST {
+        if ESTIMATE_ACCURACY {
+            manifest.add_accuracy(check::accuracy_summary(&wave_function, job.n_energy));
+        }
+        manifest.write();
+        manifest.files.clone()
+    } else {
+        plot::take_created_files(manifest.get_output_dir())
+            .into_iter()
+            .collect()
+    };
+
+    // written last, an interrupted job has none
+    let completion = Completion {
+        config_hash: manifest.config_hash(),
+        run_dir: manifest.get_output_dir().to_path_buf(),
+        files,
+    };
+    let marker = job.marker(output_dir);
+    std::fs::create_dir_all(marker.parent().unwrap()).unwrap();
+    File::create(marker)
+        .unwrap()
+        .write_all(
+            serde_json::to_string_pretty(&completion)
+                .unwrap()
+                .as_bytes(),
+        )
+        .unwrap();
+    Ok(manifest)
+}
+
+// With resume the jobs finished by an earlier run with the same configuration are skipped, only
+// the manifests of the jobs that ran are returned, or why they failed. A failed job doesn't stop the
+// others and runs again on resume.
+pub fn run_batch(
+    jobs: &[Job],
+    output_dir: &Path,
+    resume: bool,
+) -> Vec<Result<RunManifest, String>> {
+    let jobs: Vec<Job> = jobs
+        .iter()
+        .filter(|job| {
+            let finished = if resume {
+                job.completed_run(output_dir)
+            } else {
+                None
+            };
+            if let Some(run_dir) = &finished {
+                println!(
+                    "Skipped {} (n = {}, mass = {}), finished in {}",
+                    job.potential_name,
+                    job.n_energy,
+                    job.mass,
+                    run_dir.display()
+                );
+            }
+            finished.is_none()
+        })
+        .cloned()
+        .collect();
+    let cache = EnergyCache::new();
+    cache.fill(&jobs, APPROX_INF);
+
+    jobs.par_iter()
+        .map(|job| {
+            let result = run_job(job, &cache, output_dir);
+            match &result {
+                Ok(manifest) => println!(
+                    "Finished {} (n = {}, mass = {}) in {}",
+                    job.potential_name,
+                    job.n_energy,
+                    job.mass,
+                    manifest.get_output_dir().display()
+                ),
+                Err(err) => eprintln!(
+                    "Failed {} (n = {}, mass = {}): {}",
+                    job.potential_name, job.n_energy, job.mass, err
+                ),
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache_reuses_lower_energies() {
+        let cache = EnergyCache::new();
+        let job = Job::new("square", Arc::new(potentials::square), 1.0, 3);
+        let energy = cache.nth_energy(&job, APPROX_INF).unwrap();
+
+        let key = job.cache_key();
+        assert_eq!(cache.cached(&key, 3), Some(energy));
+        for n in 0..3 {
+            assert!(cache.cached(&key, n).unwrap() < energy);
+        }
+        assert_eq!(cache.cached(&key, 4), None);
+        assert_eq!(
+            cache.nth_energy(
+                &Job::new("square", Arc::new(potentials::square), 1.0, 1),
+                APPROX_INF
+            ),
+            Ok(cache.cached(&key, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn resume_skips_finished_jobs() {
+        let output_dir = std::env::temp_dir().join("schroedinger_resume_test");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        let jobs = [Job::new("square", Arc::new(potentials::square), 1.0, 0)];
+
+        let first = run_batch(&jobs, &output_dir, true);
+        assert_eq!(first.len(), 1);
+        let first = first[0].as_ref().unwrap();
+        let run_dir = first.get_output_dir().to_path_buf();
+        assert_eq!(jobs[0].completed_run(&output_dir), Some(run_dir.clone()));
+        assert!(run_batch(&jobs, &output_dir, true).is_empty());
+
+        // a missing output is computed again
+        std::fs::remove_file(run_dir.join(first.resolve_template(OUTPUT_FILE_TEMPLATE))).unwrap();
+        assert_eq!(jobs[0].completed_run(&output_dir), None);
+        assert_eq!(run_batch(&jobs, &output_dir, true).len(), 1);
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn marker_stays_in_the_completed_dir() {
+        let output_dir = Path::new("output");
+        let job = Job::new("../a/b", Arc::new(potentials::square), 1.5, 2);
+        // the dots are kept, without the slashes they are just part of the name
+        assert_eq!(
+            job.marker(output_dir),
+            output_dir.join(COMPLETED_DIR).join(".._a_b_n2_m1.5.json")
+        );
+    }
+
+    #[test]
+    fn failed_job_doesnt_stop_the_batch() {
+        let output_dir = std::env::temp_dir().join("schroedinger_failed_job_test");
+        let _ = std::fs::remove_dir_all(&output_dir);
+        // a finite well with a single bound state
+        let well: SharedPotential = Arc::new(|x: f64| if x.abs() < 1.0 { 0.0 } else { 1.0 });
+        let jobs = [
+            Job::new("well", well, 1.0, 5),
+            Job::new("square", Arc::new(potentials::square), 1.0, 0),
+        ];
+
+        let results = run_batch(&jobs, &output_dir, true);
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok());
+        // nothing is marked as finished, resume runs it again
+        assert_eq!(jobs[0].completed_run(&output_dir), None);
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+use crate::*;
+
+// Bohmian mechanics on samples of psi on a uniform grid, e.g. from Superposition::sample_evolution.
+// Derivatives are central differences of neighbouring samples, so the grid has to resolve psi
+// well, especially close to its nodes.
+
+// Q(x) = -hbar^2 / (2m) |psi|'' / |psi| at the inner samples, not finite at the nodes of psi, none
+// without inner samples
+pub fn quantum_potential(
+    samples: &[Point<f64, Complex64>],
+    mass: f64,
+    hbar: f64,
+) -> Vec<Point<f64, f64>> {
+    if samples.len() < 3 {
+        return vec![];
+    }
+    let dx = samples[1].x - samples[0].x;
+    samples
+        .windows(3)
+        .map(|w| {
+            let (left, r, right) = (w[0].y.norm(), w[1].y.norm(), w[2].y.norm());
+            Point {
+                x: w[1].x,
+                y: -hbar.powi(2) / (2.0 * mass) * (left - 2.0 * r + right) / (dx * dx * r),
+            }
+        })
+        .collect()
+}
+
+// v(x) = hbar / m Im(psi' / psi) at every sample, 0 at the ends and where psi vanishes, none if a
+// single sample has no spacing
+pub fn velocity_field(
+    samples: &[Point<f64, Complex64>],
+    mass: f64,
+    hbar: f64,
+) -> Vec<Point<f64, f64>> {
+    if samples.len() < 2 {
+        return vec![];
+    }
+    let dx = samples[1].x - samples[0].x;
+    let last = samples.len() - 1;
+    (0..samples.len())
+        .map(|i| {
+            let psi = samples[i].y;
+            let velocity = if i == 0 || i == last || psi.norm_sqr() == 0.0 {
+                0.0
+            } else {
+                let derivative = (samples[i + 1].y - samples[i - 1].y) / (2.0 * dx);
+                hbar / mass * (psi.conj() * derivative).im / psi.norm_sqr()
+            };
+            Point {
+                x: samples[i].x,
+                y: velocity,
+            }
+        })
+        .collect()
+}
+
+// linear between the samples, 0 outside of them
+fn interpolate(field: &[Point<f64, f64>], x: f64) -> f64 {
+    if field.len() < 2 {
+        return 0.0;
+    }
+    let position = (x - field[0].x) / (field[1].x - field[0].x);
+    if !(0.0..=(field.len() - 1) as f64).contains(&position) {
+        return 0.0;
+    }
+    let i = (position.floor() as usize).min(field.len() - 2);
+    let t = position - i as f64;
+    (1.0 - t) * field[i].y + t * field[i + 1].y
+}
+
+// positions at the quantiles (k + 1/2) / count of |psi|^2, distributed like Bohmian particles, all
+// of them at a single sample
+pub fn initial_positions(samples: &[Point<f64, Complex64>], count: usize) -> Vec<f64> {
+    if samples.len() < 2 {
+        return samples.iter().map(|p| p.x).cycle().take(count).collect();
+    }
+    let probability: Vec<Point<f64, f64>> = samples
+        .iter()
+        .map(|p| Point {
+            x: p.x,
+            y: p.y.norm_sqr(),
+        })
+        .collect();
+    let cumulative = cumulative_integral(&probability);
+    let total = cumulative[cumulative.len() - 1];
+
+    (0..count)
+        .map(|k| {
+            let target = (k as f64 + 0.5) / count as f64 * total;
+            inverse_cumulative(&probability, &cumulative, target)
+        })
+        .collect()
+}
+
+// positions[i][k] is x of the kth trajectory at times[i]
+#[derive(Clone, Debug)]
+pub struct Trajectories {
+    pub times: Vec<f64>,
+    pub positions: Vec<Vec<f64>>,
+}
+
+// Heun steps of dx/dt = v(x, t) from one frame to the next, frames[i] are the samples of psi at
+// times[i]. The times are the steps, they have to be short compared to the periods of psi.
+pub fn integrate_trajectories(
+    frames: &[Vec<Point<f64, Complex64>>],
+    times: &[f64],
+    mass: f64,
+    hbar: f64,
+    starts: &[f64],
+) -> Trajectories {
+    let fields: Vec<Vec<Point<f64, f64>>> = frames
+        .par_iter()
+        .map(|frame| velocity_field(frame, mass, hbar))
+        .collect();
+
+    let mut positions = vec![starts.to_vec()];
+    for i in 1..times.len() {
+        let dt = times[i] - times[i - 1];
+        let next = positions[i - 1]
+            .iter()
+            .map(|x| {
+                let k1 = interpolate(&fields[i - 1], *x);
+                let k2 = interpolate(&fields[i], x + dt * k1);
+                x + dt * (k1 + k2) / 2.0
+            })
+            .collect();
+        positions.push(next);
+    }
+    Trajectories {
+        times: times.to_vec(),
+        positions,
+    }
+}
+
+// count trajectories from the quantiles of |psi|^2 at times[0], psi sampled at n points over the
+// view of the superposition and moved with its mass
+pub fn trajectories(
+    superposition: &mut Superposition,
+    times: &[f64],
+    n: usize,
+    count: usize,
+) -> Trajectories {
+    let grid = Grid::uniform(superposition.get_view(), n);
+    let frames = superposition.sample_evolution(&grid, times);
+    let starts = initial_positions(&frames[0], count);
+    integrate_trajectories(
+        &frames,
+        times,
+        superposition.get_mass(),
+        superposition.get_hbar(),
+        &starts,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quantum_potential_of_gaussian() {
+        // |psi| = e^(-x^2 / 2), |psi|'' / |psi| = x^2 - 1
+        let grid = Grid::uniform((-6.0, 6.0), 1201);
+        let samples: Vec<Point<f64, Complex64>> = grid
+            .points()
+            .iter()
+            .map(|x| Point {
+                x: *x,
+                y: complex(0.0, (-x * x / 2.0).exp()),
+            })
+            .collect();
+        let hbar = 0.5;
+        for p in quantum_potential(&samples, 2.0, hbar) {
+            if p.x.abs() < 3.0 {
+                let exact = -hbar * hbar / 4.0 * (p.x * p.x - 1.0);
+                assert!((p.y - exact).abs() < 1e-4, "{} {}", p.x, p.y);
+            }
+        }
+        // a real state doesn't move
+        assert!(velocity_field(&samples, 2.0, hbar)
+            .iter()
+            .all(|p| p.y == 0.0));
+        assert!(quantum_potential(&samples[..2], 2.0, hbar).is_empty());
+        assert!(velocity_field(&samples[..1], 2.0, hbar).is_empty());
+    }
+
+    #[test]
+    fn positions_skip_empty_intervals() {
+        // |psi|^2 underflows to 0 between two bumps
+        let samples: Vec<Point<f64, Complex64>> = Grid::uniform((-2.0, 2.0), 401)
+            .points()
+            .iter()
+            .map(|x| Point {
+                x: *x,
+                y: complex(if x.abs() > 1.0 { 1.0 } else { 0.0 }, 0.0),
+            })
+            .collect();
+        let positions = initial_positions(&samples, 10);
+        assert!(positions.iter().all(|x| x.is_finite() && x.abs() >= 0.99));
+        assert!(positions.windows(2).all(|w| w[0] <= w[1]));
+
+        // no steps of any width if psi vanishes everywhere
+        let vanishing: Vec<Point<f64, Complex64>> = samples
+            .iter()
+            .map(|p| Point {
+                x: p.x,
+                y: complex(0.0, 0.0),
+            })
+            .collect();
+        assert!(initial_positions(&vanishing, 3)
+            .iter()
+            .all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn trajectories_follow_density() {
+        // the lowest two states of V = x^2 for m = 1, exact eigenstates keep the particles at the
+        // quantiles of |psi|^2, the WKB ones only roughly
+        let omega = 2.0f64.sqrt();
+        let hbar = 1.0;
+        let grid = Grid::uniform((-6.0, 6.0), 1001);
+        let frame = |t: f64| -> Vec<Point<f64, Complex64>> {
+            grid.points()
+                .iter()
+                .map(|x| {
+                    let ground = (-omega * x * x / (2.0 * hbar)).exp();
+                    // with the sign the phase convention gives the WKB state
+                    let excited = -(2.0 * omega / hbar).sqrt() * x * ground;
+                    Point {
+                        x: *x,
+                        y: ground * Complex64::from_polar(1.0, -omega * t / 2.0)
+                            + excited * Complex64::from_polar(1.0, -1.5 * omega * t),
+                    }
+                })
+                .collect()
+        };
+        let times: Vec<f64> = (0..=300).map(|i| i as f64 * 0.01).collect();
+        let frames: Vec<Vec<Point<f64, Complex64>>> = times.iter().map(|t| frame(*t)).collect();
+        let starts = initial_positions(&frames[0], 9);
+        let exact = integrate_trajectories(&frames, &times, 1.0, hbar, &starts);
+        assert_eq!(exact.positions.len(), times.len());
+
+        // the particles stay at the quantiles of |psi|^2 and never cross
+        let quantiles = initial_positions(&frames[frames.len() - 1], 9);
+        let end = &exact.positions[times.len() - 1];
+        for (x, quantile) in end.iter().zip(quantiles.iter()) {
+            assert!((x - quantile).abs() < 0.02, "{} != {}", x, quantile);
+        }
+        assert!(end.windows(2).all(|w| w[0] < w[1]));
+        assert!((end[4] - exact.positions[0][4]).abs() > 0.1);
+
+        let mut superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (1, complex(1.0, 0.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let approximate = trajectories(&mut superposition, &times, 1001, 9);
+        let end = &approximate.positions[times.len() - 1];
+        assert!(end.windows(2).all(|w| w[0] < w[1]));
+        for (x, exact) in end.iter().zip(exact.positions[times.len() - 1].iter()) {
+            assert!((x - exact).abs() < 0.3, "{} != {}", x, exact);
+        }
+    }
+}
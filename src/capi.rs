@@ -0,0 +1,237 @@
+use crate::*;
+use std::os::raw::{c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// C interface of the solver, build.rs generates the header schroedinger_approx.h into OUT_DIR.
+// None of the functions unwind into C, a panic is reported as NULL or a non zero return value.
+
+pub type SchroedingerPotential = extern "C" fn(x: f64, user_data: *mut c_void) -> f64;
+
+#[derive(Clone, Copy)]
+struct Callback {
+    potential: SchroedingerPotential,
+    user_data: *mut c_void,
+}
+
+// The potential is evaluated from multiple threads, the caller has to make sure the callback
+// and user_data can be used concurrently.
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    fn call(&self, x: f64) -> f64 {
+        (self.potential)(x, self.user_data)
+    }
+}
+
+pub struct SchroedingerWaveFunction {
+    wave_function: WaveFunction,
+}
+
+/// Returns NULL if potential is NULL or the wave function could not be computed.
+/// The potential is evaluated from multiple threads at once.
+#[no_mangle]
+pub extern "C" fn schroedinger_wave_function_new(
+    potential: Option<SchroedingerPotential>,
+    user_data: *mut c_void,
+    mass: f64,
+    n_energy: usize,
+    approx_inf_lower: f64,
+    approx_inf_upper: f64,
+    view_factor: f64,
+    renormalize: bool,
+) -> *mut SchroedingerWaveFunction {
+    // a function pointer can't be NULL in Rust, the Option is where C may pass one
+    let potential = match potential {
+        Some(potential) => potential,
+        None => return std::ptr::null_mut(),
+    };
+    let callback = Callback {
+        potential,
+        user_data,
+    };
+    let potential: SharedPotential = Arc::new(move |x: f64| callback.call(x));
+
+    let scaling = if renormalize {
+        ScalingType::Renormalize(1.0.into())
+    } else {
+        ScalingType::None
+    };
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        WaveFunction::new(
+            potential,
+            mass,
+            n_energy,
+            (approx_inf_lower, approx_inf_upper),
+            view_factor,
+            scaling,
+        )
+    }));
+
+    match result {
+        Ok(wave_function) => Box::into_raw(Box::new(SchroedingerWaveFunction { wave_function })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// wave_function has to be NULL or returned by schroedinger_wave_function_new and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn schroedinger_wave_function_free(
+    wave_function: *mut SchroedingerWaveFunction,
+) {
+    if !wave_function.is_null() {
+        drop(Box::from_raw(wave_function));
+    }
+}
+
+/// # Safety
+/// wave_function has to be a live pointer returned by schroedinger_wave_function_new.
+#[no_mangle]
+pub unsafe extern "C" fn schroedinger_wave_function_energy(
+    wave_function: *const SchroedingerWaveFunction,
+) -> f64 {
+    (*wave_function).wave_function.get_energy()
+}
+
+/// # Safety
+/// wave_function has to be a live pointer returned by schroedinger_wave_function_new, lower and
+/// upper have to be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn schroedinger_wave_function_view(
+    wave_function: *const SchroedingerWaveFunction,
+    lower: *mut f64,
+    upper: *mut f64,
+) {
+    let view = (*wave_function).wave_function.get_view();
+    *lower = view.0;
+    *upper = view.1;
+}
+
+/// Returns 0 on success and 1 if evaluating the wave function panicked. Outside of its domain psi
+/// is continued by its outermost part, see WaveFunction::domain.
+///
+/// # Safety
+/// wave_function has to be a live pointer returned by schroedinger_wave_function_new, re and im
+/// have to be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn schroedinger_wave_function_eval(
+    wave_function: *const SchroedingerWaveFunction,
+    x: f64,
+    re: *mut f64,
+    im: *mut f64,
+) -> c_int {
+    let wave_function = &(*wave_function).wave_function;
+    match catch_unwind(AssertUnwindSafe(|| wave_function.eval(x))) {
+        Ok(psi) => {
+            *re = psi.re;
+            *im = psi.im;
+            0
+        }
+        Err(_) => 1,
+    }
+}
+
+/// Samples n equally spaced points in [a, b] into the arrays x, re and im of length n.
+/// Returns 0 on success, 1 if the wave function can't be evaluated and 2 if a == b, the arrays
+/// aren't written then.
+///
+/// # Safety
+/// wave_function has to be a live pointer returned by schroedinger_wave_function_new, x, re and im
+/// have to be valid for writes of n values each.
+#[no_mangle]
+pub unsafe extern "C" fn schroedinger_wave_function_sample(
+    wave_function: *const SchroedingerWaveFunction,
+    a: f64,
+    b: f64,
+    n: usize,
+    x: *mut f64,
+    re: *mut f64,
+    im: *mut f64,
+) -> c_int {
+    let wave_function = &(*wave_function).wave_function;
+    let values = match catch_unwind(AssertUnwindSafe(|| {
+        evaluate_function_between(wave_function, a, b, n)
+    })) {
+        Ok(values) => values,
+        Err(_) => return 1,
+    };
+    // evaluate_function_between has no points for an empty interval
+    if values.len() != n {
+        return 2;
+    }
+
+    let x = std::slice::from_raw_parts_mut(x, n);
+    let re = std::slice::from_raw_parts_mut(re, n);
+    let im = std::slice::from_raw_parts_mut(im, n);
+    for (i, p) in values.iter().enumerate() {
+        x[i] = p.x;
+        re[i] = p.y.re;
+        im[i] = p.y.im;
+    }
+    0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    extern "C" fn square(x: f64, user_data: *mut c_void) -> f64 {
+        let factor = unsafe { *(user_data as *const f64) };
+        factor * x * x
+    }
+
+    #[test]
+    fn callback_passes_user_data() {
+        let mut factor = 3.0;
+        let callback = Callback {
+            potential: square,
+            user_data: &mut factor as *mut f64 as *mut c_void,
+        };
+        assert_eq!(callback.call(2.0), 12.0);
+    }
+
+    #[test]
+    fn null_potential() {
+        let wave_function = schroedinger_wave_function_new(
+            None,
+            std::ptr::null_mut(),
+            1.0,
+            0,
+            APPROX_INF.0,
+            APPROX_INF.1,
+            VIEW_FACTOR,
+            false,
+        );
+        assert!(wave_function.is_null());
+    }
+
+    #[test]
+    fn empty_sample_range() {
+        let wave_function = SchroedingerWaveFunction {
+            wave_function: WaveFunction::new(
+                Arc::new(potentials::square),
+                1.0,
+                0,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::None,
+            ),
+        };
+        let (mut x, mut re, mut im) = ([f64::NAN; 3], [f64::NAN; 3], [f64::NAN; 3]);
+        let status = unsafe {
+            schroedinger_wave_function_sample(
+                &wave_function,
+                1.0,
+                1.0,
+                3,
+                x.as_mut_ptr(),
+                re.as_mut_ptr(),
+                im.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, 2);
+        assert!(x.iter().chain(&re).chain(&im).all(|v| v.is_nan()));
+    }
+}
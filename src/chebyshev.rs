@@ -0,0 +1,460 @@
+use crate::newtons_method::{newtons_method_max_iters, Tolerance};
+use crate::*;
+use nalgebra::DMatrix;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+const MIN_DEGREE: usize = 16;
+const MAX_DEGREE: usize = 1024;
+// pieces that don't converge at MAX_DEGREE are halved at most this often
+const MAX_SPLITS: usize = 16;
+// pieces of higher degree are halved before their roots are taken, the eigenvalues of the
+// colleague matrix cost the cube of the degree
+const MAX_ROOT_DEGREE: usize = 64;
+// eigenvalues of the colleague matrix further than this off the real axis or outside of [-1, 1]
+// aren't roots, double roots split into pairs about sqrt(epsilon) apart
+const ROOT_CUTOFF: f64 = 1e-6;
+// samples find_real_roots takes the scale of f from
+const SCALE_SAMPLES: usize = 1000;
+// newtons method polishing the roots of the fit on f
+const POLISH_ITERS: usize = 50;
+
+// Chebyshev series on one interval, real and imaginary parts stored separately so it serializes
+// without complex number support.
+#[derive(Serialize, Clone, Debug)]
+pub struct ChebyshevPiece {
+    pub range: (f64, f64),
+    re: Vec<f64>,
+    im: Vec<f64>,
+}
+
+impl ChebyshevPiece {
+    fn new(range: (f64, f64), coefficients: &[Complex64]) -> ChebyshevPiece {
+        ChebyshevPiece {
+            range,
+            re: coefficients.iter().map(|c| c.re).collect(),
+            im: coefficients.iter().map(|c| c.im).collect(),
+        }
+    }
+
+    // interpolates func at n Chebyshev points of the first kind
+    fn interpolate(func: &dyn Func<f64, Complex64>, range: (f64, f64), n: usize) -> Vec<Complex64> {
+        let angle = |k: usize| f64::consts::PI * (k as f64 + 0.5) / n as f64;
+        let values: Vec<Complex64> = (0..n)
+            .into_par_iter()
+            .map(|k| {
+                let t = angle(k).cos();
+                func.eval((range.0 + range.1) / 2.0 + t * (range.1 - range.0) / 2.0)
+            })
+            .collect();
+
+        (0..n)
+            .map(|j| {
+                let c: Complex64 = (0..n)
+                    .map(|k| values[k] * (j as f64 * angle(k)).cos())
+                    .sum::<Complex64>()
+                    * (2.0 / n as f64);
+                if j == 0 {
+                    c / 2.0
+                } else {
+                    c
+                }
+            })
+            .collect()
+    }
+
+    // None if the last quarter of the coefficients hasn't decayed below tolerance or the fit is off
+    fn fit(
+        func: &dyn Func<f64, Complex64>,
+        range: (f64, f64),
+        n: usize,
+        tolerance: f64,
+    ) -> Option<ChebyshevPiece> {
+        let mut coefficients = ChebyshevPiece::interpolate(func, range, n);
+        let tail = coefficients[3 * n / 4..]
+            .iter()
+            .map(|c| c.norm())
+            .fold(0.0, f64::max);
+        if tail.partial_cmp(&tolerance) != Some(Ordering::Less) {
+            return None;
+        }
+
+        trim(&mut coefficients, tolerance);
+        let piece = ChebyshevPiece::new(range, &coefficients);
+
+        // decaying coefficients don't guarantee convergence if func has a kink, so the fit is
+        // also checked between the interpolation points, not at the ends where func may jump
+        let accurate = (1..n).into_par_iter().all(|k| {
+            let t = (f64::consts::PI * k as f64 / n as f64).cos();
+            let x = (range.0 + range.1) / 2.0 + t * (range.1 - range.0) / 2.0;
+            (piece.eval(x) - func.eval(x)).norm() < tolerance
+        });
+        if accurate {
+            Some(piece)
+        } else {
+            None
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.re.len() - 1
+    }
+
+    // from the coefficients by d_(k - 1) = d_(k + 1) + 2 k c_k, with d_0 halved
+    fn derivative(&self) -> ChebyshevPiece {
+        let n = self.re.len();
+        let mut d = vec![complex(0.0, 0.0); n + 1];
+        for k in (1..n).rev() {
+            d[k - 1] = d[k + 1] + 2.0 * k as f64 * complex(self.re[k], self.im[k]);
+        }
+        d[0] /= 2.0;
+        d.truncate((n - 1).max(1));
+        let scale = 2.0 / (self.range.1 - self.range.0);
+        let coefficients = d.iter().map(|c| c * scale).collect::<Vec<Complex64>>();
+        ChebyshevPiece::new(self.range, &coefficients)
+    }
+
+    // real roots of the real part in the range, the eigenvalues of its colleague matrix
+    fn real_roots(&self, tolerance: f64, splits: usize) -> Vec<f64> {
+        if self.degree() > MAX_ROOT_DEGREE && splits < MAX_SPLITS {
+            let middle = (self.range.0 + self.range.1) / 2.0;
+            return [(self.range.0, middle), (middle, self.range.1)]
+                .iter()
+                .flat_map(|range| {
+                    let mut coefficients = ChebyshevPiece::interpolate(self, *range, self.re.len());
+                    trim(&mut coefficients, tolerance);
+                    ChebyshevPiece::new(*range, &coefficients).real_roots(tolerance, splits + 1)
+                })
+                .collect();
+        }
+
+        let mut c = self.re.clone();
+        let mut dropped = 0.0;
+        while c.len() > 1 && dropped + c.last().unwrap().abs() < tolerance {
+            dropped += c.pop().unwrap().abs();
+        }
+        let n = c.len() - 1;
+        let ts = match n {
+            0 => vec![],
+            1 => vec![-c[0] / c[1]],
+            _ => {
+                // x T_0 = T_1, x T_k = (T_(k - 1) + T_(k + 1)) / 2 and T_n = -sum_j c_j / c_n T_j
+                let mut colleague = DMatrix::<f64>::zeros(n, n);
+                colleague[(0, 1)] = 1.0;
+                for k in 1..n {
+                    colleague[(k, k - 1)] = 0.5;
+                    if k + 1 < n {
+                        colleague[(k, k + 1)] = 0.5;
+                    }
+                }
+                for j in 0..n {
+                    colleague[(n - 1, j)] -= c[j] / (2.0 * c[n]);
+                }
+                colleague
+                    .complex_eigenvalues()
+                    .iter()
+                    .filter(|z| z.im.abs() < ROOT_CUTOFF)
+                    .map(|z| z.re)
+                    .collect()
+            }
+        };
+        ts.iter()
+            .filter(|t| t.abs() <= 1.0 + ROOT_CUTOFF)
+            .map(|t| {
+                (self.range.0 + self.range.1) / 2.0
+                    + t.clamp(-1.0, 1.0) * (self.range.1 - self.range.0) / 2.0
+            })
+            .collect()
+    }
+}
+
+// drops trailing coefficients as long as their sum stays below the tolerance
+fn trim(coefficients: &mut Vec<Complex64>, tolerance: f64) {
+    let mut dropped = 0.0;
+    while coefficients.len() > 1 && dropped + coefficients.last().unwrap().norm() < tolerance {
+        dropped += coefficients.pop().unwrap().norm();
+    }
+}
+
+impl Func<f64, Complex64> for ChebyshevPiece {
+    // Clenshaw recurrence
+    fn eval(&self, x: f64) -> Complex64 {
+        let t = (2.0 * x - self.range.0 - self.range.1) / (self.range.1 - self.range.0);
+        let mut b1 = complex(0.0, 0.0);
+        let mut b2 = complex(0.0, 0.0);
+        for j in (1..self.re.len()).rev() {
+            let b = complex(self.re[j], self.im[j]) + 2.0 * t * b1 - b2;
+            b2 = b1;
+            b1 = b;
+        }
+        complex(self.re[0], self.im[0]) + t * b1 - b2
+    }
+}
+
+// Piecewise Chebyshev approximation of a function, zero outside of the fitted range.
+#[derive(Serialize, Clone, Debug)]
+pub struct ChebyshevProxy {
+    pieces: Vec<ChebyshevPiece>,
+    // false if some piece didn't converge at MAX_SPLITS
+    #[serde(skip)]
+    converged: bool,
+}
+
+impl ChebyshevProxy {
+    // the function only has to be smooth between breakpoints, tolerance is absolute
+    pub fn fit(
+        func: &dyn Func<f64, Complex64>,
+        breakpoints: &[f64],
+        tolerance: f64,
+    ) -> ChebyshevProxy {
+        let mut converged = true;
+        let pieces = breakpoints
+            .windows(2)
+            .filter(|w| w[1] > w[0])
+            .flat_map(|w| fit_range(func, (w[0], w[1]), tolerance, 0, &mut converged))
+            .collect();
+        ChebyshevProxy { pieces, converged }
+    }
+
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+
+    // the ends of the pieces
+    pub fn breakpoints(&self) -> Vec<f64> {
+        let mut breakpoints = self.pieces.iter().map(|p| p.range.0).collect::<Vec<f64>>();
+        breakpoints.extend(self.pieces.last().map(|p| p.range.1));
+        breakpoints
+    }
+
+    pub fn derivative(&self) -> ChebyshevProxy {
+        ChebyshevProxy {
+            pieces: self.pieces.iter().map(|p| p.derivative()).collect(),
+            converged: self.converged,
+        }
+    }
+
+    // sorted real roots of the real part, those of neighbouring pieces closer than the tolerance
+    // in x are merged
+    pub fn real_roots(&self, tolerance: f64) -> Vec<f64> {
+        let mut roots = self
+            .pieces
+            .par_iter()
+            .flat_map_iter(|piece| piece.real_roots(tolerance, 0))
+            .collect::<Vec<f64>>();
+        roots.sort_by(cmp_f64);
+        roots.dedup_by(|b, a| (*b - *a).abs() < tolerance);
+        roots
+    }
+
+    pub fn get_pieces(&self) -> &[ChebyshevPiece] {
+        &self.pieces
+    }
+
+    pub fn coefficient_count(&self) -> usize {
+        self.pieces.iter().map(|p| p.re.len()).sum()
+    }
+}
+
+fn fit_range(
+    func: &dyn Func<f64, Complex64>,
+    range: (f64, f64),
+    tolerance: f64,
+    splits: usize,
+    converged: &mut bool,
+) -> Vec<ChebyshevPiece> {
+    let mut n = MIN_DEGREE;
+    while n <= MAX_DEGREE {
+        if let Some(piece) = ChebyshevPiece::fit(func, range, n, tolerance) {
+            return vec![piece];
+        }
+        n *= 2;
+    }
+
+    if splits >= MAX_SPLITS {
+        println!(
+            "Chebyshev fit didn't converge between {} and {}",
+            range.0, range.1
+        );
+        *converged = false;
+        let coefficients = ChebyshevPiece::interpolate(func, range, MAX_DEGREE);
+        return vec![ChebyshevPiece::new(range, &coefficients)];
+    }
+    let middle = (range.0 + range.1) / 2.0;
+    let mut pieces = fit_range(func, (range.0, middle), tolerance, splits + 1, converged);
+    pieces.extend(fit_range(
+        func,
+        (middle, range.1),
+        tolerance,
+        splits + 1,
+        converged,
+    ));
+    pieces
+}
+
+impl Func<f64, Complex64> for ChebyshevProxy {
+    fn eval(&self, x: f64) -> Complex64 {
+        let i = self.pieces.partition_point(|p| p.range.1 < x);
+        match self.pieces.get(i) {
+            Some(piece) if piece.range.0 <= x => piece.eval(x),
+            _ => complex(0.0, 0.0),
+        }
+    }
+}
+
+// a real function as the real part of a complex one
+struct RealPart<'a, F: Fn(f64) -> f64 + Sync + ?Sized>(&'a F);
+
+impl<F: Fn(f64) -> f64 + Sync + ?Sized> Func<f64, Complex64> for RealPart<'_, F> {
+    fn eval(&self, x: f64) -> Complex64 {
+        complex((self.0)(x), 0.0)
+    }
+}
+
+// Chebyshev fit of a real function between the breakpoints, accurate to tolerance times its largest
+// |f|. None if f isn't finite, if that is more than |f| at most of the samples, e.g. of 1/r close
+// to its pole, or if the fit doesn't converge, the roots of such functions have to be found one by
+// one.
+pub fn fit_real<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    f: &F,
+    breakpoints: &[f64],
+    tolerance: f64,
+) -> Option<ChebyshevProxy> {
+    let (start, end) = (breakpoints[0], breakpoints[breakpoints.len() - 1]);
+    let mut magnitudes = (0..=SCALE_SAMPLES)
+        .into_par_iter()
+        .map(|i| f(start + (end - start) * i as f64 / SCALE_SAMPLES as f64).abs())
+        .map(|v| if v.is_finite() { v } else { f64::INFINITY })
+        .collect::<Vec<f64>>();
+    magnitudes.sort_by(cmp_f64);
+    let scale = magnitudes[SCALE_SAMPLES];
+    if !scale.is_finite() || magnitudes[SCALE_SAMPLES / 2] < tolerance * scale {
+        return None;
+    }
+
+    let proxy = ChebyshevProxy::fit(
+        &RealPart(f),
+        breakpoints,
+        tolerance * scale.max(f64::MIN_POSITIVE),
+    );
+    if proxy.is_converged() {
+        Some(proxy)
+    } else {
+        None
+    }
+}
+
+// newtons method on f from each root of its fit, a root is kept as it is if newtons method fails
+// or ends up closer to a neighbouring root
+pub fn polish_roots<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    f: &F,
+    roots: &[f64],
+    polish: Tolerance,
+) -> Vec<f64> {
+    let mut polished = (0..roots.len())
+        .into_par_iter()
+        .map(|i| {
+            let left = i
+                .checked_sub(1)
+                .map_or(f64::INFINITY, |j| roots[i] - roots[j]);
+            let right = roots.get(i + 1).map_or(f64::INFINITY, |r| r - roots[i]);
+            newtons_method_max_iters(&|x| f(x), roots[i], polish, POLISH_ITERS)
+                .filter(|z| (z - roots[i]).abs() < left.min(right) / 2.0)
+                .unwrap_or(roots[i])
+        })
+        .collect::<Vec<f64>>();
+    polished.sort_by(cmp_f64);
+    polished.dedup_by(|b, a| (*b - *a).abs() <= polish.x_abs + polish.x_rel * a.abs());
+    polished
+}
+
+// all roots of f in the range at once, None if fit_real fails
+pub fn find_real_roots<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    f: &F,
+    range: (f64, f64),
+    tolerance: f64,
+    polish: Tolerance,
+) -> Option<Vec<f64>> {
+    let proxy = fit_real(f, &[range.0, range.1], tolerance)?;
+    let roots = proxy.real_roots(tolerance * (range.1 - range.0).abs());
+    Some(polish_roots(f, &roots, polish))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn smooth_function_with_jump() {
+        let func = Function::<f64, Complex64>::new(|x: f64| {
+            if x < 1.0 {
+                Complex64::from_polar((-x * x).exp(), 5.0 * x)
+            } else {
+                (3.0 * x).sin().into()
+            }
+        });
+        let proxy = ChebyshevProxy::fit(&func, &[-4.0, 1.0, 6.0], 1e-10);
+
+        assert!(proxy.coefficient_count() < 200);
+        for i in 0..1000 {
+            let x = -4.0 + 10.0 * i as f64 / 999.0;
+            assert!((proxy.eval(x) - func.eval(x)).norm() < 1e-9);
+        }
+        assert_eq!(proxy.eval(-4.5), complex(0.0, 0.0));
+        assert_eq!(proxy.eval(6.5), complex(0.0, 0.0));
+    }
+
+    #[test]
+    fn all_real_roots() {
+        let roots = find_real_roots(
+            &|x: f64| (x - 0.3) * (x + 2.0) * (x - 5.0) * (x * x + 1.0),
+            (-10.0, 10.0),
+            1e-12,
+            Tolerance::step(1e-13),
+        )
+        .unwrap();
+        assert_eq!(roots.len(), 3);
+        for (root, expected) in roots.iter().zip([-2.0, 0.3, 5.0]) {
+            assert!((root - expected).abs() < 1e-11, "{}", root);
+        }
+
+        // many roots, the fit is split before its colleague matrix is solved
+        let roots = find_real_roots(
+            &|x: f64| (3.0 * x).sin(),
+            (0.1, 30.0),
+            1e-12,
+            Tolerance::step(1e-13),
+        )
+        .unwrap();
+        assert_eq!(roots.len(), 28);
+        for (k, root) in roots.iter().enumerate() {
+            assert!((root - (k + 1) as f64 * f64::consts::PI / 3.0).abs() < 1e-11);
+        }
+
+        let proxy = fit_real(&|x: f64| x.powi(3) - x, &[-2.0, 0.5, 2.0], 1e-12).unwrap();
+        let slope = proxy.derivative();
+        for x in [-1.5, 0.0, 0.7, 1.9] {
+            assert!(
+                (slope.eval(x).re - (3.0 * x * x - 1.0)).abs() < 1e-10,
+                "{}",
+                x
+            );
+        }
+        assert_eq!(proxy.breakpoints(), vec![-2.0, 0.5, 2.0]);
+
+        let wall = |x: f64| if x < 0.0 { f64::INFINITY } else { x - 1.0 };
+        assert!(find_real_roots(&wall, (-1.0, 2.0), 1e-12, Tolerance::step(1e-13)).is_none());
+    }
+
+    #[test]
+    fn oscillating_function_is_split() {
+        let func = Function::<f64, Complex64>::new(|x: f64| Complex64::from_polar(1.0, 2000.0 * x));
+        let proxy = ChebyshevProxy::fit(&func, &[0.0, 1.0], 1e-8);
+
+        assert!(proxy.get_pieces().len() > 1);
+        for i in 0..1000 {
+            let x = i as f64 / 999.0;
+            assert!((proxy.eval(x) - func.eval(x)).norm() < 1e-7);
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::wkb_wave_func::Phase;
 use crate::*;
+use serde::Serialize;
 
 pub struct SchroedingerError<'a> {
     pub wave_func: &'a WaveFunction,
@@ -6,12 +8,278 @@ pub struct SchroedingerError<'a> {
 
 impl Func<f64, Complex64> for SchroedingerError<'_> {
     fn eval(&self, x: f64) -> Complex64 {
-        complex(-1.0 / (2.0 * self.wave_func.get_phase().mass), 0.0)
+        let phase = self.wave_func.get_phase();
+        complex(-phase.hbar.powi(2) / (2.0 * phase.mass), 0.0)
             * Derivative {
                 f: &Derivative { f: self.wave_func },
             }
             .eval(x)
-            + ((self.wave_func.get_phase().potential)(x) - self.wave_func.get_phase().energy)
-                * self.wave_func.eval(x)
+            + ((phase.potential)(x) - phase.energy) * self.wave_func.eval(x)
+    }
+}
+
+// largest jump of psi across the boundaries between its airy and wkb parts
+pub fn joint_mismatch(wave_func: &WaveFunction) -> f64 {
+    let view = wave_func.get_view();
+    wave_func
+        .get_joints()
+        .into_iter()
+        .filter(|b| *b > view.0 && *b < view.1)
+        .map(|b| {
+            let delta = f64::EPSILON.sqrt() * (1.0 + b.abs());
+            (wave_func.eval(b + delta) - wave_func.eval(b - delta)).norm()
+        })
+        .fold(0.0, f64::max)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AccuracySummary {
+    pub n: usize,
+    pub energy: f64,
+    pub energy_relative_error: f64,
+    pub psi_max_error: f64,
+    pub joint_mismatch: f64,
+    pub turning_point_error: f64,
+    pub normalization_relative_error: f64,
+}
+
+// Rough error budget of a state, each contribution is an estimate of its order of magnitude.
+pub fn accuracy_summary(wave_func: &WaveFunction, n: usize) -> AccuracySummary {
+    let phase = wave_func.get_phase();
+    let potential = phase.potential.clone();
+    let energy = phase.energy;
+    // the approx_inf the state was built over, its parts cover it
+    let approx_inf = wave_func.domain();
+
+    let energy_error = energy::extrapolate_energy(
+        n,
+        phase.mass,
+        phase.hbar,
+        &|x| potential(x),
+        approx_inf,
+        energy,
+        2,
+    )
+    .error;
+
+    let view = wave_func.get_view();
+    let psi_derivative = Derivative { f: wave_func };
+    // NaN if the turning points can't be grouped
+    let turning_point_error = turning_points::calc_ts(&phase, view).map_or(f64::NAN, |groups| {
+        groups
+            .ts
+            .iter()
+            .map(|(_, t)| {
+                psi_derivative.eval(*t).norm() * turning_points::location_error(&phase, *t)
+            })
+            .fold(0.0, f64::max)
+    });
+
+    let probability: Vec<Point<f64, f64>> = Grid::uniform(view, INTEG_STEPS)
+        .evaluate(wave_func)
+        .iter()
+        .map(|p| Point {
+            x: p.x,
+            y: p.y.norm_sqr(),
+        })
+        .collect();
+    let max_psi = probability.iter().map(|p| p.y.sqrt()).fold(0.0, f64::max);
+    let area = integrate(probability.clone(), TRAPEZE_PER_THREAD);
+    let normalization_relative_error = integration_error(&probability) / area;
+
+    let joint_mismatch = joint_mismatch(wave_func);
+
+    AccuracySummary {
+        n,
+        energy,
+        energy_relative_error: energy_error / energy.abs(),
+        psi_max_error: joint_mismatch
+            + turning_point_error
+            + normalization_relative_error * max_psi,
+        joint_mismatch,
+        turning_point_error,
+        normalization_relative_error,
+    }
+}
+
+// |hbar W + 1| of the WKB pair cos(S / hbar + pi / 4) / sqrt(p) and sin(S / hbar + pi / 4) / sqrt(p)
+// on uniform points of the view in the allowed region where WKB is used, outside of the airy ranges
+// around the turning points. W is -1 / hbar for the exact pair, with the derivatives taken
+// numerically it deviates where the phase integration or 1 / sqrt(p) degrade. None if the turning
+// points can't be grouped.
+pub fn wronskian_deviation(
+    wave_func: &WaveFunction,
+    points: usize,
+) -> Option<Vec<Point<f64, f64>>> {
+    wronskian_deviation_in(wave_func.get_phase(), wave_func.get_view(), points)
+}
+
+fn wronskian_deviation_in(
+    phase: Arc<Phase>,
+    view: (f64, f64),
+    points: usize,
+) -> Option<Vec<Point<f64, f64>>> {
+    let groups = turning_points::calc_ts(&phase, view).ok()?;
+    let cos = WkbWaveFunction::new(
+        phase.clone(),
+        1.0.into(),
+        INTEG_STEPS,
+        view.0,
+        view.0,
+        f64::consts::PI / 4.0,
+    );
+    let sin = cos.with_phase_off(-f64::consts::PI / 4.0);
+    Some(
+        Grid::uniform(view, points)
+            .points()
+            .into_par_iter()
+            .filter(|x| {
+                (phase.potential)(*x) < phase.energy
+                    && groups.ts.iter().all(|(range, _)| !is_in_range(*range, *x))
+            })
+            .map(|x| {
+                let wronskian = cos.eval(x) * Derivative { f: &sin }.eval(x)
+                    - Derivative { f: &cos }.eval(x) * sin.eval(x);
+                Point {
+                    x,
+                    y: (phase.hbar * wronskian + 1.0).norm(),
+                }
+            })
+            .collect(),
+    )
+}
+
+// largest deviations of the marginals of the Wigner function from |psi|^2 and |phi|^2, relative to
+// the largest of |psi|^2 and |phi|^2
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct MarginalDeviation {
+    pub position: f64,
+    pub momentum: f64,
+}
+
+// Wigner function of the state on uniform positions over OBSERVABLE_DOMAIN integrated over p and x.
+// The position marginal fails with too few momenta, the momentum marginal with too few positions to
+// resolve psi.
+pub fn wigner_marginals(
+    wave_func: &WaveFunction,
+    positions: usize,
+    momenta: usize,
+) -> MarginalDeviation {
+    let samples = Grid::uniform(OBSERVABLE_DOMAIN.range(wave_func), positions).evaluate(wave_func);
+    let hbar = wave_func.get_phase().hbar;
+    let wigner = phase_space::WignerFunction::new(&samples, momenta, hbar);
+    let range = phase_space::WignerFunction::momentum_range(&samples, hbar);
+    let phi = observables::momentum_space(&samples, &Grid::uniform(range, momenta), hbar);
+
+    let deviation = |marginal: Vec<Point<f64, f64>>, densities: Vec<f64>| {
+        let max = densities.iter().cloned().fold(0.0, f64::max);
+        marginal
+            .iter()
+            .zip(densities.iter())
+            .map(|(m, density)| (m.y - density).abs() / max)
+            .fold(0.0, f64::max)
+    };
+    MarginalDeviation {
+        position: deviation(
+            wigner.position_marginal(),
+            samples.iter().map(|p| p.y.norm_sqr()).collect(),
+        ),
+        momentum: deviation(
+            wigner.momentum_marginal(),
+            phi.iter().map(|p| p.y.norm_sqr()).collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wronskian_of_harmonic_oscillator() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            3,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let deviation = wronskian_deviation(&wave_func, 101).unwrap();
+        let energy = wave_func.get_phase().energy;
+        // the turning points are at +-sqrt(E)
+        assert!(!deviation.is_empty());
+        for p in &deviation {
+            assert!(potentials::square(p.x) < energy);
+            assert!((p.x.abs() - energy.sqrt()).abs() > 0.1, "{}", p.x);
+            assert!(p.y.is_finite() && p.y < 0.1, "{} {}", p.x, p.y);
+        }
+    }
+
+    #[test]
+    fn wigner_marginals_of_harmonic_oscillator() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let resolved = wigner_marginals(&wave_func, 301, 201);
+        assert!(resolved.position < 1e-10, "{:?}", resolved);
+        assert!(resolved.momentum < 5e-3, "{:?}", resolved);
+
+        let few_momenta = wigner_marginals(&wave_func, 301, 41);
+        assert!(few_momenta.position > 1e-2, "{:?}", few_momenta);
+        let few_positions = wigner_marginals(&wave_func, 11, 201);
+        assert!(few_positions.momentum > 1e-2, "{:?}", few_positions);
+    }
+
+    #[test]
+    fn accuracy_of_harmonic_oscillator() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            3,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let summary = accuracy_summary(&wave_func, 3);
+
+        assert_eq!(summary.n, 3);
+        assert!(summary.energy_relative_error < 1e-2);
+        assert!(summary.turning_point_error >= 0.0 && summary.turning_point_error < 1e-3);
+        assert!(summary.normalization_relative_error < 1e-3);
+        assert!(summary.psi_max_error.is_finite());
+        assert!(summary.psi_max_error >= summary.joint_mismatch);
+    }
+
+    #[test]
+    fn accuracy_over_own_approx_inf() {
+        let approx_inf = (-10.0, 10.0);
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            1,
+            approx_inf,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        assert_eq!(wave_func.domain(), approx_inf);
+        let summary = accuracy_summary(&wave_func, 1);
+        assert!(summary.energy_relative_error < 1e-2);
+    }
+
+    #[test]
+    fn wronskian_of_ungroupable_turning_points() {
+        // V is undefined where the validity function rises into the airy range of the right turning
+        // point, the zeros that are left don't pair up
+        let potential: SharedPotential =
+            Arc::new(|x: f64| if 0.1 < x && x < 0.3 { f64::NAN } else { x * x });
+        let phase = Arc::new(Phase::new(1.0, 1.0, UNITS.hbar, potential));
+        assert!(turning_points::calc_ts(&phase, (-3.0, 3.0)).is_err());
+        assert!(wronskian_deviation_in(phase, (-3.0, 3.0), 101).is_none());
     }
 }
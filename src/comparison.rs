@@ -0,0 +1,433 @@
+use crate::wkb_wave_func::Phase;
+use crate::*;
+use serde::Serialize;
+
+// Comparison equations w'' + R(zeta) w = 0 the uniform approximation maps psi'' + Q(x) psi = 0,
+// Q = 2m (E - V) / hbar^2, onto. zeta(x) equates the integrals of sqrt|Q| and sqrt|R| from
+// corresponding turning points, then psi = (R / Q)^(1/4) w(zeta) up to the Schwarzian derivative
+// of the mapping.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum ComparisonEquation {
+    // R = -zeta with Ai, a simple turning point, see AiryWaveFunction::langer_mapping
+    Airy,
+    // R = zeta^2 + 2 epsilon with the series of the parabolic cylinder functions, the top of a
+    // barrier close to E
+    ParabolicCylinder,
+    // R = 1 - mu^2 / zeta^2 with sqrt(zeta) J_nu(zeta), a centrifugal singularity A / (x - x_s)^2,
+    // see BesselMapping for mu
+    Bessel,
+}
+
+// The comparison equations ApproximationKind::Uniform may use besides Airy, which covers the soft
+// turning points the others don't.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct ComparisonLibrary {
+    // around the top of every barrier within BARRIER_TOP_MARGIN of E
+    pub parabolic_cylinder: bool,
+    // from a singularity at the left end of approx_inf through the first turning point
+    pub bessel: bool,
+}
+
+impl ComparisonLibrary {
+    pub const AIRY: ComparisonLibrary = ComparisonLibrary {
+        parabolic_cylinder: false,
+        bessel: false,
+    };
+    pub const ALL: ComparisonLibrary = ComparisonLibrary {
+        parabolic_cylinder: true,
+        bessel: true,
+    };
+
+    pub fn contains(&self, equation: ComparisonEquation) -> bool {
+        match equation {
+            ComparisonEquation::Airy => true,
+            ComparisonEquation::ParabolicCylinder => self.parabolic_cylinder,
+            ComparisonEquation::Bessel => self.bessel,
+        }
+    }
+}
+
+fn q(phase: &Phase, x: f64) -> f64 {
+    2.0 * phase.mass * (phase.energy - (phase.potential)(x)) / phase.hbar.powi(2)
+}
+
+// integral of sqrt|Q| between a and b
+fn action(phase: &Phase, a: f64, b: f64) -> f64 {
+    integrate(
+        evaluate_function_between(phase, a, b, INTEG_STEPS),
+        TRAPEZE_PER_THREAD,
+    )
+    .abs()
+}
+
+// x in range with f(x) = target by bisection, f has to cross target once in range
+fn invert<F: Fn(f64) -> f64>(f: F, target: f64, range: (f64, f64)) -> f64 {
+    let increasing = f(range.1) > f(range.0);
+    let (mut lower, mut upper) = range;
+    loop {
+        let middle = (lower + upper) / 2.0;
+        if middle <= lower || middle >= upper {
+            return middle;
+        }
+        if (f(middle) < target) == increasing {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+}
+
+// nu and x_s of a potential rising like A / (x - x_s)^2 towards a singularity x_s just left of
+// approx_inf, fitted to three samples next to approx_inf.0. The potential is taken as Langer
+// corrected like HydrogenLike::effective_potential, (l + 1/2)^2 in place of l (l + 1), so
+// nu = sqrt(2 m A) / hbar.
+pub fn centrifugal_singularity(phase: &Phase, approx_inf: (f64, f64)) -> Option<(f64, f64)> {
+    let width = approx_inf.1 - approx_inf.0;
+    let h = width * 1e-9;
+    let x = [1.0, 2.0, 4.0].map(|k| approx_inf.0 + k * h);
+    let v = x.map(|x| (phase.potential)(x));
+    if !(v[0] > v[1] && v[1] > v[2] && v[2] > 0.0) {
+        return None;
+    }
+    // sqrt(V) (x - x_s) is the same at all of them
+    let (r0, r1) = (v[0].sqrt(), v[1].sqrt());
+    let singularity = (r0 * x[0] - r1 * x[1]) / (r0 - r1);
+    if !(singularity < x[0] && x[0] - singularity < 1e-3 * width) {
+        return None;
+    }
+    let strength = v[0] * (x[0] - singularity).powi(2);
+    if (v[2] * (x[2] - singularity).powi(2) - strength).abs() > 1e-3 * strength {
+        return None;
+    }
+    Some((
+        (2.0 * phase.mass * strength).sqrt() / phase.hbar,
+        singularity,
+    ))
+}
+
+// Bessel's equation mapped onto psi from a centrifugal singularity through the first turning point,
+// zeta = mu there and allowed above it. sqrt(zeta) J_nu(zeta) solves
+// w'' + (1 - (nu^2 - 1/4) / zeta^2) w = 0, so where a centrifugal term is left without it the 1/4
+// of the Langer correction is taken out of Q again, Q + 1 / (4 (x - x_s)^2) is mapped onto R with
+// mu^2 = nu^2 - 1/4. Q of an s state would have no double pole left for the one of R, it keeps the
+// correction and mu = nu.
+#[derive(Clone)]
+pub struct BesselMapping {
+    phase: Arc<Phase>,
+    pub nu: f64,
+    pub singularity: f64,
+    pub turning_point: f64,
+    // of BesselMomentum, through the turning point
+    action_table: IntegralTable,
+}
+
+// sqrt|Q| of BesselMapping times dx / du with x = x_s + u^2, the integrand of its action, the
+// trapezoidal rule in x is off towards the singularity
+struct BesselMomentum<'a> {
+    mapping: &'a BesselMapping,
+}
+
+impl Func<f64, f64> for BesselMomentum<'_> {
+    fn eval(&self, u: f64) -> f64 {
+        2.0 * u
+            * self
+                .mapping
+                .q(self.mapping.singularity + u * u)
+                .abs()
+                .sqrt()
+    }
+}
+
+impl BesselMapping {
+    // the turning point of the Langer corrected Q bounds the one of the mapped Q from above, the
+    // action is tabulated over range
+    pub fn new(
+        phase: Arc<Phase>,
+        nu: f64,
+        singularity: f64,
+        turning_point: f64,
+        range: (f64, f64),
+    ) -> BesselMapping {
+        let mut mapping = BesselMapping {
+            phase,
+            nu,
+            singularity,
+            turning_point,
+            action_table: IntegralTable::default(),
+        };
+        if mapping.langer_term() > 0.0 {
+            let above = singularity + (turning_point - singularity) * 1e-9;
+            mapping.turning_point = invert(|x| mapping.q(x), 0.0, (above, turning_point));
+        }
+        let u = |x: f64| (x - singularity).max(0.0).sqrt();
+        mapping.action_table = IntegralTable::through(
+            |u| BesselMomentum { mapping: &mapping }.eval(u),
+            u(mapping.turning_point),
+            (u(range.0), u(range.1)),
+            INTEG_STEPS,
+        );
+        mapping
+    }
+
+    // the 1/4 taken out again, none for an s state up to the precision of the fitted nu
+    fn langer_term(&self) -> f64 {
+        if self.nu * self.nu - 0.25 > 1e-3 {
+            0.25
+        } else {
+            0.0
+        }
+    }
+
+    fn q(&self, x: f64) -> f64 {
+        q(&self.phase, x) + self.langer_term() / (x - self.singularity).powi(2)
+    }
+
+    fn action(&self, x: f64) -> f64 {
+        let u = |x: f64| (x - self.singularity).sqrt();
+        // NaN outside of the table
+        let table = &self.action_table;
+        let tabulated = (table.integral(u(x)) - table.integral(u(self.turning_point))).abs();
+        if tabulated.is_finite() {
+            return tabulated;
+        }
+        integrate(
+            evaluate_function_between(
+                &BesselMomentum { mapping: self },
+                u(self.turning_point),
+                u(x),
+                INTEG_STEPS,
+            ),
+            TRAPEZE_PER_THREAD,
+        )
+        .abs()
+    }
+
+    // zeta and (R / Q)^(1/4)
+    pub fn map(&self, x: f64) -> (f64, f64) {
+        let mu = (self.nu * self.nu - self.langer_term()).sqrt();
+        let s = self.action(x);
+        let q = self.q(x);
+        if s == 0.0 || q == 0.0 {
+            // R / Q -> R'(mu) / Q'(t) with R' = 2 / mu
+            let q_prime = derivative(&|x| self.q(x), self.turning_point);
+            return (mu, (2.0 / (mu * q_prime)).abs().powf(0.25));
+        }
+        let zeta = if x > self.turning_point {
+            // sqrt(zeta^2 - mu^2) >= zeta - mu bounds zeta from above
+            let allowed = |zeta: f64| (zeta * zeta - mu * mu).sqrt() - mu * (mu / zeta).acos();
+            invert(
+                allowed,
+                s,
+                (mu, s + mu * (1.0 + f64::consts::PI / 2.0) + 1.0),
+            )
+        } else {
+            // mu ln(mu / zeta) - mu <= integral <= mu ln(2 mu / zeta)
+            let forbidden = |zeta: f64| {
+                let root = (mu * mu - zeta * zeta).max(0.0).sqrt();
+                mu * ((mu + root) / zeta).ln() - root
+            };
+            let range = (
+                mu * (-s / mu - 1.0).exp(),
+                (2.0 * mu * (-s / mu).exp()).min(mu),
+            );
+            invert(forbidden, s, range)
+        };
+        let r = 1.0 - mu * mu / (zeta * zeta);
+        (zeta, (r / q).abs().powf(0.25))
+    }
+
+    // (R / Q)^(1/4) sqrt(zeta) J_nu(zeta), which tends to cos(S - pi/4) / sqrt(p) with S the action
+    // of the Langer corrected Q from its turning point, the WKB function of a soft turning point
+    // with c = 1
+    pub fn eval(&self, x: f64) -> f64 {
+        let (zeta, factor) = self.map(x);
+        (f64::consts::PI / (2.0 * self.phase.hbar)).sqrt()
+            * factor
+            * zeta.sqrt()
+            * bessel::bessel_j(self.nu, zeta)
+    }
+}
+
+// The top of a barrier at x0 mapped onto w'' + (z^2 + 2 epsilon) w = 0. Below the top
+// epsilon = -theta / pi with theta the action under the barrier, which puts the turning points of
+// both equations onto each other, above it epsilon = (E - V(x0)) / (hbar omega) of the inverted
+// oscillator fitted to the top.
+#[derive(Clone)]
+pub struct CylinderMapping {
+    phase: Arc<Phase>,
+    pub x0: f64,
+    pub epsilon: f64,
+    // oscillator length of the top, at the zeros of R and Q the prefactor is taken 1e-6 of it off
+    length: f64,
+    turning_points: Option<(f64, f64)>,
+    // of sqrt|Q| within reach of x0
+    action_table: IntegralTable,
+}
+
+impl CylinderMapping {
+    // none if E is below the top and V doesn't fall below E within reach of x0 on both sides
+    pub fn new(
+        phase: Arc<Phase>,
+        x0: f64,
+        epsilon: f64,
+        length: f64,
+        reach: f64,
+    ) -> Option<CylinderMapping> {
+        let range = (x0 - reach, x0 + reach);
+        if epsilon >= 0.0 {
+            let action_table = IntegralTable::through(|x| phase.eval(x), x0, range, INTEG_STEPS);
+            return Some(CylinderMapping {
+                phase,
+                x0,
+                epsilon,
+                length,
+                turning_points: None,
+                action_table,
+            });
+        }
+        let above = |x: f64| (phase.potential)(x) - phase.energy;
+        if !(above(x0 - reach) < 0.0 && above(x0 + reach) < 0.0) {
+            return None;
+        }
+        let turning_points = (
+            invert(above, 0.0, (x0 - reach, x0)),
+            invert(above, 0.0, (x0, x0 + reach)),
+        );
+        let action_table =
+            IntegralTable::through(|x| phase.eval(x), turning_points.0, range, INTEG_STEPS);
+        let mut mapping = CylinderMapping {
+            phase,
+            x0,
+            epsilon,
+            length,
+            turning_points: Some(turning_points),
+            action_table,
+        };
+        mapping.epsilon = -mapping.action(turning_points.0, turning_points.1) / f64::consts::PI;
+        Some(mapping)
+    }
+
+    // integral of sqrt|Q| between a and b, integrated directly outside of the table
+    fn action(&self, a: f64, b: f64) -> f64 {
+        let tabulated = (self.action_table.integral(b) - self.action_table.integral(a)).abs();
+        if tabulated.is_finite() {
+            tabulated
+        } else {
+            action(&self.phase, a, b)
+        }
+    }
+
+    fn zeta(&self, x: f64) -> f64 {
+        match self.turning_points {
+            Some((t_l, t_r)) => {
+                // turning points at -b and b
+                let b2 = -2.0 * self.epsilon;
+                let b = b2.sqrt();
+                let outside = |z: f64| {
+                    let root = (z * z - b2).max(0.0).sqrt();
+                    (z * root - b2 * ((z + root) / b).ln()) / 2.0
+                };
+                if x >= t_r {
+                    let s = self.action(t_r, x);
+                    invert(outside, s, (b, b + (2.0 * s).sqrt()))
+                } else if x <= t_l {
+                    let s = self.action(x, t_l);
+                    -invert(outside, s, (b, b + (2.0 * s).sqrt()))
+                } else {
+                    let under = |z: f64| {
+                        let root = (b2 - z * z).max(0.0).sqrt();
+                        (z * root + b2 * (z / b).clamp(-1.0, 1.0).asin()) / 2.0
+                            + f64::consts::PI * b2 / 4.0
+                    };
+                    invert(under, self.action(t_l, x), (-b, b))
+                }
+            }
+            None => {
+                let c2 = 2.0 * self.epsilon;
+                let above = |z: f64| {
+                    if c2 > 0.0 {
+                        (z * (z * z + c2).sqrt() + c2 * (z / c2.sqrt()).asinh()) / 2.0
+                    } else {
+                        z * z / 2.0
+                    }
+                };
+                let s = self.action(self.x0, x);
+                (x - self.x0).signum() * invert(above, s, (0.0, (2.0 * s).sqrt()))
+            }
+        }
+    }
+
+    // z and (R / Q)^(1/4)
+    pub fn map(&self, x: f64) -> (f64, f64) {
+        let z = self.zeta(x);
+        let factor = |x: f64, z: f64| ((z * z + 2.0 * self.epsilon) / q(&self.phase, x)).abs();
+        let ratio = factor(x, z);
+        if ratio.is_finite() && ratio > 0.0 {
+            return (z, ratio.powf(0.25));
+        }
+        // 0 / 0 at the turning points and at the top for epsilon = 0, the ratio is continuous
+        let nudged = x + 1e-6 * self.length;
+        (z, factor(nudged, self.zeta(nudged)).powf(0.25))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exact_mappings() {
+        // with m = hbar = 1 the Langer corrected V = nu^2 / (2 x^2) at E = 1/2 is Bessel's equation
+        // itself
+        let nu = 1.5;
+        let phase = Arc::new(Phase {
+            energy: 0.5,
+            mass: 1.0,
+            hbar: 1.0,
+            potential: Arc::new(move |x: f64| nu * nu / (2.0 * x * x)),
+        });
+        let bessel = BesselMapping::new(phase, nu, 0.0, nu, (0.1, 10.0));
+        assert!((bessel.turning_point - 2.0f64.sqrt()).abs() < 1e-9);
+        for x in [0.3, 1.0, nu, 2.0, 8.0] {
+            let (zeta, factor) = bessel.map(x);
+            assert!((zeta - x).abs() < 1e-5 * x, "{}: {}", x, zeta);
+            assert!((factor - 1.0).abs() < 1e-4, "{}: {}", x, factor);
+        }
+
+        // and V = -x^2 / 2 the inverted oscillator with epsilon = E on both sides of the top
+        for energy in [-0.4, 0.0, 0.3] {
+            let phase = Arc::new(Phase {
+                energy,
+                mass: 1.0,
+                hbar: 1.0,
+                potential: Arc::new(|x: f64| -x * x / 2.0),
+            });
+            let cylinder = CylinderMapping::new(phase, 0.0, energy, 1.0, 4.0).unwrap();
+            assert!((cylinder.epsilon - energy).abs() < 1e-6);
+            for x in [-3.0, -0.5, 0.0, 0.2, 0.9, 3.0] {
+                let (z, factor) = cylinder.map(x);
+                assert!((z - x).abs() < 1e-5, "E = {}, {}: {}", energy, x, z);
+                assert!(
+                    (factor - 1.0).abs() < 1e-4,
+                    "E = {}, {}: {}",
+                    energy,
+                    x,
+                    factor
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn singularity_of_hydrogen() {
+        for l in 0..3 {
+            let hydrogen = hydrogen::HydrogenLike::new(1.0, 1.0, 4, l);
+            let phase = Phase::new(0.1, 1.0, UNITS.hbar, hydrogen.effective_potential());
+            let (nu, singularity) = centrifugal_singularity(&phase, (1e-6, 200.0)).unwrap();
+            assert!((nu - (l as f64 + 0.5)).abs() < 1e-3, "{}", nu);
+            assert!(singularity.abs() < 1e-8, "{}", singularity);
+        }
+        let square = Phase::new(1.0, 1.0, UNITS.hbar, Arc::new(potentials::square));
+        assert_eq!(centrifugal_singularity(&square, APPROX_INF), None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::*;
+
+// Ramer-Douglas-Peucker on samples sharing their x values. A sample is dropped if the line between
+// the kept samples around it stays within tolerance of it in every column. The distance is measured
+// vertically, x and y of a plot usually don't have the same units.
+pub fn keep_indices(x: &[f64], columns: &[Vec<f64>], tolerance: f64) -> Vec<usize> {
+    assert!(columns.iter().all(|c| c.len() == x.len()));
+    if x.len() <= 2 {
+        return (0..x.len()).collect();
+    }
+
+    let deviation = |first: usize, last: usize, i: usize| -> f64 {
+        let t = (x[i] - x[first]) / (x[last] - x[first]);
+        let d = columns
+            .iter()
+            .map(|c| (c[i] - (c[first] + (c[last] - c[first]) * t)).abs())
+            .fold(0.0, f64::max);
+        // never drop anything next to values that can't be interpolated
+        if d.is_finite() {
+            d
+        } else {
+            f64::INFINITY
+        }
+    };
+
+    let mut keep = vec![false; x.len()];
+    keep[0] = true;
+    keep[x.len() - 1] = true;
+
+    // explicit stack, the recursion gets as deep as the number of kept samples
+    let mut segments = vec![(0, x.len() - 1)];
+    while let Some((first, last)) = segments.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+        let (index, max_deviation) = (first + 1..last)
+            .map(|i| (i, deviation(first, last, i)))
+            .max_by(|a, b| cmp_f64(&a.1, &b.1))
+            .unwrap();
+        if max_deviation > tolerance {
+            keep[index] = true;
+            segments.push((first, index));
+            segments.push((index, last));
+        }
+    }
+
+    (0..x.len()).filter(|i| keep[*i]).collect()
+}
+
+// tolerance as a fraction of the largest absolute value in the columns
+pub fn relative_tolerance(columns: &[Vec<f64>], relative: f64) -> f64 {
+    relative
+        * columns
+            .iter()
+            .flatten()
+            .filter(|y| y.is_finite())
+            .map(|y| y.abs())
+            .fold(0.0, f64::max)
+}
+
+pub fn decimate(values: Vec<Point<f64, f64>>, relative: f64) -> Vec<Point<f64, f64>> {
+    let x: Vec<f64> = values.iter().map(|p| p.x).collect();
+    let columns = vec![values.iter().map(|p| p.y).collect()];
+    let tolerance = relative_tolerance(&columns, relative);
+
+    keep_indices(&x, &columns, tolerance)
+        .iter()
+        .map(|i| values[*i].clone())
+        .collect()
+}
+
+// real and imaginary part both have to stay within the tolerance
+pub fn decimate_complex(
+    values: Vec<Point<f64, Complex64>>,
+    relative: f64,
+) -> Vec<Point<f64, Complex64>> {
+    let x: Vec<f64> = values.iter().map(|p| p.x).collect();
+    let columns = vec![
+        values.iter().map(|p| p.y.re).collect(),
+        values.iter().map(|p| p.y.im).collect(),
+    ];
+    let tolerance = relative_tolerance(&columns, relative);
+
+    keep_indices(&x, &columns, tolerance)
+        .iter()
+        .map(|i| values[*i].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn interpolate(values: &[Point<f64, f64>], x: f64) -> f64 {
+        let i = values.iter().position(|p| p.x >= x).unwrap();
+        if values[i].x == x {
+            return values[i].y;
+        }
+        let (a, b) = (&values[i - 1], &values[i]);
+        a.y + (b.y - a.y) * (x - a.x) / (b.x - a.x)
+    }
+
+    #[test]
+    fn line_keeps_endpoints() {
+        let values: Vec<Point<f64, f64>> = (0..1000)
+            .map(|i| Point {
+                x: i as f64,
+                y: 3.0 * i as f64 - 2.0,
+            })
+            .collect();
+
+        let decimated = decimate(values.clone(), 1e-9);
+        assert_eq!(decimated.len(), 2);
+        assert_eq!(decimated[0].x, values[0].x);
+        assert_eq!(decimated[1].x, values[999].x);
+    }
+
+    #[test]
+    fn sine_within_tolerance() {
+        let values: Vec<Point<f64, f64>> = (0..100000)
+            .map(|i| {
+                let x = i as f64 * 1e-4;
+                Point {
+                    x,
+                    y: (3.0 * x).sin(),
+                }
+            })
+            .collect();
+
+        let decimated = decimate(values.clone(), 1e-3);
+        assert!(decimated.len() < values.len() / 50);
+        assert!(values
+            .iter()
+            .all(|p| (interpolate(&decimated, p.x) - p.y).abs() <= 1e-3));
+    }
+}
@@ -0,0 +1,237 @@
+use crate::*;
+use serde::Serialize;
+
+// V is first sampled on this many points of the range
+const SCAN_POINTS: usize = 4000;
+// a cell is taken a closer look at if V changes this many times more over it than over both its
+// neighbours together, a point if its second difference does
+const JUMP_RATIO: f64 = 10.0;
+const KINK_RATIO: f64 = 10.0;
+// what is left of a jump at neighbouring floats, relative to 1 + |V|, as for hard walls
+const JUMP_TOLERANCE: f64 = 1e-6;
+// change of V' at a kink, relative to 1 + |V'| on both sides
+const KINK_TOLERANCE: f64 = 1e-3;
+// each halves the range around a kink candidate, which is resampled in ZOOM_CELLS cells
+const KINK_ZOOMS: usize = 20;
+const ZOOM_CELLS: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum DiscontinuityKind {
+    // V jumps, size is V right minus V left of x
+    Jump,
+    // V' jumps, size is V' right minus V' left of x
+    Kink,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Discontinuity {
+    pub x: f64,
+    pub kind: DiscontinuityKind,
+    pub size: f64,
+    // V just left and right of x
+    pub values: (f64, f64),
+}
+
+impl Discontinuity {
+    // a jump across the energy is a hard wall, the turning points handle those exactly
+    pub fn is_wall(&self, energy: f64) -> bool {
+        self.kind == DiscontinuityKind::Jump && (self.values.0 < energy) != (self.values.1 < energy)
+    }
+}
+
+// What WaveFunctionBuilder does about jumps and kinks of V in approx_inf that aren't hard walls,
+// the WKB functions are silently wrong around them.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum DiscontinuityPolicy {
+    // doesn't look for them
+    Ignore,
+    // prints them
+    Warn,
+    // no wave function for an energy at which any of them isn't a hard wall
+    Refuse,
+    // smooths V with this width if there are any, see SmoothedPotential
+    Smooth(f64),
+}
+
+// |b - a|, 0 for equal infinities
+fn difference(a: f64, b: f64) -> f64 {
+    if a == b {
+        0.0
+    } else {
+        (b - a).abs()
+    }
+}
+
+// sum of the neighbours of i, missing ones are 0
+fn neighbours(values: &[f64], i: usize, distance: usize) -> f64 {
+    let left = i.checked_sub(distance).and_then(|i| values.get(i));
+    left.copied().unwrap_or(0.0) + values.get(i + distance).copied().unwrap_or(0.0)
+}
+
+// bisects towards the half V changes more over, down to neighbouring floats, V jumps there if the
+// change doesn't vanish
+fn refine_jump<F: Fn(f64) -> f64 + ?Sized>(
+    potential: &F,
+    mut a: f64,
+    mut b: f64,
+) -> Option<Discontinuity> {
+    loop {
+        let middle = (a + b) / 2.0;
+        if middle <= a || middle >= b {
+            break;
+        }
+        let v = potential(middle);
+        if difference(potential(a), v) >= difference(v, potential(b)) {
+            b = middle;
+        } else {
+            a = middle;
+        }
+    }
+    let values = (potential(a), potential(b));
+    let jump = difference(values.0, values.1);
+    if jump.is_finite() && jump <= JUMP_TOLERANCE * (1.0 + values.0.abs().min(values.1.abs())) {
+        return None;
+    }
+    Some(Discontinuity {
+        x: (a + b) / 2.0,
+        kind: DiscontinuityKind::Jump,
+        size: values.1 - values.0,
+        values,
+    })
+}
+
+// zooms in on the largest second difference in (a, b), at a kink it stays of the order of the step
+// times the jump of V' while it goes like the step squared times V'' elsewhere
+fn refine_kink<F: Fn(f64) -> f64 + ?Sized>(
+    potential: &F,
+    mut a: f64,
+    mut b: f64,
+) -> Option<Discontinuity> {
+    let mut x = (a + b) / 2.0;
+    let mut h = (b - a) / ZOOM_CELLS as f64;
+    for _ in 0..KINK_ZOOMS {
+        h = (b - a) / ZOOM_CELLS as f64;
+        let v = (0..=ZOOM_CELLS)
+            .map(|i| potential(a + i as f64 * h))
+            .collect::<Vec<f64>>();
+        let j = (1..ZOOM_CELLS)
+            .max_by(|l, r| {
+                let second = |j: usize| (v[j + 1] - 2.0 * v[j] + v[j - 1]).abs();
+                cmp_f64(&second(*l), &second(*r))
+            })
+            .unwrap();
+        x = a + j as f64 * h;
+        a = x - 2.0 * h;
+        b = x + 2.0 * h;
+    }
+
+    // the kink is within h of x
+    let slope_left = (potential(x - h) - potential(x - 3.0 * h)) / (2.0 * h);
+    let slope_right = (potential(x + 3.0 * h) - potential(x + h)) / (2.0 * h);
+    let size = slope_right - slope_left;
+    if !size.is_finite()
+        || size.abs() <= KINK_TOLERANCE * (1.0 + slope_left.abs() + slope_right.abs())
+    {
+        return None;
+    }
+    let v = potential(x);
+    Some(Discontinuity {
+        x,
+        kind: DiscontinuityKind::Kink,
+        size,
+        values: (v, v),
+    })
+}
+
+// Jumps and kinks of V in range, sorted by x. V is scanned on a uniform grid, cells over which it
+// changes much more than over their neighbours are bisected for jumps, points where its second
+// difference stands out are zoomed in on for kinks. Features narrower than the grid spacing, like
+// a steep but smooth step, are only reported if they are steeper than the bisection can resolve.
+pub fn find_discontinuities<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    range: (f64, f64),
+) -> Vec<Discontinuity> {
+    let grid = Grid::uniform(range, SCAN_POINTS);
+    let values = grid
+        .points()
+        .par_iter()
+        .map(|x| potential(*x))
+        .collect::<Vec<f64>>();
+    // rounding errors of V
+    let noise = |i: usize| 1e3 * f64::EPSILON * (1.0 + values[i].abs());
+
+    let steps = values
+        .windows(2)
+        .map(|w| difference(w[0], w[1]))
+        .collect::<Vec<f64>>();
+    let jump_cells = (0..steps.len())
+        .filter(|i| {
+            !steps[*i].is_finite() || steps[*i] > JUMP_RATIO * neighbours(&steps, *i, 1) + noise(*i)
+        })
+        .collect::<Vec<usize>>();
+
+    let seconds = (0..values.len())
+        .map(|j| {
+            if j == 0 || j + 1 == values.len() {
+                0.0
+            } else {
+                (values[j + 1] - 2.0 * values[j] + values[j - 1]).abs()
+            }
+        })
+        .collect::<Vec<f64>>();
+    // a kink between two points makes both second differences stand out, only the larger is taken
+    let kink_points = (1..values.len() - 1)
+        .filter(|j| !jump_cells.iter().any(|i| *i + 2 >= *j && *i <= *j + 1))
+        .filter(|j| {
+            seconds[*j].is_finite()
+                && seconds[*j] > KINK_RATIO * neighbours(&seconds, *j, 2) + noise(*j)
+                && seconds[*j] >= seconds[*j - 1]
+                && seconds[*j] > seconds[*j + 1]
+        })
+        .collect::<Vec<usize>>();
+
+    let mut discontinuities = jump_cells
+        .par_iter()
+        .filter_map(|i| refine_jump(potential, grid.x(*i), grid.x(*i + 1)))
+        .chain(
+            kink_points
+                .par_iter()
+                .filter_map(|j| refine_kink(potential, grid.x(*j - 1), grid.x(*j + 1))),
+        )
+        .collect::<Vec<Discontinuity>>();
+    discontinuities.sort_by(|a, b| cmp_f64(&a.x, &b.x));
+    discontinuities
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jumps_and_kinks() {
+        assert!(find_discontinuities(&potentials::square, APPROX_INF).is_empty());
+        assert!(find_discontinuities(&potentials::mexican_hat, (-10.0, 10.0)).is_empty());
+
+        let step = |x: f64| (if x < 0.3 { 0.0 } else { 2.0 }) + x * x;
+        let found = find_discontinuities(&step, (-10.0, 10.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DiscontinuityKind::Jump);
+        assert!((found[0].x - 0.3).abs() < 1e-12);
+        assert!((found[0].size - 2.0).abs() < 1e-9);
+        assert!(found[0].is_wall(1.0) && !found[0].is_wall(3.0));
+
+        let kink = |x: f64| (x - 1.7).abs() + 0.1 * x * x;
+        let found = find_discontinuities(&kink, (-10.0, 10.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DiscontinuityKind::Kink);
+        assert!((found[0].x - 1.7).abs() < 1e-6);
+        assert!((found[0].size - 2.0).abs() < 1e-3);
+
+        // an infinite wall is a jump as well
+        let half_square = |x: f64| if x < 0.0 { f64::INFINITY } else { x * x };
+        let found = find_discontinuities(&half_square, (-10.0, 10.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].size, -f64::INFINITY);
+        assert!(found[0].is_wall(1.0));
+    }
+}
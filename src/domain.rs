@@ -0,0 +1,73 @@
+use crate::*;
+use serde::Serialize;
+
+// Interval an operation integrates psi over, resolved for every wave function on its own.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum IntegrationDomain {
+    // everything covered by the parts, i.e. approx_inf
+    ApproxInf,
+    View,
+    // see WaveFunction::effective_support
+    EffectiveSupport(f64),
+    Range(f64, f64),
+}
+
+impl IntegrationDomain {
+    pub fn range(&self, wave_function: &WaveFunction) -> (f64, f64) {
+        match *self {
+            IntegrationDomain::ApproxInf => wave_function.domain(),
+            IntegrationDomain::View => wave_function.get_view(),
+            IntegrationDomain::EffectiveSupport(epsilon) => {
+                wave_function.effective_support(epsilon)
+            }
+            IntegrationDomain::Range(a, b) => (a, b),
+        }
+    }
+
+    // smallest interval containing the ranges of all the states
+    pub fn common_range(&self, states: &[WaveFunction]) -> (f64, f64) {
+        states
+            .iter()
+            .map(|s| self.range(s))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .expect("no states to integrate")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn support_of_oscillator() {
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let support = IntegrationDomain::EffectiveSupport(1e-8).range(&wave_function);
+        let domain = IntegrationDomain::ApproxInf.range(&wave_function);
+        assert!(domain.0 < support.0 && support.1 < domain.1);
+        for range in wave_function.get_airy_ranges() {
+            assert!(support.0 < range.0 && range.1 < support.1);
+        }
+
+        // psi is negligible at the ends, the norm doesn't change
+        let max = Grid::uniform(wave_function.get_view(), 1000)
+            .evaluate(&wave_function)
+            .iter()
+            .map(|p| p.y.norm())
+            .fold(0.0, f64::max);
+        assert!(wave_function.eval(support.0).norm() < 1e-6 * max);
+        assert!(wave_function.eval(support.1).norm() < 1e-6 * max);
+        let norm = |range| Grid::uniform(range, 4000).integrate(&NormSquare { f: &wave_function });
+        assert!((norm(support) - 1.0).abs() < 1e-6);
+
+        // a looser epsilon cuts closer
+        let loose = IntegrationDomain::EffectiveSupport(1e-2).range(&wave_function);
+        assert!(support.0 < loose.0 && loose.1 < support.1);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::turning_points::Boundary;
 use crate::*;
+use ordinal::Ordinal;
 
 struct Integrand<'a, F: Fn(f64) -> f64 + Sync> {
     mass: f64,
+    hbar: f64,
     pot: &'a F,
     energy: f64,
 }
@@ -15,45 +18,195 @@ impl<F: Fn(f64) -> f64 + Sync> Func<f64, f64> for Integrand<'_, F> {
         }
 
         if pot < self.energy {
-            return (2.0 * self.mass * (self.energy - pot)).sqrt();
+            (2.0 * self.mass * (self.energy - pot)).sqrt() / self.hbar
         } else {
-            return 0.0;
+            0.0
         }
     }
 }
 
 struct SommerfeldCond<'a, F: Fn(f64) -> f64 + Sync> {
     mass: f64,
+    hbar: f64,
     pot: &'a F,
     view: (f64, f64),
+    steps: usize,
+    // types of the outermost turning points, detected at every energy if None
+    boundaries: Option<(Boundary, Boundary)>,
 }
 
-impl<F: Fn(f64) -> f64 + Sync> Func<f64, f64> for SommerfeldCond<'_, F> {
-    fn eval(&self, energy: f64) -> f64 {
+impl<F: Fn(f64) -> f64 + Sync> SommerfeldCond<'_, F> {
+    // integral of p / hbar between the turning points
+    fn phase_integral(&self, energy: f64) -> f64 {
         let integrand = Integrand {
             mass: self.mass,
+            hbar: self.hbar,
             pot: self.pot,
             energy,
         };
-        let integral = integrate(
-            evaluate_function_between(&integrand, self.view.0, self.view.1, INTEG_STEPS),
-            TRAPEZE_PER_THREAD,
-        );
-        return ((2.0 * integral - f64::consts::PI) / f64::consts::TAU) % 1.0;
+        Grid::uniform(self.view, self.steps).integrate(&integrand)
     }
+
+    // phase corrections of both turning points over pi, 1/2 between two soft turning points
+    fn maslov_offset(&self, energy: f64) -> f64 {
+        let (left, right) = self
+            .boundaries
+            .unwrap_or_else(|| turning_points::outer_boundaries(self.pot, energy, self.view));
+        (left.phase_correction() + right.phase_correction()) / f64::consts::PI
+    }
+}
+
+impl<F: Fn(f64) -> f64 + Sync> Func<f64, f64> for SommerfeldCond<'_, F> {
+    fn eval(&self, energy: f64) -> f64 {
+        let integral = self.phase_integral(energy);
+        // rem_euclid instead of %, with two hard walls the condition starts on a wrap at 0
+        (integral / f64::consts::PI - self.maslov_offset(energy)).rem_euclid(1.0)
+    }
+}
+
+// Starts from the harmonic estimate and solves the quantization condition around it, the scan in
+// first_energies is only used for potentials without a well with curvature.
+pub fn nth_energy<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+) -> f64 {
+    nth_energy_with_boundaries(n, mass, hbar, pot, view, None)
+}
+
+// nth_energy failing with why there is no such level instead of panicking, e.g. when the potential
+// binds less than n + 1 states
+pub fn nth_energy_checked<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+) -> Result<f64, String> {
+    nth_energy_traced(n, mass, hbar, pot, view, None, &mut Trace::new(false))
+        .map_err(|err| format!("no {} energy: {}", Ordinal(n), err))
+}
+
+// nth_energy with the types of the outermost turning points given instead of detected
+pub fn nth_energy_with_boundaries<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    boundaries: Option<(Boundary, Boundary)>,
+) -> f64 {
+    nth_energy_traced(n, mass, hbar, pot, view, boundaries, &mut Trace::new(false))
+        .unwrap_or_else(|err| panic!("no {} energy: {}", Ordinal(n), err))
+}
+
+// nth_energy_with_boundaries recording the solution of the quantization condition in trace, the
+// energy as x and the defect of the condition as value, the step is the one the bracket is widened
+// by and then its width while it is bisected. Nothing is recorded if the energy is found by the
+// scan.
+pub fn nth_energy_traced<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    boundaries: Option<(Boundary, Boundary)>,
+    trace: &mut Trace,
+) -> Result<f64, RootFindError> {
+    let sommerfeld_cond = SommerfeldCond {
+        mass,
+        hbar,
+        pot,
+        view,
+        steps: INTEG_STEPS,
+        boundaries,
+    };
+    match harmonic_estimates(n + 1, mass, hbar, pot, view).get(n) {
+        Some(guess) => solve_quantization(&sommerfeld_cond, n, *guess, trace),
+        None => scan_energies(n + 1, &sommerfeld_cond)
+            .map(|energies| energies[n])
+            .map_err(|message| RootFindError {
+                message,
+                trace: trace.clone(),
+            }),
+    }
+}
+
+// The first count levels of harmonic oscillators fitted to the bottom of every well,
+// V(x0) + hbar sqrt(V''(x0) / m) (n + 1/2), merged and ascending. Empty if no well has a positive
+// curvature.
+pub fn harmonic_estimates<F: Fn(f64) -> f64 + Sync>(
+    count: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+) -> Vec<f64> {
+    // step of the second difference, balances truncation and rounding error
+    let h = f64::EPSILON.powf(0.25);
+    let mut estimates: Vec<f64> = analysis::find_minima(pot, view)
+        .iter()
+        .map(|m| (m, (pot(m.x + h) - 2.0 * m.y + pot(m.x - h)) / (h * h)))
+        .filter(|(_, curvature)| curvature.is_finite() && *curvature > 0.0)
+        .flat_map(|(m, curvature)| {
+            let level_spacing = hbar * (curvature / mass).sqrt();
+            (0..count).map(move |n| m.y + level_spacing * (n as f64 + 0.5))
+        })
+        .collect();
+    estimates.sort_by(cmp_f64);
+    estimates.truncate(count);
+    estimates
+}
+
+// fails if there are less than count bound states, see scan_energies
+pub fn first_energies<F: Fn(f64) -> f64 + Sync>(
+    count: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+) -> Result<Vec<f64>, String> {
+    let sommerfeld_cond = SommerfeldCond {
+        mass,
+        hbar,
+        pot,
+        view,
+        steps: INTEG_STEPS,
+        boundaries: None,
+    };
+    scan_energies(count, &sommerfeld_cond)
 }
 
-pub fn nth_energy<F: Fn(f64) -> f64 + Sync>(n: usize, mass: f64, pot: &F, view: (f64, f64)) -> f64 {
+// Steps the energy up from 0 until count levels are found, each one is refined by refine_level. Nothing is bound above the lower of V at
+// the ends of the view, the scan stops there or after MAX_ENERGY_STEPS steps for hard walls, and
+// fails if it found less than count levels.
+fn scan_energies<F: Fn(f64) -> f64 + Sync>(
+    count: usize,
+    sommerfeld_cond: &SommerfeldCond<F>,
+) -> Result<Vec<f64>, String> {
     const ENERGY_STEP: f64 = 10.0;
     const CHECKS_PER_ENERGY_STEP: usize = INTEG_STEPS;
-    let sommerfeld_cond = SommerfeldCond { mass, pot, view };
+    const MAX_ENERGY_STEPS: usize = 1000;
 
     let mut energy = 0.0; // newtons_method_non_smooth(&|e| sommerfeld_cond.eval(e), 1e-7, 1e-7);
-    let mut i = 0;
+    let mut energies = vec![];
+
+    if count == 0 {
+        return Ok(energies);
+    }
 
-    loop {
+    let (a, b) = sommerfeld_cond.view;
+    let continuum = f64::min((sommerfeld_cond.pot)(a), (sommerfeld_cond.pot)(b));
+
+    for _ in 0..MAX_ENERGY_STEPS {
+        if energy >= continuum {
+            break;
+        }
+        task::check_cancelled();
         let vals = evaluate_function_between(
-            &sommerfeld_cond,
+            sommerfeld_cond,
             energy,
             energy + ENERGY_STEP,
             CHECKS_PER_ENERGY_STEP,
@@ -64,13 +217,482 @@ pub fn nth_energy<F: Fn(f64) -> f64 + Sync>(n: usize, mass: f64, pot: &F, view:
             .collect::<Vec<(&Point<f64, f64>, &Point<f64, f64>)>>()
             .par_iter()
             .filter(|(p1, p2)| (p1.y - p2.y).abs() > 0.5 || p1.y.signum() != p2.y.signum())
-            .map(|ps| ps.1)
-            .collect::<Vec<&Point<f64, f64>>>();
-        int_solutions.sort_by(|p1, p2| cmp_f64(&p1.x, &p2.x));
-        if i + int_solutions.len() > n {
-            return int_solutions[n - i].x;
+            .map(|ps| ps.1.x)
+            .collect::<Vec<f64>>();
+        int_solutions.sort_by(cmp_f64);
+        let refined = int_solutions
+            .par_iter()
+            .map(|e| refine_level(sommerfeld_cond, *e))
+            .collect::<Result<Vec<f64>, String>>()?;
+        energies.extend(refined.into_iter().filter(|e| *e < continuum));
+        if energies.len() >= count {
+            energies.truncate(count);
+            return Ok(energies);
         }
         energy += ENERGY_STEP - (ENERGY_STEP / (CHECKS_PER_ENERGY_STEP as f64 + 1.0));
-        i += int_solutions.len();
+    }
+    Err(format!(
+        "found {} of {} levels below E = {}, V at the ends of the view is {:?}",
+        energies.len(),
+        count,
+        energy,
+        ((sommerfeld_cond.pot)(a), (sommerfeld_cond.pot)(b))
+    ))
+}
+
+// The scan only brackets a level between two of its grid points, above is the first point past the
+// wrap of the condition. The level is solved for from there like in nth_energy, its n is the
+// number of wraps below above.
+fn refine_level<F: Fn(f64) -> f64 + Sync>(
+    sommerfeld_cond: &SommerfeldCond<F>,
+    above: f64,
+) -> Result<f64, String> {
+    let unwrapped = sommerfeld_cond.phase_integral(above) / f64::consts::PI
+        - sommerfeld_cond.maslov_offset(above);
+    let n = unwrapped.floor().max(0.0) as usize;
+    solve_quantization(sommerfeld_cond, n, above, &mut Trace::new(false))
+        .map_err(|err| format!("no {} energy: {}", Ordinal(n), err))
+}
+
+// smooth semiclassical number of states below energy, S(E) / (2 pi hbar) + 1/2 with the action
+// S(E) of a full period, the 1/2 is 1 - (phi_L + phi_R) / pi for hard walls
+pub fn counting_function<F: Fn(f64) -> f64 + Sync>(
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    energy: f64,
+) -> f64 {
+    let sommerfeld_cond = SommerfeldCond {
+        mass,
+        hbar,
+        pot,
+        view,
+        steps: INTEG_STEPS,
+        boundaries: None,
+    };
+    sommerfeld_cond.phase_integral(energy) / f64::consts::PI + 1.0
+        - sommerfeld_cond.maslov_offset(energy)
+}
+
+// largest deviation of the counting function at the energies from the staircase, N(E_n) = n + 1.
+// Above 1/2 a level was skipped or found twice.
+pub fn count_mismatch<F: Fn(f64) -> f64 + Sync>(
+    energies: &[f64],
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+) -> f64 {
+    energies
+        .par_iter()
+        .enumerate()
+        .map(|(n, e)| (counting_function(mass, hbar, pot, view, *e) - (n + 1) as f64).abs())
+        .reduce(|| 0.0, f64::max)
+}
+
+// n and E_n of every level with window.0 <= E_n <= window.1. The counting function at the upper
+// end tells how many levels to scan for, one more in case it is off by a fraction.
+pub fn energies_in_window<F: Fn(f64) -> f64 + Sync>(
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    window: (f64, f64),
+) -> Result<Vec<(usize, f64)>, String> {
+    if window.0 > window.1 {
+        return Ok(vec![]);
+    }
+    let count = counting_function(mass, hbar, pot, view, window.1)
+        .ceil()
+        .max(0.0) as usize
+        + 1;
+    Ok(first_energies(count, mass, hbar, pot, view)?
+        .into_iter()
+        .enumerate()
+        .filter(|(_, e)| window.0 <= *e && *e <= window.1)
+        .collect())
+}
+
+pub struct ExtrapolatedEnergy {
+    pub energy: f64,
+    pub error: f64,
+    pub order: f64,
+    // energies with INTEG_STEPS, 2 * INTEG_STEPS, ... steps
+    pub estimates: Vec<f64>,
+}
+
+// root of the unwrapped quantization condition, integral of p / hbar = pi n + phi_L + phi_R with
+// the phase corrections of the turning points, close to guess
+fn solve_quantization<F: Fn(f64) -> f64 + Sync>(
+    sommerfeld_cond: &SommerfeldCond<F>,
+    n: usize,
+    guess: f64,
+    trace: &mut Trace,
+) -> Result<f64, RootFindError> {
+    // widenings of the bracket around guess, each doubles the step
+    const MAX_WIDENINGS: usize = 256;
+    let defect = |e: f64| {
+        sommerfeld_cond.phase_integral(e) / f64::consts::PI
+            - (n as f64 + sommerfeld_cond.maslov_offset(e))
+    };
+    let fail = |message: String, trace: &Trace| RootFindError {
+        message,
+        trace: trace.clone(),
+    };
+
+    let mut step = f64::max(guess.abs(), 1.0) * 1e-4;
+    let (mut lower, mut upper) = (guess - step, guess + step);
+    let (mut lower_value, mut upper_value) = (f64::NAN, f64::NAN);
+    for (bound, bound_value, sign) in [
+        (&mut lower, &mut lower_value, -1.0),
+        (&mut upper, &mut upper_value, 1.0),
+    ] {
+        let mut widenings = 0;
+        loop {
+            let value = defect(*bound);
+            trace.record(*bound, value, sign * step);
+            // NaN never brackets
+            if value * sign >= 0.0 {
+                *bound_value = value;
+                break;
+            }
+            if widenings == MAX_WIDENINGS || !bound.is_finite() {
+                return Err(fail(
+                    format!(
+                        "the quantization condition doesn't change sign {} {}",
+                        if sign < 0.0 { "below" } else { "above" },
+                        guess
+                    ),
+                    trace,
+                ));
+            }
+            *bound += sign * step;
+            step *= 2.0;
+            widenings += 1;
+        }
+    }
+
+    // ENERGY_ROOT_METHOD steps from the last iterate as long as it stays in the bracket and halves
+    // it at least every other step, the bracket is bisected otherwise
+    let mut candidate = match ENERGY_ROOT_METHOD {
+        Some(_) => guess,
+        None => (lower + upper) / 2.0,
+    };
+    let mut previous: Option<(f64, f64)> = None;
+    let mut slow = false;
+    for _ in 0..100 {
+        let width = upper - lower;
+        let middle = (lower + upper) / 2.0;
+        if middle == lower || middle == upper {
+            break;
+        }
+        let x = if candidate > lower && candidate < upper {
+            candidate
+        } else {
+            middle
+        };
+        let value = defect(x);
+        trace.record(x, value, width);
+        if value == 0.0 || ENERGY_TOLERANCE.converged(x, width, value) {
+            return Ok(x);
+        }
+        if value < 0.0 {
+            lower = x;
+            lower_value = value;
+        } else {
+            upper = x;
+            upper_value = value;
+        }
+
+        let shrunk = upper - lower <= width / 2.0;
+        candidate = match ENERGY_ROOT_METHOD {
+            Some(method) if shrunk || !slow => {
+                // the first secant goes through the other end of the bracket
+                let other = if value < 0.0 {
+                    (upper, upper_value)
+                } else {
+                    (lower, lower_value)
+                };
+                x - newtons_method::root_step(&defect, method, x, value, previous.or(Some(other)))
+            }
+            _ => (lower + upper) / 2.0,
+        };
+        slow = !shrunk;
+        previous = Some((x, value));
+    }
+    Ok((lower + upper) / 2.0)
+}
+
+// Solves the quantization condition with 2 or 3 step counts and Richardson-extrapolates to
+// infinitely many steps. With 3 step counts the order of convergence is estimated as well,
+// otherwise the order of the trapezoidal rule with square root edges at the turning points is assumed.
+pub fn extrapolate_energy<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    guess: f64,
+    levels: usize,
+) -> ExtrapolatedEnergy {
+    const ASSUMED_ORDER: f64 = 1.5;
+    assert!(levels == 2 || levels == 3, "levels has to be 2 or 3");
+
+    let estimates: Vec<f64> = (0..levels)
+        .map(|k| {
+            let sommerfeld_cond = SommerfeldCond {
+                mass,
+                hbar,
+                pot,
+                view,
+                steps: INTEG_STEPS << k,
+                boundaries: None,
+            };
+            solve_quantization(&sommerfeld_cond, n, guess, &mut Trace::new(false))
+                .unwrap_or_else(|err| panic!("no {} energy: {}", Ordinal(n), err))
+        })
+        .collect();
+
+    let order = if levels == 3 {
+        let ratio = (estimates[0] - estimates[1]) / (estimates[1] - estimates[2]);
+        if ratio.is_finite() && ratio > 1.0 {
+            ratio.log2()
+        } else {
+            ASSUMED_ORDER
+        }
+    } else {
+        ASSUMED_ORDER
+    };
+
+    let finest = estimates[levels - 1];
+    let correction = (finest - estimates[levels - 2]) / (2.0f64.powf(order) - 1.0);
+    ExtrapolatedEnergy {
+        energy: finest + correction,
+        error: correction.abs(),
+        order,
+        estimates,
+    }
+}
+
+// prints the nth energy found by the scan, with RICHARDSON_LEVELS > 0 the extrapolated energy is
+// printed and returned instead
+pub fn report_energy<F: Fn(f64) -> f64 + Sync>(
+    n: usize,
+    mass: f64,
+    hbar: f64,
+    pot: &F,
+    view: (f64, f64),
+    energy: f64,
+) -> f64 {
+    if RICHARDSON_LEVELS == 0 {
+        println!("{} Energy: {:.9}", Ordinal(n), energy);
+        return energy;
+    }
+
+    let extrapolated = extrapolate_energy(n, mass, hbar, pot, view, energy, RICHARDSON_LEVELS);
+    println!(
+        "{} Energy: {:.9} +- {:.1e} (order {:.2}, estimates {:?})",
+        Ordinal(n),
+        extrapolated.energy,
+        extrapolated.error,
+        extrapolated.order,
+        extrapolated.estimates
+    );
+    extrapolated.energy
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn extrapolated_harmonic_oscillator() {
+        // WKB is exact for V = x^2, E_n = (n + 1/2) sqrt(2) with m = 1
+        let view = (-10.0, 10.0);
+        let exact = 2.5 * 2.0f64.sqrt();
+        let result = extrapolate_energy(2, 1.0, 1.0, &potentials::square, view, exact + 1e-3, 3);
+
+        assert_eq!(result.estimates.len(), 3);
+        assert!((result.energy - exact).abs() <= (result.estimates[2] - exact).abs());
+        assert!((result.energy - exact).abs() < 1e-6);
+        assert!(result.error < 1e-4);
+    }
+
+    #[test]
+    fn oscillator_with_other_hbar() {
+        // E_n = hbar omega (n + 1/2) with omega = sqrt(2 / m) for V = x^2
+        let hbar = 0.5;
+        for n in 0..4 {
+            let energy = nth_energy(n, 1.0, hbar, &potentials::square, APPROX_INF);
+            let exact = hbar * 2.0f64.sqrt() * (n as f64 + 0.5);
+            assert!(
+                (energy - exact).abs() < 1e-4 * exact,
+                "{} != {}",
+                energy,
+                exact
+            );
+        }
+        let estimates = harmonic_estimates(2, 1.0, hbar, &potentials::square, APPROX_INF);
+        assert!(float_compare(
+            estimates[1],
+            1.5 * hbar * 2.0f64.sqrt(),
+            1e-6
+        ));
+    }
+
+    #[test]
+    fn traced_energy() {
+        let mut trace = Trace::new(true);
+        let energy = nth_energy_traced(
+            2,
+            1.0,
+            1.0,
+            &potentials::square,
+            APPROX_INF,
+            None,
+            &mut trace,
+        )
+        .unwrap();
+        let last = trace.get_iterates().last().unwrap();
+        assert!((last.x - energy).abs() < 1e-9);
+        assert!(last.value.abs() < 1e-6);
+
+        // the integral vanishes for a potential that is NaN everywhere, the bracket never closes
+        let nan = |_: f64| f64::NAN;
+        let sommerfeld_cond = SommerfeldCond {
+            mass: 1.0,
+            hbar: 1.0,
+            pot: &nan,
+            view: (-1.0, 1.0),
+            steps: 100,
+            boundaries: None,
+        };
+        let err = solve_quantization(&sommerfeld_cond, 0, 1.0, &mut Trace::new(true)).unwrap_err();
+        assert!(err.message.contains("doesn't change sign above"));
+        assert!(err.trace.get_iterates().len() > 256);
+    }
+
+    #[test]
+    fn harmonic_estimates_of_square() {
+        let estimates = harmonic_estimates(4, 1.0, 1.0, &potentials::square, APPROX_INF);
+
+        assert_eq!(estimates.len(), 4);
+        for (n, e) in estimates.iter().enumerate() {
+            assert!(float_compare(*e, (n as f64 + 0.5) * 2.0f64.sqrt(), 1e-6));
+        }
+    }
+
+    #[test]
+    fn window_of_square() {
+        // E_n = (n + 1/2) sqrt(2) = 0.71, 2.12, 3.54, 4.95, 6.36, ...
+        let levels =
+            energies_in_window(1.0, 1.0, &potentials::square, APPROX_INF, (2.0, 6.0)).unwrap();
+        assert_eq!(
+            levels.iter().map(|(n, _)| *n).collect::<Vec<usize>>(),
+            vec![1, 2, 3]
+        );
+        for (n, e) in levels {
+            assert!(float_compare(e, (n as f64 + 0.5) * 2.0f64.sqrt(), 1e-3));
+        }
+        assert!(
+            energies_in_window(1.0, 1.0, &potentials::square, APPROX_INF, (-2.0, 0.5))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            energies_in_window(1.0, 1.0, &potentials::square, APPROX_INF, (6.0, 2.0))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn seeded_energy_matches_scan() {
+        let energies = first_energies(6, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF).unwrap();
+        // both solve the same condition to the precision of the floats
+        for (n, scanned) in energies.iter().enumerate() {
+            let energy = nth_energy(n, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF);
+            assert!(
+                float_compare(energy, *scanned, 1e-9),
+                "{} != {}",
+                energy,
+                scanned
+            );
+        }
+    }
+
+    #[test]
+    fn counting_function_follows_the_staircase() {
+        let energies: Vec<f64> = (0..10)
+            .map(|n| nth_energy(n, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF))
+            .collect();
+        assert!(count_mismatch(&energies, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF) < 1e-3);
+
+        // dropping a level shows up as a mismatch of one
+        let skipped = [&energies[..4], &energies[5..]].concat();
+        let mismatch = count_mismatch(&skipped, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF);
+        assert!((mismatch - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hard_walls() {
+        // infinite square well of width 4, E_n = pi^2 (n + 1)^2 / 32, both walls take pi / 2
+        let finite_box = |x: f64| if x.abs() < 2.0 { 0.0 } else { 1e6 };
+        let energies = first_energies(3, 1.0, 1.0, &finite_box, (-3.0, 3.0)).unwrap();
+        for (n, energy) in energies.iter().enumerate() {
+            let exact = (f64::consts::PI * (n + 1) as f64).powi(2) / 32.0;
+            assert!((energy - exact).abs() < 1e-3, "{} != {}", energy, exact);
+        }
+
+        // a wall at the bottom of x^2 keeps the odd levels, E = (2n + 3/2) sqrt(2)
+        let half_square = |x: f64| if x < 0.0 { 1e6 } else { x * x };
+        for n in 0..3 {
+            let energy = nth_energy(n, 1.0, 1.0, &half_square, APPROX_INF);
+            let exact = (2.0 * n as f64 + 1.5) * 2.0f64.sqrt();
+            assert!((energy - exact).abs() < 1e-3, "{} != {}", energy, exact);
+        }
+    }
+
+    #[test]
+    fn scan_without_bound_states() {
+        // nothing is bound above V at the ends of approx_inf, the scan stops there
+        let err = first_energies(1, 1.0, 1.0, &potentials::barrier, APPROX_INF).unwrap_err();
+        assert!(err.contains("found 0 of 1 levels"), "{}", err);
+        let err = nth_energy_traced(
+            0,
+            1.0,
+            1.0,
+            &potentials::field_emission,
+            APPROX_INF,
+            None,
+            &mut Trace::new(false),
+        )
+        .unwrap_err();
+        assert!(err.message.contains("found 0 of 1 levels"), "{}", err);
+    }
+
+    #[test]
+    fn checked_energy_of_missing_level() {
+        // binds a single state below the continuum at 1
+        let well = |x: f64| if x.abs() < 1.0 { 0.0 } else { 1.0 };
+        let err = nth_energy_checked(3, 1.0, 1.0, &well, APPROX_INF).unwrap_err();
+        assert!(err.starts_with("no 3rd energy"), "{}", err);
+        assert_eq!(
+            nth_energy_checked(2, 1.0, 1.0, &potentials::square, APPROX_INF),
+            Ok(nth_energy(2, 1.0, 1.0, &potentials::square, APPROX_INF))
+        );
+    }
+
+    #[test]
+    fn negative_well() {
+        // the scan in first_energies starts at zero and misses levels below it
+        let well = |x: f64| x * x - 50.0;
+        let energy = nth_energy(1, 1.0, 1.0, &well, (-20.0, 20.0));
+        assert!(
+            (energy - (1.5 * 2.0f64.sqrt() - 50.0)).abs() < 1e-4,
+            "{}",
+            energy
+        );
     }
 }
@@ -0,0 +1,194 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const FIXTURE_FILE: &str = "fixtures/regression.json";
+// psi is sampled at this many evenly spaced points of the view
+const CHECKPOINTS: usize = 11;
+// relative to the recorded energy and to the largest recorded |psi|, psi changes by about as much as
+// Ai, so the Go library and the airy_series fallback both stay far within them
+const ENERGY_TOLERANCE: f64 = 1e-6;
+const PSI_TOLERANCE: f64 = 1e-4;
+
+// potential (registry spec), mass and n of every recorded reference
+pub const CASES: [(&str, f64, usize); 8] = [
+    ("square", 1.0, 0),
+    ("square", 1.0, 5),
+    ("harmonic", 1.0, 0),
+    ("harmonic", 1.0, 3),
+    ("morse(d=10,a=1)", 1.0, 2),
+    ("mexican_hat", 1.0, 0),
+    ("mexican_hat", 1.0, 4),
+    ("double_mexican_hat", 1.0, 1),
+];
+
+// reference output of one wave function, psi at the checkpoints x
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Fixture {
+    pub potential: String,
+    pub mass: f64,
+    pub n: usize,
+    pub energy: f64,
+    pub x: Vec<f64>,
+    pub re: Vec<f64>,
+    pub im: Vec<f64>,
+}
+
+fn wave_function(potential: &str, mass: f64, n: usize) -> Result<WaveFunction, String> {
+    Ok(WaveFunction::new(
+        registry::resolve(potential)?,
+        mass,
+        n,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::Renormalize(1.0.into()),
+    ))
+}
+
+pub fn record(potential: &str, mass: f64, n: usize) -> Result<Fixture, String> {
+    let wave_function = wave_function(potential, mass, n)?;
+    let view = wave_function.get_view();
+    let values = evaluate_function_between(&wave_function, view.0, view.1, CHECKPOINTS);
+    Ok(Fixture {
+        potential: potential.to_string(),
+        mass,
+        n,
+        energy: wave_function.get_energy(),
+        x: values.iter().map(|p| p.x).collect(),
+        re: values.iter().map(|p| p.y.re).collect(),
+        im: values.iter().map(|p| p.y.im).collect(),
+    })
+}
+
+pub fn record_all() -> Result<Vec<Fixture>, String> {
+    CASES
+        .iter()
+        .map(|(potential, mass, n)| record(potential, *mass, *n))
+        .collect()
+}
+
+pub fn write(fixtures: &[Fixture], path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("can't create {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(fixtures).unwrap();
+    std::fs::write(path, json).map_err(|e| format!("can't write {}: {}", path.display(), e))
+}
+
+pub fn read(path: &Path) -> Result<Vec<Fixture>, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "can't read {}: {}, record the fixtures first",
+            path.display(),
+            e
+        )
+    })?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("invalid fixtures in {}: {}", path.display(), e))
+}
+
+// relative deviations of a new run from a fixture
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Deviation {
+    pub energy: f64,
+    pub psi: f64,
+}
+
+impl Deviation {
+    pub fn passed(&self) -> bool {
+        self.energy <= ENERGY_TOLERANCE && self.psi <= PSI_TOLERANCE
+    }
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} energy {:.2e} psi {:.2e}",
+            if self.passed() { "ok  " } else { "FAIL" },
+            self.energy,
+            self.psi
+        )
+    }
+}
+
+pub fn compare(fixture: &Fixture, wave_function: &WaveFunction) -> Deviation {
+    let scale = fixture
+        .re
+        .iter()
+        .zip(&fixture.im)
+        .map(|(re, im)| complex(*re, *im).norm())
+        .fold(0.0, f64::max);
+    let psi = fixture
+        .x
+        .iter()
+        .zip(fixture.re.iter().zip(&fixture.im))
+        .map(|(x, (re, im))| (wave_function.eval(*x) - complex(*re, *im)).norm())
+        .fold(0.0, f64::max);
+    Deviation {
+        energy: ((wave_function.get_energy() - fixture.energy) / fixture.energy).abs(),
+        psi: if scale > 0.0 { psi / scale } else { psi },
+    }
+}
+
+// recomputes every fixture, fails if one of them can't be computed
+pub fn check(fixtures: &[Fixture]) -> Result<Vec<Deviation>, String> {
+    fixtures
+        .iter()
+        .map(|fixture| {
+            let wave_function = wave_function(&fixture.potential, fixture.mass, fixture.n)?;
+            Ok(compare(fixture, &wave_function))
+        })
+        .collect()
+}
+
+// compares new runs against the fixtures recorded in path, false if one of them deviates more than
+// its tolerance
+pub fn regress(path: &Path) -> Result<bool, String> {
+    let recorded = read(path)?;
+    let deviations = check(&recorded)?;
+    for (fixture, deviation) in recorded.iter().zip(&deviations) {
+        println!("{} {} n = {}", deviation, fixture.potential, fixture.n);
+    }
+    let failed = deviations.iter().filter(|d| !d.passed()).count();
+    println!("{} of {} fixtures deviate", failed, deviations.len());
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixture_round_trip() {
+        let fixture = record("square", 1.0, 1).unwrap();
+        assert_eq!(fixture.x.len(), CHECKPOINTS);
+        assert!(record("nonexistent", 1.0, 1).is_err());
+
+        let path = std::env::temp_dir()
+            .join("schroedinger_fixtures_test")
+            .join("regression.json");
+        write(std::slice::from_ref(&fixture), &path).unwrap();
+        // json keeps the values up to rounding in the last digit
+        let fixtures = read(&path).unwrap();
+        assert_eq!(fixtures.len(), 1);
+        assert_eq!(fixtures[0].potential, "square");
+
+        let deviations = check(&fixtures).unwrap();
+        assert!(deviations[0].passed(), "{}", deviations[0]);
+
+        // a different level doesn't match
+        let mut changed = fixture;
+        changed.n = 2;
+        assert!(!check(&[changed]).unwrap()[0].passed());
+    }
+
+    #[test]
+    fn recorded_fixtures() {
+        // relative to the crate, other tests change the working directory
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE_FILE);
+        assert_eq!(read(&path).unwrap().len(), CASES.len());
+        assert!(regress(&path).unwrap());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::*;
+use rayon::prelude::*;
+
+// Sample positions shared between evaluating, integrating and plotting, so a later stage can reuse
+// the sampling of an earlier one instead of generating its own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Grid {
+    Uniform { range: (f64, f64), n: usize },
+    // Chebyshev points of the second kind, clustered towards the ends, in ascending order
+    Chebyshev { range: (f64, f64), n: usize },
+    // sorted points, see Grid::adaptive
+    Adaptive { points: Vec<f64> },
+}
+
+impl Grid {
+    pub fn uniform(range: (f64, f64), n: usize) -> Grid {
+        Grid::Uniform { range, n }
+    }
+
+    // start, start + step, ... up to end, which is included if it is on the grid
+    pub fn stepped(start: f64, end: f64, step: f64) -> Grid {
+        assert!(
+            step > 0.0 && end >= start,
+            "no grid from {} to {} in steps of {}",
+            start,
+            end,
+            step
+        );
+        // rounding shouldn't drop a point on end
+        let n = ((end - start) / step * (1.0 + 1e-12)).floor() as usize + 1;
+        Grid::uniform((start, start + (n - 1) as f64 * step), n)
+    }
+
+    pub fn chebyshev(range: (f64, f64), n: usize) -> Grid {
+        Grid::Chebyshev { range, n }
+    }
+
+    // starts uniform with n points and halves every interval whose midpoint is further than
+    // tolerance from the line between its ends, until nothing changes or max_points is reached
+    pub fn adaptive(
+        func: &dyn Func<f64, Complex64>,
+        range: (f64, f64),
+        n: usize,
+        tolerance: f64,
+        max_points: usize,
+    ) -> Grid {
+        let mut values = Grid::uniform(range, n).evaluate(func);
+
+        while values.len() < max_points {
+            let midpoints: Vec<Option<Point<f64, Complex64>>> = values
+                .par_windows(2)
+                .map(|w| {
+                    let x = (w[0].x + w[1].x) / 2.0;
+                    let y = func.eval(x);
+                    if (y - (w[0].y + w[1].y) / 2.0).norm() > tolerance {
+                        Some(Point { x, y })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            if midpoints.iter().all(|m| m.is_none()) {
+                break;
+            }
+
+            let mut refined = Vec::with_capacity(values.len() + midpoints.len());
+            for (point, midpoint) in values.iter().zip(midpoints) {
+                refined.push(point.clone());
+                refined.extend(midpoint);
+            }
+            refined.push(values.last().unwrap().clone());
+            values = refined;
+        }
+
+        Grid::Adaptive {
+            points: values.iter().map(|p| p.x).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Grid::Uniform { n, .. } | Grid::Chebyshev { n, .. } => *n,
+            Grid::Adaptive { points } => points.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_range(&self) -> (f64, f64) {
+        match self {
+            Grid::Uniform { range, .. } | Grid::Chebyshev { range, .. } => *range,
+            Grid::Adaptive { points } => (points[0], points[points.len() - 1]),
+        }
+    }
+
+    pub fn x(&self, i: usize) -> f64 {
+        match self {
+            Grid::Uniform { range, n } => {
+                index_to_range(i as f64, 0.0, (n - 1) as f64, range.0, range.1)
+            }
+            Grid::Chebyshev { range, n } => {
+                let t = -(f64::consts::PI * i as f64 / (n - 1) as f64).cos();
+                (range.0 + range.1) / 2.0 + t * (range.1 - range.0) / 2.0
+            }
+            Grid::Adaptive { points } => points[i],
+        }
+    }
+
+    pub fn points(&self) -> Vec<f64> {
+        (0..self.len()).into_par_iter().map(|i| self.x(i)).collect()
+    }
+
+    pub fn evaluate<Y: Send + Sync>(&self, f: &dyn Func<f64, Y>) -> Vec<Point<f64, Y>> {
+        if self.get_range().0 == self.get_range().1 {
+            return vec![];
+        }
+
+        (0..self.len())
+            .into_par_iter()
+            .map(|i| {
+                task::check_cancelled();
+                let x = self.x(i);
+                Point { x, y: f.eval(x) }
+            })
+            .collect()
+    }
+
+    // trapezoidal rule on the points of the grid
+    pub fn integrate<Y>(&self, f: &dyn Func<f64, Y>) -> Y
+    where
+        Y: Default
+            + Sync
+            + std::ops::AddAssign
+            + std::ops::Div<f64, Output = Y>
+            + std::ops::Mul<Output = Y>
+            + std::ops::Add<Output = Y>
+            + Send
+            + std::iter::Sum<Y>
+            + Copy
+            + From<f64>,
+    {
+        integrate(self.evaluate(f), TRAPEZE_PER_THREAD)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uniform_matches_evaluate_function_between() {
+        let f = Function::<f64, f64>::new(|x| x * x);
+        let grid = Grid::uniform((-1.5, 2.0), 101);
+        let expected = evaluate_function_between(&f, -1.5, 2.0, 101);
+
+        assert_eq!(grid.len(), 101);
+        for (p, e) in grid.evaluate(&f).iter().zip(expected.iter()) {
+            assert_eq!(p.x, e.x);
+            assert_eq!(p.y, e.y);
+        }
+    }
+
+    #[test]
+    fn stepped_grids_line_up() {
+        let grid = Grid::stepped(-1.0, 2.0, 0.1);
+        assert_eq!(grid.len(), 31);
+        assert!(float_compare(grid.get_range().1, 2.0, 1e-12));
+        assert_eq!(Grid::stepped(-1.0, 2.05, 0.1), grid);
+        assert_eq!(Grid::stepped(0.0, 0.0, 1.0).len(), 1);
+
+        let points = grid.points();
+        for (i, x) in points.iter().enumerate() {
+            assert!((x - (-1.0 + i as f64 * 0.1)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn chebyshev_points_are_ascending() {
+        let grid = Grid::chebyshev((0.0, 3.0), 50);
+        let points = grid.points();
+
+        assert!(float_compare(points[0], 0.0, 1e-12));
+        assert!(float_compare(points[49], 3.0, 1e-12));
+        assert!(points.windows(2).all(|w| w[0] < w[1]));
+        // clustered towards the ends
+        assert!(points[1] - points[0] < points[25] - points[24]);
+    }
+
+    #[test]
+    fn adaptive_refines_peak() {
+        let peak = Function::<f64, Complex64>::new(|x| (-100.0 * x * x).exp().into());
+        let grid = Grid::adaptive(&peak, (-5.0, 5.0), 11, 1e-4, 10000);
+        let points = grid.points();
+
+        assert_eq!(grid.get_range(), (-5.0, 5.0));
+        assert!(points.windows(2).all(|w| w[0] < w[1]));
+        let near_peak = points.iter().filter(|x| x.abs() < 0.5).count();
+        assert!(near_peak > points.len() / 2);
+        assert!(float_compare(
+            grid.integrate(&peak).re,
+            (f64::consts::PI / 100.0).sqrt(),
+            1e-3
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::*;
+
+const R_MIN: f64 = 1e-6;
+const R_MAX: f64 = 200.0;
+const ORBITAL_LETTERS: [char; 7] = ['s', 'p', 'd', 'f', 'g', 'h', 'i'];
+
+// radial problem of a hydrogen-like atom V(r) = -Z/r (Gaussian units) with the Langer-corrected
+// centrifugal term (l + 1/2)^2 hbar^2 / (2 m r^2)
+#[derive(Clone, Copy)]
+pub struct HydrogenLike {
+    pub z: f64,
+    pub mass: f64,
+    pub n: usize,
+    pub l: usize,
+    pub units: Units,
+}
+
+impl HydrogenLike {
+    pub fn new(z: f64, mass: f64, n: usize, l: usize) -> HydrogenLike {
+        assert!(n >= 1, "principal quantum number has to be at least 1");
+        assert!(l < n, "l = {} is not allowed for n = {}", l, n);
+        HydrogenLike {
+            z,
+            mass,
+            n,
+            l,
+            units: UNITS,
+        }
+    }
+
+    // z and mass are in these units
+    pub fn with_units(mut self, units: Units) -> HydrogenLike {
+        self.units = units;
+        self
+    }
+
+    fn langer_term(&self) -> f64 {
+        (self.l as f64 + 0.5).powi(2) * self.units.hbar.powi(2) / (2.0 * self.mass)
+    }
+
+    // reached at r = (l + 1/2)^2 hbar^2 / (m Z)
+    pub fn potential_minimum(&self) -> f64 {
+        -self.mass * self.z.powi(2)
+            / (2.0 * (self.l as f64 + 0.5).powi(2) * self.units.hbar.powi(2))
+    }
+
+    // shifted by its minimum, nth_energy only searches for positive energies
+    pub fn effective_potential(&self) -> SharedPotential {
+        let z = self.z;
+        let langer_term = self.langer_term();
+        let offset = self.potential_minimum();
+        Arc::new(move |r: f64| -> f64 { -z / r + langer_term / (r * r) - offset })
+    }
+
+    pub fn radial_quantum_number(&self) -> usize {
+        self.n - self.l - 1
+    }
+
+    pub fn exact_energy(&self) -> f64 {
+        -self.mass * self.z.powi(2) / (2.0 * (self.n as f64).powi(2) * self.units.hbar.powi(2))
+    }
+
+    pub fn semiclassical_energy(&self, wave_function: &WaveFunction) -> f64 {
+        wave_function.get_energy() + self.potential_minimum()
+    }
+
+    // without spin
+    pub fn degeneracy(&self) -> usize {
+        self.n * self.n
+    }
+
+    pub fn label(&self) -> String {
+        match ORBITAL_LETTERS.get(self.l) {
+            Some(letter) => format!("{}{}", self.n, letter),
+            None => format!("{}(l={})", self.n, self.l),
+        }
+    }
+
+    // fails if the energy, the turning points or the parts can't be found
+    pub fn wave_function(&self, scaling: ScalingType) -> Result<WaveFunction, String> {
+        // the exponential WKB part doesn't go like r^(l + 1) towards the origin, psi is mapped onto
+        // Bessel's equation there
+        let mut wave_function = WaveFunctionBuilder::new(self.effective_potential(), self.mass)
+            .with_units(self.units)
+            .with_approx_inf((R_MIN, R_MAX))
+            .with_view_factor(VIEW_FACTOR)
+            .with_scaling(scaling)
+            .with_approximation(ApproximationKind::Uniform)
+            .solve_energy(self.radial_quantum_number())?
+            .find_turning_points()?
+            .build_parts()?
+            .scale();
+        let view = wave_function.get_view();
+        wave_function.set_view((f64::max(R_MIN, view.0), view.1));
+        Ok(wave_function)
+    }
+
+    // semiclassical against exact energy of a state from wave_function
+    pub fn energy_report(&self, wave_function: &WaveFunction) -> String {
+        let energy = self.semiclassical_energy(wave_function);
+        let exact = self.exact_energy();
+        format!(
+            "{} (degeneracy {}): semiclassical energy {:.9}, exact {:.9}, relative error {:.3e}",
+            self.label(),
+            self.degeneracy(),
+            energy,
+            exact,
+            ((energy - exact) / exact).abs()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert_eq!(HydrogenLike::new(1.0, 1.0, 1, 0).label(), "1s");
+        assert_eq!(HydrogenLike::new(1.0, 1.0, 3, 2).label(), "3d");
+        assert_eq!(HydrogenLike::new(1.0, 1.0, 9, 7).label(), "9(l=7)");
+        assert_eq!(HydrogenLike::new(1.0, 1.0, 3, 2).degeneracy(), 9);
+        assert_eq!(HydrogenLike::new(1.0, 1.0, 4, 1).radial_quantum_number(), 2);
+    }
+
+    #[test]
+    fn effective_potential_minimum_is_zero() {
+        for l in 0..4 {
+            let hydrogen = HydrogenLike::new(2.0, 1.0, 5, l);
+            let potential = hydrogen.effective_potential();
+            let r_min = (l as f64 + 0.5).powi(2) * hydrogen.units.hbar.powi(2)
+                / (hydrogen.mass * hydrogen.z);
+
+            assert!(potential(r_min).abs() < 1e-12);
+            assert!(potential(r_min * 0.9) > 0.0);
+            assert!(potential(r_min * 1.1) > 0.0);
+        }
+    }
+
+    #[test]
+    fn exact_energy() {
+        let hydrogen = HydrogenLike::new(1.0, 1.0, 2, 1);
+        assert_eq!(hydrogen.exact_energy(), -0.125);
+
+        // E_n = -m Z^2 / (2 n^2 hbar^2)
+        let units = Units {
+            hbar: 0.5,
+            ..Units::ATOMIC
+        };
+        assert_eq!(hydrogen.with_units(units).exact_energy(), -0.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn l_too_large() {
+        HydrogenLike::new(1.0, 1.0, 2, 2);
+    }
+}
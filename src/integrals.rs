@@ -1,5 +1,6 @@
 use crate::*;
 use rayon::prelude::*;
+use std::cmp::Ordering;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone)]
@@ -56,6 +57,7 @@ pub fn integrate<
     let parallel: Y = batches
         .par_iter()
         .map(|batch| {
+            task::check_cancelled();
             let mut sum = Y::default();
             for i in 0..(batch.len() - 1) {
                 sum += trapezoidal_approx(&batch[i], &batch[i + 1]);
@@ -101,10 +103,157 @@ where
                 b,
             )
         })
-        .map(|x: X| Point { x, y: f.eval(x) })
+        .map(|x: X| {
+            task::check_cancelled();
+            Point { x, y: f.eval(x) }
+        })
         .collect()
 }
 
+pub fn evaluate_function_on_grid<Y>(
+    f: &dyn Func<(f64, f64), Y>,
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    n_x: usize,
+    n_y: usize,
+) -> Vec<Vec<Point<(f64, f64), Y>>>
+where
+    Y: Send + Sync,
+{
+    (0..n_x)
+        .into_par_iter()
+        .map(|i| {
+            let x = index_to_range(i as f64, 0.0, (n_x - 1) as f64, x_range.0, x_range.1);
+            (0..n_y)
+                .map(|j| {
+                    let y = index_to_range(j as f64, 0.0, (n_y - 1) as f64, y_range.0, y_range.1);
+                    Point {
+                        x: (x, y),
+                        y: f.eval((x, y)),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// running integral, the ith value is the integral from the first to the ith point
+pub fn cumulative_integral(points: &[Point<f64, f64>]) -> Vec<f64> {
+    let mut sum = 0.0;
+    let mut cumulative = Vec::with_capacity(points.len());
+    if !points.is_empty() {
+        cumulative.push(0.0);
+    }
+    for i in 1..points.len() {
+        sum += trapezoidal_approx(&points[i - 1], &points[i]);
+        cumulative.push(sum);
+    }
+    cumulative
+}
+
+// x where the cumulative integral of the points reaches target, linear within the step it falls
+// into, the start of the step if it has no width, e.g. where |psi|^2 underflows to 0
+pub fn inverse_cumulative(points: &[Point<f64, f64>], cumulative: &[f64], target: f64) -> f64 {
+    let i = cumulative
+        .partition_point(|c| *c < target)
+        .clamp(1, cumulative.len() - 1);
+    let fraction = if cumulative[i] > cumulative[i - 1] {
+        (target - cumulative[i - 1]) / (cumulative[i] - cumulative[i - 1])
+    } else {
+        0.0
+    };
+    points[i - 1].x + fraction * (points[i].x - points[i - 1].x)
+}
+
+// Richardson estimate of the error of integrate on these points, compares with the trapezoidal
+// rule on every second point
+pub fn integration_error(points: &[Point<f64, f64>]) -> f64 {
+    let points = if points.len().is_multiple_of(2) && !points.is_empty() {
+        &points[..points.len() - 1]
+    } else {
+        points
+    };
+    let fine = integrate(points.to_vec(), TRAPEZE_PER_THREAD);
+    let coarse = integrate(
+        points.iter().step_by(2).cloned().collect(),
+        TRAPEZE_PER_THREAD,
+    );
+    (fine - coarse).abs() / 3.0
+}
+
+// Running integral of f on n evenly spaced nodes from start, Simpson's rule on every interval and
+// cubic Hermite interpolation with f as the slopes in between. Built once where the integral up to
+// many points is needed.
+#[derive(Clone, Debug, Default)]
+pub struct IntegralTable {
+    start: f64,
+    step: f64,
+    values: Vec<f64>,
+    slopes: Vec<f64>,
+}
+
+impl IntegralTable {
+    pub fn new<F: Fn(f64) -> f64 + Sync>(f: F, start: f64, step: f64, n: usize) -> IntegralTable {
+        let slopes = (0..n)
+            .into_par_iter()
+            .map(|i| f(start + i as f64 * step))
+            .collect::<Vec<f64>>();
+        let middles = (0..n - 1)
+            .into_par_iter()
+            .map(|i| f(start + (i as f64 + 0.5) * step))
+            .collect::<Vec<f64>>();
+        let mut accumulated = 0.0;
+        let mut values = vec![0.0; n];
+        for i in 1..n {
+            accumulated += step / 6.0 * (slopes[i - 1] + 4.0 * middles[i - 1] + slopes[i]);
+            values[i] = accumulated;
+        }
+        IntegralTable {
+            start,
+            step,
+            values,
+            slopes,
+        }
+    }
+
+    // about n nodes within range, shifted so that anchor is one of them, e.g. a turning point
+    // where f has a kink
+    pub fn through<F: Fn(f64) -> f64 + Sync>(
+        f: F,
+        anchor: f64,
+        range: (f64, f64),
+        n: usize,
+    ) -> IntegralTable {
+        let step = (range.1 - range.0) / (n - 1) as f64;
+        if step.partial_cmp(&0.0) != Some(Ordering::Greater) {
+            return IntegralTable::default();
+        }
+        let first = ((range.0 - anchor) / step).ceil();
+        let last = ((range.1 - anchor) / step).floor();
+        IntegralTable::new(f, anchor + first * step, step, (last - first) as usize + 1)
+    }
+
+    // integral from the first node to x, NaN outside of the nodes
+    pub fn integral(&self, x: f64) -> f64 {
+        if self.values.len() < 2 {
+            return f64::NAN;
+        }
+        let last = (self.values.len() - 1) as f64;
+        // the ends of the range may round to just outside of the nodes
+        let position = (x - self.start) / self.step;
+        if !(position >= -1e-9 && position <= last + 1e-9) {
+            return f64::NAN;
+        }
+        let position = position.clamp(0.0, last);
+        let i = (position.floor() as usize).min(self.values.len() - 2);
+        let t = position - i as f64;
+        (2.0 * t.powi(3) - 3.0 * t * t + 1.0) * self.values[i]
+            + (t.powi(3) - 2.0 * t * t + t) * self.step * self.slopes[i]
+            + (3.0 * t * t - 2.0 * t.powi(3)) * self.values[i + 1]
+            + (t.powi(3) - t * t) * self.step * self.slopes[i + 1]
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -181,6 +330,40 @@ mod test {
         }
     }
 
+    #[test]
+    fn cumulative_integral_of_line() {
+        let points =
+            evaluate_function_between(&Function::<f64, f64>::new(|x| 2.0 * x), 0.0, 2.0, 5);
+        assert_eq!(
+            cumulative_integral(&points),
+            vec![0.0, 0.25, 1.0, 2.25, 4.0]
+        );
+    }
+
+    #[test]
+    fn integration_error_of_parabola() {
+        let points =
+            evaluate_function_between(&Function::<f64, f64>::new(|x| x * x), 0.0, 1.0, 101);
+        let actual_error = (integrate(points.clone(), TRAPEZE_PER_THREAD) - 1.0 / 3.0).abs();
+        let estimate = integration_error(&points);
+        assert!((estimate - actual_error).abs() < 1e-3 * actual_error);
+    }
+
+    #[test]
+    fn evaluate_product_on_grid() {
+        let product = Function::new(|(x, y): (f64, f64)| complex(x * y, 0.0));
+        let grid = evaluate_function_on_grid(&product, (-1.0, 1.0), (0.0, 2.0), 3, 5);
+
+        assert_eq!(grid.len(), 3);
+        for (i, row) in grid.iter().enumerate() {
+            assert_eq!(row.len(), 5);
+            for (j, p) in row.iter().enumerate() {
+                assert_eq!(p.x, (i as f64 - 1.0, j as f64 * 0.5));
+                assert_eq!(p.y, complex(p.x.0 * p.x.1, 0.0));
+            }
+        }
+    }
+
     fn sinusoidal_exp_complex(x: f64) -> Complex64 {
         return complex(x, x).exp();
     }
@@ -220,4 +403,27 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn integral_table_of_cosine() {
+        let table = IntegralTable::new(f64::cos, -1.0, 0.01, 301);
+        for x in [-1.0f64, -0.503, 0.0, 1.234, 2.0] {
+            let exact = x.sin() + 1.0f64.sin();
+            assert!((table.integral(x) - exact).abs() < 1e-9, "{}", x);
+        }
+        assert!(table.integral(-1.1).is_nan());
+        assert!(table.integral(2.1).is_nan());
+    }
+
+    #[test]
+    fn integral_table_through_kink() {
+        let table = IntegralTable::through(|x: f64| x.abs(), 0.3, (-1.0, 2.0), 101);
+        let origin = table.integral(0.3);
+        assert!((table.integral(0.0) - origin + 0.045).abs() < 1e-12);
+        assert!((table.integral(1.5) - origin - 1.08).abs() < 1e-12);
+        assert!(table.integral(-1.5).is_nan());
+        assert!(IntegralTable::through(f64::cos, 0.0, (1.0, 1.0), 101)
+            .integral(1.0)
+            .is_nan());
+    }
 }
@@ -0,0 +1,158 @@
+use crate::*;
+use serde::Serialize;
+
+// Nearest neighbour spacing distribution of an unfolded spectrum, compared to the Poisson
+// distribution of regular and the Wigner surmise of chaotic systems.
+#[derive(Serialize, Clone, Debug)]
+pub struct SpacingDistribution {
+    pub spacings: Vec<f64>,
+    // centers of the histogram bins and the normalized density in them
+    pub bins: Vec<(f64, f64)>,
+    // largest distance between the cumulative distribution of the spacings and the model
+    pub poisson_distance: f64,
+    pub wigner_distance: f64,
+}
+
+pub fn poisson(s: f64) -> f64 {
+    (-s).exp()
+}
+
+pub fn wigner(s: f64) -> f64 {
+    f64::consts::PI * s / 2.0 * (-f64::consts::PI * s * s / 4.0).exp()
+}
+
+fn poisson_cumulative(s: f64) -> f64 {
+    1.0 - (-s).exp()
+}
+
+fn wigner_cumulative(s: f64) -> f64 {
+    1.0 - (-f64::consts::PI * s * s / 4.0).exp()
+}
+
+// maps the energies onto a spectrum with unit mean spacing, the energies have to be ascending
+pub fn unfold(energies: &[f64], counting_function: &(dyn Fn(f64) -> f64 + Sync)) -> Vec<f64> {
+    energies.par_iter().map(|e| counting_function(*e)).collect()
+}
+
+// Kolmogorov-Smirnov distance of the sorted samples to a cumulative distribution
+fn distance(sorted: &[f64], cumulative: fn(f64) -> f64) -> f64 {
+    let n = sorted.len() as f64;
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let model = cumulative(*s);
+            f64::max(
+                (model - i as f64 / n).abs(),
+                (model - (i + 1) as f64 / n).abs(),
+            )
+        })
+        .fold(0.0, f64::max)
+}
+
+impl SpacingDistribution {
+    // histogram of the spacings of an unfolded spectrum in bin_count bins between 0 and max_spacing
+    pub fn new(unfolded: &[f64], bin_count: usize, max_spacing: f64) -> SpacingDistribution {
+        let spacings: Vec<f64> = unfolded.windows(2).map(|w| w[1] - w[0]).collect();
+        let width = max_spacing / bin_count as f64;
+
+        let mut counts = vec![0usize; bin_count];
+        for s in &spacings {
+            let bin = (s / width) as usize;
+            if *s >= 0.0 && bin < bin_count {
+                counts[bin] += 1;
+            }
+        }
+        let bins = counts
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                (
+                    (i as f64 + 0.5) * width,
+                    *count as f64 / (spacings.len() as f64 * width),
+                )
+            })
+            .collect();
+
+        let mut sorted = spacings.clone();
+        sorted.sort_by(cmp_f64);
+        let poisson_distance = distance(&sorted, poisson_cumulative);
+        let wigner_distance = distance(&sorted, wigner_cumulative);
+
+        SpacingDistribution {
+            spacings,
+            bins,
+            poisson_distance,
+            wigner_distance,
+        }
+    }
+
+    pub fn mean_spacing(&self) -> f64 {
+        self.spacings.iter().sum::<f64>() / self.spacings.len() as f64
+    }
+}
+
+// spacing distribution of a computed spectrum of the potential, unfolded with its semiclassical
+// counting function
+pub fn level_spacings<F: Fn(f64) -> f64 + Sync>(
+    energies: &[f64],
+    potential: &F,
+    mass: f64,
+    bin_count: usize,
+) -> SpacingDistribution {
+    let unfolded = unfold(energies, &|e| {
+        energy::counting_function(mass, UNITS.hbar, potential, APPROX_INF, e)
+    });
+    SpacingDistribution::new(&unfolded, bin_count, 4.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn models_are_normalized() {
+        let grid = Grid::uniform((0.0, 20.0), 20001);
+        let p = Function::<f64, f64>::new(poisson);
+        let w = Function::<f64, f64>::new(wigner);
+        let sw = Function::<f64, f64>::new(|s| s * wigner(s));
+
+        assert!((grid.integrate(&p) - 1.0).abs() < 1e-6);
+        assert!((grid.integrate(&w) - 1.0).abs() < 1e-6);
+        // unit mean spacing
+        assert!((grid.integrate(&sw) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn exponential_spacings_look_poissonian() {
+        // deterministic samples of an exponential distribution, s = -ln(1 - u)
+        let n = 10000;
+        let mut levels = vec![0.0];
+        for i in 0..n {
+            let u = (i as f64 * 0.618034) % 1.0;
+            levels.push(levels[i] - (1.0 - u).ln());
+        }
+        let distribution = SpacingDistribution::new(&levels, 40, 4.0);
+
+        assert!((distribution.mean_spacing() - 1.0).abs() < 1e-2);
+        assert!(distribution.poisson_distance < 0.01);
+        assert!(distribution.wigner_distance > 0.1);
+        for (s, density) in distribution.bins.iter().take(10) {
+            assert!((density - poisson(*s)).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn harmonic_oscillator_is_rigid() {
+        // the WKB levels of a single well follow the counting function exactly
+        let energies: Vec<f64> = (0..20)
+            .into_par_iter()
+            .map(|n| energy::nth_energy(n, 1.0, 1.0, &potentials::square, APPROX_INF))
+            .collect();
+        let distribution = level_spacings(&energies, &potentials::square, 1.0, 20);
+
+        for s in &distribution.spacings {
+            assert!((s - 1.0).abs() < 1e-2, "{}", s);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+#![allow(dead_code)]
+
+#[cfg(not(target_arch = "wasm32"))]
+mod airy;
+pub mod airy_series;
+pub mod airy_wave_func;
+pub mod analysis;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod animation;
+#[cfg(feature = "ndarray")]
+pub mod arrays;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+pub mod bessel;
+pub mod bohmian;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capi;
+pub mod check;
+pub mod chebyshev;
+pub mod comparison;
+pub mod decimation;
+pub mod discontinuities;
+pub mod domain;
+pub mod energy;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fixtures;
+pub mod grid;
+pub mod hydrogen;
+pub mod integrals;
+pub mod level_statistics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod manifest;
+pub mod matrix_elements;
+pub mod mixed_state;
+pub mod newtons_method;
+pub mod npz;
+pub mod observables;
+pub mod operators;
+pub mod optimize;
+pub mod perturbation;
+pub mod phase_space;
+pub mod photoionization;
+pub mod plot;
+pub mod polynomial;
+pub mod potential_cache;
+pub mod potentials;
+pub mod registry;
+pub mod resonance;
+pub mod sampled;
+pub mod selftest;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod smoothing;
+pub mod sweep;
+pub mod task;
+pub mod time_dependent;
+pub mod transmission;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui;
+pub mod turning_points;
+pub mod units;
+pub mod utils;
+pub mod variational;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+pub mod wave_function_2d;
+pub mod wave_function_builder;
+pub mod wkb_wave_func;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::airy::airy_ai;
+use crate::airy_wave_func::AiryWaveFunction;
+pub use crate::comparison::ComparisonLibrary;
+pub use crate::discontinuities::DiscontinuityPolicy;
+pub use crate::domain::IntegrationDomain;
+pub use crate::grid::Grid;
+use crate::integrals::*;
+pub use crate::integrals::Point;
+use crate::newtons_method::derivative;
+pub use crate::newtons_method::{RootFindError, RootMethod, Tolerance, Trace};
+pub use crate::plot::{PotentialColumn, ProbabilityUnit};
+use crate::potentials::SharedPotential;
+use crate::units::Units;
+use crate::utils::Func;
+use crate::utils::*;
+use crate::wave_function_builder::*;
+use crate::wkb_wave_func::WkbWaveFunction;
+use num::complex::Complex64;
+use num::pow::Pow;
+use rayon::iter::*;
+use std::f64;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+pub const INTEG_STEPS: usize = 64000;
+pub const TRAPEZE_PER_THREAD: usize = 1000;
+pub const NUMBER_OF_POINTS: usize = 100000;
+pub const NUMBER_OF_POINTS_2D: usize = 500;
+// written first by plot::plot_wavefunction_progressive, before it is refined to NUMBER_OF_POINTS
+pub const PREVIEW_POINTS: usize = 1000;
+// (start, end, step) the plots sample on instead of NUMBER_OF_POINTS over the view of each state,
+// so the outputs of different states and potentials share their x values. Nothing is decimated
+// with it.
+pub const SAMPLING_GRID: Option<(f64, f64, f64)> = None;
+// 0 writes every point, otherwise plot data is thinned as long as the lines between the written
+// points stay within this fraction of the largest value
+pub const DECIMATION_TOLERANCE: f64 = 0.0;
+// the probability outputs write |psi|^2 per unit length or integrated over bins of a width
+pub const PROBABILITY_UNIT: ProbabilityUnit = ProbabilityUnit::PerLength;
+// appends V(x), as it is or rescaled to the range of psi, to the wave function data
+pub const POTENTIAL_COLUMN: PotentialColumn = PotentialColumn::None;
+
+// 0 prints the energies as found, 2 or 3 Richardson-extrapolates them with an error estimate
+pub const RICHARDSON_LEVELS: usize = 0;
+
+pub const AIRY_TRANSITION_FRACTION: f64 = 0.5;
+pub const ENABLE_AIRY_JOINTS: bool = true;
+// matches value and derivative of the airy and WKB functions at the edges of the airy ranges,
+// the joints aren't needed then
+pub const MATCH_AIRY_DERIVATIVES: bool = true;
+// which part evaluates psi at the boundary of two parts and at the edges of the airy ranges
+pub const PART_BOUNDARY: PartBoundary = PartBoundary::HalfOpen;
+// overall phase every wave function is rotated to after its scaling
+pub const PHASE_CONVENTION: PhaseConvention = PhaseConvention::None;
+// Uniform replaces the airy functions, WKB functions and the joints between them at soft turning
+// points by one Airy function of Langer's mapped argument
+pub const APPROXIMATION: ApproximationKind = ApproximationKind::Patched;
+// comparison equations the uniform approximation may use besides Airy's, the parabolic cylinder
+// at barrier tops and Bessel's equation at a centrifugal singularity left of approx_inf
+pub const COMPARISON_LIBRARY: ComparisonLibrary = ComparisonLibrary::ALL;
+
+// renormalizes superpositions of eigenstates from sum |c_n|^2 N_n instead of integrating |psi|^2,
+// the check integrates anyway and reports deviations above the tolerance, relative to the factor
+pub const ANALYTIC_SUPERPOSITION_NORM: bool = true;
+pub const CHECK_SUPERPOSITION_NORM: bool = false;
+pub const SUPERPOSITION_NORM_TOLERANCE: f64 = 1e-3;
+
+// psi of the oscillator levels n = 0, 1 of a well is replaced by the Hermite function fitted to its
+// bottom, up to this fraction of the way to the oscillator's turning points and blended into the
+// WKB and Airy parts from there on
+pub const HARMONIC_BOTTOMS: bool = false;
+pub const HARMONIC_CORE_FRACTION: f64 = 0.5;
+
+// psi within this many hbar omega of the top of a barrier, omega of the inverted oscillator fitted
+// there, is replaced by parabolic cylinder functions in BARRIER_TOP_CORE oscillator lengths of the
+// top, blended into the WKB and Airy parts over the next BARRIER_TOP_TRANSITION. 0 keeps the airy
+// functions everywhere, as does a barrier deviating more than BARRIER_TOP_ANHARMONICITY hbar omega
+// from the parabola there.
+pub const BARRIER_TOP_MARGIN: f64 = 1.0;
+pub const BARRIER_TOP_CORE: f64 = 3.0;
+pub const BARRIER_TOP_TRANSITION: f64 = 1.0;
+pub const BARRIER_TOP_ANHARMONICITY: f64 = 1.0;
+
+pub const VALIDITY_LL_FACTOR: f64 = 3.5;
+
+// when the root finds stop, see Tolerance: the bisection of the quantization condition for the
+// energy, newtons method for the turning points and for the edges of the airy ranges, where the
+// validity condition is met, with regula falsi for the outermost ones
+pub const ENERGY_TOLERANCE: Tolerance = Tolerance::FLOATS;
+pub const TURNING_POINT_TOLERANCE: Tolerance = Tolerance::step(1e-7);
+pub const BOUNDARY_TOLERANCE: Tolerance = Tolerance {
+    x_abs: 1e-9,
+    x_rel: 0.0,
+    f_abs: 1e-9,
+};
+// steps the energy takes in the bracket of the quantization condition before it is bisected, each
+// evaluation of the condition integrates over approx_inf, None only bisects
+pub const ENERGY_ROOT_METHOD: Option<RootMethod> = Some(RootMethod::Secant);
+// records and prints the iterates of the energy solution of every state
+pub const TRACE_ROOT_FINDS: bool = false;
+// the zeros of the validity function and the turning points of smooth potentials are the roots of
+// Chebyshev fits accurate to this, relative to the largest value in the view, 0 searches them one
+// by one with newtons method
+pub const GLOBAL_ROOT_TOLERANCE: f64 = 1e-10;
+// solves V = E and the validity condition of polynomial potentials with the eigenvalues of
+// companion matrices instead
+pub const POLYNOMIAL_TURNING_POINTS: bool = true;
+
+// memoizes the potential while solving for the energy and building the wave function,
+// pays off for potentials that are expensive to evaluate
+pub const CACHE_POTENTIAL: bool = false;
+
+// standard deviation of the Gaussian V is convolved with before solving, for potentials with kinks
+// or steps, 0 solves for V as given. Where V moved by more than SMOOTHING_TOLERANCE is reported.
+pub const SMOOTHING_WIDTH: f64 = 0.0;
+pub const SMOOTHING_TOLERANCE: f64 = 1e-3;
+// whether jumps and kinks of V that aren't hard walls are looked for, and refused or smoothed
+pub const DISCONTINUITY_POLICY: DiscontinuityPolicy = DiscontinuityPolicy::Ignore;
+
+pub const UNITS: Units = Units::ATOMIC;
+
+pub const APPROX_INF: (f64, f64) = (-200.0, 200.0);
+pub const VIEW_FACTOR: f64 = 0.5;
+// what renormalization and observables like matrix elements integrate psi over, psi is negligible
+// outside of its effective support while approx_inf is mostly empty
+pub const NORMALIZATION_DOMAIN: IntegrationDomain = IntegrationDomain::ApproxInf;
+pub const OBSERVABLE_DOMAIN: IntegrationDomain = IntegrationDomain::EffectiveSupport(1e-8);
+
+pub const WRITE_MANIFEST: bool = true;
+// error budget of every state in the manifest, costs about as much as computing the state again
+pub const ESTIMATE_ACCURACY: bool = false;
+pub const RUN_DIR_TEMPLATE: &str = "{potential}_n{n}_m{mass}_{started}";
+pub const OUTPUT_FILE_TEMPLATE: &str = "psi_{potential}_n{n}_m{mass}.dat";
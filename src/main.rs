@@ -1,82 +1,569 @@
-#![allow(dead_code)]
-#![feature(test)]
-
-mod airy;
-mod airy_wave_func;
-mod benchmarks;
-mod check;
-mod energy;
-mod integrals;
-mod newtons_method;
-mod plot;
-mod potentials;
-mod tui;
-mod turning_points;
-mod utils;
-mod wave_function_builder;
-mod wkb_wave_func;
-
-use crate::airy::airy_ai;
-use crate::airy_wave_func::AiryWaveFunction;
-use crate::integrals::*;
-use crate::newtons_method::derivative;
-use crate::utils::Func;
-use crate::utils::*;
-use crate::wave_function_builder::*;
-use crate::wkb_wave_func::WkbWaveFunction;
-use num::complex::Complex64;
-use num::pow::Pow;
-use rayon::iter::*;
-use std::f64;
-use std::fs::File;
-use std::io::Write;
+use schroedinger_approx::wave_function_builder::ScalingType;
+use schroedinger_approx::*;
 use std::path::Path;
 use std::sync::Arc;
 
-const INTEG_STEPS: usize = 64000;
-const TRAPEZE_PER_THREAD: usize = 1000;
-const NUMBER_OF_POINTS: usize = 100000;
-
-const AIRY_TRANSITION_FRACTION: f64 = 0.5;
-const ENABLE_AIRY_JOINTS: bool = true;
-
-const VALIDITY_LL_FACTOR: f64 = 3.5;
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("serve") {
+        let option = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+        };
+        let port = match option("--port") {
+            Some(p) => p.parse().unwrap_or_else(|_| {
+                eprintln!("invalid port '{}'", p);
+                std::process::exit(1);
+            }),
+            None => 8080,
+        };
+        let host = option("--host").map_or(server::DEFAULT_HOST, |h| h.as_str());
+        server::serve(host, port);
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("tui") {
+        tui::run();
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("count") {
+        if let Err(e) = count_levels(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("phase") {
+        if let Err(e) = dump_phase(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("compare") {
+        if let Err(e) = compare(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("transmission") {
+        if let Err(e) = tabulate_transmission(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("selftest") {
+        match selftest(&args) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("batch") {
+        if let Err(e) = batch(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("regress") {
+        match regress(&args) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-const APPROX_INF: (f64, f64) = (-200.0, 200.0);
-const VIEW_FACTOR: f64 = 0.5;
+    let nth_energy = 5;
+    let mut manifest = manifest::RunManifest::new(
+        "square",      // potential
+        1.0,           // mass
+        &[nth_energy], // nth energies
+        Path::new("output"),
+    )
+    .in_run_dir(RUN_DIR_TEMPLATE);
+    let output_dir = manifest.get_output_dir().to_path_buf();
+    let output_dir = output_dir.as_path();
+    let output_file = manifest.resolve_template(OUTPUT_FILE_TEMPLATE);
+    let output_file = output_file.as_str();
 
-fn main() {
     let wave_function = wave_function_builder::WaveFunction::new(
-        &potentials::square,
+        Arc::new(potentials::square),
         1.0, // mass
-        5,  // nth energy
+        nth_energy,
         APPROX_INF,
         VIEW_FACTOR,
         ScalingType::Renormalize(1.0.into())
     );
     
+    // let stage = wave_function_builder::WaveFunctionBuilder::new(Arc::new(potentials::mexican_hat), 1.0)
+    //     .with_scaling(ScalingType::Renormalize(1.0.into()))
+    //     .with_phase_convention(wave_function_builder::PhaseConvention::LeftTurningPoint) // or FirstNode, PHASE_CONVENTION by default
+    //     .solve_energy(5) // or .with_energy(3.2) for a known energy
+    //     .unwrap()
+    //     .find_turning_points()
+    //     .unwrap();
+    // println!("turning points: {:?}, view: {:?}", stage.get_turning_points(), stage.get_view());
+    // let wave_function = stage.build_parts().unwrap().scale();
+
     // let wave_function = wave_function_builder::Superposition::new(
     //     &potentials::square,
     //     1.0, // mass
     //     &[
-    //         (9,  complex(0.0, 0.0 * f64::consts::PI / 3.0).exp()), // (nth energy, phase)
-    //         (12, complex(0.0, 1.0 * f64::consts::PI / 3.0).exp()), // (nth energy, phase)
-    //         (15, complex(0.0, 2.0 * f64::consts::PI / 3.0).exp()), // (nth energy, phase)
+    //         (9,  utils::complex(0.0, 0.0 * std::f64::consts::PI / 3.0).exp()), // (nth energy, phase)
+    //         (12, utils::complex(0.0, 1.0 * std::f64::consts::PI / 3.0).exp()), // (nth energy, phase)
+    //         (15, utils::complex(0.0, 2.0 * std::f64::consts::PI / 3.0).exp()), // (nth energy, phase)
     //        
     //     ],
     //     APPROX_INF,
     //     VIEW_FACTOR,
-    //     ScalingType::Renormalize(complex(1.0, 0.0)),
+    //     ScalingType::Renormalize(utils::complex(1.0, 0.0)),
     // );
+
+    // let wave_function = wave_function_builder::Superposition::coherent(
+    //     &potentials::square,
+    //     1.0, // mass
+    //     utils::complex(2.0, 0.0), // alpha
+    //     20, // n_max
+    //     APPROX_INF,
+    //     VIEW_FACTOR,
+    //     ScalingType::Renormalize(utils::complex(1.0, 0.0)),
+    // );
+
+    // let wave_function = wave_function_2d::WaveFunction2D::new(
+    //     wave_function_builder::WaveFunction::new(Arc::new(potentials::square), 1.0, 2, APPROX_INF, VIEW_FACTOR, ScalingType::None),
+    //     wave_function_builder::WaveFunction::new(Arc::new(potentials::square), 1.0, 3, APPROX_INF, VIEW_FACTOR, ScalingType::None),
+    //     ScalingType::Renormalize(utils::complex(1.0, 0.0)),
+    // );
+
+    // batch::run_batch(
+    //     &[
+    //         batch::Job::for_energies("square", Arc::new(potentials::square), 1.0, &[1, 2, 3]),
+    //         batch::Job::for_energies("mexican_hat", Arc::new(potentials::mexican_hat), 1.0, &[0, 4]),
+    //     ]
+    //     .concat(),
+    //     Path::new("output"),
+    //     args.iter().any(|a| a == "--resume"), // skips the jobs an earlier run with the same configuration finished
+    // );
+
+    // let hydrogen = hydrogen::HydrogenLike::new(1.0, 1.0, 3, 1); // (Z, mass, n, l)
+    // let wave_function = hydrogen.wave_function(ScalingType::Renormalize(1.0.into())).unwrap();
+    // println!("{}", hydrogen.energy_report(&wave_function));
+
+    // let states = wave_function_builder::eigenstates(Arc::new(potentials::square), 1.0, 0..4, APPROX_INF, VIEW_FACTOR, ScalingType::None);
+    // let system = time_dependent::DrivenSystem::dipole(&states);
+    // let pulse = |t: f64| 0.01 * (-((t - 50.0) / 15.0).powi(2)).exp() * (2.0 * t).cos(); // f(t)
+    // println!("{:?}", system.transition_probabilities(&pulse, 0, (0.0, 100.0), 100000)); // (drive, initial state, time, steps)
+
+    // let corrections = perturbation::corrections(Arc::new(potentials::square), &|x: f64| 0.1 * x.powi(4), 1.0, 2, 2); // (potential, delta_v, mass, n, order)
+    // println!("E = {} + {} + {:?} = {}", corrections.energy, corrections.first_order, corrections.second_order, corrections.corrected_energy());
+
+    // let trial = |p: &[f64], x: f64| (-p[0].abs() * x * x - p[1].abs() * x.powi(4)).exp(); // (parameters, x)
+    // variational::minimize(&potentials::mexican_hat, 1.0, &trial, &[0.5, 0.1], APPROX_INF); // (potential, mass, trial, start, view)
+
+    // let rabi = time_dependent::RabiOscillation::new(&states[0], &states[1], 0.01, 0.0); // (lower, upper, amplitude, detuning)
+    // let populations = rabi.populations(4.0 * std::f64::consts::TAU / rabi.rabi_frequency(), 400000); // (duration, steps)
+    // println!("max deviation from RWA: {}", time_dependent::RabiOscillation::max_deviation(&populations));
+
+    // let compressed = wave_function.compress(1e-6); // (tolerance relative to max |psi|)
+    // println!("{} Chebyshev coefficients, {}", compressed.coefficient_count(), serde_json::to_string(&compressed).unwrap());
+
+    // let leaky_well = |x: f64| if x < 0.0 { x * x } else { x * x * (-x * x / 16.0).exp() }; // escapes to the right
+    // println!("{:?}", resonance::resonances(1.0, &leaky_well, 0..5, APPROX_INF)); // (mass, potential, n_range, range)
     
-    let output_dir = Path::new("output");
-    
-    // For WaveFunction
-    plot::plot_wavefunction(&wave_function, output_dir, "data.txt");
-    // plot::plot_wavefunction_parts(&wave_function, output_dir, "data.txt");
-    // plot::plot_probability(&wave_function, output_dir, "data.txt");
+    // For WaveFunction, --potential-column none, raw or rescaled adds V(x) to the data
+    let potential_column = match args
+        .iter()
+        .position(|a| a == "--potential-column")
+        .and_then(|i| args.get(i + 1))
+        .map(|c| c.as_str())
+    {
+        None => POTENTIAL_COLUMN,
+        Some("none") => PotentialColumn::None,
+        Some("raw") => PotentialColumn::Raw,
+        Some("rescaled") => PotentialColumn::Rescaled,
+        Some(other) => {
+            eprintln!("--potential-column '{}' isn't none, raw or rescaled", other);
+            std::process::exit(1);
+        }
+    };
+    plot::plot_wavefunction_with(&wave_function, potential_column, output_dir, output_file);
+    // plot::plot_wavefunction_progressive(wave_function.clone(), output_dir, output_file).join().unwrap(); // preview with PREVIEW_POINTS first, join waits for the refinement
+    // plot::plot_wavefunction_parts(&wave_function, output_dir, output_file);
+    // plot::plot_probability(&wave_function, output_dir, output_file);
+    // plot::write_pgfplots_wavefunction(&wave_function, 5, output_dir, "psi.tex"); // (wave_function, n, output_dir, output_file)
+    // plot::write_wolfram_wavefunction(&wave_function, 5, output_dir, "psi.wl"); // (wave_function, n, output_dir, output_file)
     
     // For Superposition
-    // plot::plot_superposition(&wave_function, output_dir, "data.txt");
-    // plot::plot_probability_superposition(&wave_function, output_dir, "data.txt");
+    // plot::plot_superposition(&wave_function, output_dir, output_file);
+    // plot::plot_superposition_parts(&wave_function, output_dir, output_file);
+    // animation::write_animation(&mut wave_function, &(0..200).map(|i| i as f64 * 0.05).collect::<Vec<f64>>(), 25, &output_dir.join("evolution.mp4")).unwrap(); // (superposition, times, fps, .gif or .mp4)
+    // plot::plot_trajectories(&bohmian::trajectories(&mut wave_function, &(0..400).map(|i| i as f64 * 0.01).collect::<Vec<f64>>(), 2000, 20), output_dir, output_file); // (superposition, times, samples, trajectories)
+    // plot::plot_probability_superposition(&wave_function, output_dir, output_file);
+    // plot::plot_superposition_phase(&wave_function, output_dir, output_file);
+
+    // For HydrogenLike
+    // plot::plot_radial_wavefunction(&wave_function, &hydrogen.label(), output_dir, output_file);
+
+    // For eigenstates
+    // plot::write_qutip(&states, 1000, output_dir, "states.npz"); // (states, points, output_dir, output_file)
+
+    // For RabiOscillation
+    // plot::plot_rabi_populations(&populations, output_dir, output_file);
+
+    // For energy levels
+    // plot::plot_levels(&potentials::mexican_hat, 1.0, 0..10, output_dir, output_file); // (potential, mass, n_range)
+    // plot::plot_states(Arc::new(potentials::square), 1.0, &[0, 1, 2], true, output_dir, output_file); // (potential, mass, ns, offset by E_n)
+
+    // For the labelled spectrum of a multi-well potential
+    // plot::write_labelled_spectrum(&analysis::labelled_spectrum(Arc::new(potentials::double_mexican_hat), 1.0, 0..10), output_dir, output_file); // (potential, mass, n_range)
+
+    // For avoided crossings in a parameter sweep
+    // let sweep = sweep::Sweep::compute(&|lambda, x| potentials::mexican_hat(x) + lambda * x, 1.0, &(0..50).map(|i| i as f64 * 0.1).collect::<Vec<f64>>(), 6); // (family, mass, parameters, count)
+    // plot::plot_sweep(&sweep, &sweep.avoided_crossings(0.2), output_dir, output_file);
+
+    // For level spacing statistics
+    // let energies = energy::first_energies(200, 1.0, 1.0, &potentials::triple_mexican_hat, APPROX_INF).unwrap();
+    // plot::plot_spacing_distribution(&level_statistics::level_spacings(&energies, &potentials::triple_mexican_hat, 1.0, 20), output_dir, output_file); // (energies, potential, mass, bin_count)
+
+    // For the spectrum map
+    // plot::plot_spectrum_map(Arc::new(potentials::square), 1.0, 0..40, output_dir, output_file); // (potential, mass, n_range)
+
+    // For the photoionization cross section of a bound state, sigma(E) / alpha into the continuum above V at the open ends of approx_inf
+    // plot::plot_cross_section(photoionization::cross_section(&wave_function, &transmission::parse_energy_range("0.01:5:200").unwrap(), APPROX_INF).unwrap(), output_dir, output_file); // (bound state, energies, approx_inf)
+
+    // For the Wronskian of the WKB pair, deviations localize where the approximation degrades
+    // plot::plot_wronskian_deviation(&check::wronskian_deviation(&wave_function, 200).unwrap(), output_dir, output_file); // (wave_function, points)
+
+    // For the marginals of the Wigner function against |psi|^2 and |phi|^2
+    // println!("{:?}", check::wigner_marginals(&wave_function, 400, 301)); // (wave_function, positions, momenta)
+
+    // For the sensitivity of the turning points and the norm to the energy
+    // println!("{}", analysis::turning_point_sensitivity(&wave_function, 1e-4).unwrap()); // (wave_function, delta)
+
+    // For <psi|f(x)|psi> of any f over the effective support, with an error estimate
+    // println!("<x^3> = {}", observables::expectation_of(&wave_function, &|x: f64| x.powi(3))); // (wave_function, f)
+    // println!("<p^4> = {}", observables::momentum_moment_of(&wave_function, 4)); // (wave_function, k)
+
+    // For |psi|^2 at a point, or at the origin of a hydrogen-like state, over a sweep of a parameter
+    // println!("{:?}", analysis::density_at(&wave_function, 0.0)); // (wave_function, x)
+    // println!("{}", serde_json::to_string(&analysis::density_sweep(&|z| { let h = hydrogen::HydrogenLike::new(z, 1.0, 1, 0); analysis::contact_density(&h, &h.wave_function(ScalingType::None).unwrap()) }, &[0.9, 1.0, 1.1])).unwrap()); // (density, parameters)
+
+    // For computing in the background, cancel() stops it at the next check and join() fails
+    // let task = task::spawn_wave_function(wave_function_builder::WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0), 5); // (builder, n)
+    // let wave_function = task.join().unwrap();
+
+    // For comparing with another state, rotates psi so that their overlap is real and positive
+    // wave_function.align_phase_to(&other); // (other), needs a mutable wave_function
+
+    // For every state in an energy range, e.g. around the top of a barrier, each with its n
+    // let states = wave_function_builder::WaveFunction::all_in_energy_window(Arc::new(potentials::mexican_hat), 1.0, (200.0, 260.0), APPROX_INF, VIEW_FACTOR, ScalingType::None); // (potential, mass, (e_min, e_max), approx_inf, view_factor, scaling)
+
+    // For plotting only where |psi| is above a fraction of its value at the outermost turning points
+    // wave_function.set_view(wave_function.effective_support(1e-4)); // (epsilon), needs a mutable wave_function
+
+    // For WaveFunction2D
+    // plot::plot_probability_2d(&wave_function, output_dir, output_file);
+
+    if WRITE_MANIFEST {
+        // For WaveFunction
+        if ESTIMATE_ACCURACY {
+            manifest.add_accuracy(check::accuracy_summary(&wave_function, nth_energy));
+        }
+        // For Superposition
+        // let statistics = analysis::energy_statistics(&wave_function).unwrap();
+        // println!("{}", statistics);
+        // manifest.set_energy_statistics(statistics);
+        manifest.write();
+    }
+}
+
+// schroedinger count <potential> [--mass m] [--levels n]: writes the counting function and the
+// staircase of the first n levels, the potential is looked up in the registry, e.g. harmonic(omega=2)
+fn count_levels(args: &[String]) -> Result<(), String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let name = args.get(2).map(|a| a.as_str()).unwrap_or("square");
+    let potential = registry::resolve(name)?;
+    let potential = |x| potential(x);
+    let mass = match option("--mass") {
+        Some(m) => m.parse().map_err(|_| format!("invalid mass '{}'", m))?,
+        None => 1.0,
+    };
+    let levels: usize = match option("--levels") {
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("invalid number of levels '{}'", n))?,
+        None => 20,
+    };
+
+    let energies: Vec<f64> = (0..levels)
+        .map(|n| energy::nth_energy_checked(n, mass, UNITS.hbar, &potential, APPROX_INF))
+        .collect::<Result<_, _>>()?;
+    let mismatch = energy::count_mismatch(&energies, mass, UNITS.hbar, &potential, APPROX_INF);
+    println!("largest deviation of N(E_n) from n + 1: {:.3e}", mismatch);
+    if mismatch > 0.5 {
+        println!("a level was skipped or found twice");
+    }
+
+    let manifest = manifest::RunManifest::new(
+        name,
+        mass,
+        &(0..levels).collect::<Vec<usize>>(),
+        Path::new("output"),
+    )
+    .in_run_dir(RUN_DIR_TEMPLATE);
+    plot::plot_counting_function(
+        &potential,
+        mass,
+        &energies,
+        manifest.get_output_dir(),
+        &manifest.resolve_template(OUTPUT_FILE_TEMPLATE),
+    );
+    Ok(())
+}
+
+// schroedinger phase <potential> [--mass m] [--n n]: writes p(x), the de Broglie wavelength and the
+// accumulated phase of the nth state over its view
+fn dump_phase(args: &[String]) -> Result<(), String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let name = args.get(2).map(|a| a.as_str()).unwrap_or("square");
+    let potential = registry::resolve(name)?;
+    let mass = match option("--mass") {
+        Some(m) => m.parse().map_err(|_| format!("invalid mass '{}'", m))?,
+        None => 1.0,
+    };
+    let n = match option("--n") {
+        Some(n) => n.parse().map_err(|_| format!("invalid n '{}'", n))?,
+        None => 0,
+    };
+
+    let energy = wave_function_builder::WaveFunctionBuilder::new(potential, mass).solve_energy(n)?;
+    let phase = energy.get_phase();
+    let view = energy.find_turning_points()?.get_view();
+    let values = wkb_wave_func::semiclassical_quantities(&phase, view, NUMBER_OF_POINTS);
+
+    let manifest = manifest::RunManifest::new(name, mass, &[n], Path::new("output"))
+        .in_run_dir(RUN_DIR_TEMPLATE);
+    plot::plot_semiclassical(
+        &values,
+        manifest.get_output_dir(),
+        &manifest.resolve_template(OUTPUT_FILE_TEMPLATE),
+    );
+    Ok(())
+}
+
+// schroedinger compare <potential> --reference <file> [--mass m] [--n n]: plots the nth state
+// next to psi from another code, given as columns x, Re(psi) and optionally Im(psi), after
+// scaling and rotating the reference onto it
+fn compare(args: &[String]) -> Result<(), String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let name = args.get(2).map(|a| a.as_str()).unwrap_or("square");
+    let potential = registry::resolve(name)?;
+    let reference = sampled::SampledWaveFunction::read(Path::new(
+        option("--reference").ok_or("missing --reference <file>")?,
+    ))?;
+    let mass = match option("--mass") {
+        Some(m) => m.parse().map_err(|_| format!("invalid mass '{}'", m))?,
+        None => 1.0,
+    };
+    let n = match option("--n") {
+        Some(n) => n.parse().map_err(|_| format!("invalid n '{}'", n))?,
+        None => 0,
+    };
+
+    let wave_function = wave_function_builder::WaveFunction::new(
+        potential,
+        mass,
+        n,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::Renormalize(1.0.into()),
+    );
+    let manifest = manifest::RunManifest::new(name, mass, &[n], Path::new("output"))
+        .in_run_dir(RUN_DIR_TEMPLATE);
+    let deviation = plot::plot_comparison(
+        &wave_function,
+        &reference,
+        manifest.get_output_dir(),
+        &manifest.resolve_template(OUTPUT_FILE_TEMPLATE),
+    );
+    println!(
+        "largest deviation from the reference, relative to the largest |psi|: {:.3e}",
+        deviation
+    );
+    Ok(())
+}
+
+// schroedinger transmission --potential <barrier> --e-range a:b:n [--mass m] [--fermi mu:kT]:
+// writes T(E) and, with --fermi, integrates it against the Fermi-Dirac occupation
+fn tabulate_transmission(args: &[String]) -> Result<(), String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let name = option("--potential").map_or("barrier", |p| p.as_str());
+    let potential = registry::resolve(name)?;
+    let energies = transmission::parse_energy_range(
+        option("--e-range").ok_or("missing --e-range a:b:n")?,
+    )?;
+    let mass = match option("--mass") {
+        Some(m) => m.parse().map_err(|_| format!("invalid mass '{}'", m))?,
+        None => 1.0,
+    };
+
+    let table = transmission::transmission_table(&*potential, mass, &energies);
+    if let Some(fermi) = option("--fermi") {
+        let (mu, kt) = fermi
+            .split_once(':')
+            .and_then(|(mu, kt)| Some((mu.parse().ok()?, kt.parse().ok()?)))
+            .ok_or(format!("--fermi '{}' isn't of the form mu:kT", fermi))?;
+        println!(
+            "integral of T(E) f(E): {:.6e}",
+            transmission::weighted_transmission(&table, &transmission::fermi_dirac(mu, kt))
+        );
+    }
+
+    let manifest = manifest::RunManifest::new(name, mass, &[], Path::new("output"))
+        .in_run_dir(RUN_DIR_TEMPLATE);
+    plot::plot_transmission(
+        table,
+        manifest.get_output_dir(),
+        &manifest.resolve_template(OUTPUT_FILE_TEMPLATE),
+    );
+    Ok(())
+}
+
+// schroedinger batch <potential>... [--levels n,...] [--mass m] [--resume]: writes the levels of
+// every potential, looked up in the registry, with one energy scan per potential. With --resume the
+// jobs an earlier run with the same configuration finished are skipped.
+fn batch(args: &[String]) -> Result<(), String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let mass = match option("--mass") {
+        Some(m) => m.parse().map_err(|_| format!("invalid mass '{}'", m))?,
+        None => 1.0,
+    };
+    let levels: Vec<usize> = match option("--levels") {
+        Some(levels) => levels
+            .split(',')
+            .map(|n| n.trim().parse().map_err(|_| format!("invalid level '{}'", n)))
+            .collect::<Result<_, _>>()?,
+        None => vec![0],
+    };
+
+    let mut names = vec![];
+    let mut rest = args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--resume" => {}
+            "--levels" | "--mass" => {
+                rest.next();
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ => names.push(arg.as_str()),
+        }
+    }
+    if names.is_empty() {
+        return Err(
+            "missing potential, e.g. schroedinger batch square 'harmonic(omega=2)'".to_string(),
+        );
+    }
+
+    let mut jobs = vec![];
+    for name in names {
+        jobs.extend(batch::Job::for_energies(
+            name,
+            registry::resolve(name)?,
+            mass,
+            &levels,
+        ));
+    }
+    let resume = args.iter().any(|a| a == "--resume");
+    let results = batch::run_batch(&jobs, Path::new("output"), resume);
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    if failed > 0 {
+        return Err(format!("{} of {} jobs failed", failed, results.len()));
+    }
+    Ok(())
+}
+
+// schroedinger regress [--record] [--file fixtures.json]: compares new runs against the recorded
+// fixtures, --record replaces them. False if one of them deviates more than its tolerance.
+fn regress(args: &[String]) -> Result<bool, String> {
+    let path = args
+        .iter()
+        .position(|a| a == "--file")
+        .and_then(|i| args.get(i + 1))
+        .map_or(fixtures::FIXTURE_FILE, |f| f.as_str());
+    let path = Path::new(path);
+
+    if args.iter().any(|a| a == "--record") {
+        let recorded = fixtures::record_all()?;
+        fixtures::write(&recorded, path)?;
+        println!("recorded {} fixtures in {}", recorded.len(), path.display());
+        return Ok(true);
+    }
+
+    fixtures::regress(path)
+}
+
+// schroedinger selftest [--samples k] [--seed s] [--potential spec]: checks normalization, node
+// count, symmetry, the deviation from the uniform approximation and the order of the energies for
+// k random levels of the registry potential, or of random potentials without --potential. False if
+// one of them fails.
+fn selftest(args: &[String]) -> Result<bool, String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+    };
+    let samples = match option("--samples") {
+        Some(k) => k
+            .parse()
+            .map_err(|_| format!("invalid number of samples '{}'", k))?,
+        None => 8,
+    };
+    let seed = match option("--seed") {
+        Some(s) => s.parse().map_err(|_| format!("invalid seed '{}'", s))?,
+        None => rand::random(),
+    };
+
+    let outcomes = selftest::run(seed, samples, option("--potential").map(|p| p.as_str()))?;
+    for outcome in &outcomes {
+        println!("{}", outcome);
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed).count();
+    println!(
+        "{} of {} checks failed, rerun with --seed {}",
+        failed,
+        outcomes.len(),
+        seed
+    );
+    Ok(failed == 0)
 }
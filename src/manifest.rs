@@ -0,0 +1,380 @@
+use crate::analysis::EnergyStatistics;
+use crate::check::AccuracySummary;
+use crate::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Clone)]
+pub struct SolverConfig {
+    pub integ_steps: usize,
+    pub trapeze_per_thread: usize,
+    pub number_of_points: usize,
+    pub number_of_points_2d: usize,
+    pub preview_points: usize,
+    pub sampling_grid: Option<(f64, f64, f64)>,
+    pub decimation_tolerance: f64,
+    pub probability_unit: ProbabilityUnit,
+    pub potential_column: PotentialColumn,
+    pub airy_transition_fraction: f64,
+    pub enable_airy_joints: bool,
+    pub match_airy_derivatives: bool,
+    pub part_boundary: PartBoundary,
+    pub phase_convention: PhaseConvention,
+    pub approximation: ApproximationKind,
+    pub comparison_library: ComparisonLibrary,
+    pub analytic_superposition_norm: bool,
+    pub check_superposition_norm: bool,
+    pub superposition_norm_tolerance: f64,
+    pub validity_ll_factor: f64,
+    pub harmonic_bottoms: bool,
+    pub harmonic_core_fraction: f64,
+    pub energy_tolerance: Tolerance,
+    pub energy_root_method: Option<RootMethod>,
+    pub global_root_tolerance: f64,
+    pub polynomial_turning_points: bool,
+    pub cache_potential: bool,
+    pub turning_point_tolerance: Tolerance,
+    pub boundary_tolerance: Tolerance,
+    pub barrier_top_margin: f64,
+    pub barrier_top_core: f64,
+    pub barrier_top_transition: f64,
+    pub barrier_top_anharmonicity: f64,
+    pub smoothing_width: f64,
+    pub smoothing_tolerance: f64,
+    pub discontinuity_policy: DiscontinuityPolicy,
+    pub approx_inf: (f64, f64),
+    pub normalization_domain: IntegrationDomain,
+    pub observable_domain: IntegrationDomain,
+    pub view_factor: f64,
+    pub richardson_levels: usize,
+    pub units: Units,
+    pub output_file_template: &'static str,
+}
+
+impl SolverConfig {
+    pub fn current() -> SolverConfig {
+        SolverConfig {
+            integ_steps: INTEG_STEPS,
+            trapeze_per_thread: TRAPEZE_PER_THREAD,
+            number_of_points: NUMBER_OF_POINTS,
+            number_of_points_2d: NUMBER_OF_POINTS_2D,
+            preview_points: PREVIEW_POINTS,
+            sampling_grid: SAMPLING_GRID,
+            decimation_tolerance: DECIMATION_TOLERANCE,
+            probability_unit: PROBABILITY_UNIT,
+            potential_column: POTENTIAL_COLUMN,
+            airy_transition_fraction: AIRY_TRANSITION_FRACTION,
+            enable_airy_joints: ENABLE_AIRY_JOINTS,
+            match_airy_derivatives: MATCH_AIRY_DERIVATIVES,
+            part_boundary: PART_BOUNDARY,
+            phase_convention: PHASE_CONVENTION,
+            approximation: APPROXIMATION,
+            comparison_library: COMPARISON_LIBRARY,
+            analytic_superposition_norm: ANALYTIC_SUPERPOSITION_NORM,
+            check_superposition_norm: CHECK_SUPERPOSITION_NORM,
+            superposition_norm_tolerance: SUPERPOSITION_NORM_TOLERANCE,
+            validity_ll_factor: VALIDITY_LL_FACTOR,
+            harmonic_bottoms: HARMONIC_BOTTOMS,
+            harmonic_core_fraction: HARMONIC_CORE_FRACTION,
+            energy_tolerance: ENERGY_TOLERANCE,
+            energy_root_method: ENERGY_ROOT_METHOD,
+            global_root_tolerance: GLOBAL_ROOT_TOLERANCE,
+            polynomial_turning_points: POLYNOMIAL_TURNING_POINTS,
+            cache_potential: CACHE_POTENTIAL,
+            turning_point_tolerance: TURNING_POINT_TOLERANCE,
+            boundary_tolerance: BOUNDARY_TOLERANCE,
+            barrier_top_margin: BARRIER_TOP_MARGIN,
+            barrier_top_core: BARRIER_TOP_CORE,
+            barrier_top_transition: BARRIER_TOP_TRANSITION,
+            barrier_top_anharmonicity: BARRIER_TOP_ANHARMONICITY,
+            smoothing_width: SMOOTHING_WIDTH,
+            smoothing_tolerance: SMOOTHING_TOLERANCE,
+            discontinuity_policy: DISCONTINUITY_POLICY,
+            approx_inf: APPROX_INF,
+            normalization_domain: NORMALIZATION_DOMAIN,
+            observable_domain: OBSERVABLE_DOMAIN,
+            view_factor: VIEW_FACTOR,
+            richardson_levels: RICHARDSON_LEVELS,
+            units: UNITS,
+            output_file_template: OUTPUT_FILE_TEMPLATE,
+        }
+    }
+}
+
+// Provenance of an output run, written as manifest.json next to the produced files.
+#[derive(Serialize, Clone)]
+pub struct RunManifest {
+    pub crate_version: String,
+    pub potential: String,
+    pub potential_parameters: BTreeMap<String, f64>,
+    pub mass: f64,
+    pub n_values: Vec<usize>,
+    pub solver: SolverConfig,
+    pub started: u64,
+    pub finished: Option<u64>,
+    pub files: Vec<String>,
+    pub accuracy: Vec<AccuracySummary>,
+    // of the superposition, if the run plots one
+    pub energy_statistics: Option<EnergyStatistics>,
+    // values of the Go airy_ai that were replaced by airy_series, 0 unless the library is broken
+    pub ai_failures: usize,
+    // of the configuration, see config_hash
+    pub config_hash: String,
+    #[serde(skip)]
+    output_dir: PathBuf,
+}
+
+// "morse(d=10,a=1)" becomes "morse_d_10_a_1"
+pub(crate) fn path_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-+.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl RunManifest {
+    pub fn new(potential: &str, mass: f64, n_values: &[usize], output_dir: &Path) -> RunManifest {
+        // absolute, so the manifest stays valid if the working directory changes
+        let output_dir = std::env::current_dir().unwrap().join(output_dir);
+        RunManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            potential: potential.to_string(),
+            potential_parameters: BTreeMap::new(),
+            mass,
+            n_values: n_values.to_vec(),
+            solver: SolverConfig::current(),
+            started: unix_time(),
+            finished: None,
+            files: vec![],
+            accuracy: vec![],
+            energy_statistics: None,
+            ai_failures: 0,
+            config_hash: String::new(),
+            output_dir,
+        }
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: f64) -> RunManifest {
+        self.potential_parameters.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn add_accuracy(&mut self, summary: AccuracySummary) {
+        self.accuracy.push(summary);
+    }
+
+    pub fn set_energy_statistics(&mut self, statistics: EnergyStatistics) {
+        self.energy_statistics = Some(statistics);
+    }
+
+    // Moves the output into a subdirectory named after the resolved template and creates it. The
+    // directory of another run with the same name, e.g. started in the same second, isn't reused,
+    // _2, _3, ... is appended to the name then.
+    pub fn in_run_dir(mut self, template: &str) -> RunManifest {
+        let base = self.output_dir.join(self.resolve_template(template));
+        std::fs::create_dir_all(base.parent().unwrap()).unwrap();
+        let mut run_dir = base.clone();
+        for suffix in 2.. {
+            match std::fs::create_dir(&run_dir) {
+                Ok(()) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    run_dir = PathBuf::from(format!("{}_{}", base.display(), suffix));
+                }
+                Err(e) => panic!("can't create {}: {}", run_dir.display(), e),
+            }
+        }
+        self.output_dir = run_dir;
+        self
+    }
+
+    // Replaces {potential}, {n}, {mass}, {started} and {<parameter name>} in the template.
+    // Multiple n values are joined with '-'. The values end up in paths, characters of registry
+    // specs like "morse(d=10,a=1)" that don't belong there are replaced by '_'.
+    pub fn resolve_template(&self, template: &str) -> String {
+        let n = self
+            .n_values
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join("-");
+
+        let resolved = template
+            .replace("{potential}", &path_safe(&self.potential))
+            .replace("{n}", &n)
+            .replace("{mass}", &path_safe(&self.mass.to_string()))
+            .replace("{started}", &self.started.to_string());
+
+        self.potential_parameters
+            .iter()
+            .fold(resolved, |resolved, (name, value)| {
+                resolved.replace(&format!("{{{}}}", name), &path_safe(&value.to_string()))
+            })
+    }
+
+    // FNV-1a of everything that determines the results, i.e. not the times and files. Unlike the std
+    // hasher it is the same across builds, so it can be compared with earlier runs.
+    pub fn config_hash(&self) -> String {
+        let config = serde_json::json!({
+            "crate_version": self.crate_version,
+            "potential": self.potential,
+            "potential_parameters": self.potential_parameters,
+            "mass": self.mass,
+            "n_values": self.n_values,
+            "solver": self.solver,
+        });
+        let hash = serde_json::to_string(&config)
+            .unwrap()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| {
+                (hash ^ b as u64).wrapping_mul(0x100000001b3)
+            });
+        format!("{:016x}", hash)
+    }
+
+    pub fn get_output_dir(&self) -> &Path {
+        self.output_dir.as_path()
+    }
+
+    // the files plot::create_file and plot::record_file got for the output directory since its
+    // last manifest
+    fn produced_files(&self) -> Vec<String> {
+        plot::take_created_files(&self.output_dir)
+            .into_iter()
+            .collect()
+    }
+
+    pub fn write(&mut self) {
+        self.finished = Some(unix_time());
+        self.files = self.produced_files();
+        self.ai_failures = airy_wave_func::ai_failures();
+        self.config_hash = self.config_hash();
+
+        let mut manifest_file = File::create(self.output_dir.join(MANIFEST_FILE)).unwrap();
+        manifest_file
+            .write_all(serde_json::to_string_pretty(self).unwrap().as_bytes())
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_produced_files() {
+        let output_dir = std::env::temp_dir().join("schroedinger_manifest_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+
+        let mut manifest =
+            RunManifest::new("square", 1.0, &[1, 2], &output_dir).with_parameter("a", 2.0);
+        plot::create_file(&output_dir.join("data.txt"));
+        // not written by this run
+        File::create(output_dir.join("other.txt")).unwrap();
+        manifest.write();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output_dir.join(MANIFEST_FILE)).unwrap())
+                .unwrap();
+
+        assert_eq!(written["potential"], "square");
+        assert_eq!(written["potential_parameters"]["a"], 2.0);
+        assert_eq!(written["n_values"], serde_json::json!([1, 2]));
+        assert_eq!(written["files"], serde_json::json!(["data.txt"]));
+        assert_eq!(written["solver"]["integ_steps"], INTEG_STEPS);
+        assert_eq!(written["config_hash"], manifest.config_hash());
+        assert_ne!(
+            manifest.config_hash(),
+            RunManifest::new("square", 1.0, &[1, 2], &output_dir).config_hash()
+        );
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn every_setting_is_hashed() {
+        // only change what is printed or where the run goes, not the results
+        const UNHASHED: [&str; 4] = [
+            "TRACE_ROOT_FINDS",
+            "WRITE_MANIFEST",
+            "ESTIMATE_ACCURACY",
+            "RUN_DIR_TEMPLATE",
+        ];
+        let solver = serde_json::to_value(SolverConfig::current()).unwrap();
+        let missing: Vec<&str> = include_str!("lib.rs")
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub const "))
+            .filter_map(|line| line.split(':').next())
+            .filter(|name| !UNHASHED.contains(name) && solver.get(name.to_lowercase()).is_none())
+            .collect();
+        assert!(missing.is_empty(), "not in SolverConfig: {:?}", missing);
+    }
+
+    #[test]
+    fn templates() {
+        let manifest = RunManifest::new("double_well", 0.5, &[3, 4], Path::new("output"))
+            .with_parameter("depth", 2.5);
+
+        assert_eq!(
+            manifest.resolve_template("psi_{potential}_n{n}_m{mass}.dat"),
+            "psi_double_well_n3-4_m0.5.dat"
+        );
+        assert_eq!(
+            manifest.resolve_template("{potential}_d{depth}_{unknown}"),
+            "double_well_d2.5_{unknown}"
+        );
+        assert_eq!(
+            manifest.resolve_template("{started}"),
+            manifest.started.to_string()
+        );
+
+        let spec = RunManifest::new("morse(d=10,a=1)", 1.0, &[0], Path::new("output"));
+        assert_eq!(
+            spec.resolve_template("{potential}_n{n}"),
+            "morse_d_10_a_1_n0"
+        );
+        assert_eq!(
+            RunManifest::new("a/b", 1.0, &[0], Path::new("output")).resolve_template("{potential}"),
+            "a_b"
+        );
+    }
+
+    #[test]
+    fn run_dirs_are_not_shared() {
+        let output_dir = std::env::temp_dir().join("schroedinger_run_dir_test");
+        let _ = std::fs::remove_dir_all(&output_dir);
+
+        // same second, same template
+        let dirs: Vec<PathBuf> = (0..3)
+            .map(|_| {
+                RunManifest::new("square", 1.0, &[0], &output_dir)
+                    .in_run_dir("{potential}_n{n}")
+                    .get_output_dir()
+                    .to_path_buf()
+            })
+            .collect();
+        assert_eq!(dirs[0], output_dir.join("square_n0"));
+        assert_eq!(dirs[1], output_dir.join("square_n0_2"));
+        assert_eq!(dirs[2], output_dir.join("square_n0_3"));
+
+        std::fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use crate::*;
+
+// Matrix elements <m|W|n> between states of the same potential, integrated on a shared grid.
+// The states are normalized here, independent of the scaling they were built with.
+
+struct Integrand<'a> {
+    bra: &'a dyn Func<f64, Complex64>,
+    op: &'a (dyn Fn(f64) -> f64 + Sync),
+    ket: &'a dyn Func<f64, Complex64>,
+}
+
+impl Func<f64, Complex64> for Integrand<'_> {
+    fn eval(&self, x: f64) -> Complex64 {
+        self.bra.eval(x).conj() * (self.op)(x) * self.ket.eval(x)
+    }
+}
+
+pub fn common_view(states: &[WaveFunction]) -> (f64, f64) {
+    let lower = states
+        .iter()
+        .map(|s| s.get_view().0)
+        .min_by(cmp_f64)
+        .unwrap();
+    let upper = states
+        .iter()
+        .map(|s| s.get_view().1)
+        .max_by(cmp_f64)
+        .unwrap();
+    (lower, upper)
+}
+
+// on OBSERVABLE_DOMAIN of all the states
+pub fn common_grid(states: &[WaveFunction]) -> Grid {
+    common_grid_in(states, OBSERVABLE_DOMAIN)
+}
+
+pub fn common_grid_in(states: &[WaveFunction], domain: IntegrationDomain) -> Grid {
+    Grid::uniform(domain.common_range(states), INTEG_STEPS)
+}
+
+pub fn norm(psi: &dyn Func<f64, Complex64>, grid: &Grid) -> f64 {
+    integrate(
+        grid.evaluate(psi)
+            .par_iter()
+            .map(|p| Point {
+                x: p.x,
+                y: p.y.norm_sqr(),
+            })
+            .collect(),
+        TRAPEZE_PER_THREAD,
+    )
+    .sqrt()
+}
+
+// <bra|op|ket> without normalization
+pub fn matrix_element(
+    bra: &dyn Func<f64, Complex64>,
+    op: &(dyn Fn(f64) -> f64 + Sync),
+    ket: &dyn Func<f64, Complex64>,
+    grid: &Grid,
+) -> Complex64 {
+    grid.integrate(&Integrand { bra, op, ket })
+}
+
+// <m|op|n> for all pairs of normalized states
+pub fn matrix(states: &[WaveFunction], op: &(dyn Fn(f64) -> f64 + Sync)) -> Vec<Vec<Complex64>> {
+    matrix_in(states, op, OBSERVABLE_DOMAIN)
+}
+
+pub fn matrix_in(
+    states: &[WaveFunction],
+    op: &(dyn Fn(f64) -> f64 + Sync),
+    domain: IntegrationDomain,
+) -> Vec<Vec<Complex64>> {
+    let grid = common_grid_in(states, domain);
+    let norms: Vec<f64> = states.iter().map(|s| norm(s, &grid)).collect();
+
+    states
+        .iter()
+        .zip(norms.iter())
+        .map(|(bra, bra_norm)| {
+            states
+                .iter()
+                .zip(norms.iter())
+                .map(|(ket, ket_norm)| matrix_element(bra, op, ket, &grid) / (bra_norm * ket_norm))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn dipole_matrix(states: &[WaveFunction]) -> Vec<Vec<Complex64>> {
+    matrix(states, &|x| x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Gaussian {
+        center: f64,
+    }
+
+    impl Func<f64, Complex64> for Gaussian {
+        fn eval(&self, x: f64) -> Complex64 {
+            ((-(x - self.center).powi(2) / 2.0).exp()).into()
+        }
+    }
+
+    #[test]
+    fn gaussian_matrix_elements() {
+        let grid = Grid::uniform((-20.0, 20.0), INTEG_STEPS);
+        let psi = Gaussian { center: 1.0 };
+
+        let norm = norm(&psi, &grid);
+        assert!(float_compare(norm * norm, f64::consts::PI.sqrt(), 1e-8));
+
+        let position = matrix_element(&psi, &|x| x, &psi, &grid) / (norm * norm);
+        assert!(complex_compare(position, complex(1.0, 0.0), 1e-8));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::*;
+
+// sweeps of the Jacobi method, it converges quadratically once the matrix is nearly diagonal
+const JACOBI_SWEEPS: usize = 64;
+// eigenvalues of rho below this are dropped from the entropy, 0 ln 0 = 0
+const ENTROPY_CUTOFF: f64 = 1e-14;
+
+// Weighted mixture rho = sum_k p_k |psi_k><psi_k| of wave functions, e.g. a thermal ensemble. The
+// probabilities are normalized to 1 and every member is normalized on the grid it is sampled on,
+// the members don't have to be orthogonal or of the same potential.
+pub struct MixedState {
+    members: Vec<(f64, WaveFunction)>,
+}
+
+impl MixedState {
+    pub fn new(members: Vec<(f64, WaveFunction)>) -> Result<MixedState, String> {
+        if let Some((p, _)) = members.iter().find(|(p, _)| !p.is_finite() || *p < 0.0) {
+            return Err(format!(
+                "probability {} isn't a finite non negative number",
+                p
+            ));
+        }
+        let total: f64 = members.iter().map(|(p, _)| p).sum();
+        if total <= 0.0 {
+            return Err("the probabilities of a mixed state can't all be 0".to_string());
+        }
+        Ok(MixedState {
+            members: members.into_iter().map(|(p, w)| (p / total, w)).collect(),
+        })
+    }
+
+    pub fn get_probabilities(&self) -> Vec<f64> {
+        self.members.iter().map(|(p, _)| *p).collect()
+    }
+
+    pub fn get_members(&self) -> &[(f64, WaveFunction)] {
+        &self.members
+    }
+
+    // normalized psi_k at the points of the grid, every member is evaluated only once
+    fn samples(&self, grid: &Grid) -> Vec<Vec<Point<f64, Complex64>>> {
+        self.members
+            .iter()
+            .map(|(_, wave_function)| {
+                let values = grid.evaluate(wave_function);
+                let norm = integrate(
+                    values
+                        .iter()
+                        .map(|p| Point {
+                            x: p.x,
+                            y: p.y.norm_sqr(),
+                        })
+                        .collect(),
+                    TRAPEZE_PER_THREAD,
+                )
+                .sqrt();
+                values
+                    .into_iter()
+                    .map(|p| Point {
+                        x: p.x,
+                        y: p.y / norm,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // rho(x_i, x_j) = sum_k p_k psi_k(x_i) psi_k(x_j)^*
+    pub fn density_matrix(&self, grid: &Grid) -> Vec<Vec<Complex64>> {
+        let samples = self.samples(grid);
+        (0..grid.len())
+            .into_par_iter()
+            .map(|i| {
+                (0..grid.len())
+                    .map(|j| {
+                        self.members
+                            .iter()
+                            .zip(samples.iter())
+                            .map(|((p, _), psi)| *p * psi[i].y * psi[j].y.conj())
+                            .sum()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // rho(x) = rho(x, x), integrates to 1
+    pub fn density(&self, grid: &Grid) -> Vec<Point<f64, f64>> {
+        let samples = self.samples(grid);
+        (0..grid.len())
+            .map(|i| Point {
+                x: grid.x(i),
+                y: self
+                    .members
+                    .iter()
+                    .zip(samples.iter())
+                    .map(|((p, _), psi)| p * psi[i].y.norm_sqr())
+                    .sum(),
+            })
+            .collect()
+    }
+
+    // sqrt(p_k p_l) <psi_k|psi_l>, it has the same nonzero eigenvalues as rho on the grid
+    fn weighted_overlaps(&self, grid: &Grid) -> Vec<Vec<Complex64>> {
+        let samples = self.samples(grid);
+        let overlap = |bra: &[Point<f64, Complex64>], ket: &[Point<f64, Complex64>]| {
+            integrate(
+                bra.iter()
+                    .zip(ket.iter())
+                    .map(|(b, k)| Point {
+                        x: b.x,
+                        y: b.y.conj() * k.y,
+                    })
+                    .collect(),
+                TRAPEZE_PER_THREAD,
+            )
+        };
+        self.members
+            .iter()
+            .zip(samples.iter())
+            .map(|((p_k, _), psi_k)| {
+                self.members
+                    .iter()
+                    .zip(samples.iter())
+                    .map(|((p_l, _), psi_l)| (p_k * p_l).sqrt() * overlap(psi_k, psi_l))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Tr rho^2, 1 for a pure state
+    pub fn purity(&self, grid: &Grid) -> f64 {
+        self.weighted_overlaps(grid)
+            .iter()
+            .flatten()
+            .map(|a| a.norm_sqr())
+            .sum()
+    }
+
+    // eigenvalues of rho in descending order, without the zeros of the members' complement
+    pub fn eigenvalues(&self, grid: &Grid) -> Vec<f64> {
+        hermitian_eigenvalues(&self.weighted_overlaps(grid))
+    }
+
+    // -Tr rho ln rho
+    pub fn von_neumann_entropy(&self, grid: &Grid) -> f64 {
+        -self
+            .eigenvalues(grid)
+            .iter()
+            .filter(|l| **l > ENTROPY_CUTOFF)
+            .map(|l| l * l.ln())
+            .sum::<f64>()
+    }
+}
+
+// Cyclic Jacobi rotations of a real symmetric matrix until the off diagonal part vanishes,
+// returns the diagonal.
+fn symmetric_eigenvalues(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = a.len();
+    for _ in 0..JACOBI_SWEEPS {
+        let off: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j].powi(2))
+            .sum();
+        if off <= f64::EPSILON.powi(2) * (0..n).map(|i| a[i][i].powi(2)).sum::<f64>() {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p].clone(), a[q].clone());
+                a[p] = row_p
+                    .iter()
+                    .zip(&row_q)
+                    .map(|(x, y)| c * x - s * y)
+                    .collect();
+                a[q] = row_p
+                    .iter()
+                    .zip(&row_q)
+                    .map(|(x, y)| s * x + c * y)
+                    .collect();
+            }
+        }
+    }
+    (0..n).map(|i| a[i][i]).collect()
+}
+
+// The hermitian H = A + iB has the eigenvalues of the real symmetric [[A, -B], [B, A]], each of
+// them twice.
+fn hermitian_eigenvalues(h: &[Vec<Complex64>]) -> Vec<f64> {
+    let n = h.len();
+    let real = (0..2 * n)
+        .map(|i| {
+            (0..2 * n)
+                .map(|j| {
+                    let value = h[i % n][j % n];
+                    match (i < n, j < n) {
+                        (true, true) | (false, false) => value.re,
+                        (true, false) => -value.im,
+                        (false, true) => value.im,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mut eigenvalues = symmetric_eigenvalues(real);
+    eigenvalues.sort_by(|a, b| cmp_f64(b, a));
+    eigenvalues.into_iter().step_by(2).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn states() -> Vec<WaveFunction> {
+        eigenstates(
+            Arc::new(potentials::square),
+            1.0,
+            0..2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        )
+    }
+
+    #[test]
+    fn hermitian_spectrum() {
+        // eigenvalues 1 and 3
+        let h = vec![
+            vec![complex(2.0, 0.0), complex(0.0, 1.0)],
+            vec![complex(0.0, -1.0), complex(2.0, 0.0)],
+        ];
+        let eigenvalues = hermitian_eigenvalues(&h);
+        assert!(float_compare(eigenvalues[0], 3.0, 1e-12));
+        assert!(float_compare(eigenvalues[1], 1.0, 1e-12));
+    }
+
+    #[test]
+    fn mixture_of_two_levels() {
+        let mut states = states();
+        let view = matrix_elements::common_view(&states);
+        let grid = Grid::uniform(view, 400);
+        let excited = states.pop().unwrap();
+        let ground = states.pop().unwrap();
+
+        let mixed = MixedState::new(vec![(3.0, ground), (1.0, excited)]).unwrap();
+        assert_eq!(mixed.get_probabilities(), vec![0.75, 0.25]);
+
+        let purity = mixed.purity(&grid);
+        assert!((purity - 0.625).abs() < 1e-2, "purity {}", purity);
+        let entropy = mixed.von_neumann_entropy(&grid);
+        let expected = -(0.75f64 * 0.75f64.ln() + 0.25 * 0.25f64.ln());
+        assert!((entropy - expected).abs() < 1e-2, "entropy {}", entropy);
+
+        let density = mixed.density(&grid);
+        assert!((integrate(density.clone(), TRAPEZE_PER_THREAD) - 1.0).abs() < 1e-9);
+        let rho = mixed.density_matrix(&grid);
+        for i in (0..grid.len()).step_by(37) {
+            assert!((rho[i][i].re - density[i].y).abs() < 1e-12);
+            for j in (0..grid.len()).step_by(41) {
+                assert!((rho[i][j] - rho[j][i].conj()).norm() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn pure_state() {
+        let ground = states().remove(0);
+        let grid = Grid::uniform(ground.get_view(), 400);
+        let pure = MixedState::new(vec![(0.5, ground)]).unwrap();
+        assert!((pure.purity(&grid) - 1.0).abs() < 1e-12);
+        assert!(pure.von_neumann_entropy(&grid).abs() < 1e-12);
+
+        assert!(MixedState::new(vec![]).is_err());
+        assert!(MixedState::new(vec![(-1.0, states().remove(0))]).is_err());
+    }
+}
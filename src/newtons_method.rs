@@ -1,110 +1,140 @@
 use crate::integrals::*;
 use crate::utils::cmp_f64;
+use nalgebra::{DMatrix, DVector};
 use num::Float;
 use rayon::prelude::*;
-use std::cmp::Ordering;
+use serde::Serialize;
+use std::fmt;
 use std::fmt::Debug;
 use std::ops::*;
 use std::sync::Arc;
 
-#[derive(Default, Debug)]
-pub struct Vec2 {
-    x: f64,
-    y: f64,
+// When a root find stops: once its last step is below x_abs + x_rel |x|, or once |f(x)| is below
+// f_abs, whichever comes first. A component of 0 never stops it, Tolerance::FLOATS iterates as far
+// as the floats allow.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Tolerance {
+    pub x_abs: f64,
+    pub x_rel: f64,
+    pub f_abs: f64,
 }
 
-impl Vec2 {
-    pub fn dot(&self, other: &Vec2) -> f64 {
-        return self.x * other.x + self.y + other.y;
-    }
+impl Tolerance {
+    pub const FLOATS: Tolerance = Tolerance {
+        x_abs: 0.0,
+        x_rel: 0.0,
+        f_abs: 0.0,
+    };
 
-    pub fn mag(&self) -> f64 {
-        return (self.x.powi(2) * self.y.powi(2)).sqrt();
+    // only the step size, what newtons method used to test
+    pub const fn step(x_abs: f64) -> Tolerance {
+        Tolerance {
+            x_abs,
+            x_rel: 0.0,
+            f_abs: 0.0,
+        }
     }
 
-    pub fn pseudo_inverse(&self) -> CoVec2 {
-        CoVec2(self.x, self.y) * (1.0 / (self.x.powi(2) + self.y.powi(2)))
+    // only |f(x)|, what regula falsi used to test
+    pub const fn residual(f_abs: f64) -> Tolerance {
+        Tolerance {
+            x_abs: 0.0,
+            x_rel: 0.0,
+            f_abs,
+        }
     }
-}
 
-impl Add for Vec2 {
-    type Output = Vec2;
+    pub fn converged(&self, x: f64, step: f64, residual: f64) -> bool {
+        step.abs() < self.x_abs + self.x_rel * x.abs() || residual.abs() < self.f_abs
+    }
 
-    fn add(self, other: Self) -> Self::Output {
-        Vec2 {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
+    // bound on the distance of x to the root once converged, slope is f'(x)
+    pub fn x_error(&self, x: f64, slope: f64) -> f64 {
+        let by_step = self.x_abs + self.x_rel * x.abs();
+        let by_residual = if self.f_abs > 0.0 {
+            self.f_abs / slope.abs()
+        } else {
+            0.0
+        };
+        by_step.max(by_residual)
     }
 }
 
-impl Sub for Vec2 {
-    type Output = Vec2;
-
-    fn sub(self, other: Self) -> Self::Output {
-        Vec2 {
-            x: self.x - other.x,
-            y: self.x - other.y,
-        }
-    }
+// x, f(x) and the step taken from x in one iteration of a root find
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct Iterate {
+    pub x: f64,
+    pub value: f64,
+    pub step: f64,
 }
 
-impl Mul<f64> for Vec2 {
-    type Output = Vec2;
+// Iterates of the root finds it is passed to, only recorded if enabled. Shows why a root find
+// failed or converged to the wrong root without println!s in the finders.
+#[derive(Clone, PartialEq, Debug, Default, Serialize)]
+pub struct Trace {
+    enabled: bool,
+    iterates: Vec<Iterate>,
+}
 
-    fn mul(self, s: f64) -> Self::Output {
-        Vec2 {
-            x: self.x * s,
-            y: self.y * s,
+impl Trace {
+    pub fn new(enabled: bool) -> Trace {
+        Trace {
+            enabled,
+            iterates: vec![],
         }
     }
-}
-
-#[derive(Debug)]
-pub struct CoVec2(f64, f64);
 
-impl Add for CoVec2 {
-    type Output = CoVec2;
-
-    fn add(self, other: Self) -> Self::Output {
-        CoVec2(self.0 + other.0, self.1 + other.1)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
     }
-}
 
-impl Sub for CoVec2 {
-    type Output = CoVec2;
+    pub fn record(&mut self, x: f64, value: f64, step: f64) {
+        if self.enabled {
+            self.iterates.push(Iterate { x, value, step });
+        }
+    }
 
-    fn sub(self, other: Self) -> Self::Output {
-        CoVec2(self.0 - other.0, self.1 - other.1)
+    pub fn get_iterates(&self) -> &[Iterate] {
+        &self.iterates
     }
 }
 
-impl Mul<Vec2> for CoVec2 {
-    type Output = f64;
-
-    fn mul(self, vec: Vec2) -> Self::Output {
-        return self.0 * vec.x + self.1 * vec.y;
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>5} {:>24} {:>11} {:>11}", "i", "x", "f(x)", "step")?;
+        for (i, iterate) in self.iterates.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>5} {:>24.16e} {:>11.3e} {:>11.3e}",
+                i, iterate.x, iterate.value, iterate.step
+            )?;
+        }
+        Ok(())
     }
 }
 
-impl Mul<f64> for CoVec2 {
-    type Output = CoVec2;
+// why a root find gave up, with its iterates if it was traced
+#[derive(Clone, PartialEq, Debug)]
+pub struct RootFindError {
+    pub message: String,
+    pub trace: Trace,
+}
 
-    fn mul(self, s: f64) -> Self::Output {
-        CoVec2(self.0 * s, self.1 * s)
+impl fmt::Display for RootFindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.trace.iterates.is_empty() {
+            write!(f, ", iterates:\n{}", self.trace)?;
+        }
+        Ok(())
     }
 }
 
-fn gradient<F>(f: F, x: f64) -> Vec2
-where
-    F: Fn(f64) -> Vec2,
-{
-    let x_component = |x| f(x).x;
-    let y_component = |x| f(x).y;
-    return Vec2 {
-        x: derivative(&x_component, x),
-        y: derivative(&y_component, x),
-    };
+// for the stages that report their errors as strings
+impl From<RootFindError> for String {
+    fn from(err: RootFindError) -> String {
+        err.to_string()
+    }
 }
 
 pub fn derivative<F, R>(func: &F, x: f64) -> R
@@ -128,7 +158,7 @@ where
     return ((fifteen_m1 - six_m2) + m3) / ten_dx1;
 }
 
-pub fn newtons_method<F>(f: &F, mut guess: f64, precision: f64) -> f64
+pub fn newtons_method<F>(f: &F, mut guess: f64, tolerance: Tolerance) -> f64
 where
     F: Fn(f64) -> f64,
 {
@@ -139,8 +169,9 @@ where
             panic!("Devision by zero");
         }
 
-        let step = f(guess) / deriv;
-        if step.abs() < precision {
+        let value = f(guess);
+        let step = value / deriv;
+        if tolerance.converged(guess, step, value) {
             return guess;
         } else {
             guess -= step;
@@ -148,44 +179,195 @@ where
     }
 }
 
-pub fn newtons_method_2d<F>(f: &F, mut guess: f64, precision: f64) -> f64
+// jacobian of f at x by central differences
+pub fn jacobian<F>(f: &F, x: &DVector<f64>) -> DMatrix<f64>
 where
-    F: Fn(f64) -> Vec2,
-    F::Output: Debug,
+    F: Fn(&DVector<f64>) -> DVector<f64> + ?Sized,
 {
-    loop {
-        let jacobian = gradient(f, guess);
-        let step: f64 = jacobian.pseudo_inverse() * f(guess);
-        if step.abs() < precision {
-            return guess;
-        } else {
-            guess -= step;
+    let columns = (0..x.len())
+        .map(|j| {
+            let h = f64::epsilon().cbrt() * x[j].abs().max(1.0);
+            let mut forward = x.clone();
+            let mut backward = x.clone();
+            forward[j] += h;
+            backward[j] -= h;
+            (f(&forward) - f(&backward)) / (2.0 * h)
+        })
+        .collect::<Vec<DVector<f64>>>();
+    DMatrix::from_columns(&columns)
+}
+
+// Gauss-Newton for f: R^n -> R^m, newtons method if m = n. Steps by the least squares solution of
+// J step = f(x), from the SVD of the jacobian with singular values below SINGULAR_CUTOFF times the
+// largest taken as 0. The trace records the norms of x, f(x) and the step.
+pub fn gauss_newton<F>(
+    f: &F,
+    mut x: DVector<f64>,
+    tolerance: Tolerance,
+    max_iters: usize,
+    trace: &mut Trace,
+) -> Result<DVector<f64>, RootFindError>
+where
+    F: Fn(&DVector<f64>) -> DVector<f64> + ?Sized,
+{
+    const SINGULAR_CUTOFF: f64 = 1e-12;
+    let fail = |message: String, trace: &Trace| RootFindError {
+        message,
+        trace: trace.clone(),
+    };
+    for _ in 0..max_iters {
+        let value = f(&x);
+        let svd = jacobian(f, &x).svd(true, true);
+        let cutoff = SINGULAR_CUTOFF * svd.singular_values.max();
+        let step = svd
+            .solve(&value, cutoff)
+            .map_err(|message| fail(message.to_string(), trace))?;
+        trace.record(x.norm(), value.norm(), step.norm());
+        if !step.iter().all(|s| s.is_finite()) {
+            return Err(fail(
+                format!("the step from {:?} isn't finite", x.as_slice()),
+                trace,
+            ));
         }
+        if tolerance.converged(x.norm(), step.norm(), value.norm()) {
+            return Ok(x);
+        }
+        x -= step;
     }
+    Err(fail(
+        format!("no convergence in {} iterations", max_iters),
+        trace,
+    ))
+}
+
+// x minimizing |f(x)|^2 for f: R -> R^2, the case n = 1, m = 2 of gauss_newton
+pub fn newtons_method_2d<F>(f: &F, guess: f64, tolerance: Tolerance) -> f64
+where
+    F: Fn(f64) -> (f64, f64),
+{
+    let vector_f = |x: &DVector<f64>| {
+        let (a, b) = f(x[0]);
+        DVector::from_vec(vec![a, b])
+    };
+    let x = gauss_newton(
+        &vector_f,
+        DVector::from_element(1, guess),
+        tolerance,
+        usize::MAX,
+        &mut Trace::new(false),
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+    x[0]
 }
 
 pub fn newtons_method_max_iters<F>(
     f: &F,
-    mut guess: f64,
-    precision: f64,
+    guess: f64,
+    tolerance: Tolerance,
     max_iters: usize,
 ) -> Option<f64>
 where
     F: Fn(f64) -> f64,
 {
+    newtons_method_traced(f, guess, tolerance, max_iters, &mut Trace::new(false)).ok()
+}
+
+// newtons_method_max_iters recording its iterates in trace, the error has a copy of them
+pub fn newtons_method_traced<F>(
+    f: &F,
+    guess: f64,
+    tolerance: Tolerance,
+    max_iters: usize,
+    trace: &mut Trace,
+) -> Result<f64, RootFindError>
+where
+    F: Fn(f64) -> f64,
+{
+    root_traced(f, guess, RootMethod::Newton, tolerance, max_iters, trace)
+}
+
+// How root_traced steps. Halley's method uses the second derivative as well and converges
+// cubically, the secant through the last two iterates needs only one evaluation of f per step
+// instead of the six of derivative, it pays off for expensive functions.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum RootMethod {
+    Newton,
+    Halley,
+    Secant,
+}
+
+pub fn second_derivative<F: Fn(f64) -> f64 + ?Sized>(func: &F, x: f64) -> f64 {
+    // balances truncation and rounding error of the second difference
+    let h = f64::epsilon().powf(0.25) * x.abs().max(1.0);
+    (func(x + h) - 2.0 * func(x) + func(x - h)) / (h * h)
+}
+
+// step of method from x, where f is value, previous is the last iterate and f there, without one
+// the secant method takes a newton step
+pub fn root_step<F: Fn(f64) -> f64 + ?Sized>(
+    f: &F,
+    method: RootMethod,
+    x: f64,
+    value: f64,
+    previous: Option<(f64, f64)>,
+) -> f64 {
+    match (method, previous) {
+        (RootMethod::Secant, Some((x_previous, value_previous))) => {
+            value * (x - x_previous) / (value - value_previous)
+        }
+        (RootMethod::Halley, _) => {
+            let first = derivative(f, x);
+            let second = second_derivative(f, x);
+            2.0 * value * first / (2.0 * first * first - value * second)
+        }
+        _ => value / derivative(f, x),
+    }
+}
+
+// root of f close to guess by method, recording its iterates in trace, the error has a copy of
+// them
+pub fn root_traced<F>(
+    f: &F,
+    mut guess: f64,
+    method: RootMethod,
+    tolerance: Tolerance,
+    max_iters: usize,
+    trace: &mut Trace,
+) -> Result<f64, RootFindError>
+where
+    F: Fn(f64) -> f64,
+{
+    let fail = |message: String, trace: &Trace| RootFindError {
+        message,
+        trace: trace.clone(),
+    };
+    // the secant starts from a difference quotient
+    let mut previous = if method == RootMethod::Secant {
+        let x = guess + f64::epsilon().sqrt() * guess.abs().max(1.0);
+        Some((x, f(x)))
+    } else {
+        None
+    };
     for _ in 0..max_iters {
-        let derivative = derivative(f, guess);
-        if derivative == 0.0 {
-            return None;
+        let value = f(guess);
+        let step = root_step(f, method, guess, value, previous);
+        trace.record(guess, value, step);
+        if !step.is_finite() {
+            return Err(fail(
+                format!("the {:?} step from {} isn't finite", method, guess),
+                trace,
+            ));
         }
-        let step = f(guess) / derivative;
-        if step.abs() < precision {
-            return Some(guess);
-        } else {
-            guess -= step;
+        if tolerance.converged(guess, step, value) {
+            return Ok(guess);
         }
+        previous = Some((guess, value));
+        guess -= step;
     }
-    None
+    Err(fail(
+        format!("no convergence in {} iterations", max_iters),
+        trace,
+    ))
 }
 
 fn sigmoid(x: f64) -> f64 {
@@ -217,7 +399,7 @@ where
     return (a * f(b) - b * f(a)) / (f(b) - f(a));
 }
 
-pub fn regula_falsi_method<F>(f: &F, mut a: f64, mut b: f64, precision: f64) -> f64
+pub fn regula_falsi_method<F>(f: &F, mut a: f64, mut b: f64, tolerance: Tolerance) -> f64
 where
     F: Fn(f64) -> f64 + ?Sized,
 {
@@ -228,38 +410,56 @@ where
     }
 
     let mut c = regula_falsi_c(f, a, b);
-    while f64::abs(f(c)) > precision {
+    let mut step = f64::INFINITY;
+    while !tolerance.converged(c, step, f(c)) {
         b = regula_falsi_c(f, a, b);
         a = regula_falsi_c(f, a, b);
-        c = regula_falsi_c(f, a, b);
+        let next = regula_falsi_c(f, a, b);
+        step = next - c;
+        c = next;
     }
     return c;
 }
 
-pub fn regula_falsi_bisection<F>(f: &F, guess: f64, bisection_step: f64, precision: f64) -> f64
+pub fn regula_falsi_bisection<F>(
+    f: &F,
+    guess: f64,
+    bisection_step: f64,
+    tolerance: Tolerance,
+) -> f64
 where
     F: Fn(f64) -> f64 + ?Sized,
 {
     let (a, b) = bisection_search_sign_change(f, guess, bisection_step);
-    return regula_falsi_method(f, a, b, precision);
+    regula_falsi_method(f, a, b, tolerance)
 }
 
+// zeros closer than this many tolerances are taken to be the same
+const SAME_ZERO_FACTOR: f64 = 10.0;
+
 #[derive(Clone)]
 pub struct NewtonsMethodFindNewZero<F>
 where
     F: Fn(f64) -> f64 + ?Sized + Clone,
 {
     f: Arc<F>,
+    tolerance: Tolerance,
+    // keeps the modified function finite at the previous zeros and tells double zeros apart
     precision: f64,
     max_iters: usize,
     previous_zeros: Vec<(i32, f64)>,
 }
 
 impl<F: Fn(f64) -> f64 + ?Sized + Clone> NewtonsMethodFindNewZero<F> {
-    pub(crate) fn new(f: Arc<F>, precision: f64, max_iters: usize) -> NewtonsMethodFindNewZero<F> {
+    pub(crate) fn new(
+        f: Arc<F>,
+        tolerance: Tolerance,
+        max_iters: usize,
+    ) -> NewtonsMethodFindNewZero<F> {
         NewtonsMethodFindNewZero {
             f,
-            precision,
+            tolerance,
+            precision: tolerance.x_abs.max(tolerance.f_abs),
             max_iters,
             previous_zeros: vec![],
         }
@@ -279,23 +479,53 @@ impl<F: Fn(f64) -> f64 + ?Sized + Clone> NewtonsMethodFindNewZero<F> {
     }
 
     pub(crate) fn next_zero(&mut self, guess: f64) -> Option<f64> {
-        let zero = newtons_method_max_iters(
-            &|x| self.modified_func(x),
-            guess,
-            self.precision,
-            self.max_iters,
-        );
+        self.next_zero_from(&[guess])
+    }
 
-        if let Some(z) = zero {
-            // to avoid hitting maxima and minima twice
-            if derivative(&|x| self.modified_func(x), z).abs() < self.precision {
-                self.previous_zeros.push((2, z));
-            } else {
-                self.previous_zeros.push((1, z));
+    // newtons method from each candidate in order until one converges to a zero that wasn't found
+    // before. If all of them lead back to known zeros the first of those is a repeated zero, the
+    // modified function only vanishes there if the multiplicity is higher than recorded.
+    pub(crate) fn next_zero_from(&mut self, candidates: &[f64]) -> Option<f64> {
+        let mut repeated = None;
+        for guess in candidates {
+            let zero = newtons_method_max_iters(
+                &|x| self.modified_func(x),
+                *guess,
+                self.tolerance,
+                self.max_iters,
+            )
+            .filter(|z| z.is_finite());
+
+            match zero.map(|z| (z, self.known_zero(z))) {
+                Some((_, Some(known))) => {
+                    repeated = repeated.or(Some(known));
+                }
+                Some((z, None)) => {
+                    // to avoid hitting maxima and minima twice
+                    if derivative(&|x| self.modified_func(x), z).abs() < self.precision {
+                        self.previous_zeros.push((2, z));
+                    } else {
+                        self.previous_zeros.push((1, z));
+                    }
+                    return Some(z);
+                }
+                None => {}
             }
         }
+        // once more in the divisor, the multiplicity goes up by one
+        if let Some(z) = repeated {
+            self.previous_zeros.push((1, z));
+        }
+        repeated
+    }
 
-        return zero;
+    // the previous zero within a few tolerances of x
+    fn known_zero(&self, x: f64) -> Option<f64> {
+        let distance = SAME_ZERO_FACTOR * (self.precision + self.tolerance.x_rel * x.abs());
+        self.previous_zeros
+            .iter()
+            .map(|(_, z)| *z)
+            .find(|z| (x - z).abs() <= distance)
     }
 
     pub(crate) fn get_previous_zeros(&self) -> Vec<f64> {
@@ -304,14 +534,49 @@ impl<F: Fn(f64) -> f64 + ?Sized + Clone> NewtonsMethodFindNewZero<F> {
             .map(|(_, z)| *z)
             .collect::<Vec<f64>>()
     }
+
+    // the previous zeros with every repeated zero once
+    pub(crate) fn get_distinct_zeros(&self) -> Vec<f64> {
+        let mut distinct: Vec<f64> = vec![];
+        for (_, z) in &self.previous_zeros {
+            if !distinct.contains(z) {
+                distinct.push(*z);
+            }
+        }
+        distinct
+    }
+}
+
+pub fn make_guess<F>(f: &F, range: (f64, f64), n: usize) -> Option<f64>
+where
+    F: Fn(f64) -> f64 + Sync,
+{
+    make_guesses(f, range, n, 1, &[], Tolerance::step(0.0))
+        .first()
+        .copied()
 }
 
-pub fn make_guess<F>(f: &F, (start, end): (f64, f64), n: usize) -> Option<f64>
+// Up to count guesses for newtons method, the local minima of |f| / (1 - exp(-f'^2)) on n samples
+// of the range, smallest first. Samples within one spacing or the tolerance of a known zero are
+// left out, they are its shoulders and would lead back to it.
+pub fn make_guesses<F>(
+    f: &F,
+    (start, end): (f64, f64),
+    n: usize,
+    count: usize,
+    known_zeros: &[f64],
+    tolerance: Tolerance,
+) -> Vec<f64>
 where
     F: Fn(f64) -> f64 + Sync,
 {
-    let sort_func = |(_, y1): &(f64, f64), (_, y2): &(f64, f64)| -> Ordering { cmp_f64(&y1, &y2) };
-    let mut points: Vec<(f64, f64)> = (0..n)
+    let spacing = ((end - start) / n as f64).abs();
+    let near_known = |x: f64| {
+        known_zeros
+            .iter()
+            .any(|z| (x - z).abs() <= spacing.max(tolerance.x_abs + tolerance.x_rel * z.abs()))
+    };
+    let points: Vec<(f64, f64)> = (0..n)
         .into_par_iter()
         .map(|i| index_to_range(i as f64, 0.0, n as f64, start, end))
         .map(move |x| {
@@ -320,14 +585,24 @@ where
         })
         .map(|(x, y)| (x, y.abs()))
         .collect();
-    points.sort_by(sort_func);
-    points.get(0).map(|point| point.0)
+
+    let below = |i: usize, j: Option<usize>| {
+        j.and_then(|j| points.get(j))
+            .is_none_or(|(_, y)| points[i].1 <= *y)
+    };
+    let mut minima = (0..points.len())
+        .filter(|i| points[*i].1.is_finite() && !near_known(points[*i].0))
+        .filter(|i| below(*i, i.checked_sub(1)) && below(*i, Some(*i + 1)))
+        .map(|i| points[i])
+        .collect::<Vec<(f64, f64)>>();
+    minima.sort_by(|(_, y1), (_, y2)| cmp_f64(y1, y2));
+    minima.iter().take(count).map(|(x, _)| *x).collect()
 }
 
 pub fn newtons_method_find_new_zero<F>(
     f: &F,
     guess: f64,
-    precision: f64,
+    tolerance: Tolerance,
     max_iters: usize,
     known_zeros: &Vec<f64>,
 ) -> Option<f64>
@@ -335,7 +610,7 @@ where
     F: Fn(f64) -> f64,
 {
     let f_modified = |x| f(x) / known_zeros.iter().fold(0.0, |acc, &z| acc * (x - z));
-    newtons_method_max_iters(&f_modified, guess, precision, max_iters)
+    newtons_method_max_iters(&f_modified, guess, tolerance, max_iters)
 }
 
 #[cfg(test)]
@@ -379,12 +654,12 @@ mod test {
             let zero = index_to_range(i as f64, 0.0, 100.0, 0.1, 10.0);
             let func = |x| x * x - zero * zero;
             assert!(float_compare(
-                newtons_method(&func, 100.0, 1e-7),
+                newtons_method(&func, 100.0, Tolerance::step(1e-7)),
                 zero,
                 1e-4,
             ));
             assert!(float_compare(
-                newtons_method(&func, -100.0, 1e-7),
+                newtons_method(&func, -100.0, Tolerance::step(1e-7)),
                 -zero,
                 1e-4,
             ));
@@ -397,23 +672,125 @@ mod test {
             let zero = index_to_range(i as f64, 0.0, 100.0, 0.1, 10.0);
             let func = |x| (x - zero) * (x + zero) * (x - zero / 2.0);
             assert!(float_compare(
-                newtons_method(&func, 100.0, 1e-7),
+                newtons_method(&func, 100.0, Tolerance::step(1e-7)),
                 zero,
                 1e-4,
             ));
             assert!(float_compare(
-                newtons_method(&func, -100.0, 1e-7),
+                newtons_method(&func, -100.0, Tolerance::step(1e-7)),
                 -zero,
                 1e-4,
             ));
             assert!(float_compare(
-                newtons_method(&func, 0.0, 1e-7),
+                newtons_method(&func, 0.0, Tolerance::step(1e-7)),
                 zero / 2.0,
                 1e-4,
             ));
         }
     }
 
+    #[test]
+    fn tolerance_components() {
+        // newtons method converges linearly on a triple root, the error shrinks by 2/3 and the
+        // step is a third of it
+        let cube = |x: f64| (x - 1000.0).powi(3);
+        let by_step = newtons_method(&cube, 1001.0, Tolerance::step(1e-3)) - 1000.0;
+        assert!((2e-3..3e-3).contains(&by_step), "{}", by_step);
+        let relative = Tolerance {
+            x_abs: 0.0,
+            x_rel: 1e-6,
+            f_abs: 0.0,
+        };
+        let by_relative_step = newtons_method(&cube, 1001.0, relative) - 1000.0;
+        assert!(
+            (2e-3..3e-3).contains(&by_relative_step),
+            "{}",
+            by_relative_step
+        );
+        let by_residual = newtons_method(&cube, 1001.0, Tolerance::residual(1e-3)) - 1000.0;
+        assert!((0.066..0.1).contains(&by_residual), "{}", by_residual);
+
+        assert_eq!(Tolerance::residual(1e-3).x_error(0.0, -4.0), 2.5e-4);
+        assert!((relative.x_error(1000.0, 0.0) - 1e-3).abs() < 1e-15);
+    }
+
+    #[test]
+    fn traced_failure() {
+        // no real root, newtons method jumps around
+        let func = |x: f64| x * x + 1.0;
+        let mut trace = Trace::new(true);
+        let err =
+            newtons_method_traced(&func, 0.5, Tolerance::step(1e-7), 20, &mut trace).unwrap_err();
+        assert_eq!(err.trace.get_iterates().len(), 20);
+        assert_eq!(err.trace, trace);
+        assert_eq!(trace.get_iterates()[0].x, 0.5);
+        assert!((trace.get_iterates()[0].value - 1.25).abs() < 1e-12);
+        assert!(err
+            .to_string()
+            .starts_with("no convergence in 20 iterations, iterates:"));
+
+        let mut untraced = Trace::new(false);
+        let err = newtons_method_traced(&func, 0.5, Tolerance::step(1e-7), 20, &mut untraced)
+            .unwrap_err();
+        assert!(err.trace.get_iterates().is_empty());
+        assert_eq!(err.to_string(), "no convergence in 20 iterations");
+
+        let zero =
+            newtons_method_traced(&|x| x * x - 4.0, 3.0, Tolerance::step(1e-9), 50, &mut trace);
+        assert!((zero.unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn root_methods() {
+        // Wallis' cubic
+        let cubic = |x: f64| x.powi(3) - 2.0 * x - 5.0;
+        let root = 2.094_551_481_542_326_5;
+        let mut iterations = vec![];
+        for method in [RootMethod::Newton, RootMethod::Halley, RootMethod::Secant] {
+            let mut trace = Trace::new(true);
+            let zero = root_traced(&cubic, 3.0, method, Tolerance::step(1e-12), 100, &mut trace);
+            assert!((zero.unwrap() - root).abs() < 1e-12, "{:?}", method);
+            iterations.push(trace.get_iterates().len());
+        }
+        assert!(iterations[1] < iterations[0], "{:?}", iterations);
+
+        assert!((second_derivative(&cubic, 2.0) - 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn gauss_newton_systems() {
+        // x^2 + y^2 = 4 and x y = 1, x = sqrt(2 + sqrt(3)) and y = 1 / x
+        let system = |v: &DVector<f64>| {
+            DVector::from_vec(vec![v[0] * v[0] + v[1] * v[1] - 4.0, v[0] * v[1] - 1.0])
+        };
+        let mut trace = Trace::new(true);
+        let start = DVector::from_vec(vec![2.0, 0.5]);
+        let root = gauss_newton(&system, start, Tolerance::step(1e-12), 50, &mut trace).unwrap();
+        let x = (2.0 + 3.0f64.sqrt()).sqrt();
+        assert!((root[0] - x).abs() < 1e-10 && (root[1] - 1.0 / x).abs() < 1e-10);
+        assert!(trace.get_iterates().len() < 10);
+
+        // straight line through three points in the least squares sense
+        let points = [(0.0, 1.0), (1.0, 2.0), (2.0, 4.0)];
+        let residuals = |v: &DVector<f64>| {
+            DVector::from_iterator(3, points.iter().map(|(t, y)| v[0] * t + v[1] - y))
+        };
+        let line = gauss_newton(
+            &residuals,
+            DVector::zeros(2),
+            Tolerance::step(1e-12),
+            50,
+            &mut Trace::new(false),
+        )
+        .unwrap();
+        assert!((line[0] - 1.5).abs() < 1e-9 && (line[1] - 5.0 / 6.0).abs() < 1e-9);
+
+        assert!(
+            (newtons_method_2d(&|x| (x - 1.0, x - 3.0), 0.0, Tolerance::step(1e-12)) - 2.0).abs()
+                < 1e-9
+        );
+    }
+
     #[test]
     fn newtons_method_find_next_polynomial() {
         for i in 0..10 {
@@ -425,8 +802,11 @@ mod test {
                     let test_func = |x: f64| (x - a) * (x - b) * (x - c);
 
                     for _guess in [a, b, c] {
-                        let mut finder =
-                            NewtonsMethodFindNewZero::new(Arc::new(test_func), 1e-15, 10000000);
+                        let mut finder = NewtonsMethodFindNewZero::new(
+                            Arc::new(test_func),
+                            Tolerance::step(1e-15),
+                            10000000,
+                        );
 
                         finder.next_zero(1.0);
                         finder.next_zero(1.0);
@@ -455,7 +835,8 @@ mod test {
 
         let test_func = |x: f64| 5.0 * (3.0 * x + 1.0).abs() - (1.5 * x.powi(2) + x - 50.0).powi(2);
 
-        let mut finder = NewtonsMethodFindNewZero::new(Arc::new(test_func), 1e-11, 100000000);
+        let mut finder =
+            NewtonsMethodFindNewZero::new(Arc::new(test_func), Tolerance::step(1e-11), 100000000);
 
         for _i in 0..4 {
             let guess = make_guess(&|x| finder.modified_func(x), interval, 1000);
@@ -475,11 +856,31 @@ mod test {
         }
     }
 
+    #[test]
+    fn guesses_avoid_known_zeros() {
+        let f = |x: f64| (x - 1.0) * (x + 2.0) * (x - 4.0);
+        let guesses = make_guesses(&f, (-5.0, 5.0), 1000, 3, &[], Tolerance::step(1e-9));
+        assert_eq!(guesses.len(), 3);
+        for zero in [-2.0, 1.0, 4.0] {
+            assert!(guesses.iter().any(|g| (g - zero).abs() < 0.01), "{}", zero);
+        }
+
+        let guesses = make_guesses(&f, (-5.0, 5.0), 1000, 3, &[1.0], Tolerance::step(1e-9));
+        assert!(guesses.iter().all(|g| (g - 1.0).abs() > 0.01));
+
+        let mut finder = NewtonsMethodFindNewZero::new(Arc::new(f), Tolerance::step(1e-12), 1000);
+        assert!((finder.next_zero(0.9).unwrap() - 1.0).abs() < 1e-10);
+        // the first candidate fails, the second leads to a new zero
+        let zero = finder.next_zero_from(&[f64::NAN, 3.5]).unwrap();
+        assert!((zero - 4.0).abs() < 1e-10);
+        assert_eq!(finder.get_previous_zeros().len(), 2);
+    }
+
     #[test]
     fn regula_falsi_bisection_test() {
         let func = |x: f64| x * (x - 2.0) * (x + 2.0);
 
-        let actual = regula_falsi_bisection(&func, -1e-3, -1e-3, 1e-5);
+        let actual = regula_falsi_bisection(&func, -1e-3, -1e-3, Tolerance::residual(1e-5));
         let expected = -2.0;
 
         println!("expected: {}, actual {}", expected, actual);
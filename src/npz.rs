@@ -0,0 +1,150 @@
+use crate::*;
+
+// Minimal writer for numpy .npz archives, a zip of uncompressed .npy arrays, so the output can be
+// read with numpy.load without pulling in a zip crate.
+#[derive(Default)]
+pub struct NpzWriter {
+    arrays: Vec<(String, Vec<u8>)>,
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// .npy version 1.0, the header is padded so the data starts 64 byte aligned
+fn npy(descr: &str, shape: &[usize], data: Vec<u8>) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    let unpadded = 10 + header.len() + 1;
+    header += &" ".repeat((64 - unpadded % 64) % 64);
+    header += "\n";
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+impl NpzWriter {
+    pub fn new() -> NpzWriter {
+        NpzWriter { arrays: vec![] }
+    }
+
+    // data in row major order
+    pub fn add_f64(&mut self, name: &str, shape: &[usize], data: &[f64]) {
+        assert_eq!(shape.iter().product::<usize>(), data.len());
+        let bytes = data.iter().flat_map(|x| x.to_le_bytes()).collect();
+        self.arrays
+            .push((format!("{}.npy", name), npy("<f8", shape, bytes)));
+    }
+
+    pub fn add_complex(&mut self, name: &str, shape: &[usize], data: &[Complex64]) {
+        assert_eq!(shape.iter().product::<usize>(), data.len());
+        let bytes = data
+            .iter()
+            .flat_map(|c| [c.re.to_le_bytes(), c.im.to_le_bytes()])
+            .flatten()
+            .collect();
+        self.arrays
+            .push((format!("{}.npy", name), npy("<c16", shape, bytes)));
+    }
+
+    // stored zip entries with a central directory, dated 1980-01-01
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut directory = vec![];
+
+        for (name, data) in &self.arrays {
+            let offset = bytes.len() as u32;
+            let crc = crc32(data);
+            // version, flags, method, time, date, crc, compressed and uncompressed size
+            let mut common = vec![];
+            common.extend_from_slice(&20u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&0x21u16.to_le_bytes());
+            common.extend_from_slice(&crc.to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+
+            bytes.extend_from_slice(&0x04034b50u32.to_le_bytes());
+            bytes.extend_from_slice(&common);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(data);
+
+            directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+            directory.extend_from_slice(&20u16.to_le_bytes());
+            directory.extend_from_slice(&common);
+            // comment length, disk, internal and external attributes
+            directory.extend_from_slice(&[0u8; 10]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let directory_offset = bytes.len() as u32;
+        bytes.extend_from_slice(&directory);
+        bytes.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        bytes.extend_from_slice(&[0u8; 4]);
+        bytes.extend_from_slice(&(self.arrays.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.arrays.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&directory_offset.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes
+    }
+
+    pub fn write(&self, path: &Path) {
+        let mut file = plot::create_file(path);
+        file.write_all(&self.to_bytes()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn npy_header_is_aligned() {
+        let array = npy("<f8", &[3, 2], vec![0; 48]);
+        let header_len = u16::from_le_bytes([array[8], array[9]]) as usize;
+        let header = std::str::from_utf8(&array[10..10 + header_len]).unwrap();
+
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': (3, 2), }"));
+        assert!(header.ends_with('\n'));
+        assert_eq!(array.len(), 10 + header_len + 48);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::*;
+use serde::Serialize;
+use std::fmt::Display;
+
+// samples of psi for the momentum moments, the momenta go up to their Nyquist momentum
+// pi hbar / dx in steps of pi hbar / L, half of what a state of extent L needs
+const MOMENTUM_SAMPLES: usize = 4096;
+
+// <psi|f(x)|psi> / <psi|psi> with the trapezoidal rule on INTEG_STEPS intervals. The error is the
+// Richardson estimate |fine - coarse| / 3 from the same ratio on every other point.
+// It is infinite if f(x) |psi|^2 isn't finite somewhere on the grid, e.g. 1 / x^2 at x = 0 where
+// psi doesn't vanish.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Expectation {
+    pub value: f64,
+    pub error: f64,
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} +- {:.1e}", self.value, self.error)
+    }
+}
+
+// trapezoidal rule on all the samples and on every other one, the number of intervals is even
+fn trapezoids(step: f64, values: &[f64]) -> (f64, f64) {
+    let rule = |values: Vec<f64>, step: f64| {
+        step * (values.iter().sum::<f64>() - (values[0] + values[values.len() - 1]) / 2.0)
+    };
+    (
+        rule(values.to_vec(), step),
+        rule(values.iter().step_by(2).copied().collect(), 2.0 * step),
+    )
+}
+
+// over OBSERVABLE_DOMAIN of the state, i.e. its effective support
+pub fn expectation_of<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    wave_function: &WaveFunction,
+    f: &F,
+) -> Expectation {
+    expectation_in(wave_function, OBSERVABLE_DOMAIN.range(wave_function), f)
+}
+
+pub fn expectation_in<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    psi: &dyn Func<f64, Complex64>,
+    range: (f64, f64),
+    f: &F,
+) -> Expectation {
+    let grid = Grid::uniform(range, INTEG_STEPS + 1);
+    let (densities, weighted): (Vec<f64>, Vec<f64>) = grid
+        .evaluate(psi)
+        .par_iter()
+        .map(|p| {
+            let density = p.y.norm_sqr();
+            (density, density * f(p.x))
+        })
+        .unzip();
+    ratio(
+        (range.1 - range.0) / INTEG_STEPS as f64,
+        &densities,
+        &weighted,
+    )
+}
+
+fn ratio(step: f64, densities: &[f64], weighted: &[f64]) -> Expectation {
+    let (norm, coarse_norm) = trapezoids(step, densities);
+    let (moment, coarse_moment) = trapezoids(step, weighted);
+
+    let value = moment / norm;
+    let error = (value - coarse_moment / coarse_norm).abs() / 3.0;
+    Expectation {
+        value,
+        error: if value.is_finite() {
+            error
+        } else {
+            f64::INFINITY
+        },
+    }
+}
+
+// phi(p) = (2 pi hbar)^(-1/2) integral of e^(-i p x / hbar) psi(x) dx at the momenta, by the
+// trapezoidal rule on uniform samples of psi
+pub fn momentum_space(
+    samples: &[Point<f64, Complex64>],
+    momenta: &Grid,
+    hbar: f64,
+) -> Vec<Point<f64, Complex64>> {
+    let dx = samples[1].x - samples[0].x;
+    let last = samples.len() - 1;
+    momenta
+        .points()
+        .par_iter()
+        .map(|p| {
+            let sum = samples
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    let weight = if i == 0 || i == last { 0.5 } else { 1.0 };
+                    weight * s.y * Complex64::from_polar(1.0, -p * s.x / hbar)
+                })
+                .sum::<Complex64>();
+            Point {
+                x: *p,
+                y: sum * dx / (2.0 * f64::consts::PI * hbar).sqrt(),
+            }
+        })
+        .collect()
+}
+
+// <p^k> over OBSERVABLE_DOMAIN of the state
+pub fn momentum_moment_of(wave_function: &WaveFunction, k: i32) -> Expectation {
+    momentum_moment(
+        wave_function,
+        OBSERVABLE_DOMAIN.range(wave_function),
+        wave_function.get_phase().hbar,
+        k,
+    )
+}
+
+// <p^k> as the integral of p^k |phi(p)|^2, psi taken as 0 outside of range. Unlike applying
+// Operator::P k times this needs no derivatives of psi, but a kink of psi, e.g. at a joint, leaves
+// a tail of |phi|^2 like p^-4 and the moments from k = 3 on depend on the highest momentum.
+pub fn momentum_moment(
+    psi: &dyn Func<f64, Complex64>,
+    range: (f64, f64),
+    hbar: f64,
+    k: i32,
+) -> Expectation {
+    let samples = Grid::uniform(range, MOMENTUM_SAMPLES).evaluate(psi);
+    let nyquist = f64::consts::PI * hbar * (MOMENTUM_SAMPLES - 1) as f64 / (range.1 - range.0);
+    let momenta = Grid::uniform((-nyquist, nyquist), 2 * MOMENTUM_SAMPLES + 1);
+    let (densities, weighted): (Vec<f64>, Vec<f64>) = momentum_space(&samples, &momenta, hbar)
+        .iter()
+        .map(|p| {
+            let density = p.y.norm_sqr();
+            (density, density * p.x.powi(k))
+        })
+        .unzip();
+    ratio(nyquist / MOMENTUM_SAMPLES as f64, &densities, &weighted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::operators::test::Gaussian;
+    use crate::operators::{Apply, Operator};
+
+    #[test]
+    fn moments_of_oscillator() {
+        // V = x^2 with m = 1, omega = sqrt(2), <x^2> = (n + 1/2) / omega
+        let state = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            4,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let one = expectation_of(&state, &|_| 1.0);
+        assert!((one.value - 1.0).abs() < 1e-12 && one.error < 1e-12);
+
+        let square = expectation_of(&state, &|x: f64| x * x);
+        let exact = 4.5 / 2.0f64.sqrt();
+        // the patched state is 1.6% high, all of it from the linearized airy functions around
+        // the turning points, the tails past the airy ranges add less than 1e-4
+        assert!((square.value - exact).abs() < 0.02 * exact, "{}", square);
+        assert!(square.error < 1e-5 * exact, "{}", square);
+
+        // odd moments of an even state
+        let cube = expectation_of(&state, &|x: f64| x.powi(3));
+        assert!(cube.value.abs() < 1e-3 * exact, "{}", cube);
+
+        let pole = expectation_in(&state, (-1.0, 1.0), &|x: f64| 1.0 / x);
+        assert_eq!(pole.error, f64::INFINITY);
+    }
+
+    #[test]
+    fn momentum_moments_of_gaussian() {
+        // phi is a Gaussian of width hbar, <p^2> = hbar^2 / 2, <p^4> = 3/4 hbar^4, <p^6> = 15/8 hbar^6
+        let hbar = Gaussian.get_hbar();
+        for (k, expected) in [(2, 0.5), (4, 0.75), (6, 1.875)] {
+            let moment = momentum_moment(&Gaussian, (-12.0, 12.0), hbar, k);
+            let expected = expected * hbar.powi(k);
+            assert!(
+                (moment.value - expected).abs() < 1e-8 * expected,
+                "{}: {}",
+                k,
+                moment
+            );
+        }
+        assert!(
+            momentum_moment(&Gaussian, (-12.0, 12.0), hbar, 1)
+                .value
+                .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn momentum_moments_agree_with_derivatives() {
+        // <p^2> = E for V = x^2 with m = 1
+        let state = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            4,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let range = OBSERVABLE_DOMAIN.range(&state);
+        let grid = Grid::uniform(range, INTEG_STEPS);
+        let derivatives = (matrix_elements::norm(&state.apply(Operator::P), &grid)
+            / matrix_elements::norm(&state, &grid))
+        .powi(2);
+        let phase_space = momentum_moment_of(&state, 2);
+        let energy = state.get_energy();
+        assert!(
+            (derivatives - energy).abs() < 0.03 * energy,
+            "{}",
+            derivatives
+        );
+        assert!(
+            (phase_space.value - derivatives).abs() < 0.02 * derivatives,
+            "{} != {}",
+            phase_space,
+            derivatives
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::*;
+
+// Operators acting on a state, applied pointwise so the result can be evaluated, plotted or used
+// in matrix elements like any other Func, e.g. state.apply(Operator::X).apply(Operator::P).
+#[derive(Clone, Copy, Debug)]
+pub enum Operator {
+    X,
+    // -i hbar d/dx
+    P,
+    // ladder operators of the harmonic oscillator with the given mass and angular frequency
+    Lowering { mass: f64, omega: f64 },
+    Raising { mass: f64, omega: f64 },
+}
+
+pub trait Apply: Func<f64, Complex64> + Sized {
+    // of the units the state is given in, P and the ladder operators depend on it
+    fn get_hbar(&self) -> f64;
+
+    // points where the state isn't smooth, derivatives are taken from one side close to them
+    fn get_joints(&self) -> Vec<f64> {
+        vec![]
+    }
+
+    fn apply(&self, operator: Operator) -> Applied<'_> {
+        Applied {
+            operator,
+            state: self,
+            joints: self.get_joints(),
+            hbar: self.get_hbar(),
+        }
+    }
+}
+
+pub struct Applied<'a> {
+    operator: Operator,
+    state: &'a dyn Func<f64, Complex64>,
+    joints: Vec<f64>,
+    hbar: f64,
+}
+
+impl Applied<'_> {
+    fn derivative(&self, x: f64) -> Complex64 {
+        let dx = f64::EPSILON.sqrt();
+        let f = |x: f64| self.state.eval(x);
+
+        match self.joints.iter().find(|j| (x - **j).abs() < 4.0 * dx) {
+            // second order one sided difference on the side of the joint x lies on
+            Some(joint) => {
+                let h = if x >= *joint { dx } else { -dx };
+                (-3.0 * f(x) + 4.0 * f(x + h) - f(x + 2.0 * h)) / (2.0 * h)
+            }
+            None => derivative(&f, x),
+        }
+    }
+
+    fn momentum(&self, x: f64) -> Complex64 {
+        complex(0.0, -self.hbar) * self.derivative(x)
+    }
+}
+
+impl Func<f64, Complex64> for Applied<'_> {
+    fn eval(&self, x: f64) -> Complex64 {
+        match self.operator {
+            Operator::X => x * self.state.eval(x),
+            Operator::P => self.momentum(x),
+            Operator::Lowering { mass, omega } => {
+                (mass * omega / (2.0 * self.hbar)).sqrt()
+                    * (x * self.state.eval(x)
+                        + complex(0.0, 1.0 / (mass * omega)) * self.momentum(x))
+            }
+            Operator::Raising { mass, omega } => {
+                (mass * omega / (2.0 * self.hbar)).sqrt()
+                    * (x * self.state.eval(x)
+                        - complex(0.0, 1.0 / (mass * omega)) * self.momentum(x))
+            }
+        }
+    }
+}
+
+impl Apply for Applied<'_> {
+    fn get_hbar(&self) -> f64 {
+        self.hbar
+    }
+
+    fn get_joints(&self) -> Vec<f64> {
+        self.joints.clone()
+    }
+}
+
+impl Apply for WaveFunction {
+    fn get_hbar(&self) -> f64 {
+        self.get_phase().hbar
+    }
+
+    fn get_joints(&self) -> Vec<f64> {
+        WaveFunction::get_joints(self)
+    }
+}
+
+impl Apply for Superposition {
+    fn get_hbar(&self) -> f64 {
+        Superposition::get_hbar(self)
+    }
+
+    fn get_joints(&self) -> Vec<f64> {
+        Superposition::get_joints(self)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    // ground state of the harmonic oscillator with m = 1, omega = 1 and hbar = 1, also used by the
+    // tests of observables
+    pub(crate) struct Gaussian;
+
+    impl Func<f64, Complex64> for Gaussian {
+        fn eval(&self, x: f64) -> Complex64 {
+            (-x * x / 2.0).exp().into()
+        }
+    }
+
+    impl Apply for Gaussian {
+        fn get_hbar(&self) -> f64 {
+            1.0
+        }
+    }
+
+    // jumps at 0
+    struct Step;
+
+    impl Func<f64, Complex64> for Step {
+        fn eval(&self, x: f64) -> Complex64 {
+            if x < 0.0 {
+                x.into()
+            } else {
+                (x + 1.0).into()
+            }
+        }
+    }
+
+    impl Apply for Step {
+        fn get_hbar(&self) -> f64 {
+            1.0
+        }
+
+        fn get_joints(&self) -> Vec<f64> {
+            vec![0.0]
+        }
+    }
+
+    #[test]
+    fn ladder_operators_on_ground_state() {
+        let ladder = (
+            Operator::Lowering {
+                mass: 1.0,
+                omega: 1.0,
+            },
+            Operator::Raising {
+                mass: 1.0,
+                omega: 1.0,
+            },
+        );
+        for x in [-2.0, -0.5, 0.3, 1.7] {
+            let psi = Gaussian.eval(x);
+            assert!(Gaussian.apply(ladder.0).eval(x).norm() < 1e-6);
+            assert!(complex_compare(
+                Gaussian.apply(ladder.1).eval(x),
+                2.0f64.sqrt() * x * psi,
+                1e-6
+            ));
+            assert!(complex_compare(
+                Gaussian.apply(Operator::P).eval(x),
+                complex(0.0, x) * psi,
+                1e-6
+            ));
+            assert!(complex_compare(
+                Gaussian.apply(Operator::X).apply(Operator::X).eval(x),
+                x * x * psi,
+                1e-12
+            ));
+        }
+    }
+
+    #[test]
+    fn derivative_at_joint() {
+        let momentum = Step.apply(Operator::P);
+        for x in [-1e-9, 0.0, 1e-9] {
+            assert!(complex_compare(momentum.eval(x), complex(0.0, -1.0), 1e-6));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::utils::cmp_f64;
+
+// Derivative free minimization with the Nelder-Mead simplex method.
+// Returns the best parameters and the value of f there.
+pub fn nelder_mead<F>(
+    f: &F,
+    start: &[f64],
+    step: f64,
+    precision: f64,
+    max_iters: usize,
+) -> (Vec<f64>, f64)
+where
+    F: Fn(&[f64]) -> f64,
+{
+    const REFLECTION: f64 = 1.0;
+    const EXPANSION: f64 = 2.0;
+    const CONTRACTION: f64 = 0.5;
+    const SHRINK: f64 = 0.5;
+
+    let dim = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = (0..=dim)
+        .map(|i| {
+            let mut point = start.to_vec();
+            if i > 0 {
+                point[i - 1] += step;
+            }
+            let value = f(&point);
+            (point, value)
+        })
+        .collect();
+
+    let towards = |from: &[f64], to: &[f64], factor: f64| -> Vec<f64> {
+        from.iter()
+            .zip(to)
+            .map(|(a, b)| a + factor * (b - a))
+            .collect()
+    };
+
+    for _ in 0..max_iters {
+        simplex.sort_by(|a, b| cmp_f64(&a.1, &b.1));
+        if (simplex[dim].1 - simplex[0].1).abs() <= precision {
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..dim)
+            .map(|j| simplex[..dim].iter().map(|(p, _)| p[j]).sum::<f64>() / dim as f64)
+            .collect();
+        let worst = simplex[dim].clone();
+
+        let reflected = towards(&centroid, &worst.0, -REFLECTION);
+        let reflected_value = f(&reflected);
+
+        if reflected_value < simplex[0].1 {
+            let expanded = towards(&centroid, &worst.0, -EXPANSION);
+            let expanded_value = f(&expanded);
+            simplex[dim] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[dim - 1].1 {
+            simplex[dim] = (reflected, reflected_value);
+        } else {
+            let contracted = towards(&centroid, &worst.0, CONTRACTION);
+            let contracted_value = f(&contracted);
+            if contracted_value < worst.1 {
+                simplex[dim] = (contracted, contracted_value);
+            } else {
+                let best = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    vertex.0 = towards(&best, &vertex.0, SHRINK);
+                    vertex.1 = f(&vertex.0);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| cmp_f64(&a.1, &b.1));
+    simplex.swap_remove(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rosenbrock() {
+        let f = |p: &[f64]| (1.0 - p[0]).powi(2) + 100.0 * (p[1] - p[0] * p[0]).powi(2);
+        let (min, value) = nelder_mead(&f, &[-1.2, 1.0], 0.5, 1e-14, 10000);
+        assert!((min[0] - 1.0).abs() < 1e-4);
+        assert!((min[1] - 1.0).abs() < 1e-4);
+        assert!(value < 1e-8);
+    }
+
+    #[test]
+    fn parabola_1d() {
+        let (min, value) = nelder_mead(
+            &|p: &[f64]| (p[0] - 3.0).powi(2) + 2.0,
+            &[0.0],
+            1.0,
+            1e-14,
+            1000,
+        );
+        assert!((min[0] - 3.0).abs() < 1e-6);
+        assert!((value - 2.0).abs() < 1e-12);
+    }
+}
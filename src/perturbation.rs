@@ -0,0 +1,124 @@
+use crate::*;
+
+// number of unperturbed states the sums of second order and the state correction run over
+const BASIS_SIZE: usize = 12;
+
+// Stationary perturbation theory for H = H_0 + delta_v, with the WKB states of H_0 as basis.
+pub struct Corrections {
+    pub n: usize,
+    pub energy: f64,
+    pub first_order: f64,
+    pub second_order: Option<f64>,
+    // first order state correction psi_n + sum_m coefficient_m psi_m
+    pub coefficients: Vec<(usize, Complex64)>,
+    basis: Vec<WaveFunction>,
+    norms: Vec<f64>,
+}
+
+impl Corrections {
+    pub fn corrected_energy(&self) -> f64 {
+        self.energy + self.first_order + self.second_order.unwrap_or(0.0)
+    }
+}
+
+// the state up to first order, normalized to first order as well
+impl Func<f64, Complex64> for Corrections {
+    fn eval(&self, x: f64) -> Complex64 {
+        let unperturbed = self.basis[self.n].eval(x) / self.norms[self.n];
+        unperturbed
+            + self
+                .coefficients
+                .iter()
+                .map(|(m, c)| c * self.basis[*m].eval(x) / self.norms[*m])
+                .sum::<Complex64>()
+    }
+}
+
+pub fn corrections<G: Fn(f64) -> f64 + Sync>(
+    potential: SharedPotential,
+    delta_v: &G,
+    mass: f64,
+    n: usize,
+    order: usize,
+) -> Corrections {
+    assert!(
+        order == 1 || order == 2,
+        "only first and second order are supported"
+    );
+
+    let basis_size = usize::max(BASIS_SIZE, n + 1);
+    let basis = eigenstates(
+        potential,
+        mass,
+        0..basis_size,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::None,
+    );
+    let energies: Vec<f64> = basis.iter().map(|s| s.get_energy()).collect();
+
+    let grid = matrix_elements::common_grid(&basis);
+    let norms: Vec<f64> = basis
+        .iter()
+        .map(|s| matrix_elements::norm(s, &grid))
+        .collect();
+    let elements: Vec<Complex64> = (0..basis_size)
+        .map(|m| {
+            matrix_elements::matrix_element(&basis[m], delta_v, &basis[n], &grid)
+                / (norms[m] * norms[n])
+        })
+        .collect();
+
+    let others = (0..basis_size).filter(|m| *m != n);
+    let coefficients = others
+        .clone()
+        .map(|m| (m, elements[m] / (energies[n] - energies[m])))
+        .collect();
+    let second_order = if order == 2 {
+        Some(
+            others
+                .map(|m| elements[m].norm_sqr() / (energies[n] - energies[m]))
+                .sum(),
+        )
+    } else {
+        None
+    };
+
+    Corrections {
+        n,
+        energy: energies[n],
+        first_order: elements[n].re,
+        second_order,
+        coefficients,
+        basis,
+        norms,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn harmonic_oscillator_stiffer() {
+        // x^2 -> (1 + eps) x^2 scales the energies by sqrt(1 + eps)
+        let eps = 0.1;
+        let result = corrections(
+            Arc::new(potentials::square),
+            &|x: f64| eps * x * x,
+            1.0,
+            1,
+            2,
+        );
+
+        let exact = result.energy * (1.0 + eps).sqrt();
+        // the WKB states of low n are only accurate to a few percent
+        assert!(float_compare(
+            result.energy * eps / 2.0,
+            result.first_order,
+            0.1
+        ));
+        assert!(result.second_order.unwrap() < 0.0);
+        assert!((result.corrected_energy() - exact).abs() < 1e-2 * exact);
+    }
+}
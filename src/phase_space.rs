@@ -0,0 +1,114 @@
+use crate::*;
+
+// W(x, p) = 1 / (pi hbar) integral of psi*(x + y) psi(x - y) e^(2i p y / hbar) dy on uniform samples
+// of psi, with y running over the samples as far as they reach on both sides of x. The momenta
+// cover one period -pi hbar / (2 dx) to pi hbar / (2 dx) of the sum in p.
+// values[i][j] is W at positions[i] and momenta[j].
+pub struct WignerFunction {
+    pub positions: Vec<f64>,
+    pub momenta: Vec<f64>,
+    pub values: Vec<Vec<f64>>,
+}
+
+impl WignerFunction {
+    pub fn new(samples: &[Point<f64, Complex64>], momenta: usize, hbar: f64) -> WignerFunction {
+        let dx = samples[1].x - samples[0].x;
+        let momenta = Grid::uniform(Self::momentum_range(samples, hbar), momenta).points();
+        let last = samples.len() - 1;
+
+        let values = (0..samples.len())
+            .into_par_iter()
+            .map(|i| {
+                momenta
+                    .iter()
+                    .map(|p| {
+                        // the terms of y and -y are complex conjugates
+                        let sum = (1..=i.min(last - i))
+                            .map(|k| {
+                                let phase = 2.0 * p * k as f64 * dx / hbar;
+                                2.0 * (samples[i + k].y.conj()
+                                    * samples[i - k].y
+                                    * Complex64::from_polar(1.0, phase))
+                                .re
+                            })
+                            .sum::<f64>();
+                        dx / (f64::consts::PI * hbar) * (samples[i].y.norm_sqr() + sum)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        WignerFunction {
+            positions: samples.iter().map(|p| p.x).collect(),
+            momenta,
+            values,
+        }
+    }
+
+    pub fn momentum_range(samples: &[Point<f64, Complex64>], hbar: f64) -> (f64, f64) {
+        let p_max = f64::consts::PI * hbar / (2.0 * (samples[1].x - samples[0].x));
+        (-p_max, p_max)
+    }
+
+    // integral of W over p, |psi(x)|^2 exactly once there are more than half as many momenta as
+    // samples, fewer of them alias
+    pub fn position_marginal(&self) -> Vec<Point<f64, f64>> {
+        self.positions
+            .iter()
+            .zip(self.values.iter())
+            .map(|(x, row)| Point {
+                x: *x,
+                y: integrate(samples(&self.momenta, row), TRAPEZE_PER_THREAD),
+            })
+            .collect()
+    }
+
+    // integral of W over x, |phi(p)|^2 as long as the samples resolve psi
+    pub fn momentum_marginal(&self) -> Vec<Point<f64, f64>> {
+        (0..self.momenta.len())
+            .map(|j| {
+                let column: Vec<f64> = self.values.iter().map(|row| row[j]).collect();
+                Point {
+                    x: self.momenta[j],
+                    y: integrate(samples(&self.positions, &column), TRAPEZE_PER_THREAD),
+                }
+            })
+            .collect()
+    }
+}
+
+fn samples(x: &[f64], y: &[f64]) -> Vec<Point<f64, f64>> {
+    x.iter()
+        .zip(y.iter())
+        .map(|(x, y)| Point { x: *x, y: *y })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wigner_function_of_gaussian() {
+        // psi = pi^(-1/4) e^(-x^2 / 2), W = e^(-x^2 - p^2 / hbar^2) / (pi hbar)
+        let hbar = 0.5;
+        let samples = Grid::uniform((-8.0, 8.0), 401).evaluate(&Function::new(|x: f64| {
+            complex(f64::consts::PI.powf(-0.25) * (-x * x / 2.0).exp(), 0.0)
+        }));
+        let wigner = WignerFunction::new(&samples, 301, hbar);
+        for (i, x) in wigner.positions.iter().enumerate().step_by(20) {
+            for (j, p) in wigner.momenta.iter().enumerate().step_by(15) {
+                let exact = (-x * x - p * p / (hbar * hbar)).exp() / (f64::consts::PI * hbar);
+                assert!((wigner.values[i][j] - exact).abs() < 1e-10, "{} {}", x, p);
+            }
+        }
+
+        for (p, sample) in wigner.position_marginal().iter().zip(samples.iter()) {
+            assert!((p.y - sample.y.norm_sqr()).abs() < 1e-12);
+        }
+        for p in wigner.momentum_marginal() {
+            let exact = (-p.x * p.x / (hbar * hbar)).exp() / (f64::consts::PI.sqrt() * hbar);
+            assert!((p.y - exact).abs() < 1e-10);
+        }
+    }
+}
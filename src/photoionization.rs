@@ -0,0 +1,301 @@
+use crate::wkb_wave_func::Phase;
+use crate::*;
+
+// Photoionization or -dissociation of a bound state into the WKB continuum of the same potential.
+// The continuum states at E are normalized on the energy scale, <E|E'> = delta(E - E'), so in the
+// length gauge
+//     sigma(E) = 4 pi^2 alpha hbar omega sum |<E|x|n>|^2,  hbar omega = E - E_n
+// summed over the continuum states at E, with alpha the fine structure constant of the charge of
+// the particle, which cross_section leaves out.
+
+// closer to the turning point than this the Langer factor (zeta / Q)^(1/4) is taken from V' there
+const SMALL_ZETA: f64 = 1e-3;
+const BISECTION_STEPS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Standing {
+    // reflected at the only turning point, sqrt(2 m) / hbar (zeta / Q)^(1/4) Ai(-zeta) with
+    // 2/3 zeta^(3/2) the action from it, positive towards the open end. Far from the turning point
+    // this is sqrt(2 m / (pi hbar^2 k)) sin(S + pi / 4).
+    Reflected {
+        turning_point: f64,
+        open_right: bool,
+        // |Q'| at the turning point
+        slope: f64,
+    },
+    // open to both sides without turning points, sqrt(m / (pi hbar^2 k)) cos S and sin S with S
+    // counted from approx_inf.0
+    Cos,
+    Sin,
+}
+
+// Energy normalized WKB continuum state, Q = 2 m (E - V) / hbar^2 = k^2 in the allowed regions.
+// The action is tabulated over u = x - a scaled as u |u| = x - a around a turning point a, in which
+// its integrand is smooth, and interpolated with cubic Hermite polynomials. Without a turning point
+// u = x.
+pub struct ContinuumState {
+    phase: Arc<Phase>,
+    standing: Standing,
+    // integral of sqrt|Q| dx by u from the first node
+    action: IntegralTable,
+}
+
+fn q(phase: &Phase, x: f64) -> f64 {
+    2.0 * phase.mass * (phase.energy - (phase.potential)(x)) / (phase.hbar * phase.hbar)
+}
+
+impl ContinuumState {
+    fn new(phase: Arc<Phase>, standing: Standing, approx_inf: (f64, f64)) -> ContinuumState {
+        // the turning point is a node, the nodes cover approx_inf
+        let (start, step, n) = match standing {
+            Standing::Reflected { turning_point, .. } => {
+                let (left, right) = (
+                    (turning_point - approx_inf.0).sqrt(),
+                    (approx_inf.1 - turning_point).sqrt(),
+                );
+                let step = (left + right) / (INTEG_STEPS - 1) as f64;
+                let below = (left / step).ceil();
+                (
+                    -below * step,
+                    step,
+                    below as usize + (right / step).ceil() as usize + 1,
+                )
+            }
+            _ => (
+                approx_inf.0,
+                (approx_inf.1 - approx_inf.0) / (INTEG_STEPS - 1) as f64,
+                INTEG_STEPS,
+            ),
+        };
+        let mut state = ContinuumState {
+            phase,
+            standing,
+            action: IntegralTable::default(),
+        };
+        state.action = IntegralTable::new(|u| state.integrand(u), start, step, n);
+        state
+    }
+
+    fn x(&self, u: f64) -> f64 {
+        match self.standing {
+            Standing::Reflected { turning_point, .. } => turning_point + u * u.abs(),
+            _ => u,
+        }
+    }
+
+    fn u(&self, x: f64) -> f64 {
+        match self.standing {
+            Standing::Reflected { turning_point, .. } => {
+                (x - turning_point).signum() * (x - turning_point).abs().sqrt()
+            }
+            _ => x,
+        }
+    }
+
+    // sqrt|Q| dx / du
+    fn integrand(&self, u: f64) -> f64 {
+        let k = q(&self.phase, self.x(u)).abs().sqrt();
+        match self.standing {
+            Standing::Reflected { .. } => 2.0 * u.abs() * k,
+            _ => k,
+        }
+    }
+
+    // NaN outside of the nodes
+    fn action_at(&self, x: f64) -> f64 {
+        self.action.integral(self.u(x))
+    }
+
+    pub fn get_energy(&self) -> f64 {
+        self.phase.energy
+    }
+}
+
+impl Func<f64, Complex64> for ContinuumState {
+    // 0 where the action is infinite, behind hard walls, and outside of approx_inf
+    fn eval(&self, x: f64) -> Complex64 {
+        let (mass, hbar) = (self.phase.mass, self.phase.hbar);
+        let value = match self.standing {
+            Standing::Reflected {
+                turning_point,
+                open_right,
+                slope,
+            } => {
+                let action = self.action_at(x) - self.action_at(turning_point);
+                let action = if open_right { action } else { -action };
+                let zeta = action.signum() * (1.5 * action.abs()).powf(2.0 / 3.0);
+                if !zeta.is_finite() {
+                    return 0.0.into();
+                }
+                // Q = slope (x - a) and zeta = slope^(1/3) (x - a) next to the turning point
+                let ratio = if zeta.abs() < SMALL_ZETA {
+                    slope.powf(-2.0 / 3.0)
+                } else {
+                    zeta / q(&self.phase, x)
+                };
+                (2.0 * mass).sqrt() / hbar
+                    * ratio.powf(0.25)
+                    * airy_series::airy_ai(complex(-zeta, 0.0)).re
+            }
+            Standing::Cos | Standing::Sin => {
+                let k = q(&self.phase, x).sqrt();
+                let action = self.action_at(x);
+                let amplitude = (mass / (f64::consts::PI * hbar * hbar * k)).sqrt();
+                if self.standing == Standing::Cos {
+                    amplitude * action.cos()
+                } else {
+                    amplitude * action.sin()
+                }
+            }
+        };
+        if value.is_finite() {
+            value.into()
+        } else {
+            0.0.into()
+        }
+    }
+}
+
+// The continuum states at the energy of phase, one reflected at the only turning point in
+// approx_inf if V is above E at one end of it, two standing waves if E is above V everywhere.
+// Barriers between two open ends aren't supported.
+pub fn continuum_states(
+    phase: Arc<Phase>,
+    approx_inf: (f64, f64),
+) -> Result<Vec<ContinuumState>, String> {
+    let energy = phase.energy;
+    let scan = Grid::uniform(approx_inf, INTEG_STEPS).points();
+    let allowed = scan
+        .par_iter()
+        .map(|x| (phase.potential)(*x) < energy)
+        .collect::<Vec<bool>>();
+    let crossings = (1..allowed.len())
+        .filter(|i| allowed[*i] != allowed[*i - 1])
+        .collect::<Vec<usize>>();
+
+    match (allowed[0], allowed[allowed.len() - 1], crossings.len()) {
+        (false, false, _) => Err(format!(
+            "V is above E = {} at both ends of approx_inf, there is no continuum",
+            energy
+        )),
+        (true, true, 0) => Ok(vec![
+            ContinuumState::new(phase.clone(), Standing::Cos, approx_inf),
+            ContinuumState::new(phase, Standing::Sin, approx_inf),
+        ]),
+        (left_open, _, 1) => {
+            let (mut a, mut b) = (scan[crossings[0] - 1], scan[crossings[0]]);
+            for _ in 0..BISECTION_STEPS {
+                let middle = (a + b) / 2.0;
+                if middle <= a || middle >= b {
+                    break;
+                }
+                if ((phase.potential)(middle) < energy) == left_open {
+                    a = middle;
+                } else {
+                    b = middle;
+                }
+            }
+            let turning_point = (a + b) / 2.0;
+            let slope = (2.0 * phase.mass / (phase.hbar * phase.hbar)
+                * derivative(&|x| (phase.potential)(x), turning_point))
+            .abs();
+            let standing = Standing::Reflected {
+                turning_point,
+                open_right: !left_open,
+                slope,
+            };
+            Ok(vec![ContinuumState::new(phase, standing, approx_inf)])
+        }
+        (_, _, n) => Err(format!(
+            "the continuum at E = {} has {} turning points, only a single one or none are supported",
+            energy, n
+        )),
+    }
+}
+
+// sigma(E) / alpha at the energies, see the top of the file. The matrix elements are integrated
+// over OBSERVABLE_DOMAIN of the bound state, which is normalized on it.
+pub fn cross_section(
+    bound: &WaveFunction,
+    energies: &Grid,
+    approx_inf: (f64, f64),
+) -> Result<Vec<Point<f64, f64>>, String> {
+    let grid = matrix_elements::common_grid(std::slice::from_ref(bound));
+    let norm = matrix_elements::norm(bound, &grid);
+    let phase = bound.get_phase();
+    energies
+        .points()
+        .iter()
+        .map(|energy| {
+            let photon = energy - bound.get_energy();
+            if photon <= 0.0 {
+                return Err(format!(
+                    "E = {} isn't above the bound state at {}",
+                    energy,
+                    bound.get_energy()
+                ));
+            }
+            let states = continuum_states(Arc::new(phase.with_energy(*energy)), approx_inf)?;
+            let strength = states
+                .iter()
+                .map(|state| {
+                    (matrix_elements::matrix_element(state, &|x| x, bound, &grid) / norm).norm_sqr()
+                })
+                .sum::<f64>();
+            Ok(Point {
+                x: *energy,
+                y: 4.0 * f64::consts::PI.powi(2) * photon * strength,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn linear_potential_is_exact() {
+        // the Langer form is exact for V = -F x, psi = (F l^2)^(-1/2) Ai(-(x - a) / l) with
+        // l^3 = hbar^2 / (2 m F) and a = -E / F
+        let (force, mass, energy) = (1.5, 1.0, 0.5);
+        let phase = Phase::new(energy, mass, UNITS.hbar, Arc::new(move |x: f64| -force * x));
+        let states = continuum_states(Arc::new(phase), (-20.0, 20.0)).unwrap();
+        assert_eq!(states.len(), 1);
+
+        let l = (UNITS.hbar * UNITS.hbar / (2.0 * mass * force)).cbrt();
+        let a = -energy / force;
+        let amplitude = 1.0 / (force * l * l).sqrt();
+        for x in [-5.0, -1.0, -0.4, -0.3, 0.5, 3.0, 12.0] {
+            let expected = amplitude * airy_series::airy_ai(complex(-(x - a) / l, 0.0)).re;
+            let actual = states[0].eval(x);
+            assert!(
+                (actual.re - expected).abs() < 1e-5 * amplitude && actual.im == 0.0,
+                "{}: {} != {}",
+                x,
+                actual,
+                expected
+            );
+        }
+        assert_eq!(states[0].eval(25.0), complex(0.0, 0.0));
+    }
+
+    #[test]
+    fn free_standing_waves() {
+        let (mass, energy) = (2.0, 3.0);
+        let phase = Phase::new(energy, mass, UNITS.hbar, Arc::new(|_: f64| 0.0));
+        let states = continuum_states(Arc::new(phase), (-10.0, 10.0)).unwrap();
+        assert_eq!(states.len(), 2);
+
+        let k = (2.0 * mass * energy).sqrt() / UNITS.hbar;
+        let amplitude = (mass / (f64::consts::PI * UNITS.hbar * UNITS.hbar * k)).sqrt();
+        for x in [-10.0, -3.3, 0.0, 7.1, 10.0] {
+            let s = k * (x + 10.0);
+            assert!((states[0].eval(x).re - amplitude * s.cos()).abs() < 1e-9 * amplitude);
+            assert!((states[1].eval(x).re - amplitude * s.sin()).abs() < 1e-9 * amplitude);
+        }
+
+        let well = Phase::new(-1.0, mass, UNITS.hbar, Arc::new(|x: f64| x * x - 4.0));
+        assert!(continuum_states(Arc::new(well), (-10.0, 10.0)).is_err());
+    }
+}
@@ -1,5 +1,51 @@
+use crate::analysis::LevelLabel;
+use crate::level_statistics::SpacingDistribution;
+use crate::npz::NpzWriter;
+use crate::sampled::SampledWaveFunction;
+use crate::sweep::{AvoidedCrossing, Sweep};
+use crate::task::Task;
+use crate::time_dependent::Populations;
+use crate::wave_function_2d::WaveFunction2D;
+use crate::wkb_wave_func::SemiclassicalPoint;
 use crate::*;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// names of the files the writers created by their directory, taken by the manifest written there
+static CREATED_FILES: Mutex<BTreeMap<PathBuf, BTreeSet<String>>> = Mutex::new(BTreeMap::new());
+
+// lists path in the manifest of its directory, for files written other than with create_file
+pub fn record_file(path: &Path) {
+    // absolute, like the output directory of the manifest
+    let path = std::env::current_dir().unwrap().join(path);
+    if let (Some(dir), Some(name)) = (path.parent(), path.file_name()) {
+        CREATED_FILES
+            .lock()
+            .unwrap()
+            .entry(dir.to_path_buf())
+            .or_default()
+            .insert(name.to_string_lossy().to_string());
+    }
+}
+
+// File::create for the outputs of a run, they are listed in its manifest
+pub fn create_file(path: &Path) -> File {
+    record_file(path);
+    File::create(path).unwrap()
+}
+
+// the files recorded for dir since the last call, dir is absolute
+pub fn take_created_files(dir: &Path) -> BTreeSet<String> {
+    CREATED_FILES
+        .lock()
+        .unwrap()
+        .remove(dir)
+        .unwrap_or_default()
+}
 
 pub fn to_gnuplot_string_complex<X>(values: Vec<Point<X, Complex64>>) -> String
 where
@@ -8,7 +54,8 @@ where
     values
         .par_iter()
         .map(|p| -> String { format!("{} {} {}\n", p.x, p.y.re, p.y.im) })
-        .reduce(|| String::new(), |s: String, current: String| s + &*current)
+        .collect::<Vec<String>>()
+        .concat()
 }
 
 pub fn to_gnuplot_string<X, Y>(values: Vec<Point<X, Y>>) -> String
@@ -19,53 +66,86 @@ where
     values
         .par_iter()
         .map(|p| -> String { format!("{} {}\n", p.x, p.y) })
-        .reduce(|| String::new(), |s: String, current: String| s + &*current)
+        .collect::<Vec<String>>()
+        .concat()
 }
 
-pub fn plot_wavefunction_parts(wave_function: &WaveFunction, output_dir: &Path, output_file: &str) {
-    std::env::set_current_dir(&output_dir).unwrap();
+// x, |y| and arg(y) in (-pi, pi]
+pub fn to_gnuplot_string_polar<X>(values: Vec<Point<X, Complex64>>) -> String
+where
+    X: fmt::Display + Send + Sync,
+{
+    values
+        .par_iter()
+        .map(|p| -> String { format!("{} {} {}\n", p.x, p.y.norm(), p.y.arg()) })
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+// pinned grids keep every point, so the samples of different outputs line up
+fn thin(values: Vec<Point<f64, f64>>) -> Vec<Point<f64, f64>> {
+    if DECIMATION_TOLERANCE > 0.0 && SAMPLING_GRID.is_none() {
+        decimation::decimate(values, DECIMATION_TOLERANCE)
+    } else {
+        values
+    }
+}
+
+fn thin_complex(values: Vec<Point<f64, Complex64>>) -> Vec<Point<f64, Complex64>> {
+    if DECIMATION_TOLERANCE > 0.0 && SAMPLING_GRID.is_none() {
+        decimation::decimate_complex(values, DECIMATION_TOLERANCE)
+    } else {
+        values
+    }
+}
 
+// SAMPLING_GRID if it is set, n points over the view otherwise
+fn sampling_grid(view: (f64, f64), n: usize) -> Grid {
+    match SAMPLING_GRID {
+        Some((start, end, step)) => Grid::stepped(start, end, step),
+        None => Grid::uniform(view, n),
+    }
+}
+
+pub fn plot_wavefunction_parts(wave_function: &WaveFunction, output_dir: &Path, output_file: &str) {
     let wkb_values = wave_function
         .get_wkb_ranges_in_view()
         .iter()
-        .map(|range| evaluate_function_between(wave_function, range.0, range.1, NUMBER_OF_POINTS))
+        .map(|range| {
+            thin_complex(Grid::uniform(*range, NUMBER_OF_POINTS).evaluate(wave_function))
+        })
         .collect::<Vec<Vec<Point<f64, Complex64>>>>();
 
     let airy_values = wave_function
         .get_airy_ranges()
         .iter()
         .map(|range| {
-            evaluate_function_between(
-                wave_function,
+            let range = (
                 f64::max(wave_function.get_view().0, range.0),
                 f64::min(wave_function.get_view().1, range.1),
-                NUMBER_OF_POINTS,
-            )
+            );
+            thin_complex(Grid::uniform(range, NUMBER_OF_POINTS).evaluate(wave_function))
         })
         .collect::<Vec<Vec<Point<f64, Complex64>>>>();
 
     let wkb_values_str = wkb_values
         .par_iter()
         .map(|values| to_gnuplot_string_complex(values.to_vec()))
-        .reduce(
-            || String::new(),
-            |s: String, current: String| s + "\n\n" + &*current,
-        );
+        .collect::<Vec<String>>()
+        .join("\n\n");
 
     let airy_values_str = airy_values
         .par_iter()
         .map(|values| to_gnuplot_string_complex(values.to_vec()))
-        .reduce(
-            || String::new(),
-            |s: String, current: String| s + "\n\n" + &*current,
-        );
+        .collect::<Vec<String>>()
+        .join("\n\n");
 
-    let mut data_full = File::create(output_file).unwrap();
+    let mut data_full = create_file(&output_dir.join(output_file));
     data_full.write_all(wkb_values_str.as_ref()).unwrap();
     data_full.write_all("\n\n".as_bytes()).unwrap();
     data_full.write_all(airy_values_str.as_ref()).unwrap();
 
-    let mut plot_3d_file = File::create("plot_3d.gnuplot").unwrap();
+    let mut plot_3d_file = create_file(&output_dir.join("plot_3d.gnuplot"));
 
     let wkb_3d_cmd = (1..=wkb_values.len())
         .into_iter()
@@ -95,7 +175,7 @@ pub fn plot_wavefunction_parts(wave_function: &WaveFunction, output_dir: &Path,
     let plot_3d_cmd: String = "splot ".to_string() + &wkb_3d_cmd + ", " + &airy_3d_cmd;
     plot_3d_file.write_all(plot_3d_cmd.as_ref()).unwrap();
 
-    let mut plot_file = File::create("plot.gnuplot").unwrap();
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
     let wkb_cmd = (1..=wkb_values.len())
         .into_iter()
         .map(|n| {
@@ -125,7 +205,7 @@ pub fn plot_wavefunction_parts(wave_function: &WaveFunction, output_dir: &Path,
 
     plot_file.write_all(plot_cmd.as_ref()).unwrap();
 
-    let mut plot_imag_file = File::create("plot_im.gnuplot").unwrap();
+    let mut plot_imag_file = create_file(&output_dir.join("plot_im.gnuplot"));
 
     let wkb_im_cmd = (1..=wkb_values.len())
         .into_iter()
@@ -165,31 +245,35 @@ pub fn plot_complex_function(
     output_file: &str,
     color_plot: bool,
 ) {
-    std::env::set_current_dir(&output_dir).unwrap();
-    let values = evaluate_function_between(func, view.0, view.1, NUMBER_OF_POINTS);
+    let values = thin_complex(sampling_grid(view, NUMBER_OF_POINTS).evaluate(func));
 
     let values_str = to_gnuplot_string_complex(values);
 
-    let mut data_file = File::create(output_file).unwrap();
+    let mut data_file = create_file(&output_dir.join(output_file));
 
     data_file.write_all(values_str.as_bytes()).unwrap();
 
-    let mut plot_3d_file = File::create("plot_3d.gnuplot").unwrap();
+    write_complex_scripts(title, output_dir, output_file, color_plot);
+}
+
+// scripts for the columns x, Re, Im written by plot_complex_function
+fn write_complex_scripts(title: &str, output_dir: &Path, output_file: &str, color_plot: bool) {
+    let mut plot_3d_file = create_file(&output_dir.join("plot_3d.gnuplot"));
     plot_3d_file
         .write_all(format!("set xlabel \"x\"\nset ylabel \"Re({title})\"\nset zlabel \"Im({title})\"\nsplot \"{}\" u 1:2:3 t \"{}\" w l", output_file, title).as_bytes())
         .unwrap();
 
-    let mut plot_file = File::create("plot.gnuplot").unwrap();
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
     plot_file
         .write_all(format!("set xlabel \"x\"\nset ylabel \"Re({title})\"\nplot \"{}\" u 1:2 t \"Re({})\" w l", output_file, title).as_bytes())
         .unwrap();
 
-    let mut plot_im_file = File::create("plot_im.gnuplot").unwrap();
+    let mut plot_im_file = create_file(&output_dir.join("plot_im.gnuplot"));
     plot_im_file
         .write_all(format!("set xlabel \"x\"\nset ylabel \"Im({title})\"\nplot \"{}\" u 1:3 t \"Im({})\" w l", output_file, title).as_bytes())
         .unwrap();
     if color_plot {
-        let mut plot_color_file = File::create("plot_color.gnuplot").unwrap();
+        let mut plot_color_file = create_file(&output_dir.join("plot_color.gnuplot"));
         plot_color_file
         .write_all(
             format!(
@@ -202,7 +286,208 @@ pub fn plot_complex_function(
     }
 }
 
+// like plot_complex_function with a fourth column, the Region::code of every x
 pub fn plot_wavefunction(wave_function: &WaveFunction, output_dir: &Path, output_file: &str) {
+    plot_wavefunction_with(wave_function, POTENTIAL_COLUMN, output_dir, output_file);
+}
+
+// V(x) as a fifth column of the wave function data, for drawing the potential under the state
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum PotentialColumn {
+    None,
+    Raw,
+    // mapped affinely from its range in the view onto the range of Re and Im of psi, the mapping
+    // is written as # potential_mapping a b for a V + b
+    Rescaled,
+}
+
+pub fn plot_wavefunction_with(
+    wave_function: &WaveFunction,
+    column: PotentialColumn,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    write_wavefunction_data(
+        wave_function,
+        NUMBER_OF_POINTS,
+        column,
+        output_dir,
+        output_file,
+    );
+    write_wavefunction_scripts(column, output_dir, output_file);
+}
+
+// Writes plot_wavefunction's data with PREVIEW_POINTS and its scripts right away and refines the
+// data to NUMBER_OF_POINTS in the background. Cancelling the task keeps the preview.
+pub fn plot_wavefunction_progressive(
+    wave_function: WaveFunction,
+    output_dir: &Path,
+    output_file: &str,
+) -> Task<()> {
+    write_wavefunction_data(
+        &wave_function,
+        PREVIEW_POINTS,
+        POTENTIAL_COLUMN,
+        output_dir,
+        output_file,
+    );
+    write_wavefunction_scripts(POTENTIAL_COLUMN, output_dir, output_file);
+    let output_dir = output_dir.to_path_buf();
+    let output_file = output_file.to_string();
+    task::spawn(move || {
+        write_wavefunction_data(
+            &wave_function,
+            NUMBER_OF_POINTS,
+            POTENTIAL_COLUMN,
+            &output_dir,
+            &output_file,
+        )
+    })
+}
+
+// (a, b) mapping the finite values of V in the view onto the range of Re and Im of psi by a V + b
+fn potential_mapping<F: Fn(f64) -> f64 + ?Sized>(
+    values: &[Point<f64, Complex64>],
+    potential: &F,
+) -> (f64, f64) {
+    let range = |ys: &mut dyn Iterator<Item = f64>| {
+        ys.filter(|y| y.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+                (lo.min(y), hi.max(y))
+            })
+    };
+    let psi = range(&mut values.iter().flat_map(|p| [p.y.re, p.y.im]));
+    let v = range(&mut values.iter().map(|p| potential(p.x)));
+    if v.1.partial_cmp(&v.0) != Some(Ordering::Greater)
+        || psi.1.partial_cmp(&psi.0) != Some(Ordering::Greater)
+    {
+        return (1.0, 0.0);
+    }
+    let a = (psi.1 - psi.0) / (v.1 - v.0);
+    (a, psi.0 - a * v.0)
+}
+
+// Written to a temporary file that replaces the data file at the end, so plotting it while it is
+// refined always shows one complete pass.
+fn write_wavefunction_data(
+    wave_function: &WaveFunction,
+    points: usize,
+    column: PotentialColumn,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values =
+        thin_complex(sampling_grid(wave_function.get_view(), points).evaluate(wave_function));
+    let phase = wave_function.get_phase();
+    let potential = phase.potential.as_ref();
+    let (header, mapping) = match column {
+        PotentialColumn::None => (String::new(), None),
+        PotentialColumn::Raw => ("# x re im region v\n".to_string(), Some((1.0, 0.0))),
+        PotentialColumn::Rescaled => {
+            let (a, b) = potential_mapping(&values, potential);
+            (
+                format!(
+                    "# potential_mapping {} {}\n# x re im region a_v_plus_b\n",
+                    a, b
+                ),
+                Some((a, b)),
+            )
+        }
+    };
+    let values_str = values
+        .par_iter()
+        .map(|p| {
+            let v = match mapping {
+                Some((a, b)) => {
+                    // gnuplot skips NaN, walls would stretch the axis
+                    let v = potential(p.x);
+                    if v.is_finite() {
+                        format!(" {}", a * v + b)
+                    } else {
+                        " NaN".to_string()
+                    }
+                }
+                None => String::new(),
+            };
+            format!(
+                "{} {} {} {}{}\n",
+                p.x,
+                p.y.re,
+                p.y.im,
+                wave_function.region(p.x).code(),
+                v
+            )
+        })
+        .collect::<Vec<String>>()
+        .concat();
+
+    let partial = output_dir.join(format!("{}.part", output_file));
+    let mut data_file = File::create(&partial).unwrap();
+    data_file.write_all(header.as_bytes()).unwrap();
+    data_file.write_all(values_str.as_bytes()).unwrap();
+    std::fs::rename(&partial, output_dir.join(output_file)).unwrap();
+    record_file(&output_dir.join(output_file));
+}
+
+fn write_wavefunction_scripts(column: PotentialColumn, output_dir: &Path, output_file: &str) {
+    write_complex_scripts("Psi", output_dir, output_file, true);
+
+    if column != PotentialColumn::None {
+        let mut plot_potential_file =
+            create_file(&output_dir.join("plot_potential.gnuplot"));
+        plot_potential_file
+            .write_all(
+                format!(
+                    "set xlabel \"x\"\nplot \"{}\" u 1:2 t \"Re(Psi)\" w l, \"{}\" u 1:5 t \"V\" w l",
+                    output_file, output_file
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+    }
+
+    let mut plot_regions_file = create_file(&output_dir.join("plot_regions.gnuplot"));
+    plot_regions_file
+        .write_all(format!("set xlabel \"x\"\nset ylabel \"Re(Psi)\"\nplot \"{}\" u 1:($4 == 0 ? $2 : NaN) t \"WKB\" w l lc 1, \"{}\" u 1:($4 == 1 ? $2 : NaN) t \"Airy\" w l lc 2, \"{}\" u 1:($4 == 2 ? $2 : NaN) t \"joint\" w l lc 3", output_file, output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// Psi as the first block and the reference, aligned to it, at its samples in the view as the
+// second, both with the columns x, Re, Im. Returns the deviation of the aligned reference.
+pub fn plot_comparison(
+    wave_function: &WaveFunction,
+    reference: &SampledWaveFunction,
+    output_dir: &Path,
+    output_file: &str,
+) -> f64 {
+    let view = wave_function.get_view();
+    let reference = reference.aligned_to(wave_function);
+    let values = thin_complex(sampling_grid(view, NUMBER_OF_POINTS).evaluate(wave_function));
+    let samples = reference
+        .get_values()
+        .iter()
+        .filter(|p| p.x >= view.0 && p.x <= view.1)
+        .cloned()
+        .collect::<Vec<Point<f64, Complex64>>>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string_complex(values).as_bytes())
+        .unwrap();
+    data_file.write_all("\n\n".as_bytes()).unwrap();
+    data_file
+        .write_all(to_gnuplot_string_complex(samples).as_bytes())
+        .unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot_comparison.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"\nset ylabel \"Re(Psi)\"\nplot \"{}\" u 1:2 i 0 t \"WKB\" w l, \"{}\" u 1:2 i 1 t \"reference\" w l", output_file, output_file).as_bytes())
+        .unwrap();
+
+    reference.max_deviation(wave_function)
+}
+
+pub fn plot_superposition(wave_function: &Superposition, output_dir: &Path, output_file: &str) {
     plot_complex_function(
         wave_function,
         wave_function.get_view(),
@@ -213,71 +498,1348 @@ pub fn plot_wavefunction(wave_function: &WaveFunction, output_dir: &Path, output
     );
 }
 
-pub fn plot_superposition(wave_function: &Superposition, output_dir: &Path, output_file: &str) {
-    plot_complex_function(
+// Every member c_n psi_n as its own block followed by the total, shows which members dominate
+// where. Members of other potentials are labelled by their index in get_members.
+pub fn plot_superposition_parts(
+    superposition: &Superposition,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let grid = sampling_grid(superposition.get_view(), NUMBER_OF_POINTS);
+    let members = superposition.get_members();
+    let labels = members
+        .iter()
+        .enumerate()
+        .map(|(i, (n, _))| match n {
+            Some(n) => format!("n = {}", n),
+            None => format!("member {}", i),
+        })
+        .chain(std::iter::once("Psi".to_string()))
+        .collect::<Vec<String>>();
+
+    let values_str = (0..members.len())
+        .map(|i| {
+            let term = superposition.member_term(i).unwrap();
+            to_gnuplot_string_complex(thin_complex(grid.evaluate(&term)))
+        })
+        .chain(std::iter::once(to_gnuplot_string_complex(thin_complex(
+            grid.evaluate(superposition),
+        ))))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let cmd = |columns: &str, part: &str| {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let style = if i == members.len() { " lw 2" } else { "" };
+                let title = if part.is_empty() {
+                    label.clone()
+                } else {
+                    format!("{}({})", part, label)
+                };
+                format!(
+                    "\"{}\" u {} i {} t \"{}\" w l{}",
+                    output_file, columns, i, title, style
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(("plot ".to_string() + &cmd("1:2", "Re")).as_bytes())
+        .unwrap();
+    let mut plot_imag_file = create_file(&output_dir.join("plot_im.gnuplot"));
+    plot_imag_file
+        .write_all(("plot ".to_string() + &cmd("1:3", "Im")).as_bytes())
+        .unwrap();
+    let mut plot_3d_file = create_file(&output_dir.join("plot_3d.gnuplot"));
+    plot_3d_file
+        .write_all(("splot ".to_string() + &cmd("1:2:3", "")).as_bytes())
+        .unwrap();
+}
+
+// writes |func| and arg(func) and a script plotting the magnitude as height and the phase as hue
+pub fn plot_phase_colored(
+    func: &dyn Func<f64, Complex64>,
+    view: (f64, f64),
+    title: &str,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values = thin_complex(sampling_grid(view, NUMBER_OF_POINTS).evaluate(func));
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string_polar(values).as_bytes())
+        .unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot_phase.gnuplot"));
+    plot_file
+        .write_all(
+            format!(
+                "set xlabel \"x\"\nset ylabel \"|{title}|\"\nset cbrange [-pi:pi]\nset cblabel \"arg({title})\"\nset cbtics (\"-pi\" -pi, \"0\" 0, \"pi\" pi)\nset palette model HSV defined (0 0 1 1, 1 1 1 1)\nplot \"{}\" u 1:2:3 w filledcurves y1=0 lc palette z t \"|{title}|\", \"{}\" u 1:2 w l lc black notitle",
+                output_file, output_file
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+}
+
+pub fn plot_superposition_phase(
+    wave_function: &Superposition,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    plot_phase_colored(
         wave_function,
         wave_function.get_view(),
         "Psi",
         output_dir,
         output_file,
-        true,
     );
 }
 
-pub fn plot_probability(wave_function: &WaveFunction, output_dir: &Path, output_file: &str) {
-    std::env::set_current_dir(&output_dir).unwrap();
-    let values = evaluate_function_between(
+// pgfplots keeps every coordinate in TeX's memory, so the data is always thinned to at least this
+const PGFPLOTS_TOLERANCE: f64 = 1e-3;
+
+fn pgfplots_coordinates(values: &[Point<f64, f64>]) -> String {
+    values
+        .iter()
+        .map(|p| format!("({:e}, {:e})\n", p.x, p.y))
+        .collect()
+}
+
+// tikzpicture with Re and Im of func, include it with \input and \usepackage{pgfplots}
+pub fn write_pgfplots(
+    func: &dyn Func<f64, Complex64>,
+    view: (f64, f64),
+    title: &str,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values = decimation::decimate_complex(
+        Grid::uniform(view, NUMBER_OF_POINTS).evaluate(func),
+        f64::max(DECIMATION_TOLERANCE, PGFPLOTS_TOLERANCE),
+    );
+    let re: Vec<Point<f64, f64>> = values.iter().map(|p| Point { x: p.x, y: p.y.re }).collect();
+    let im: Vec<Point<f64, f64>> = values.iter().map(|p| Point { x: p.x, y: p.y.im }).collect();
+
+    // real states get no line at zero
+    let max_re = re.iter().map(|p| p.y.abs()).fold(0.0, f64::max);
+    let max_im = im.iter().map(|p| p.y.abs()).fold(0.0, f64::max);
+    let im_plot = if max_im > PGFPLOTS_TOLERANCE * max_re {
+        format!(
+            "\\addplot[red, dashed] coordinates {{\n{}}};\n\\addlegendentry{{$\\mathrm{{Im}}\\,\\psi$}}\n",
+            pgfplots_coordinates(&im)
+        )
+    } else {
+        String::new()
+    };
+
+    let tex = format!(
+        "\\begin{{tikzpicture}}\n\\begin{{axis}}[\n    xlabel={{$x$}},\n    ylabel={{$\\psi(x)$}},\n    title={{{}}},\n    no markers,\n    legend pos=north east,\n]\n\\addplot[blue] coordinates {{\n{}}};\n\\addlegendentry{{$\\mathrm{{Re}}\\,\\psi$}}\n{}\\end{{axis}}\n\\end{{tikzpicture}}\n",
+        title,
+        pgfplots_coordinates(&re),
+        im_plot
+    );
+
+    let mut tex_file = create_file(&output_dir.join(output_file));
+    tex_file.write_all(tex.as_bytes()).unwrap();
+}
+
+pub fn write_pgfplots_wavefunction(
+    wave_function: &WaveFunction,
+    n: usize,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    write_pgfplots(
         wave_function,
-        wave_function.get_view().0,
-        wave_function.get_view().1,
-        NUMBER_OF_POINTS,
-    )
-    .par_iter()
-    .map(|p| Point {
-        x: p.x,
-        y: p.y.norm_sqr(),
-    })
-    .collect();
+        wave_function.get_view(),
+        &format!("$n = {}$, $E = {:.6}$", n, wave_function.get_energy()),
+        output_dir,
+        output_file,
+    );
+}
 
-    let values_str = to_gnuplot_string(values);
+// Wolfram Language has no e notation for numbers
+fn wolfram_number(x: f64) -> String {
+    if x.is_nan() {
+        "Indeterminate".to_string()
+    } else if x.is_infinite() {
+        (if x > 0.0 { "Infinity" } else { "-Infinity" }).to_string()
+    } else {
+        format!("{:e}", x).replace('e', "*^")
+    }
+}
 
-    let mut data_file = File::create(output_file).unwrap();
+// .wl file defining <name> as an Interpolation of func and <name>Metadata as an association,
+// load it with Get["file.wl"]
+pub fn write_wolfram(
+    func: &dyn Func<f64, Complex64>,
+    view: (f64, f64),
+    name: &str,
+    metadata: &[(&str, f64)],
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values = thin_complex(sampling_grid(view, NUMBER_OF_POINTS).evaluate(func));
 
-    data_file.write_all(values_str.as_bytes()).unwrap();
+    let data = values
+        .par_iter()
+        .map(|p| {
+            format!(
+                "{{{}, Complex[{}, {}]}}",
+                wolfram_number(p.x),
+                wolfram_number(p.y.re),
+                wolfram_number(p.y.im)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",\n  ");
+    let metadata = metadata
+        .iter()
+        .map(|(key, value)| format!("\"{}\" -> {}", key, wolfram_number(*value)))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let wl = format!(
+        "{name}Metadata = <|{}, \"view\" -> {{{}, {}}}|>;\n\n{name}Data = {{\n  {}\n}};\n\n{name} = Interpolation[{name}Data];\n",
+        metadata,
+        wolfram_number(view.0),
+        wolfram_number(view.1),
+        data
+    );
 
-    let mut plot_file = File::create("plot.gnuplot").unwrap();
+    let mut wl_file = create_file(&output_dir.join(output_file));
+    wl_file.write_all(wl.as_bytes()).unwrap();
+}
+
+pub fn write_wolfram_wavefunction(
+    wave_function: &WaveFunction,
+    n: usize,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let phase = wave_function.get_phase();
+    write_wolfram(
+        wave_function,
+        wave_function.get_view(),
+        "psi",
+        &[
+            ("n", n as f64),
+            ("energy", phase.energy),
+            ("mass", phase.mass),
+            ("hbar", phase.hbar),
+        ],
+        output_dir,
+        output_file,
+    );
+}
+
+// .npz with the energies, the grid and the states as rows, scaled to unit norm as vectors so
+// every row is a ket for QuTiP, plus a script loading them
+pub fn write_qutip(states: &[WaveFunction], points: usize, output_dir: &Path, output_file: &str) {
+    let view = matrix_elements::common_view(states);
+    let x = Grid::uniform(view, points).points();
+
+    let rows: Vec<Vec<Complex64>> = states
+        .par_iter()
+        .map(|state| {
+            let row: Vec<Complex64> = x.iter().map(|x| state.eval(*x)).collect();
+            let norm = row.iter().map(|c| c.norm_sqr()).sum::<f64>().sqrt();
+            row.iter().map(|c| c / norm).collect()
+        })
+        .collect();
+    let energies: Vec<f64> = states.iter().map(|s| s.get_energy()).collect();
+
+    let mut npz = NpzWriter::new();
+    npz.add_f64("energies", &[energies.len()], &energies);
+    npz.add_f64("x", &[points], &x);
+    npz.add_complex("states", &[states.len(), points], &rows.concat());
+    npz.write(&output_dir.join(output_file));
+
+    let mut loader = create_file(&output_dir.join("load_qutip.py"));
+    loader
+        .write_all(
+            format!(
+                "import numpy as np\nimport qutip\n\ndata = np.load(\"{}\")\nx = data[\"x\"]\nenergies = data[\"energies\"]\n# kets on the grid x, sum |c_i|^2 = 1\nstates = [qutip.Qobj(row.reshape(-1, 1)) for row in data[\"states\"]]\n# the Hamiltonian in the basis of the states\nH = qutip.Qobj(np.diag(energies))\n",
+                output_file
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+}
+
+// How the probability outputs write |psi|^2: per unit length at the points, or integrated over
+// bins of this width centered at them, to compare with a histogram of sampled positions.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum ProbabilityUnit {
+    PerLength,
+    PerBin(f64),
+}
+
+// |psi|^2 in the view in the unit and its integral over the view
+fn probability_values(
+    f: &dyn Func<f64, Complex64>,
+    view: (f64, f64),
+    unit: ProbabilityUnit,
+) -> (Vec<Point<f64, f64>>, f64) {
+    let density: Vec<Point<f64, f64>> = sampling_grid(view, NUMBER_OF_POINTS)
+        .evaluate(f)
+        .par_iter()
+        .map(|p| Point {
+            x: p.x,
+            y: p.y.norm_sqr(),
+        })
+        .collect();
+    let norm = integrate(density.clone(), TRAPEZE_PER_THREAD);
+
+    match unit {
+        ProbabilityUnit::PerLength => (thin(density), norm),
+        ProbabilityUnit::PerBin(width) => {
+            let bins = ((view.1 - view.0) / width).ceil().max(1.0) as usize;
+            let mut probabilities = vec![0.0; bins];
+            for w in density.windows(2) {
+                let bin = ((w[0].x + w[1].x) / 2.0 - view.0) / width;
+                probabilities[(bin as usize).min(bins - 1)] +=
+                    (w[1].x - w[0].x) * (w[0].y + w[1].y) / 2.0;
+            }
+            let values = probabilities
+                .iter()
+                .enumerate()
+                .map(|(i, p)| Point {
+                    x: view.0 + (i as f64 + 0.5) * width,
+                    y: *p,
+                })
+                .collect();
+            (values, norm)
+        }
+    }
+}
+
+// # comments before the probability data with what it takes to put it on an absolute scale:
+// psi is scaling times the unscaled wave function, renormalized over the range if there is one,
+// and norm is the integral of |psi|^2 over the view
+fn probability_header(
+    energies: &[f64],
+    scaling: Complex64,
+    normalization: Option<(f64, f64)>,
+    norm: f64,
+    unit: ProbabilityUnit,
+) -> String {
+    let energies = energies
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    let normalization = match normalization {
+        Some((a, b)) => format!("renormalized {} {}", a, b),
+        None => "fixed".to_string(),
+    };
+    let (unit, column) = match unit {
+        ProbabilityUnit::PerLength => ("per_length".to_string(), "|psi|^2"),
+        ProbabilityUnit::PerBin(width) => (format!("per_bin {}", width), "P_bin"),
+    };
+    format!(
+        "# energy {}\n# scaling {} {}\n# normalization {}\n# norm {}\n# unit {}\n# x {}\n",
+        energies, scaling.re, scaling.im, normalization, norm, unit, column
+    )
+}
+
+fn write_probability(
+    header: String,
+    values: Vec<Point<f64, f64>>,
+    unit: ProbabilityUnit,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(header.as_bytes()).unwrap();
+    data_file
+        .write_all(to_gnuplot_string(values).as_bytes())
+        .unwrap();
+
+    let (label, style) = match unit {
+        ProbabilityUnit::PerLength => ("|Psi|^2", "w l"),
+        ProbabilityUnit::PerBin(_) => ("P_bin", "w steps"),
+    };
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
     plot_file
-        .write_all(format!("set xlabel \"x\"; set ylabel \"|Psi|^2\"; plot \"{}\" u 1:2 t \"|Psi|^2\" w l", output_file).as_bytes())
+        .write_all(
+            format!(
+                "set xlabel \"x\"; set ylabel \"{}\"; plot \"{}\" u 1:2 t \"{}\" {}",
+                label, output_file, label, style
+            )
+            .as_bytes(),
+        )
         .unwrap();
 }
 
+pub fn plot_probability(wave_function: &WaveFunction, output_dir: &Path, output_file: &str) {
+    plot_probability_in(wave_function, PROBABILITY_UNIT, output_dir, output_file);
+}
+
+pub fn plot_probability_in(
+    wave_function: &WaveFunction,
+    unit: ProbabilityUnit,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let (values, norm) = probability_values(wave_function, wave_function.get_view(), unit);
+    let header = probability_header(
+        &[wave_function.get_energy()],
+        wave_function.get_scaling(),
+        wave_function.get_normalization(),
+        norm,
+        unit,
+    );
+    write_probability(header, values, unit, output_dir, output_file);
+}
+
 pub fn plot_probability_superposition(
     wave_function: &Superposition,
     output_dir: &Path,
     output_file: &str,
 ) {
-    std::env::set_current_dir(&output_dir).unwrap();
-    let values = evaluate_function_between(
+    plot_probability_superposition_in(wave_function, PROBABILITY_UNIT, output_dir, output_file);
+}
+
+pub fn plot_probability_superposition_in(
+    wave_function: &Superposition,
+    unit: ProbabilityUnit,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let (values, norm) = probability_values(wave_function, wave_function.get_view(), unit);
+    let header = probability_header(
+        &wave_function.get_member_energies(),
+        wave_function.get_scaling(),
+        wave_function.get_normalization(),
+        norm,
+        unit,
+    );
+    write_probability(header, values, unit, output_dir, output_file);
+}
+
+pub fn plot_probability_2d(wave_function: &WaveFunction2D, output_dir: &Path, output_file: &str) {
+    let rows = wave_function.probability_grid(NUMBER_OF_POINTS_2D, NUMBER_OF_POINTS_2D);
+
+    // gnuplot expects the scan lines of a grid to be separated by a blank line
+    let values_str = rows
+        .par_iter()
+        .map(|row| {
+            row.iter()
+                .map(|p| format!("{} {} {}\n", p.x.0, p.x.1, p.y))
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"; set ylabel \"y\"; set cblabel \"|Psi|^2\"; set pm3d map; splot \"{}\" u 1:2:3 t \"|Psi|^2\" w pm3d", output_file).as_bytes())
+        .unwrap();
+}
+
+pub fn plot_radial_wavefunction(
+    wave_function: &WaveFunction,
+    label: &str,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let view = wave_function.get_view();
+    plot_complex_function(
         wave_function,
-        wave_function.get_view().0,
-        wave_function.get_view().1,
-        NUMBER_OF_POINTS,
-    )
-    .par_iter()
-    .map(|p| Point {
-        x: p.x,
-        y: p.y.norm_sqr(),
-    })
-    .collect();
+        (f64::max(0.0, view.0), view.1),
+        &format!("u_{}", label),
+        output_dir,
+        output_file,
+        false,
+    );
+}
+
+pub fn plot_rabi_populations(populations: &[Populations], output_dir: &Path, output_file: &str) {
+    let values_str = populations
+        .par_iter()
+        .map(|p| format!("{} {} {} {}\n", p.t, p.lower, p.upper, p.upper_analytic))
+        .collect::<Vec<String>>()
+        .concat();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(
+            format!(
+                "set xlabel \"t\"; set ylabel \"P\"; plot \"{0}\" u 1:2 t \"P_1\" w l, \"{0}\" u 1:3 t \"P_2\" w l, \"{0}\" u 1:4 t \"P_2 (RWA)\" w l dt 2",
+                output_file
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+}
+
+// energy level diagram: the potential and a line at every E_n between its classical turning points
+pub fn plot_levels<F: Fn(f64) -> f64 + Sync>(
+    potential: &F,
+    mass: f64,
+    n_range: std::ops::Range<usize>,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let energies =
+        energy::first_energies(n_range.end, mass, UNITS.hbar, potential, APPROX_INF).unwrap();
+    let levels: Vec<(usize, f64, (f64, f64))> = n_range
+        .filter_map(|n| {
+            turning_points::classical_turning_points(potential, energies[n], APPROX_INF)
+                .map(|bounds| (n, energies[n], bounds))
+        })
+        .collect();
+
+    let lower = levels.iter().map(|l| l.2 .0).fold(f64::INFINITY, f64::min);
+    let upper = levels.iter().map(|l| l.2 .1).fold(f64::NEG_INFINITY, f64::max);
+    let view = (
+        lower - (upper - lower) * VIEW_FACTOR,
+        upper + (upper - lower) * VIEW_FACTOR,
+    );
+
+    let potential_values = Grid::uniform(view, NUMBER_OF_POINTS)
+        .points()
+        .into_par_iter()
+        .map(|x| Point {
+            x,
+            y: potential(x),
+        })
+        .collect();
+
+    // index 0 is the potential, index 1 the levels as separate segments
+    let levels_str = levels
+        .iter()
+        .map(|(n, energy, (a, b))| format!("{} {} {}\n{} {} {}\n\n", a, energy, n, b, energy, n))
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string(thin(potential_values)).as_bytes())
+        .unwrap();
+    data_file.write_all("\n\n".as_bytes()).unwrap();
+    data_file.write_all(levels_str.as_bytes()).unwrap();
+
+    let max_energy = levels.iter().map(|l| l.1).fold(0.0, f64::max);
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"\nset ylabel \"E\"\nset yrange [:{}]\nplot \"{}\" i 0 u 1:2 t \"V\" w l, \"{}\" i 1 u 1:2 t \"E_n\" w l", max_energy * 1.2, output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// Several eigenstates on one grid over the union of their views, with the columns x, Re and Im of
+// every state in the order of ns. With offset each state is shifted up by its energy and V is
+// appended as a last column, for the stacked plot over the potential.
+pub fn plot_states(
+    potential: SharedPotential,
+    mass: f64,
+    ns: &[usize],
+    offset: bool,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let states = eigenstates_of(
+        potential.clone(),
+        mass,
+        ns,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::Renormalize(1.0.into()),
+    );
+    let view = IntegrationDomain::View.common_range(&states);
 
-    let values_str = to_gnuplot_string(values);
+    let columns = ns
+        .iter()
+        .map(|n| format!(" re_{} im_{}", n, n))
+        .collect::<String>();
+    let header = format!(
+        "# n {}\n# energy {}\n# x{}{}\n",
+        ns.iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join(" "),
+        states
+            .iter()
+            .map(|s| s.get_energy().to_string())
+            .collect::<Vec<String>>()
+            .join(" "),
+        columns,
+        if offset { " v" } else { "" }
+    );
+    let rows = sampling_grid(view, NUMBER_OF_POINTS)
+        .points()
+        .into_par_iter()
+        .map(|x| {
+            let values = states
+                .iter()
+                .map(|s| {
+                    let psi = s.calc_psi(x);
+                    let shift = if offset { s.get_energy() } else { 0.0 };
+                    format!(" {} {}", psi.re + shift, psi.im + shift)
+                })
+                .collect::<String>();
+            if offset {
+                format!("{}{} {}\n", x, values, potential(x))
+            } else {
+                format!("{}{}\n", x, values)
+            }
+        })
+        .collect::<Vec<String>>()
+        .concat();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(header.as_bytes()).unwrap();
+    data_file.write_all(rows.as_bytes()).unwrap();
+
+    let mut lines = ns
+        .iter()
+        .enumerate()
+        .map(|(i, n)| {
+            format!(
+                "\"{}\" u 1:{} t \"Re(Psi_{})\" w l",
+                output_file,
+                2 * i + 2,
+                n
+            )
+        })
+        .collect::<Vec<String>>();
+    if offset {
+        lines.push(format!(
+            "\"{}\" u 1:{} t \"V\" w l",
+            output_file,
+            2 * ns.len() + 2
+        ));
+    }
+    let mut plot_file = create_file(&output_dir.join("plot_states.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"\nplot {}", lines.join(", ")).as_bytes())
+        .unwrap();
+}
 
-    let mut data_file = File::create(output_file).unwrap();
+// table of the states with the well they live in and their quantum number within it, plotted as
+// levels over the well index
+pub fn write_labelled_spectrum(labels: &[LevelLabel], output_dir: &Path, output_file: &str) {
+    let rows = labels
+        .iter()
+        .map(|l| {
+            let probabilities = l
+                .probabilities
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!(
+                "{} {} {} {} {}\n",
+                l.n, l.energy, l.well, l.local_n, probabilities
+            )
+        })
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all("# n energy well local_n probability_per_well...\n".as_bytes())
+        .unwrap();
+    data_file.write_all(rows.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"well\"\nset ylabel \"E\"\nset xtics 1\nset offsets 0.5, 0.5, 0, 0\nplot \"{}\" u 3:2 t \"E_n\" w p pt 7, \"{}\" u 3:2:4 w labels offset 1.5, 0 notitle", output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// E_n(lambda) as one curve per n, with the avoided crossings marked
+pub fn plot_sweep(
+    sweep: &Sweep,
+    crossings: &[AvoidedCrossing],
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let count = sweep.energies.iter().map(|e| e.len()).min().unwrap_or(0);
+    let curves = (0..count)
+        .map(|n| to_gnuplot_string(sweep.curve(n)))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    let crossings_str = crossings
+        .iter()
+        .map(|c| format!("{} {} {} {}\n", c.parameter, c.gap, c.lower, c.upper))
+        .collect::<String>();
+
+    // index 0 are the crossings, the curves follow in the order of n
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all("# lambda gap lower upper\n".as_bytes())
+        .unwrap();
+    data_file.write_all(crossings_str.as_bytes()).unwrap();
+    data_file.write_all("\n\n".as_bytes()).unwrap();
+    data_file.write_all(curves.as_bytes()).unwrap();
+
+    let arrows = crossings
+        .iter()
+        .map(|c| {
+            format!(
+                "set arrow from {}, graph 0 to {}, graph 1 nohead dt 2\n",
+                c.parameter, c.parameter
+            )
+        })
+        .collect::<String>();
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"lambda\"\nset ylabel \"E\"\n{}plot for [i=1:{}] \"{}\" i i u 1:2 t sprintf(\"E_%d\", i - 1) w l", arrows, count, output_file).as_bytes())
+        .unwrap();
+}
+
+// the semiclassical counting function N(E) over the staircase of the energies, the staircase is
+// index 1 of the data file
+pub fn plot_counting_function<F: Fn(f64) -> f64 + Sync>(
+    potential: &F,
+    mass: f64,
+    energies: &[f64],
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let lower = potentials::minimum(potential, APPROX_INF).1;
+    let upper = energies
+        .last()
+        .map_or(lower + 1.0, |e| *e + (*e - lower) * 0.1);
+    let counting_values = Grid::uniform((lower, upper), NUMBER_OF_POINTS_2D)
+        .points()
+        .into_par_iter()
+        .map(|e| Point {
+            x: e,
+            y: energy::counting_function(mass, UNITS.hbar, potential, APPROX_INF, e),
+        })
+        .collect();
+
+    // vertical steps at every energy
+    let staircase = energies
+        .iter()
+        .enumerate()
+        .map(|(n, e)| format!("{} {}\n{} {}\n", e, n, e, n + 1))
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string(counting_values).as_bytes())
+        .unwrap();
+    data_file.write_all("\n\n".as_bytes()).unwrap();
+    data_file
+        .write_all(format!("{} 0\n", lower).as_bytes())
+        .unwrap();
+    data_file.write_all(staircase.as_bytes()).unwrap();
+    data_file
+        .write_all(format!("{} {}\n", upper, energies.len()).as_bytes())
+        .unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"E\"\nset ylabel \"N(E)\"\nset key left top\nplot \"{}\" i 0 u 1:2 t \"S(E) / (2 pi hbar) + 1/2\" w l, \"{}\" i 1 u 1:2 t \"levels\" w l", output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// WKB transmission factor over the energy, on a logarithmic scale
+pub fn plot_transmission(table: Vec<Point<f64, f64>>, output_dir: &Path, output_file: &str) {
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string(table).as_bytes())
+        .unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"E\"\nset ylabel \"T(E)\"\nset logscale y\nplot \"{}\" u 1:2 t \"T(E)\" w l", output_file).as_bytes())
+        .unwrap();
+}
 
+// sigma(E) / alpha of photoionization::cross_section over the energy of the continuum state
+pub fn plot_cross_section(table: Vec<Point<f64, f64>>, output_dir: &Path, output_file: &str) {
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(to_gnuplot_string(table).as_bytes())
+        .unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"E\"\nset ylabel \"sigma(E) / alpha\"\nplot \"{}\" u 1:2 t \"sigma\" w l", output_file).as_bytes())
+        .unwrap();
+}
+
+// one line per time, t and then x of every trajectory, drawn as paths in the x-t plane
+pub fn plot_trajectories(
+    trajectories: &bohmian::Trajectories,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values_str = trajectories
+        .times
+        .iter()
+        .zip(trajectories.positions.iter())
+        .map(|(t, positions)| {
+            let columns = positions
+                .iter()
+                .map(|x| format!(" {}", x))
+                .collect::<String>();
+            format!("{}{}\n", t, columns)
+        })
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let count = trajectories.positions.first().map_or(0, |p| p.len());
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"\nset ylabel \"t\"\nplot for [i=2:{}] \"{}\" u i:1 notitle w l lc rgb \"black\"", count + 1, output_file).as_bytes())
+        .unwrap();
+}
+
+// x, p(x), the de Broglie wavelength and the phase, with the wavelength on the second axis
+pub fn plot_semiclassical(values: &[SemiclassicalPoint], output_dir: &Path, output_file: &str) {
+    let values_str = values
+        .iter()
+        .map(|p| format!("{} {} {} {}\n", p.x, p.momentum, p.wavelength, p.phase))
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"\nset ytics nomirror\nset y2tics\nset y2label \"lambda\"\nplot \"{}\" u 1:2 t \"p\" w l, \"{}\" u 1:4 t \"integral of p / hbar\" w l, \"{}\" u 1:3 axes x1y2 t \"lambda\" w l", output_file, output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// |hbar W + 1| of the WKB pair over x, log scaled since it spans orders of magnitude
+pub fn plot_wronskian_deviation(
+    deviation: &[Point<f64, f64>],
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values_str = deviation
+        .iter()
+        .map(|p| format!("{} {}\n", p.x, p.y))
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
     data_file.write_all(values_str.as_bytes()).unwrap();
 
-    let mut plot_file = File::create("plot.gnuplot").unwrap();
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
     plot_file
-        .write_all(format!("set xlabel \"x\"; set ylabel \"|Psi|^2\"; plot \"{}\" u 1:2 t \"|Psi|^2\" w l", output_file).as_bytes())
+        .write_all(format!("set xlabel \"x\"\nset logscale y\nplot \"{}\" u 1:2 t \"|hbar W + 1|\" w lp", output_file).as_bytes())
         .unwrap();
 }
+
+// histogram of the unfolded spacings next to the Poisson and Wigner distributions
+pub fn plot_spacing_distribution(
+    distribution: &SpacingDistribution,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let values_str = distribution
+        .bins
+        .iter()
+        .map(|(s, density)| {
+            format!(
+                "{} {} {} {}\n",
+                s,
+                density,
+                level_statistics::poisson(*s),
+                level_statistics::wigner(*s)
+            )
+        })
+        .collect::<String>();
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file
+        .write_all(
+            format!(
+                "# s P(s) poisson wigner, KS distance poisson {} wigner {}\n",
+                distribution.poisson_distance, distribution.wigner_distance
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"s\"\nset ylabel \"P(s)\"\nset style fill solid 0.3\nplot \"{}\" u 1:2 t \"spacings\" w boxes, \"{}\" u 1:3 t \"Poisson\" w l, \"{}\" u 1:4 t \"Wigner\" w l", output_file, output_file, output_file).as_bytes())
+        .unwrap();
+}
+
+// |psi_n(x)|^2 of normalized eigenstates on a grid of x and n, the states share one energy scan
+pub fn plot_spectrum_map(
+    potential: SharedPotential,
+    mass: f64,
+    n_range: std::ops::Range<usize>,
+    output_dir: &Path,
+    output_file: &str,
+) {
+    let states = eigenstates(
+        potential,
+        mass,
+        n_range.clone(),
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::None,
+    );
+    let grid = matrix_elements::common_grid(&states);
+
+    let x = Grid::uniform(grid.get_range(), NUMBER_OF_POINTS_2D).points();
+    let rows: Vec<Vec<f64>> = states
+        .par_iter()
+        .map(|state| {
+            let norm = matrix_elements::norm(state, &grid);
+            x.iter()
+                .map(|x| state.eval(*x).norm_sqr() / (norm * norm))
+                .collect()
+        })
+        .collect();
+
+    // every scan line of a pm3d grid needs the same x values, so all rows keep the same points
+    let kept = if DECIMATION_TOLERANCE > 0.0 {
+        let tolerance = decimation::relative_tolerance(&rows, DECIMATION_TOLERANCE);
+        decimation::keep_indices(&x, &rows, tolerance)
+    } else {
+        (0..x.len()).collect()
+    };
+
+    // gnuplot expects the scan lines of a grid to be separated by a blank line
+    let values_str = rows
+        .par_iter()
+        .zip(n_range.into_par_iter())
+        .map(|(row, n)| {
+            kept.iter()
+                .map(|i| format!("{} {} {}\n", x[*i], n, row[*i]))
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut data_file = create_file(&output_dir.join(output_file));
+    data_file.write_all(values_str.as_bytes()).unwrap();
+
+    let mut plot_file = create_file(&output_dir.join("plot.gnuplot"));
+    plot_file
+        .write_all(format!("set xlabel \"x\"; set ylabel \"n\"; set cblabel \"|Psi_n|^2\"; set pm3d map; splot \"{}\" u 1:2:3 t \"|Psi_n|^2\" w pm3d", output_file).as_bytes())
+        .unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cancelled_refinement_keeps_preview() {
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            0,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let output_dir = std::env::temp_dir().join("schroedinger_progressive_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let lines = || {
+            std::fs::read_to_string(output_dir.join("psi.dat"))
+                .unwrap()
+                .lines()
+                .count()
+        };
+
+        let refining = plot_wavefunction_progressive(wave_function, &output_dir, "psi.dat");
+        assert_eq!(lines(), PREVIEW_POINTS);
+        assert!(output_dir.join("plot_regions.gnuplot").exists());
+        refining.cancel();
+        assert_eq!(refining.join().err(), Some("cancelled".to_string()));
+        assert_eq!(lines(), PREVIEW_POINTS);
+    }
+
+    #[test]
+    fn rescaled_potential_column() {
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            1,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let output_dir = std::env::temp_dir().join("schroedinger_potential_column_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        plot_wavefunction_with(
+            &wave_function,
+            PotentialColumn::Rescaled,
+            &output_dir,
+            "psi.dat",
+        );
+        let data = std::fs::read_to_string(output_dir.join("psi.dat")).unwrap();
+        let mapping = data
+            .lines()
+            .next()
+            .unwrap()
+            .split(' ')
+            .collect::<Vec<&str>>();
+        assert_eq!(mapping[0..2], ["#", "potential_mapping"]);
+        let (a, b): (f64, f64) = (mapping[2].parse().unwrap(), mapping[3].parse().unwrap());
+
+        let rows = data
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .map(|l| {
+                l.split(' ')
+                    .map(|c| c.parse().unwrap())
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+        let psi_max = rows.iter().map(|r| r[1].max(r[2])).fold(f64::MIN, f64::max);
+        let v_max = rows.iter().map(|r| r[4]).fold(f64::MIN, f64::max);
+        assert!((psi_max - v_max).abs() < 1e-9 * psi_max.abs());
+        for row in rows.iter().step_by(1000) {
+            assert!((row[4] - (a * potentials::square(row[0]) + b)).abs() < 1e-9);
+        }
+        assert!(output_dir.join("plot_potential.gnuplot").exists());
+    }
+
+    #[test]
+    fn probability_header_and_bins() {
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            0,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let view = wave_function.get_view();
+        let (_, norm) = probability_values(&wave_function, view, ProbabilityUnit::PerLength);
+        assert!((norm - 1.0).abs() < 1e-2);
+        let (bins, binned_norm) =
+            probability_values(&wave_function, view, ProbabilityUnit::PerBin(0.1));
+        assert_eq!(binned_norm, norm);
+        assert!((bins.iter().map(|p| p.y).sum::<f64>() - norm).abs() < 1e-9);
+
+        let output_dir = std::env::temp_dir().join("schroedinger_probability_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        plot_probability_in(
+            &wave_function,
+            ProbabilityUnit::PerBin(0.1),
+            &output_dir,
+            "probability.dat",
+        );
+        let data = std::fs::read_to_string(output_dir.join("probability.dat")).unwrap();
+        let header = data
+            .lines()
+            .filter(|l| l.starts_with('#'))
+            .collect::<Vec<&str>>();
+        let range = wave_function.get_normalization().unwrap();
+        assert_eq!(
+            header[0],
+            format!("# energy {}", wave_function.get_energy())
+        );
+        assert_eq!(
+            header[2],
+            format!("# normalization renormalized {} {}", range.0, range.1)
+        );
+        assert_eq!(header[4], "# unit per_bin 0.1");
+        assert_eq!(data.lines().count(), header.len() + bins.len());
+    }
+
+    #[test]
+    fn stacked_states() {
+        let output_dir = std::env::temp_dir().join("schroedinger_states_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let rows = |offset: bool| {
+            plot_states(
+                Arc::new(potentials::square),
+                1.0,
+                &[2, 0],
+                offset,
+                &output_dir,
+                "states.dat",
+            );
+            std::fs::read_to_string(output_dir.join("states.dat"))
+                .unwrap()
+                .lines()
+                .map(|l| l.to_string())
+                .collect::<Vec<String>>()
+        };
+        let plain = rows(false);
+        let stacked = rows(true);
+        assert_eq!(plain[0], "# n 2 0");
+        assert_eq!(plain[2], "# x re_2 im_2 re_0 im_0");
+        assert_eq!(stacked[2], "# x re_2 im_2 re_0 im_0 v");
+        let energies = plain[1]
+            .split(' ')
+            .skip(2)
+            .map(|e| e.parse().unwrap())
+            .collect::<Vec<f64>>();
+        assert!(energies[0] > energies[1]);
+
+        assert_eq!(plain.len(), NUMBER_OF_POINTS + 3);
+        let parse = |l: &String| {
+            l.split(' ')
+                .map(|c| c.parse().unwrap())
+                .collect::<Vec<f64>>()
+        };
+        for (p, s) in plain[3..].iter().zip(&stacked[3..]) {
+            let (p, s) = (parse(p), parse(s));
+            assert_eq!(p.len(), 5);
+            assert_eq!(s.len(), 6);
+            assert_eq!(p[0], s[0]);
+            for i in 1..5 {
+                assert!((s[i] - p[i] - energies[(i - 1) / 2]).abs() < 1e-9);
+            }
+            assert_eq!(s[5], potentials::square(s[0]));
+        }
+    }
+
+    #[test]
+    fn level_diagram() {
+        let output_dir = std::env::temp_dir().join("schroedinger_levels_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        plot_levels(&potentials::square, 1.0, 1..3, &output_dir, "levels.dat");
+        let data = std::fs::read_to_string(output_dir.join("levels.dat")).unwrap();
+        let rows = data
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                l.split(' ')
+                    .map(|c| c.parse().unwrap())
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+
+        // index 1 has the ends of every level, V = x^2 has its turning points at +-sqrt(E)
+        let ends = rows
+            .iter()
+            .filter(|r| r.len() == 3)
+            .collect::<Vec<&Vec<f64>>>();
+        assert_eq!(ends.len(), 4);
+        for (pair, n) in ends.chunks(2).zip(1..3) {
+            let energy = (n as f64 + 0.5) * 2.0f64.sqrt();
+            for (end, sign) in pair.iter().zip([-1.0, 1.0]) {
+                assert_eq!(end[2], n as f64);
+                assert!((end[1] - energy).abs() < 1e-3 * energy);
+                assert!((end[0] - sign * end[1].sqrt()).abs() < 1e-6);
+            }
+        }
+
+        // index 0 is V over the levels
+        let potential = rows
+            .iter()
+            .filter(|r| r.len() == 2)
+            .collect::<Vec<&Vec<f64>>>();
+        assert!(potential[0][0] < ends[2][0] && potential.last().unwrap()[0] > ends[3][0]);
+        for row in potential.iter().step_by(1000) {
+            assert_eq!(row[1], potentials::square(row[0]));
+        }
+        assert!(output_dir.join("plot.gnuplot").exists());
+    }
+
+    #[test]
+    fn spectrum_map_scan_lines() {
+        let output_dir = std::env::temp_dir().join("schroedinger_spectrum_map_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        plot_spectrum_map(
+            Arc::new(potentials::square),
+            1.0,
+            1..4,
+            &output_dir,
+            "map.dat",
+        );
+        let data = std::fs::read_to_string(output_dir.join("map.dat")).unwrap();
+
+        // one scan line per n on the same x, each |psi_n|^2 integrates to 1
+        let scan_lines = data.split("\n\n").collect::<Vec<&str>>();
+        assert_eq!(scan_lines.len(), 3);
+        let mut x = None;
+        for (scan_line, n) in scan_lines.iter().zip(1..4) {
+            let rows = scan_line
+                .lines()
+                .map(|l| {
+                    l.split(' ')
+                        .map(|c| c.parse().unwrap())
+                        .collect::<Vec<f64>>()
+                })
+                .collect::<Vec<Vec<f64>>>();
+            assert_eq!(rows.len(), NUMBER_OF_POINTS_2D);
+            assert!(rows.iter().all(|r| r[1] == n as f64 && r[2] >= 0.0));
+            let line_x = rows.iter().map(|r| r[0]).collect::<Vec<f64>>();
+            assert_eq!(*x.get_or_insert(line_x.clone()), line_x);
+
+            let dx = rows[1][0] - rows[0][0];
+            let norm = rows.iter().map(|r| r[2]).sum::<f64>() * dx;
+            assert!((norm - 1.0).abs() < 1e-2, "n = {}: {}", n, norm);
+        }
+    }
+
+    // e^(-x^2) e^(ikx), a state with a phase running along x
+    struct WavePacket {
+        k: f64,
+    }
+
+    impl Func<f64, Complex64> for WavePacket {
+        fn eval(&self, x: f64) -> Complex64 {
+            Complex64::from_polar((-x * x).exp(), self.k * x)
+        }
+    }
+
+    #[test]
+    fn phase_colored_magnitude_and_phase() {
+        let output_dir = std::env::temp_dir().join("schroedinger_phase_colored_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let packet = WavePacket { k: 3.0 };
+        plot_phase_colored(&packet, (-2.0, 2.0), "f", &output_dir, "phase.dat");
+        let data = std::fs::read_to_string(output_dir.join("phase.dat")).unwrap();
+        let rows = data
+            .lines()
+            .map(|l| {
+                l.split(' ')
+                    .map(|c| c.parse().unwrap())
+                    .collect::<Vec<f64>>()
+            })
+            .collect::<Vec<Vec<f64>>>();
+        assert_eq!(rows.len(), NUMBER_OF_POINTS);
+        for row in rows.iter().step_by(997) {
+            let value = packet.eval(row[0]);
+            assert_eq!(row[1], value.norm());
+            assert!(-f64::consts::PI < row[2] && row[2] <= f64::consts::PI);
+            assert!((Complex64::from_polar(row[1], row[2]) - value).norm() < 1e-12);
+        }
+
+        let script = std::fs::read_to_string(output_dir.join("plot_phase.gnuplot")).unwrap();
+        assert!(script.contains("\"phase.dat\" u 1:2:3 w filledcurves y1=0 lc palette z"));
+        assert!(script.contains("set cblabel \"arg(f)\""));
+    }
+
+    #[test]
+    fn pgfplots_of_real_and_complex_functions() {
+        let output_dir = std::env::temp_dir().join("schroedinger_pgfplots_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let coordinates = |tex: &str| {
+            tex.lines()
+                .filter_map(|l| l.strip_prefix('(')?.strip_suffix(')')?.split_once(", "))
+                .map(|(x, y)| (x.parse().unwrap(), y.parse().unwrap()))
+                .collect::<Vec<(f64, f64)>>()
+        };
+
+        write_pgfplots(
+            &WavePacket { k: 0.0 },
+            (-3.0, 3.0),
+            "real",
+            &output_dir,
+            "real.tex",
+        );
+        let tex = std::fs::read_to_string(output_dir.join("real.tex")).unwrap();
+        assert!(tex.starts_with("\\begin{tikzpicture}"));
+        assert!(tex.contains("title={real}"));
+        // a real function gets no Im plot
+        assert_eq!(tex.matches("\\addplot").count(), 1);
+        // always thinned, the kept coordinates are samples of the function
+        let re = coordinates(&tex);
+        assert!(2 < re.len() && re.len() < NUMBER_OF_POINTS / 10);
+        assert_eq!((re[0].0, re.last().unwrap().0), (-3.0, 3.0));
+        for (x, y) in re {
+            assert_eq!(y, (-x * x).exp());
+        }
+
+        write_pgfplots(
+            &WavePacket { k: 3.0 },
+            (-3.0, 3.0),
+            "complex",
+            &output_dir,
+            "complex.tex",
+        );
+        let tex = std::fs::read_to_string(output_dir.join("complex.tex")).unwrap();
+        assert_eq!(tex.matches("\\addplot").count(), 2);
+        assert!(tex.contains("\\addplot[red, dashed]"));
+        assert_eq!(coordinates(&tex).len() % 2, 0);
+    }
+
+    #[test]
+    fn wolfram_numbers_and_file() {
+        assert_eq!(wolfram_number(1.5e-10), "1.5*^-10");
+        assert_eq!(wolfram_number(-2.0), "-2*^0");
+        assert_eq!(wolfram_number(f64::NAN), "Indeterminate");
+        assert_eq!(wolfram_number(f64::NEG_INFINITY), "-Infinity");
+
+        let output_dir = std::env::temp_dir().join("schroedinger_wolfram_test");
+        std::fs::create_dir_all(&output_dir).unwrap();
+        let packet = WavePacket { k: 3.0 };
+        write_wolfram(
+            &packet,
+            (-2.0, 2.0),
+            "packet",
+            &[("k", 3.0)],
+            &output_dir,
+            "packet.wl",
+        );
+        let wl = std::fs::read_to_string(output_dir.join("packet.wl")).unwrap();
+        assert!(wl.starts_with("packetMetadata = <|\"k\" -> 3*^0, \"view\" -> {-2*^0, 2*^0}|>;\n"));
+        assert!(wl.ends_with("packet = Interpolation[packetData];\n"));
+
+        let data = wl
+            .lines()
+            .filter(|l| l.contains("Complex["))
+            .collect::<Vec<&str>>();
+        assert_eq!(data.len(), NUMBER_OF_POINTS);
+        let first = packet.eval(-2.0);
+        assert_eq!(
+            data[0],
+            format!(
+                "  {{-2*^0, Complex[{}, {}]}},",
+                wolfram_number(first.re),
+                wolfram_number(first.im)
+            )
+        );
+        assert!(data.last().unwrap().starts_with("  {2*^0, Complex["));
+    }
+
+    #[test]
+    fn gnuplot_strings_keep_the_order() {
+        let values = (0..10000)
+            .map(|i| Point {
+                x: i as f64,
+                y: complex(-(i as f64), 0.5),
+            })
+            .collect::<Vec<Point<f64, Complex64>>>();
+        let real = values
+            .iter()
+            .map(|p| Point { x: p.x, y: p.y.re })
+            .collect::<Vec<Point<f64, f64>>>();
+
+        let complex_str = to_gnuplot_string_complex(values.clone());
+        for (i, line) in complex_str.lines().enumerate() {
+            assert_eq!(line, format!("{} {} 0.5", i, -(i as f64)));
+        }
+        let real_str = to_gnuplot_string(real.clone());
+        assert_eq!(real_str.lines().count(), 10000);
+        for (i, line) in real_str.lines().enumerate() {
+            assert_eq!(line, format!("{} {}", i, -(i as f64)));
+        }
+
+        // the same bytes on every run, not the order the threads finish in
+        for _ in 0..3 {
+            assert_eq!(to_gnuplot_string_complex(values.clone()), complex_str);
+            assert_eq!(to_gnuplot_string(real.clone()), real_str);
+        }
+    }
+}
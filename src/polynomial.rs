@@ -0,0 +1,239 @@
+use crate::*;
+use nalgebra::DMatrix;
+
+// highest degree Polynomial::detect recognizes
+const MAX_DEGREE: usize = 16;
+// Chebyshev coefficients of V below this times its largest value are dropped
+const TRIM_TOLERANCE: f64 = 1e-12;
+// V has to match the polynomial to this times its largest value at the check points
+const MATCH_TOLERANCE: f64 = 1e-10;
+const CHECK_POINTS: usize = 101;
+// eigenvalues of the companion matrix further than this off the real axis, relative to 1 + |t|,
+// aren't roots, a double root splits into a pair about sqrt(epsilon) apart
+const IMAGINARY_CUTOFF: f64 = 1e-6;
+
+// sum_k coefficients[k] t^k of t = (x - center) / scale, which maps the range it was detected on
+// to [-1, 1] and keeps the companion matrix well conditioned
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    center: f64,
+    scale: f64,
+    coefficients: Vec<f64>,
+}
+
+impl Polynomial {
+    // V as a polynomial of degree up to MAX_DEGREE if it is one on the range, interpolated at
+    // Chebyshev points and checked in between
+    pub fn detect<F: Fn(f64) -> f64 + Sync + ?Sized>(
+        potential: &F,
+        range: (f64, f64),
+    ) -> Option<Polynomial> {
+        let center = (range.0 + range.1) / 2.0;
+        let scale = (range.1 - range.0) / 2.0;
+        let n = MAX_DEGREE + 1;
+        let angle = |k: usize| f64::consts::PI * (k as f64 + 0.5) / n as f64;
+        let values = (0..n)
+            .map(|k| potential(center + scale * angle(k).cos()))
+            .collect::<Vec<f64>>();
+        if !values.iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let largest = values.iter().fold(f64::MIN_POSITIVE, |m, v| m.max(v.abs()));
+
+        let mut chebyshev = (0..n)
+            .map(|j| {
+                let c = (0..n)
+                    .map(|k| values[k] * (j as f64 * angle(k)).cos())
+                    .sum::<f64>()
+                    * 2.0
+                    / n as f64;
+                if j == 0 {
+                    c / 2.0
+                } else {
+                    c
+                }
+            })
+            .collect::<Vec<f64>>();
+        while chebyshev.len() > 1 && chebyshev.last().unwrap().abs() < TRIM_TOLERANCE * largest {
+            chebyshev.pop();
+        }
+
+        // T_(k + 1) = 2 t T_k - T_(k - 1) in powers of t
+        let mut coefficients = vec![0.0; chebyshev.len()];
+        coefficients[0] = chebyshev[0];
+        let (mut previous, mut current) = (vec![1.0], vec![0.0, 1.0]);
+        for c in &chebyshev[1..] {
+            current
+                .iter()
+                .enumerate()
+                .for_each(|(k, a)| coefficients[k] += c * a);
+            let mut next = vec![0.0; current.len() + 1];
+            current
+                .iter()
+                .enumerate()
+                .for_each(|(k, a)| next[k + 1] += 2.0 * a);
+            previous.iter().enumerate().for_each(|(k, a)| next[k] -= a);
+            previous = current;
+            current = next;
+        }
+        let polynomial = Polynomial {
+            center,
+            scale,
+            coefficients,
+        };
+
+        let matches = (0..CHECK_POINTS).into_par_iter().all(|i| {
+            let x = range.0 + (range.1 - range.0) * i as f64 / (CHECK_POINTS - 1) as f64;
+            let v = potential(x);
+            v.is_finite()
+                && (polynomial.eval(x) - v).abs() <= MATCH_TOLERANCE * largest.max(v.abs())
+        });
+        if matches {
+            Some(polynomial)
+        } else {
+            None
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    // Horner's scheme
+    pub fn eval(&self, x: f64) -> f64 {
+        let t = (x - self.center) / self.scale;
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |acc, a| acc * t + a)
+    }
+
+    fn with_coefficients(&self, coefficients: Vec<f64>) -> Polynomial {
+        Polynomial {
+            center: self.center,
+            scale: self.scale,
+            coefficients,
+        }
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let coefficients = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, a)| k as f64 * a / self.scale)
+            .collect::<Vec<f64>>();
+        if coefficients.is_empty() {
+            self.with_coefficients(vec![0.0])
+        } else {
+            self.with_coefficients(coefficients)
+        }
+    }
+
+    // p(x) - value
+    pub fn shifted(&self, value: f64) -> Polynomial {
+        let mut coefficients = self.coefficients.clone();
+        coefficients[0] -= value;
+        self.with_coefficients(coefficients)
+    }
+
+    pub fn scaled(&self, factor: f64) -> Polynomial {
+        self.with_coefficients(self.coefficients.iter().map(|a| factor * a).collect())
+    }
+
+    // both have to be detected on the same range
+    pub fn sum(&self, other: &Polynomial) -> Polynomial {
+        assert_eq!((self.center, self.scale), (other.center, other.scale));
+        let n = self.coefficients.len().max(other.coefficients.len());
+        let coefficient = |p: &Polynomial, k: usize| p.coefficients.get(k).copied().unwrap_or(0.0);
+        self.with_coefficients(
+            (0..n)
+                .map(|k| coefficient(self, k) + coefficient(other, k))
+                .collect(),
+        )
+    }
+
+    pub fn product(&self, other: &Polynomial) -> Polynomial {
+        assert_eq!((self.center, self.scale), (other.center, other.scale));
+        let mut coefficients = vec![0.0; self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                coefficients[i + j] += a * b;
+            }
+        }
+        self.with_coefficients(coefficients)
+    }
+
+    // sorted real roots, the real eigenvalues of the companion matrix
+    pub fn real_roots(&self) -> Vec<f64> {
+        let mut a = self.coefficients.clone();
+        while a.len() > 1 && *a.last().unwrap() == 0.0 {
+            a.pop();
+        }
+        let n = a.len() - 1;
+        if n == 0 {
+            return vec![];
+        }
+        let mut companion = DMatrix::<f64>::zeros(n, n);
+        for k in 0..n {
+            if k + 1 < n {
+                companion[(k + 1, k)] = 1.0;
+            }
+            companion[(k, n - 1)] = -a[k] / a[n];
+        }
+        let mut roots = companion
+            .complex_eigenvalues()
+            .iter()
+            .filter(|z| z.im.abs() <= IMAGINARY_CUTOFF * (1.0 + z.re.abs()))
+            .map(|z| self.center + self.scale * z.re)
+            .collect::<Vec<f64>>();
+        roots.sort_by(cmp_f64);
+        let precision = self.root_precision();
+        roots.dedup_by(|b, a| (*b - *a).abs() <= precision);
+        roots
+    }
+
+    // roots of real_roots closer than this are the same root
+    pub fn root_precision(&self) -> f64 {
+        IMAGINARY_CUTOFF * self.scale.abs()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detected_polynomials() {
+        // the coefficients are accurate to epsilon times the largest value on the range
+        let hat = Polynomial::detect(&potentials::mexican_hat, (-10.0, 10.0)).unwrap();
+        assert_eq!(hat.degree(), 4);
+        for x in [-7.0, 0.3, 4.0, 9.5] {
+            let v = potentials::mexican_hat(x);
+            assert!((hat.eval(x) - v).abs() < 1e-10, "{}", x);
+        }
+
+        let roots = hat.shifted(100.0).real_roots();
+        let expected = [
+            -26.0f64.sqrt(),
+            -6.0f64.sqrt(),
+            6.0f64.sqrt(),
+            26.0f64.sqrt(),
+        ];
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "{}", root);
+        }
+        assert!(hat.shifted(-1.0).real_roots().is_empty());
+
+        // V' = 4 x (x^2 - 16)
+        let slope = hat.derivative();
+        assert!((slope.eval(1.0) + 60.0).abs() < 1e-9);
+        let square = slope.product(&slope).sum(&hat.scaled(-1.0));
+        assert!((square.eval(1.0) - (3600.0 - 225.0)).abs() < 1e-6);
+
+        assert!(Polynomial::detect(&potentials::barrier, (-10.0, 10.0)).is_none());
+        assert!(Polynomial::detect(&|x: f64| x.abs(), (-1.0, 1.0)).is_none());
+    }
+}
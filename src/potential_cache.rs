@@ -0,0 +1,101 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const SHARDS: usize = 64;
+// entries per shard, a full shard is cleared
+const SHARD_CAPACITY: usize = 1 << 14;
+
+// Memoizes a potential on the exact value of x. The energy scan integrates over the same grid for
+// every energy it tries, so an expensive potential is evaluated once per grid point instead of
+// once per energy. Sharded so the rayon threads don't all wait on one lock.
+pub struct CachedPotential<F: Fn(f64) -> f64 + Sync + Send> {
+    potential: F,
+    shards: Vec<Mutex<HashMap<u64, f64>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<F: Fn(f64) -> f64 + Sync + Send> CachedPotential<F> {
+    pub fn new(potential: F) -> CachedPotential<F> {
+        CachedPotential {
+            potential,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    fn shard(&self, key: u64) -> &Mutex<HashMap<u64, f64>> {
+        // Fibonacci hashing, neighbouring x differ mostly in the low bits
+        &self.shards[(key.wrapping_mul(0x9e3779b97f4a7c15) >> 58) as usize % SHARDS]
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        let key = x.to_bits();
+        if let Some(v) = self.shard(key).lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *v;
+        }
+
+        // evaluated without holding the lock, two threads may compute the same value
+        let v = (self.potential)(x);
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let mut shard = self.shard(key).lock().unwrap();
+        if shard.len() >= SHARD_CAPACITY {
+            shard.clear();
+        }
+        shard.insert(key, v);
+        v
+    }
+
+    pub fn get_hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn get_misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+// the cache as a potential for the functions taking Fn(f64) -> f64
+pub fn cached<F: Fn(f64) -> f64 + Sync + Send>(potential: F) -> impl Fn(f64) -> f64 + Sync + Send {
+    let cache = CachedPotential::new(potential);
+    move |x| cache.eval(x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evaluates_each_point_once() {
+        let calls = AtomicUsize::new(0);
+        let cache = CachedPotential::new(|x: f64| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            x * x
+        });
+        let grid = Grid::uniform((-3.0, 3.0), 1000);
+
+        for _ in 0..5 {
+            for x in grid.points() {
+                assert_eq!(cache.eval(x), x * x);
+            }
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1000);
+        assert_eq!(cache.get_misses(), 1000);
+        assert_eq!(cache.get_hits(), 4000);
+    }
+
+    #[test]
+    fn same_energies_as_uncached() {
+        let cache = CachedPotential::new(potentials::mexican_hat);
+        let expected =
+            energy::first_energies(3, 1.0, 1.0, &potentials::mexican_hat, APPROX_INF).unwrap();
+        let energies = energy::first_energies(3, 1.0, 1.0, &|x| cache.eval(x), APPROX_INF).unwrap();
+
+        assert_eq!(energies, expected);
+        assert!(cache.get_hits() > 100 * cache.get_misses());
+    }
+}
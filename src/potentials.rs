@@ -2,6 +2,17 @@ use crate::*;
 
 const ENERGY_INF: f64 = 1e6;
 
+// V(x), implemented by every Fn(f64) -> f64, so closures capturing runtime parameters can be used
+// as well as the free functions below
+pub trait Potential: Fn(f64) -> f64 {}
+
+impl<F: Fn(f64) -> f64> Potential for F {}
+
+pub type SharedPotential = Arc<dyn Potential + Send + Sync>;
+
+// a potential that can be chosen by name, e.g. on the command line
+pub type NamedPotential = (&'static str, fn(f64) -> f64);
+
 #[allow(unused)]
 pub fn smooth_step(x: f64) -> f64 {
     const TRANSITION: f64 = 0.5;
@@ -86,3 +97,142 @@ pub fn triple_mexican_hat(x: f64) -> f64 {
 pub fn square(x: f64) -> f64 {
     x * x
 }
+
+pub static POTENTIALS: [NamedPotential; 5] = [
+    ("smooth_step", smooth_step),
+    ("mexican_hat", mexican_hat),
+    ("double_mexican_hat", double_mexican_hat),
+    ("triple_mexican_hat", triple_mexican_hat),
+    ("square", square),
+];
+
+pub fn by_name(name: &str) -> Option<&'static fn(f64) -> f64> {
+    POTENTIALS
+        .iter()
+        .find(|(potential_name, _)| *potential_name == name)
+        .map(|(_, potential)| potential)
+}
+
+// Gaussian barrier of height 10
+pub fn barrier(x: f64) -> f64 {
+    10.0 * (-x * x).exp()
+}
+
+// triangular barrier of a metal surface at x = 0 with work function 5 in a field of strength 1,
+// the situation of cold field emission
+pub fn field_emission(x: f64) -> f64 {
+    if x < 0.0 {
+        0.0
+    } else {
+        5.0 - x
+    }
+}
+
+// potentials without bound states, for transmission
+pub static BARRIERS: [NamedPotential; 2] =
+    [("barrier", barrier), ("field_emission", field_emission)];
+
+pub fn barrier_by_name(name: &str) -> Option<&'static fn(f64) -> f64> {
+    BARRIERS
+        .iter()
+        .find(|(barrier_name, _)| *barrier_name == name)
+        .map(|(_, barrier)| barrier)
+}
+
+// global minimum of the potential in range as (x, V(x)): the lowest point of a uniform scan,
+// refined by a golden section search between its neighbours
+pub fn minimum<F: Fn(f64) -> f64 + Sync + ?Sized>(potential: &F, range: (f64, f64)) -> (f64, f64) {
+    let grid = Grid::uniform(range, NUMBER_OF_POINTS);
+    let (i, _) = (0..grid.len())
+        .into_par_iter()
+        .map(|i| (i, potential(grid.x(i))))
+        .filter(|(_, v)| !v.is_nan())
+        .min_by(|a, b| cmp_f64(&a.1, &b.1))
+        .expect("potential is NaN everywhere");
+
+    let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (grid.x(i.max(1) - 1), grid.x((i + 1).min(grid.len() - 1)));
+    while b - a > f64::EPSILON.sqrt() * (a.abs() + b.abs()).max(1.0) {
+        let c = b - ratio * (b - a);
+        let d = a + ratio * (b - a);
+        if potential(c) < potential(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+
+    // the search can't do better than the scan at a kink or a jump
+    let x = (a + b) / 2.0;
+    let scanned = grid.x(i);
+    if potential(x) < potential(scanned) {
+        (x, potential(x))
+    } else {
+        (scanned, potential(scanned))
+    }
+}
+
+// an energy at or below the minimum has no classically allowed region and therefore no turning
+// points to build a wave function around
+pub fn check_energy<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    energy: f64,
+    range: (f64, f64),
+) -> Result<(), String> {
+    let (x, v) = minimum(potential, range);
+    if energy > v {
+        Ok(())
+    } else {
+        Err(format!(
+            "energy {} is not above the minimum of the potential, V({}) = {}, there are no \
+             classical turning points",
+            energy, x, v
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn minimum_of_shifted_parabola() {
+        let (x, v) = minimum(&|x: f64| (x - 1.234).powi(2) - 3.0, APPROX_INF);
+        assert!(float_compare(x, 1.234, 1e-6));
+        assert!(float_compare(v, -3.0, 1e-12));
+    }
+
+    #[test]
+    fn minimum_of_mexican_hat() {
+        let (x, v) = minimum(&mexican_hat, (-10.0, 10.0));
+        assert!(float_compare(x.abs(), 4.0, 1e-6));
+        assert!(v.abs() < 1e-10);
+        assert!(check_energy(&mexican_hat, -1.0, (-10.0, 10.0)).is_err());
+        assert!(check_energy(&mexican_hat, 1.0, (-10.0, 10.0)).is_ok());
+    }
+
+    #[test]
+    fn closure_capturing_runtime_parameter() {
+        // omega is only known at runtime, the closure owns it
+        let omega: f64 = "2".parse().unwrap();
+        let potential: SharedPotential = Arc::new(move |x: f64| 0.5 * omega * omega * x * x);
+        let states = [0, 2].map(|n| {
+            WaveFunction::new(
+                potential.clone(),
+                1.0,
+                n,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::None,
+            )
+        });
+        for (state, n) in states.iter().zip([0, 2]) {
+            let energy = omega * (n as f64 + 0.5);
+            assert!(float_compare(state.get_energy(), energy, 1e-3));
+        }
+
+        // the states shared the potential and released it with them
+        drop(states);
+        assert_eq!(Arc::strong_count(&potential), 1);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+type Factory = Arc<dyn Fn(&[f64]) -> SharedPotential + Send + Sync>;
+
+#[derive(Clone)]
+struct Entry {
+    // names and defaults, the factory gets the values in this order
+    parameters: Vec<(String, f64)>,
+    factory: Factory,
+}
+
+// Potentials by name, so the CLI and the TUI can refer to "square", "harmonic(omega=2)" or
+// "morse(d=10,a=1)" the same way. Starts with the built-in potentials, more can be added at runtime
+// with register.
+static REGISTRY: OnceLock<RwLock<HashMap<String, Entry>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<String, Entry>> {
+    REGISTRY.get_or_init(|| {
+        let mut entries = HashMap::new();
+        for (name, potential) in potentials::POTENTIALS
+            .iter()
+            .chain(potentials::BARRIERS.iter())
+        {
+            let potential = *potential;
+            entries.insert(
+                name.to_string(),
+                Entry {
+                    parameters: vec![],
+                    factory: Arc::new(move |_| Arc::new(potential)),
+                },
+            );
+        }
+        let mut builtin = |name: &str, parameters: &[(&str, f64)], factory: Factory| {
+            entries.insert(
+                name.to_string(),
+                Entry {
+                    parameters: parameters
+                        .iter()
+                        .map(|(p, d)| (p.to_string(), *d))
+                        .collect(),
+                    factory,
+                },
+            );
+        };
+        // 1/2 omega^2 x^2, E_n = hbar omega (n + 1/2) for a mass of 1
+        builtin(
+            "harmonic",
+            &[("omega", 1.0)],
+            Arc::new(|p| {
+                let omega = p[0];
+                Arc::new(move |x| 0.5 * omega * omega * x * x)
+            }),
+        );
+        builtin(
+            "morse",
+            &[("d", 10.0), ("a", 1.0), ("x0", 0.0)],
+            Arc::new(|p| {
+                let (d, a, x0) = (p[0], p[1], p[2]);
+                Arc::new(move |x| d * (1.0 - (-a * (x - x0)).exp()).powi(2))
+            }),
+        );
+        RwLock::new(entries)
+    })
+}
+
+// adds or replaces a potential, the factory gets the values of the parameters in their order
+pub fn register<G>(name: &str, parameters: &[(&str, f64)], factory: G)
+where
+    G: Fn(&[f64]) -> SharedPotential + Send + Sync + 'static,
+{
+    registry().write().unwrap().insert(
+        name.to_string(),
+        Entry {
+            parameters: parameters
+                .iter()
+                .map(|(p, d)| (p.to_string(), *d))
+                .collect(),
+            factory: Arc::new(factory),
+        },
+    );
+}
+
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = registry().read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+// parameters with their defaults
+pub fn schema(name: &str) -> Option<Vec<(String, f64)>> {
+    registry()
+        .read()
+        .unwrap()
+        .get(name)
+        .map(|entry| entry.parameters.clone())
+}
+
+// "name" or "name(key=value, ...)"
+pub fn parse_spec(spec: &str) -> Result<(String, Vec<(String, f64)>), String> {
+    let spec = spec.trim();
+    let (name, arguments) = match spec.find('(') {
+        None => (spec, ""),
+        Some(open) => {
+            let arguments = spec[open + 1..]
+                .strip_suffix(')')
+                .ok_or(format!("missing ')' in '{}'", spec))?;
+            (spec[..open].trim(), arguments)
+        }
+    };
+    if name.is_empty() {
+        return Err(format!("missing potential name in '{}'", spec));
+    }
+
+    let mut values = vec![];
+    for argument in arguments.split(',').filter(|a| !a.trim().is_empty()) {
+        let (key, value) = argument
+            .split_once('=')
+            .ok_or(format!("'{}' isn't of the form key=value", argument.trim()))?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid value '{}' for {}", value.trim(), key.trim()))?;
+        values.push((key.trim().to_string(), value));
+    }
+    Ok((name.to_string(), values))
+}
+
+pub fn resolve(spec: &str) -> Result<SharedPotential, String> {
+    let (name, values) = parse_spec(spec)?;
+    let entry = registry()
+        .read()
+        .unwrap()
+        .get(&name)
+        .cloned()
+        .ok_or(format!("unknown potential '{}'", name))?;
+
+    let mut arguments: Vec<f64> = entry.parameters.iter().map(|(_, d)| *d).collect();
+    for (key, value) in values {
+        let i = entry
+            .parameters
+            .iter()
+            .position(|(p, _)| *p == key)
+            .ok_or(format!(
+                "{} has no parameter '{}', expected one of {:?}",
+                name,
+                key,
+                entry
+                    .parameters
+                    .iter()
+                    .map(|(p, _)| p)
+                    .collect::<Vec<&String>>()
+            ))?;
+        arguments[i] = value;
+    }
+    Ok((entry.factory)(&arguments))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin_potentials() {
+        let square = resolve("square").unwrap();
+        assert_eq!(square(3.0), 9.0);
+
+        let harmonic = resolve("harmonic(omega=2)").unwrap();
+        assert_eq!(harmonic(1.5), 0.5 * 4.0 * 1.5 * 1.5);
+
+        let morse = resolve(" morse( a = 2, d=5 ) ").unwrap();
+        assert_eq!(morse(0.0), 0.0);
+        assert!((morse(1.0) - 5.0 * (1.0 - (-2.0f64).exp()).powi(2)).abs() < 1e-12);
+        assert_eq!(schema("morse").unwrap()[2], ("x0".to_string(), 0.0));
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert!(resolve("nonexistent").is_err());
+        assert!(resolve("harmonic(omega=2").is_err());
+        assert!(resolve("harmonic(omega)").is_err());
+        assert!(resolve("harmonic(omega=fast)").is_err());
+        assert!(resolve("harmonic(mass=2)").err().unwrap().contains("omega"));
+        assert!(resolve("(omega=2)").is_err());
+    }
+
+    #[test]
+    fn registered_at_runtime() {
+        register("shifted_square", &[("shift", 0.0)], |p| {
+            let shift = p[0];
+            Arc::new(move |x| (x - shift).powi(2))
+        });
+        assert!(names().contains(&"shifted_square".to_string()));
+        let potential = resolve("shifted_square(shift=1)").unwrap();
+        assert_eq!(potential(1.0), 0.0);
+
+        let energy = energy::nth_energy(0, 1.0, UNITS.hbar, &|x| potential(x), APPROX_INF);
+        assert!((energy - 0.5 * 2.0f64.sqrt()).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::*;
+use ordinal::Ordinal;
+use serde::Serialize;
+
+// real energies the actions are sampled at around the current estimate, their polynomial is
+// continued to the complex energy
+const INTERPOLATION_NODES: usize = 7;
+const NEWTON_ITERATIONS: usize = 50;
+// relative size of the last Newton step at convergence
+const TOLERANCE: f64 = 1e-12;
+const BISECTION_STEPS: usize = 200;
+
+// Quasibound state of a well behind a barrier, the pole of the S-matrix at E_r - i Gamma / 2.
+#[derive(Serialize, Clone, Debug)]
+pub struct Resonance {
+    pub n: usize,
+    pub energy: f64,
+    pub width: f64,
+    // hbar / T e^(-2 theta) at the Bohr-Sommerfeld energy of the well, the first order of width
+    pub gamow_width: f64,
+    pub iterations: usize,
+}
+
+impl Resonance {
+    pub fn pole(&self) -> Complex64 {
+        complex(self.energy, -self.width / 2.0)
+    }
+
+    pub fn lifetime(&self) -> f64 {
+        UNITS.hbar / self.width
+    }
+}
+
+struct Momentum<'a, F: Fn(f64) -> f64 + Sync + ?Sized> {
+    potential: &'a F,
+    mass: f64,
+    energy: f64,
+}
+
+// |p| / hbar, real in the well and imaginary under the barrier
+impl<F: Fn(f64) -> f64 + Sync + ?Sized> Func<f64, f64> for Momentum<'_, F> {
+    fn eval(&self, x: f64) -> f64 {
+        let v = (self.potential)(x);
+        if v.is_finite() {
+            (2.0 * self.mass * (v - self.energy)).abs().sqrt() / UNITS.hbar
+        } else {
+            0.0
+        }
+    }
+}
+
+// the highest barrier and the lowest well left of it, escape is only possible to the right
+struct Geometry {
+    range: (f64, f64),
+    bottom: Point<f64, f64>,
+    top: Point<f64, f64>,
+}
+
+impl Geometry {
+    fn new<F: Fn(f64) -> f64 + Sync + ?Sized>(
+        potential: &F,
+        range: (f64, f64),
+    ) -> Result<Geometry, String> {
+        // in the tail of a decaying barrier rounding errors make tiny extrema, they are never the
+        // highest maximum
+        let top = analysis::find_maxima(potential, range)
+            .into_iter()
+            .max_by(|a, b| cmp_f64(&a.y, &b.y))
+            .ok_or("the potential has no barrier")?;
+        let bottom = analysis::find_minima(potential, (range.0, top.x))
+            .into_iter()
+            .min_by(|a, b| cmp_f64(&a.y, &b.y))
+            .ok_or("there is no well left of the barrier")?;
+        Ok(Geometry { range, bottom, top })
+    }
+
+    // actions of the well and of the barrier at a real energy, integrated between the turning
+    // points on Chebyshev grids to resolve the square root edges
+    fn actions<F: Fn(f64) -> f64 + Sync + ?Sized>(
+        &self,
+        potential: &F,
+        mass: f64,
+        energy: f64,
+    ) -> (f64, f64) {
+        let a = crossing(potential, self.range.0, self.bottom.x, energy);
+        let b = crossing(potential, self.bottom.x, self.top.x, energy);
+        let c = crossing(potential, self.top.x, self.range.1, energy);
+        let momentum = Momentum {
+            potential,
+            mass,
+            energy,
+        };
+        (
+            Grid::chebyshev((a, b), INTEG_STEPS).integrate(&momentum),
+            Grid::chebyshev((b, c), INTEG_STEPS).integrate(&momentum),
+        )
+    }
+}
+
+// where V crosses the energy between a and b, V - energy has to change its sign
+fn crossing<F: Fn(f64) -> f64 + ?Sized>(potential: &F, mut a: f64, mut b: f64, energy: f64) -> f64 {
+    let sign_a = (potential(a) - energy).signum();
+    for _ in 0..BISECTION_STEPS {
+        let middle = (a + b) / 2.0;
+        if middle <= a.min(b) || middle >= a.max(b) {
+            break;
+        }
+        if (potential(middle) - energy).signum() == sign_a {
+            a = middle;
+        } else {
+            b = middle;
+        }
+    }
+    (a + b) / 2.0
+}
+
+// Lagrange polynomial through samples of f at real points, evaluated at a complex z. Close to
+// the real axis it continues an analytic f.
+fn continue_analytically(nodes: &[f64], values: &[f64], z: Complex64) -> Complex64 {
+    (0..nodes.len())
+        .map(|i| {
+            let basis: Complex64 = (0..nodes.len())
+                .filter(|j| *j != i)
+                .map(|j| (z - nodes[j]) / (nodes[i] - nodes[j]))
+                .product();
+            basis * values[i]
+        })
+        .sum()
+}
+
+// Locates the pole of the nth quasibound state of the well left of the highest barrier in range,
+// escape is only possible through the barrier to the right. The connection formulas with an outgoing wave
+// beyond the barrier give the condition
+//     cot W(E) = i/4 e^(-2 theta(E))
+// with the action W of the well and theta of the barrier. Both are continued from the real axis to
+// complex E and the condition is solved with Newton's method, starting at the Bohr-Sommerfeld
+// energy of the well and the Gamow width.
+pub fn resonance<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    n: usize,
+    mass: f64,
+    potential: &F,
+    range: (f64, f64),
+) -> Result<Resonance, String> {
+    let geometry = Geometry::new(potential, range)?;
+    let real_energy = energy::nth_energy(
+        n,
+        mass,
+        UNITS.hbar,
+        &|x| potential(x),
+        (range.0, geometry.top.x),
+    );
+    if real_energy >= geometry.top.y {
+        return Err(format!(
+            "the {} level of the well at {} is above the barrier at {}",
+            Ordinal(n),
+            real_energy,
+            geometry.top.y
+        ));
+    }
+    if potential(range.1) >= real_energy {
+        return Err(format!(
+            "the {} level at {} is bound, the potential at {} is above it",
+            Ordinal(n),
+            real_energy,
+            range.1
+        ));
+    }
+
+    // condition and its slope at e, from actions sampled on the real axis around e
+    let condition = |e: Complex64| -> Result<(Complex64, Complex64), String> {
+        let radius = 0.1 * f64::min(e.re - geometry.bottom.y, geometry.top.y - e.re);
+        if radius.is_nan() || radius <= 0.0 {
+            return Err(format!("left the well at E = {}", e));
+        }
+        let nodes = Grid::chebyshev((e.re - radius, e.re + radius), INTERPOLATION_NODES).points();
+        let (w, theta): (Vec<f64>, Vec<f64>) = nodes
+            .par_iter()
+            .map(|e| geometry.actions(potential, mass, *e))
+            .unzip();
+        let f = |e: Complex64| {
+            let w = continue_analytically(&nodes, &w, e);
+            let theta = continue_analytically(&nodes, &theta, e);
+            w.cos() - complex(0.0, 0.25) * (-2.0 * theta).exp() * w.sin()
+        };
+        let h = radius * 1e-3;
+        Ok((f(e), (f(e + h) - f(e - h)) / (2.0 * h)))
+    };
+
+    // first order in e^(-2 theta): Gamma = e^(-2 theta) / (2 dW/dE)
+    let (_, slope) = condition(complex(real_energy, 0.0))?;
+    let (_, theta) = geometry.actions(potential, mass, real_energy);
+    let gamow_width = (-2.0 * theta).exp() / (2.0 * slope.norm());
+
+    let mut e = complex(real_energy, -gamow_width / 2.0);
+    for iterations in 1..=NEWTON_ITERATIONS {
+        let (value, slope) = condition(e)?;
+        let step = value / slope;
+        e -= step;
+        if step.norm() <= TOLERANCE * e.norm().max(1.0) {
+            return Ok(Resonance {
+                n,
+                energy: e.re,
+                width: -2.0 * e.im,
+                gamow_width,
+                iterations,
+            });
+        }
+    }
+    Err(format!(
+        "Newton's method didn't converge for the {} resonance, last estimate {}",
+        Ordinal(n),
+        e
+    ))
+}
+
+// the resonances n_range, skipping levels above the barrier
+pub fn resonances<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    mass: f64,
+    potential: &F,
+    n_range: std::ops::Range<usize>,
+    range: (f64, f64),
+) -> Vec<Resonance> {
+    n_range
+        .filter_map(|n| resonance(n, mass, potential, range).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // harmonic well with a barrier of height 16 / e at x = 4 to the right
+    fn leaky_well(x: f64) -> f64 {
+        if x < 0.0 {
+            x * x
+        } else {
+            x * x * (-x * x / 16.0).exp()
+        }
+    }
+
+    #[test]
+    fn thick_barrier_matches_gamow() {
+        let resonance = resonance(0, 1.0, &leaky_well, APPROX_INF).unwrap();
+
+        // nearly the ground state of the harmonic oscillator, E = 1 / sqrt(2)
+        assert!((resonance.energy - 0.5f64.sqrt()).abs() < 1e-2);
+        assert!(resonance.width > 0.0);
+        assert!(
+            (resonance.width - resonance.gamow_width).abs() < 1e-3 * resonance.gamow_width,
+            "{:?}",
+            resonance
+        );
+        assert!(resonance.width < 1e-3);
+    }
+
+    #[test]
+    fn widths_grow_towards_the_top() {
+        let resonances = resonances(1.0, &leaky_well, 0..6, APPROX_INF);
+        assert!(resonances.len() >= 3);
+        for pair in resonances.windows(2) {
+            assert!(pair[0].energy < pair[1].energy);
+            assert!(pair[0].width < pair[1].width);
+        }
+
+        // the pole satisfies the continued quantization condition, not the first order estimate
+        let highest = resonances.last().unwrap();
+        assert!((highest.width - highest.gamow_width).abs() > 1e-3 * highest.gamow_width);
+        assert!(highest.pole().im < 0.0);
+    }
+
+    #[test]
+    fn well_without_barrier() {
+        assert!(resonance(0, 1.0, &potentials::square, APPROX_INF).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::*;
+
+// psi computed elsewhere, e.g. by a Numerov code, given at sorted points and linearly
+// interpolated between them, 0 outside of them
+#[derive(Clone)]
+pub struct SampledWaveFunction {
+    values: Vec<Point<f64, Complex64>>,
+}
+
+impl SampledWaveFunction {
+    pub fn new(mut values: Vec<Point<f64, Complex64>>) -> Result<SampledWaveFunction, String> {
+        if values.len() < 2 {
+            return Err("a sampled wave function needs at least two points".to_string());
+        }
+        if values.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return Err("a sampled wave function has to be finite".to_string());
+        }
+        values.sort_by(|a, b| cmp_f64(&a.x, &b.x));
+        if values.windows(2).any(|w| w[0].x == w[1].x) {
+            return Err("the x of the samples have to be distinct".to_string());
+        }
+        Ok(SampledWaveFunction { values })
+    }
+
+    // Columns x, Re(psi) and optionally Im(psi) separated by whitespace, empty lines and lines
+    // starting with '#' are skipped.
+    pub fn parse(text: &str) -> Result<SampledWaveFunction, String> {
+        let values = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(i, line)| {
+                let columns = line
+                    .split_whitespace()
+                    .map(|c| c.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|e| format!("line {}: {}", i + 1, e))?;
+                match columns[..] {
+                    [x, re] => Ok(Point {
+                        x,
+                        y: complex(re, 0.0),
+                    }),
+                    [x, re, im] => Ok(Point {
+                        x,
+                        y: complex(re, im),
+                    }),
+                    _ => Err(format!(
+                        "line {}: expected 2 or 3 columns, found {}",
+                        i + 1,
+                        columns.len()
+                    )),
+                }
+            })
+            .collect::<Result<Vec<Point<f64, Complex64>>, String>>()?;
+        SampledWaveFunction::new(values)
+    }
+
+    pub fn read(path: &Path) -> Result<SampledWaveFunction, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+        SampledWaveFunction::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn get_values(&self) -> &[Point<f64, Complex64>] {
+        &self.values
+    }
+
+    pub fn get_range(&self) -> (f64, f64) {
+        (self.values[0].x, self.values.last().unwrap().x)
+    }
+
+    // Scaled to the norm of wave_function on the samples and rotated so that their overlap is real
+    // and positive. The samples don't have to cover the view, only the points are compared.
+    pub fn aligned_to(&self, wave_function: &dyn Func<f64, Complex64>) -> SampledWaveFunction {
+        let reference: Vec<Complex64> = self
+            .values
+            .par_iter()
+            .map(|p| wave_function.eval(p.x))
+            .collect();
+        let integrate_samples = |f: &dyn Fn(Complex64, Complex64) -> Complex64| {
+            integrals::integrate(
+                self.values
+                    .iter()
+                    .zip(&reference)
+                    .map(|(p, psi)| Point {
+                        x: p.x,
+                        y: f(p.y, *psi),
+                    })
+                    .collect(),
+                TRAPEZE_PER_THREAD,
+            )
+        };
+        let own_norm = integrate_samples(&|sample, _| sample.norm_sqr().into()).re;
+        let norm = integrate_samples(&|_, psi| psi.norm_sqr().into()).re;
+        let overlap = integrate_samples(&|sample, psi| sample.conj() * psi);
+
+        let scale = if own_norm > 0.0 {
+            (norm / own_norm).sqrt()
+        } else {
+            1.0
+        };
+        let rotation = if overlap.norm() > 0.0 {
+            overlap / overlap.norm()
+        } else {
+            complex(1.0, 0.0)
+        };
+        SampledWaveFunction {
+            values: self
+                .values
+                .iter()
+                .map(|p| Point {
+                    x: p.x,
+                    y: p.y * scale * rotation,
+                })
+                .collect(),
+        }
+    }
+
+    // largest |psi - sample| relative to the largest |psi| at the samples
+    pub fn max_deviation(&self, wave_function: &dyn Func<f64, Complex64>) -> f64 {
+        let (deviation, max) = self
+            .values
+            .par_iter()
+            .map(|p| {
+                let psi = wave_function.eval(p.x);
+                ((psi - p.y).norm(), psi.norm())
+            })
+            .reduce(|| (0.0, 0.0), |a, b| (a.0.max(b.0), a.1.max(b.1)));
+        if max > 0.0 {
+            deviation / max
+        } else {
+            deviation
+        }
+    }
+}
+
+impl Func<f64, Complex64> for SampledWaveFunction {
+    fn eval(&self, x: f64) -> Complex64 {
+        let (first, last) = self.get_range();
+        if !(first..=last).contains(&x) {
+            return complex(0.0, 0.0);
+        }
+        let i = self.values.partition_point(|p| p.x < x).max(1);
+        let (left, right) = (&self.values[i - 1], &self.values[i]);
+        left.y + (right.y - left.y) * ((x - left.x) / (right.x - left.x))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Gaussian;
+
+    impl Func<f64, Complex64> for Gaussian {
+        fn eval(&self, x: f64) -> Complex64 {
+            complex((-x * x / 2.0).exp(), 0.0)
+        }
+    }
+
+    #[test]
+    fn parse_and_align() {
+        let text = (0..=400)
+            .map(|i| {
+                let x = i as f64 / 40.0 - 5.0;
+                // another sign and scale, as another code would write it
+                format!("{} {}\n", x, -3.0 * (-x * x / 2.0).exp())
+            })
+            .collect::<String>();
+        let sampled = SampledWaveFunction::parse(&format!("# x psi\n\n{}", text)).unwrap();
+        assert_eq!(sampled.get_values().len(), 401);
+        assert_eq!(sampled.get_range(), (-5.0, 5.0));
+        assert!(complex_compare(
+            sampled.eval(0.0),
+            complex(-3.0, 0.0),
+            1e-12
+        ));
+        assert_eq!(sampled.eval(6.0), complex(0.0, 0.0));
+        // halfway between two samples
+        let expected = (sampled.eval(0.0) + sampled.eval(0.025)) / 2.0;
+        assert!(complex_compare(sampled.eval(0.0125), expected, 1e-12));
+
+        assert!(sampled.max_deviation(&Gaussian) > 1.0);
+        let aligned = sampled.aligned_to(&Gaussian);
+        assert!(aligned.max_deviation(&Gaussian) < 1e-6);
+
+        // a complex phase is removed as well
+        let rotated = SampledWaveFunction::parse("-1 0 1\n0 0 2\n1 0 1").unwrap();
+        let aligned = rotated.aligned_to(&Gaussian);
+        assert!(aligned.eval(0.0).im.abs() < 1e-12 && aligned.eval(0.0).re > 0.0);
+
+        assert!(SampledWaveFunction::parse("1 2 3 4").is_err());
+        assert!(SampledWaveFunction::parse("1 a").is_err());
+        assert!(SampledWaveFunction::parse("1 2").is_err());
+        assert!(SampledWaveFunction::parse("1 2\n1 3").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+const MAX_LEVEL: usize = 6;
+const NORM_TOLERANCE: f64 = 1e-2;
+// relative to the largest |psi|^2
+const SYMMETRY_TOLERANCE: f64 = 1e-2;
+// sign changes between samples smaller than this fraction of the largest |psi| are noise in the
+// tails, not nodes
+const NODE_THRESHOLD: f64 = 1e-3;
+const SAMPLE_POINTS: usize = 2000;
+// largest |psi_uniform - psi| relative to the largest |psi|. The patched approximation keeps the
+// phase error of its linearized airy functions next to their ranges, most of all at the lowest
+// levels: 9.6% at the ground state of x^2, 6.2% at n = 3 and 3.3% at n = 6.
+const UNIFORM_TOLERANCE: f64 = 1e-1;
+
+// A single well from the registry with randomized parameters and whether it is even. Multiple
+// wells are left out, their tunnelling doublets aren't resolved. The Morse wells hold at least
+// MAX_LEVEL + 2 levels.
+fn random_potential(rng: &mut StdRng) -> (String, bool) {
+    match rng.gen_range(0..3) {
+        0 => ("square".to_string(), true),
+        1 => (
+            format!("harmonic(omega={:.3})", rng.gen_range(0.5..2.0)),
+            true,
+        ),
+        _ => (
+            format!(
+                "morse(d={:.3},a={:.3})",
+                rng.gen_range(20.0..40.0),
+                rng.gen_range(0.3..0.6)
+            ),
+            false,
+        ),
+    }
+}
+
+// whether V(-x) = V(x) within rounding at samples of APPROX_INF, the symmetry is only checked for
+// even potentials
+fn is_even(potential: &str) -> Result<bool, String> {
+    let shared = registry::resolve(potential)?;
+    let reach = f64::min(-APPROX_INF.0, APPROX_INF.1);
+    Ok((1..=SAMPLE_POINTS).all(|i| {
+        let x = i as f64 / SAMPLE_POINTS as f64 * reach;
+        let (right, left) = (shared(x), shared(-x));
+        right == left || (right - left).abs() <= 1e-12 * f64::max(right.abs(), left.abs())
+    }))
+}
+
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    pub property: &'static str,
+    pub potential: String,
+    pub n: usize,
+    pub value: f64,
+    pub passed: bool,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<14} {} n = {}: {:.3e}",
+            if self.passed { "pass" } else { "FAIL" },
+            self.property,
+            self.potential,
+            self.n,
+            self.value
+        )
+    }
+}
+
+// sign changes of the real part, samples below the threshold are skipped
+pub fn count_nodes(values: &[Point<f64, Complex64>]) -> usize {
+    let max = values.iter().map(|p| p.y.norm()).fold(0.0, f64::max);
+    let signs: Vec<f64> = values
+        .iter()
+        .filter(|p| p.y.re.abs() > NODE_THRESHOLD * max)
+        .map(|p| p.y.re.signum())
+        .collect();
+    signs.windows(2).filter(|s| s[0] != s[1]).count()
+}
+
+// The airy ranges around the turning points with their distance added on both sides, where the
+// view can't be found it is all of approx_inf and too wide to be sampled. The view if there are
+// no turning points or they can't be grouped.
+fn sample_range(wave_function: &WaveFunction) -> (f64, f64) {
+    let view = wave_function.get_view();
+    let ts = turning_points::calc_ts(&wave_function.get_phase(), view)
+        .map(|groups| groups.ts)
+        .unwrap_or_default();
+    if ts.is_empty() {
+        return view;
+    }
+    let lower = ts
+        .iter()
+        .map(|(range, _)| range.0)
+        .fold(f64::INFINITY, f64::min);
+    let upper = ts
+        .iter()
+        .map(|(range, _)| range.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let margin = upper - lower;
+    ((lower - margin).max(view.0), (upper + margin).min(view.1))
+}
+
+// max ||psi(x)|^2 - |psi(-x)|^2| relative to the largest |psi|^2, on the part of the sampled range
+// mirrored onto itself
+pub fn asymmetry(wave_function: &WaveFunction) -> f64 {
+    let range = sample_range(wave_function);
+    let half = range.1.min(-range.0);
+    if half <= 0.0 {
+        return f64::INFINITY;
+    }
+    let values = Grid::uniform((-half, half), SAMPLE_POINTS).evaluate(wave_function);
+    let max = values.iter().map(|p| p.y.norm_sqr()).fold(0.0, f64::max);
+    values
+        .iter()
+        .zip(values.iter().rev())
+        .map(|(l, r)| (l.y.norm_sqr() - r.y.norm_sqr()).abs())
+        .fold(0.0, f64::max)
+        / max
+}
+
+fn check_level(potential: &str, even: bool, n: usize) -> Result<Vec<Outcome>, String> {
+    let shared = registry::resolve(potential)?;
+    let wave_function = WaveFunction::new(
+        shared.clone(),
+        1.0,
+        n,
+        APPROX_INF,
+        VIEW_FACTOR,
+        ScalingType::Renormalize(1.0.into()),
+    );
+    let outcome = |property, value: f64, passed| Outcome {
+        property,
+        potential: potential.to_string(),
+        n,
+        value,
+        passed,
+    };
+
+    let grid = Grid::uniform(sample_range(&wave_function), SAMPLE_POINTS);
+    let values = grid.evaluate(&wave_function);
+    let norm = grid.integrate(&NormSquare { f: &wave_function });
+    let nodes = count_nodes(&values);
+    let mut outcomes = vec![
+        outcome("normalization", norm, (norm - 1.0).abs() < NORM_TOLERANCE),
+        outcome("nodes", nodes as f64, nodes == n),
+    ];
+    if even {
+        let asymmetry = asymmetry(&wave_function);
+        outcomes.push(outcome(
+            "symmetry",
+            asymmetry,
+            asymmetry < SYMMETRY_TOLERANCE,
+        ));
+    }
+
+    // Langer's uniform approximation at the same energy, it has no joints and differs from the
+    // patched one mostly next to the airy ranges
+    let uniform = WaveFunctionBuilder::new(shared.clone(), 1.0)
+        .with_scaling(ScalingType::Renormalize(1.0.into()))
+        .with_approximation(ApproximationKind::Uniform)
+        .with_energy(wave_function.get_energy())?
+        .find_turning_points()?
+        .build_parts()?
+        .scale();
+    let max = values.iter().map(|p| p.y.norm()).fold(0.0, f64::max);
+    let deviation = grid
+        .evaluate(&uniform)
+        .iter()
+        .zip(&values)
+        .map(|(u, p)| (u.y - p.y).norm())
+        .fold(0.0, f64::max)
+        / max;
+    outcomes.push(outcome("uniform", deviation, deviation < UNIFORM_TOLERANCE));
+
+    let next = energy::nth_energy(n + 1, 1.0, UNITS.hbar, &|x| shared(x), APPROX_INF);
+    let gap = next - wave_function.get_energy();
+    outcomes.push(outcome("monotonic", gap, gap > 0.0));
+    Ok(outcomes)
+}
+
+// Normalization, node count, symmetry of even potentials, agreement with the uniform approximation
+// and E_n < E_n+1 for random levels of the given registry potential, or of random ones, with unit
+// mass. The same seed checks the same levels.
+pub fn run(seed: u64, samples: usize, potential: Option<&str>) -> Result<Vec<Outcome>, String> {
+    let fixed = match potential {
+        Some(potential) => Some((potential.to_string(), is_even(potential)?)),
+        None => None,
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut outcomes = vec![];
+    for _ in 0..samples {
+        let (potential, even) = match &fixed {
+            Some(fixed) => fixed.clone(),
+            None => random_potential(&mut rng),
+        };
+        let n = rng.gen_range(0..=MAX_LEVEL);
+        // a panic of the solver is a failed check as well
+        match catch_unwind(AssertUnwindSafe(|| check_level(&potential, even, n))) {
+            Ok(checked) => outcomes.extend(checked?),
+            Err(_) => outcomes.push(Outcome {
+                property: "computation",
+                potential,
+                n,
+                value: f64::NAN,
+                passed: false,
+            }),
+        }
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nodes_of_samples() {
+        let samples = |f: &dyn Fn(f64) -> f64| -> Vec<Point<f64, Complex64>> {
+            (0..200)
+                .map(|i| {
+                    let x = i as f64 / 199.0 * 10.0 - 5.0;
+                    Point {
+                        x,
+                        y: complex(f(x), 0.0),
+                    }
+                })
+                .collect()
+        };
+        // the tails oscillate around zero far below the threshold
+        let tails = |x: f64| 1e-6 * (20.0 * x).sin();
+        assert_eq!(count_nodes(&samples(&|x| (-x * x).exp() + tails(x))), 0);
+        assert_eq!(count_nodes(&samples(&|x| x * (-x * x).exp() + tails(x))), 1);
+        assert_eq!(
+            count_nodes(&samples(&|x| (x * x - 1.0) * (-x * x).exp() + tails(x))),
+            2
+        );
+    }
+
+    #[test]
+    fn properties_of_oscillator() {
+        let outcomes = check_level("square", true, 3).unwrap();
+        assert_eq!(outcomes.len(), 5);
+        for outcome in &outcomes {
+            assert!(outcome.passed, "{}", outcome);
+        }
+        assert!(check_level("nonexistent", true, 0).is_err());
+    }
+
+    #[test]
+    fn evenness_of_potentials() {
+        assert!(is_even("square").unwrap());
+        assert!(is_even("harmonic(omega=1.5)").unwrap());
+        assert!(!is_even("morse(d=30,a=0.5)").unwrap());
+        assert!(is_even("nonexistent").is_err());
+    }
+}
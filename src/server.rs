@@ -0,0 +1,473 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// only reachable from the same machine unless another host is given
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_POINTS: usize = 1000;
+const MAX_POINTS: usize = NUMBER_OF_POINTS;
+// highest state, the energy scan grows with n
+const MAX_N: usize = 200;
+// a request is a few numbers and a potential spec
+const MAX_BODY: usize = 1 << 16;
+// request line and headers together
+const MAX_HEADER: usize = 1 << 13;
+// connections handled at once, further ones are answered with 503
+const MAX_CONNECTIONS: usize = 16;
+// a client that doesn't send or receive for this long gives up its connection
+const TIMEOUT: Duration = Duration::from_secs(10);
+// a wave function taking longer is cancelled and answered with 503
+const COMPUTE_DEADLINE: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Deserialize)]
+pub struct WaveFunctionRequest {
+    pub potential: String,
+    pub mass: f64,
+    pub n: usize,
+    pub points: Option<usize>,
+    pub view: Option<(f64, f64)>,
+}
+
+#[derive(Serialize)]
+pub struct WaveFunctionResponse {
+    pub potential: String,
+    pub mass: f64,
+    pub n: usize,
+    pub energy: f64,
+    pub view: (f64, f64),
+    pub x: Vec<f64>,
+    pub re: Vec<f64>,
+    pub im: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    Malformed(String),
+    // Content-Length above MAX_BODY
+    TooLarge(usize),
+    // request line and headers longer than MAX_HEADER
+    HeaderTooLarge,
+}
+
+impl ParseError {
+    fn response(&self) -> HttpResponse {
+        match self {
+            ParseError::Malformed(message) => HttpResponse::error(400, message),
+            ParseError::TooLarge(length) => HttpResponse::error(
+                413,
+                &format!(
+                    "the body has {} bytes, at most {} are accepted",
+                    length, MAX_BODY
+                ),
+            ),
+            ParseError::HeaderTooLarge => HttpResponse::error(
+                431,
+                &format!("the headers have more than {} bytes", MAX_HEADER),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ComputeError {
+    Invalid(String),
+    // not done within COMPUTE_DEADLINE
+    TimedOut,
+}
+
+impl ComputeError {
+    fn response(&self) -> HttpResponse {
+        match self {
+            ComputeError::Invalid(message) => HttpResponse::error(400, message),
+            ComputeError::TimedOut => HttpResponse::error(
+                503,
+                &format!(
+                    "the wave function took longer than {} s",
+                    COMPUTE_DEADLINE.as_secs()
+                ),
+            ),
+        }
+    }
+}
+
+impl From<String> for ComputeError {
+    fn from(message: String) -> ComputeError {
+        ComputeError::Invalid(message)
+    }
+}
+
+impl HttpResponse {
+    fn ok(body: String) -> HttpResponse {
+        HttpResponse { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            body: serde_json::to_string(&ErrorResponse {
+                error: message.to_string(),
+            })
+            .unwrap(),
+        }
+    }
+
+    fn reason(&self) -> &str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+pub fn parse_request<R: BufRead>(reader: &mut R) -> Result<HttpRequest, ParseError> {
+    let malformed = |e: &dyn std::fmt::Display| ParseError::Malformed(e.to_string());
+    let mut head = Read::take(&mut *reader, MAX_HEADER as u64);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)
+        .map_err(|e| malformed(&e))?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| malformed(&"missing method"))?
+        .to_string();
+    let path = parts
+        .next()
+        .ok_or_else(|| malformed(&"missing path"))?
+        .to_string();
+
+    let mut content_length = 0;
+    loop {
+        if head.limit() == 0 {
+            return Err(ParseError::HeaderTooLarge);
+        }
+        let mut header = String::new();
+        head.read_line(&mut header).map_err(|e| malformed(&e))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| malformed(&"invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(ParseError::TooLarge(content_length));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| malformed(&e))?;
+
+    Ok(HttpRequest { method, path, body })
+}
+
+// Nothing is bound above the lower of V at the ends of APPROX_INF, like in the energy scan. A well
+// has a harmonic estimate below it, a flat bottomed one only a minimum.
+fn has_bound_states(potential: &SharedPotential, mass: f64) -> bool {
+    let potential = |x| potential(x);
+    let continuum = f64::min(potential(APPROX_INF.0), potential(APPROX_INF.1));
+    energy::harmonic_estimates(1, mass, UNITS.hbar, &potential, APPROX_INF)
+        .first()
+        .is_some_and(|e| *e < continuum)
+        || analysis::find_minima(&potential, APPROX_INF)
+            .iter()
+            .any(|m| m.y < continuum)
+}
+
+fn compute_wave_function(
+    request: &WaveFunctionRequest,
+) -> Result<WaveFunctionResponse, ComputeError> {
+    compute_wave_function_within(request, COMPUTE_DEADLINE)
+}
+
+// the solve is cancelled once it runs longer than deadline
+fn compute_wave_function_within(
+    request: &WaveFunctionRequest,
+    deadline: Duration,
+) -> Result<WaveFunctionResponse, ComputeError> {
+    let potential = registry::resolve(&request.potential)?;
+    let points = request.points.unwrap_or(DEFAULT_POINTS);
+    if !(2..=MAX_POINTS).contains(&points) {
+        return Err(format!("points has to be between 2 and {}", MAX_POINTS).into());
+    }
+    if request.n > MAX_N {
+        return Err(format!("n has to be at most {}", MAX_N).into());
+    }
+    // also rejects NaN and infinity
+    if !request.mass.is_finite() || request.mass <= 0.0 {
+        return Err("mass has to be positive".to_string().into());
+    }
+    if !has_bound_states(&potential, request.mass) {
+        return Err(format!("{} has no bound states", request.potential).into());
+    }
+
+    let (mass, n, view) = (request.mass, request.n, request.view);
+    let task = task::spawn(move || {
+        let wave_function = WaveFunction::new(
+            potential,
+            mass,
+            n,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let view = view.unwrap_or(wave_function.get_view());
+        let values = catch_unwind(AssertUnwindSafe(|| {
+            evaluate_function_between(&wave_function, view.0, view.1, points)
+        }))
+        .map_err(|_| "failed to evaluate the wave function".to_string())?;
+        Ok::<_, String>((wave_function.get_energy(), view, values))
+    });
+    let started = Instant::now();
+    while !task.is_finished() {
+        if started.elapsed() > deadline {
+            task.cancel();
+            return Err(ComputeError::TimedOut);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let (energy, view, values) = task
+        .join()
+        .map_err(|_| "failed to compute the wave function".to_string())??;
+
+    Ok(WaveFunctionResponse {
+        potential: request.potential.clone(),
+        mass: request.mass,
+        n: request.n,
+        energy,
+        view,
+        x: values.iter().map(|p| p.x).collect(),
+        re: values.iter().map(|p| p.y.re).collect(),
+        im: values.iter().map(|p| p.y.im).collect(),
+    })
+}
+
+pub fn handle(request: &HttpRequest) -> HttpResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/potentials") => {
+            HttpResponse::ok(serde_json::to_string(&registry::names()).unwrap())
+        }
+        ("POST", "/wavefunction") => {
+            let wave_function_request: WaveFunctionRequest =
+                match serde_json::from_slice(&request.body) {
+                    Ok(r) => r,
+                    Err(e) => return HttpResponse::error(400, &e.to_string()),
+                };
+            match compute_wave_function(&wave_function_request) {
+                Ok(response) => HttpResponse::ok(serde_json::to_string(&response).unwrap()),
+                Err(e) => e.response(),
+            }
+        }
+        (_, "/potentials") | (_, "/wavefunction") => HttpResponse::error(405, "method not allowed"),
+        _ => HttpResponse::error(404, "not found"),
+    }
+}
+
+fn handle_connection(mut stream: TcpStream) {
+    // idle clients would otherwise hold one of the MAX_CONNECTIONS forever
+    if let Err(e) = stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+    {
+        println!("Failed to set the timeouts: {}", e);
+        return;
+    }
+    let response = match parse_request(&mut BufReader::new(&mut stream)) {
+        Ok(request) => {
+            println!("{} {}", request.method, request.path);
+            handle(&request)
+        }
+        Err(e) => e.response(),
+    };
+    respond(&mut stream, &response);
+}
+
+fn respond(stream: &mut TcpStream, response: &HttpResponse) {
+    let message = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        response.body.len(),
+        response.body
+    );
+    if let Err(e) = stream.write_all(message.as_bytes()) {
+        println!("Failed to send response: {}", e);
+    }
+}
+
+// One thread per connection up to MAX_CONNECTIONS at once. Pass 0.0.0.0 as host to accept
+// connections from other machines.
+pub fn serve(host: &str, port: u16) {
+    let listener = TcpListener::bind((host, port)).unwrap();
+    println!("Listening on {}:{}", host, port);
+
+    static OPEN: AtomicUsize = AtomicUsize::new(0);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(mut stream) => {
+                if OPEN.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    OPEN.fetch_sub(1, Ordering::SeqCst);
+                    respond(
+                        &mut stream,
+                        &HttpResponse::error(503, "too many connections"),
+                    );
+                    continue;
+                }
+                std::thread::spawn(move || {
+                    handle_connection(stream);
+                    OPEN.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            Err(e) => println!("Connection failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_post_request() {
+        let raw = "POST /wavefunction HTTP/1.1\r\nHost: localhost\r\ncontent-length: 13\r\n\r\n{\"mass\": 1.0}";
+        let request = parse_request(&mut raw.as_bytes()).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/wavefunction");
+        assert_eq!(request.body, b"{\"mass\": 1.0}");
+
+        let huge = format!(
+            "POST /wavefunction HTTP/1.1\r\ncontent-length: {}\r\n\r\n",
+            1usize << 40
+        );
+        let error = parse_request(&mut huge.as_bytes()).err().unwrap();
+        assert_eq!(error, ParseError::TooLarge(1 << 40));
+        assert_eq!(error.response().status, 413);
+
+        let long = format!(
+            "GET /potentials HTTP/1.1\r\nCookie: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER)
+        );
+        let error = parse_request(&mut long.as_bytes()).err().unwrap();
+        assert_eq!(error, ParseError::HeaderTooLarge);
+        assert_eq!(error.response().status, 431);
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let request = |method: &str, path: &str, body: &str| HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.as_bytes().to_vec(),
+        };
+
+        assert_eq!(handle(&request("GET", "/", "")).status, 404);
+        assert_eq!(handle(&request("GET", "/wavefunction", "")).status, 405);
+        assert_eq!(handle(&request("POST", "/wavefunction", "{")).status, 400);
+        assert_eq!(
+            handle(&request(
+                "POST",
+                "/wavefunction",
+                "{\"potential\": \"unknown\", \"mass\": 1.0, \"n\": 0}"
+            ))
+            .status,
+            400
+        );
+        assert_eq!(
+            handle(&request(
+                "POST",
+                "/wavefunction",
+                "{\"potential\": \"square\", \"mass\": 1.0, \"n\": 100000}"
+            ))
+            .status,
+            400
+        );
+        assert_eq!(handle(&request("GET", "/potentials", "")).status, 200);
+
+        // JSON has no infinity, but the request may come from elsewhere
+        let infinite_mass = WaveFunctionRequest {
+            potential: "square".to_string(),
+            mass: f64::INFINITY,
+            n: 0,
+            points: None,
+            view: None,
+        };
+        assert!(compute_wave_function(&infinite_mass).is_err());
+
+        // the energy scan would run up to its limit
+        let unbound = WaveFunctionRequest {
+            potential: "barrier".to_string(),
+            mass: 1.0,
+            n: 0,
+            points: None,
+            view: None,
+        };
+        assert_eq!(
+            compute_wave_function(&unbound).err(),
+            Some(ComputeError::Invalid(
+                "barrier has no bound states".to_string()
+            ))
+        );
+        let response = handle(&request(
+            "POST",
+            "/wavefunction",
+            "{\"potential\": \"barrier\", \"mass\": 1.0, \"n\": 0}",
+        ));
+        assert_eq!(response.status, 400);
+        assert!(
+            response.body.contains("no bound states"),
+            "{}",
+            response.body
+        );
+    }
+
+    #[test]
+    fn expired_deadline_cancels_the_solve() {
+        let request = WaveFunctionRequest {
+            potential: "square".to_string(),
+            mass: 1.0,
+            n: 0,
+            points: None,
+            view: None,
+        };
+        let started = Instant::now();
+        let error = compute_wave_function_within(&request, Duration::ZERO)
+            .err()
+            .unwrap();
+        assert_eq!(error, ComputeError::TimedOut);
+        assert_eq!(error.response().status, 503);
+        // answered after the first poll instead of when the solve is done
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
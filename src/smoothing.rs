@@ -0,0 +1,186 @@
+use crate::*;
+
+// grid spacing of the interpolated potential in smoothing widths
+const SAMPLES_PER_WIDTH: f64 = 4.0;
+// the Gaussian is cut off this many widths from its center
+const CUTOFF: f64 = 8.0;
+
+// erfc(x) with a relative error below 1.2e-7 everywhere (Numerical Recipes' erfcc), smooth in x
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let coefficients = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ];
+    let poly = coefficients.iter().rev().fold(0.0, |acc, c| c + t * acc);
+    let ans = t * (-z * z + poly).exp();
+    if x >= 0.0 {
+        ans
+    } else {
+        2.0 - ans
+    }
+}
+
+// standard normal distribution and density
+fn cdf(u: f64) -> f64 {
+    0.5 * erfc(-u / f64::consts::SQRT_2)
+}
+
+fn density(u: f64) -> f64 {
+    (-0.5 * u * u).exp() / (2.0 * f64::consts::PI).sqrt()
+}
+
+// where the smoothing moved V by more than the tolerance, with the largest deviation in the range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmoothingChange {
+    pub range: (f64, f64),
+    pub max_deviation: f64,
+}
+
+// V convolved with a Gaussian of standard deviation width, for potentials with kinks or steps whose
+// finite-difference derivatives throw off the turning point search. V is linearly interpolated on a
+// grid over range, continued linearly outside of it, and the interpolant is convolved exactly, so
+// the result is smooth however the grid falls on the steps.
+pub struct SmoothedPotential {
+    range: (f64, f64),
+    width: f64,
+    step: f64,
+    values: Vec<f64>,
+}
+
+impl SmoothedPotential {
+    pub fn new<F: Fn(f64) -> f64 + Sync + ?Sized>(
+        potential: &F,
+        range: (f64, f64),
+        width: f64,
+    ) -> SmoothedPotential {
+        assert!(width > 0.0, "the smoothing width has to be positive");
+        let n = (((range.1 - range.0) * SAMPLES_PER_WIDTH / width).ceil() as usize).max(1);
+        let step = (range.1 - range.0) / n as f64;
+        let values = (0..=n)
+            .into_par_iter()
+            .map(|i| potential(range.0 + i as f64 * step))
+            .collect();
+        SmoothedPotential {
+            range,
+            width,
+            step,
+            values,
+        }
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+
+    fn x(&self, i: usize) -> f64 {
+        self.range.0 + i as f64 * self.step
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        let n = self.values.len() - 1;
+        let u = |y: f64| (y - x) / self.width;
+
+        // linear continuations left and right of the grid
+        let slope = |i: usize| (self.values[i + 1] - self.values[i]) / self.step;
+        let (left, right) = (u(self.range.0), u(self.range.1));
+        let at_left = self.values[0] + slope(0) * (x - self.range.0);
+        let at_right = self.values[n] + slope(n - 1) * (x - self.range.1);
+        let mut sum = at_left * cdf(left) - slope(0) * self.width * density(left)
+            + at_right * (1.0 - cdf(right))
+            + slope(n - 1) * self.width * density(right);
+
+        let reach = CUTOFF * self.width;
+        let first = ((x - reach - self.range.0) / self.step).floor().max(0.0) as usize;
+        let last = (((x + reach - self.range.0) / self.step).ceil().max(0.0) as usize).min(n);
+        for i in first..last {
+            let (a, b) = (self.x(i), self.x(i + 1));
+            // the interpolant is values[i] + slope (y - a) = at_x + slope width u
+            let at_x = self.values[i] + slope(i) * (x - a);
+            sum += at_x * (cdf(u(b)) - cdf(u(a)))
+                + slope(i) * self.width * (density(u(a)) - density(u(b)));
+        }
+        sum
+    }
+
+    // grid points where the smoothed potential deviates from the original by more than tolerance,
+    // merged into ranges
+    pub fn changes(&self, tolerance: f64) -> Vec<SmoothingChange> {
+        let deviations = (0..self.values.len())
+            .into_par_iter()
+            .map(|i| (self.eval(self.x(i)) - self.values[i]).abs())
+            .collect::<Vec<f64>>();
+
+        let mut changes: Vec<SmoothingChange> = vec![];
+        let mut previous_changed = false;
+        for (i, deviation) in deviations.iter().enumerate() {
+            let changed = *deviation > tolerance;
+            if changed {
+                match changes.last_mut() {
+                    Some(change) if previous_changed => {
+                        change.range.1 = self.x(i);
+                        change.max_deviation = change.max_deviation.max(*deviation);
+                    }
+                    _ => changes.push(SmoothingChange {
+                        range: (self.x(i), self.x(i)),
+                        max_deviation: *deviation,
+                    }),
+                }
+            }
+            previous_changed = changed;
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn linear_potential_is_unchanged() {
+        let smoothed = SmoothedPotential::new(&|x: f64| 2.0 * x - 1.0, (-10.0, 10.0), 0.1);
+        for x in [-5.0, -0.33, 0.0, 1.234, 7.0] {
+            assert!((smoothed.eval(x) - (2.0 * x - 1.0)).abs() < 1e-9, "{}", x);
+        }
+        assert!(smoothed.changes(1e-6).is_empty());
+    }
+
+    #[test]
+    fn step_becomes_error_function() {
+        let step = |x: f64| if x < 0.3 { 0.0 } else { 1.0 };
+        let width = 0.05;
+        let smoothed = SmoothedPotential::new(&step, (-5.0, 5.0), width);
+        // the interpolant ramps the step over one grid cell, a quarter of the width
+        for x in [0.2, 0.3, 0.32, 0.45] {
+            let expected = cdf((x - 0.3) / width);
+            assert!((smoothed.eval(x) - expected).abs() < 6e-2, "{}", x);
+        }
+
+        // no kinks left for the derivatives to trip over
+        let slope = |x: f64| derivative(&|x| smoothed.eval(x), x);
+        for x in [0.25, 0.3, 0.35] {
+            let expected = density((x - 0.3) / width) / width;
+            assert!(
+                (slope(x) - expected).abs() < 0.15 * density(0.0) / width,
+                "{}",
+                x
+            );
+        }
+
+        let changes = smoothed.changes(1e-3);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].range.0 < 0.3 && changes[0].range.1 > 0.3);
+        assert!(changes[0].range.1 - changes[0].range.0 < 10.0 * width);
+        assert!(changes[0].max_deviation > 0.3);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::*;
+use serde::Serialize;
+
+// E_n(lambda) of a family of potentials V(lambda, x), energies[i][n] belongs to parameters[i].
+pub struct Sweep {
+    pub parameters: Vec<f64>,
+    pub energies: Vec<Vec<f64>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AvoidedCrossing {
+    pub parameter: f64,
+    pub gap: f64,
+    // the curves n and n + 1 approaching each other
+    pub lower: usize,
+    pub upper: usize,
+}
+
+impl Sweep {
+    pub fn new(parameters: Vec<f64>, energies: Vec<Vec<f64>>) -> Sweep {
+        assert_eq!(parameters.len(), energies.len());
+        Sweep {
+            parameters,
+            energies,
+        }
+    }
+
+    // the first count energies at every parameter, the parameters have to be ascending
+    pub fn compute(
+        family: &(dyn Fn(f64, f64) -> f64 + Sync),
+        mass: f64,
+        parameters: &[f64],
+        count: usize,
+    ) -> Sweep {
+        let energies = parameters
+            .par_iter()
+            .map(|lambda| {
+                let potential = |x| family(*lambda, x);
+                (0..count)
+                    .map(|n| energy::nth_energy(n, mass, UNITS.hbar, &potential, APPROX_INF))
+                    .collect()
+            })
+            .collect();
+        Sweep::new(parameters.to_vec(), energies)
+    }
+
+    pub fn curve(&self, n: usize) -> Vec<Point<f64, f64>> {
+        self.parameters
+            .iter()
+            .zip(self.energies.iter())
+            .map(|(lambda, energies)| Point {
+                x: *lambda,
+                y: energies[n],
+            })
+            .collect()
+    }
+
+    // Interior local minima of the gap between neighbouring curves that are smaller than
+    // threshold times the largest gap of the same pair in the sweep. The parameter and the gap
+    // are refined with a parabola through the smallest gap and its neighbours.
+    pub fn avoided_crossings(&self, threshold: f64) -> Vec<AvoidedCrossing> {
+        let count = self.energies.iter().map(|e| e.len()).min().unwrap_or(0);
+        let mut crossings: Vec<AvoidedCrossing> = (1..count)
+            .flat_map(|upper| {
+                let gaps: Vec<f64> = self
+                    .energies
+                    .iter()
+                    .map(|e| e[upper] - e[upper - 1])
+                    .collect();
+                let max_gap = gaps.iter().cloned().fold(0.0, f64::max);
+
+                (1..gaps.len().saturating_sub(1))
+                    .filter(|i| {
+                        gaps[*i] < gaps[i - 1]
+                            && gaps[*i] <= gaps[i + 1]
+                            && gaps[*i] < threshold * max_gap
+                    })
+                    .map(|i| {
+                        let (parameter, gap) = parabola_minimum(
+                            (self.parameters[i - 1], gaps[i - 1]),
+                            (self.parameters[i], gaps[i]),
+                            (self.parameters[i + 1], gaps[i + 1]),
+                        );
+                        AvoidedCrossing {
+                            parameter,
+                            gap,
+                            lower: upper - 1,
+                            upper,
+                        }
+                    })
+                    .collect::<Vec<AvoidedCrossing>>()
+            })
+            .collect();
+        crossings.sort_by(|a, b| cmp_f64(&a.parameter, &b.parameter));
+        crossings
+    }
+}
+
+// vertex of the parabola through three points, the middle one if they are on a line
+fn parabola_minimum(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+    let denominator = (b.0 - a.0) * (b.1 - c.1) - (b.0 - c.0) * (b.1 - a.1);
+    if denominator == 0.0 {
+        return b;
+    }
+    let numerator = (b.0 - a.0).powi(2) * (b.1 - c.1) - (b.0 - c.0).powi(2) * (b.1 - a.1);
+    let x = (b.0 - 0.5 * numerator / denominator).clamp(a.0, c.0);
+
+    // Lagrange form of the parabola at x
+    let y = a.1 * (x - b.0) * (x - c.0) / ((a.0 - b.0) * (a.0 - c.0))
+        + b.1 * (x - a.0) * (x - c.0) / ((b.0 - a.0) * (b.0 - c.0))
+        + c.1 * (x - a.0) * (x - b.0) / ((c.0 - a.0) * (c.0 - b.0));
+    (x, y.min(b.1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn two_level_crossing() {
+        // E = 5 +- sqrt((lambda - 0.3)^2 + 0.01) with a third level far away
+        let parameters: Vec<f64> = (0..101).map(|i| -1.0 + 0.02 * i as f64).collect();
+        let energies = parameters
+            .iter()
+            .map(|l| {
+                let half_gap = ((l - 0.3).powi(2) + 0.01).sqrt();
+                vec![5.0 - half_gap, 5.0 + half_gap, 20.0 + *l]
+            })
+            .collect();
+        let crossings = Sweep::new(parameters, energies).avoided_crossings(0.5);
+
+        assert_eq!(crossings.len(), 1);
+        assert_eq!((crossings[0].lower, crossings[0].upper), (0, 1));
+        assert!((crossings[0].parameter - 0.3).abs() < 1e-3);
+        assert!((crossings[0].gap - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn harmonic_oscillator_has_no_crossings() {
+        fn family(omega: f64, x: f64) -> f64 {
+            0.5 * omega * omega * x * x
+        }
+        let parameters: Vec<f64> = (1..6).map(|i| i as f64).collect();
+        let sweep = Sweep::compute(&family, 1.0, &parameters, 3);
+
+        for (omega, energies) in sweep.parameters.iter().zip(sweep.energies.iter()) {
+            assert!((energies[2] - 2.5 * omega).abs() < 1e-2 * omega);
+        }
+        assert!(sweep.avoided_crossings(0.5).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use crate::*;
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+// Shared flag to stop a computation started with spawn, clones refer to the same flag.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+thread_local! {
+    // set on the threads of a task, none everywhere else
+    static TOKEN: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+// payload of the unwind started by check_cancelled
+struct Cancelled;
+
+// Unwinds out of the running task if it was cancelled. Called inside the hot loops, outside a task
+// it does nothing. resume_unwind skips the panic hook, so nothing is printed.
+pub fn check_cancelled() {
+    let cancelled = TOKEN.with(|t| t.borrow().as_ref().is_some_and(|t| t.is_cancelled()));
+    if cancelled {
+        panic::resume_unwind(Box::new(Cancelled));
+    }
+}
+
+// A computation running on its own thread with its own rayon pool, whose threads all see the token.
+pub struct Task<T> {
+    handle: JoinHandle<Result<T, Box<dyn Any + Send>>>,
+    token: CancellationToken,
+}
+
+impl<T> Task<T> {
+    // the computation stops at the next check, join tells if it got that far
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn get_token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    // waits for the computation, fails if it was cancelled or panicked
+    pub fn join(self) -> Result<T, String> {
+        match self.handle.join() {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(payload)) | Err(payload) => Err(panic_message(payload)),
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if payload.is::<Cancelled>() {
+        "cancelled".to_string()
+    } else if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panicked".to_string()
+    }
+}
+
+pub fn spawn<T, F>(compute: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let token = CancellationToken::new();
+    let pool_token = token.clone();
+    let thread_token = token.clone();
+    let handle = thread::spawn(move || {
+        TOKEN.with(|t| *t.borrow_mut() = Some(thread_token));
+        let pool = rayon::ThreadPoolBuilder::new()
+            .start_handler(move |_| TOKEN.with(|t| *t.borrow_mut() = Some(pool_token.clone())))
+            .build()
+            .expect("can't start the threads of a task");
+        panic::catch_unwind(AssertUnwindSafe(|| pool.install(compute)))
+    });
+    Task { handle, token }
+}
+
+pub fn spawn_wave_function(builder: WaveFunctionBuilder, n: usize) -> Task<WaveFunction> {
+    spawn(move || {
+        builder
+            .solve_energy(n)
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale()
+    })
+}
+
+pub fn spawn_superposition(
+    potential: SharedPotential,
+    mass: f64,
+    coefficients: Vec<(usize, Complex64)>,
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    scaling: ScalingType,
+) -> Task<Superposition> {
+    spawn(move || {
+        Superposition::new(
+            potential,
+            mass,
+            &coefficients,
+            approx_inf,
+            view_factor,
+            scaling,
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn cancelled_task() {
+        check_cancelled();
+
+        let task = spawn_wave_function(
+            WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0),
+            0,
+        );
+        assert!(task.join().unwrap().get_energy() > 0.0);
+
+        // runs until it is cancelled
+        let points: Vec<Point<f64, f64>> = (0..1000)
+            .map(|i| Point {
+                x: i as f64,
+                y: i as f64,
+            })
+            .collect();
+        let task = spawn(move || loop {
+            integrals::integrate(points.clone(), 10);
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!task.is_finished());
+        let cancelled = Instant::now();
+        task.cancel();
+        assert_eq!(task.join().err(), Some("cancelled".to_string()));
+        assert!(cancelled.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(
+            spawn(|| panic!("broken")).join().err(),
+            Some("broken".to_string())
+        );
+    }
+}
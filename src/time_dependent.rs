@@ -0,0 +1,274 @@
+use crate::*;
+
+// Time dependent perturbation W(x) * f(t) in the basis of the unperturbed eigenstates.
+// The coefficients are in the interaction picture, psi(x, t) = sum_n c_n(t) e^(-i E_n t / hbar) psi_n(x),
+// and follow i hbar dc_m/dt = f(t) sum_n W_mn e^(i (E_m - E_n) t / hbar) c_n.
+pub struct DrivenSystem {
+    energies: Vec<f64>,
+    coupling: Vec<Vec<Complex64>>,
+    hbar: f64,
+}
+
+impl DrivenSystem {
+    pub fn new(
+        states: &[WaveFunction],
+        perturbation: &(dyn Fn(f64) -> f64 + Sync),
+    ) -> DrivenSystem {
+        DrivenSystem::from_matrix(
+            states.iter().map(|s| s.get_energy()).collect(),
+            matrix_elements::matrix(states, perturbation),
+        )
+    }
+
+    // W(x) = x, e.g. an electric field in dipole approximation with f(t) = -q E(t), hbar is the
+    // one of the states
+    pub fn dipole(states: &[WaveFunction]) -> DrivenSystem {
+        let mut system = DrivenSystem::from_matrix(
+            states.iter().map(|s| s.get_energy()).collect(),
+            matrix_elements::dipole_matrix(states),
+        );
+        if let Some(state) = states.first() {
+            system.hbar = state.get_phase().hbar;
+        }
+        system
+    }
+
+    pub fn from_matrix(energies: Vec<f64>, coupling: Vec<Vec<Complex64>>) -> DrivenSystem {
+        assert_eq!(energies.len(), coupling.len());
+        assert!(coupling.iter().all(|row| row.len() == energies.len()));
+        DrivenSystem {
+            energies,
+            coupling,
+            hbar: UNITS.hbar,
+        }
+    }
+
+    pub fn get_energies(&self) -> &[f64] {
+        &self.energies
+    }
+
+    pub fn get_coupling(&self) -> &[Vec<Complex64>] {
+        &self.coupling
+    }
+
+    fn angular_frequency(&self, m: usize, n: usize) -> f64 {
+        (self.energies[m] - self.energies[n]) / self.hbar
+    }
+
+    fn derivative(&self, drive: f64, t: f64, c: &[Complex64]) -> Vec<Complex64> {
+        (0..c.len())
+            .map(|m| {
+                let sum: Complex64 = (0..c.len())
+                    .map(|n| {
+                        self.coupling[m][n]
+                            * Complex64::from_polar(1.0, self.angular_frequency(m, n) * t)
+                            * c[n]
+                    })
+                    .sum();
+                complex(0.0, -drive / self.hbar) * sum
+            })
+            .collect()
+    }
+
+    // integrates the coefficients with the classical Runge-Kutta method, returns (t, c(t)) for every step
+    pub fn evolve(
+        &self,
+        drive: &dyn Fn(f64) -> f64,
+        initial: &[Complex64],
+        time: (f64, f64),
+        steps: usize,
+    ) -> Vec<(f64, Vec<Complex64>)> {
+        assert_eq!(initial.len(), self.energies.len());
+        let dt = (time.1 - time.0) / steps as f64;
+        let add = |c: &[Complex64], k: &[Complex64], f: f64| -> Vec<Complex64> {
+            c.iter().zip(k).map(|(c, k)| c + k * f).collect()
+        };
+
+        let mut c = initial.to_vec();
+        let mut result = vec![(time.0, c.clone())];
+        for i in 0..steps {
+            let t = time.0 + i as f64 * dt;
+            let k1 = self.derivative(drive(t), t, &c);
+            let k2 = self.derivative(drive(t + dt / 2.0), t + dt / 2.0, &add(&c, &k1, dt / 2.0));
+            let k3 = self.derivative(drive(t + dt / 2.0), t + dt / 2.0, &add(&c, &k2, dt / 2.0));
+            let k4 = self.derivative(drive(t + dt), t + dt, &add(&c, &k3, dt));
+
+            c = (0..c.len())
+                .map(|m| c[m] + (k1[m] + 2.0 * k2[m] + 2.0 * k3[m] + k4[m]) * dt / 6.0)
+                .collect();
+            result.push((t + dt, c.clone()));
+        }
+        result
+    }
+
+    // probabilities to find the system in each state at the end, starting in state `initial`
+    pub fn transition_probabilities(
+        &self,
+        drive: &dyn Fn(f64) -> f64,
+        initial: usize,
+        time: (f64, f64),
+        steps: usize,
+    ) -> Vec<f64> {
+        let mut c = vec![complex(0.0, 0.0); self.energies.len()];
+        c[initial] = 1.0.into();
+        let (_, c) = self.evolve(drive, &c, time, steps).pop().unwrap();
+        c.iter().map(|c| c.norm_sqr()).collect()
+    }
+
+    // first order amplitude -i/hbar integral W_mn f(t) e^(i w_mn t) dt for m != n
+    pub fn first_order_amplitude(
+        &self,
+        drive: &(dyn Fn(f64) -> f64 + Sync),
+        m: usize,
+        n: usize,
+        time: (f64, f64),
+        steps: usize,
+    ) -> Complex64 {
+        let omega = self.angular_frequency(m, n);
+        let integrand = (0..steps)
+            .into_par_iter()
+            .map(|i| {
+                let t = index_to_range(i as f64, 0.0, (steps - 1) as f64, time.0, time.1);
+                Point {
+                    x: t,
+                    y: drive(t) * Complex64::from_polar(1.0, omega * t),
+                }
+            })
+            .collect();
+        complex(0.0, -1.0 / self.hbar)
+            * self.coupling[m][n]
+            * integrate(integrand, TRAPEZE_PER_THREAD)
+    }
+}
+
+// Two levels driven by amplitude * cos(frequency * t), compared with the analytic Rabi formula
+// in the rotating wave approximation. The comparison only holds for a weak and nearly resonant drive.
+pub struct RabiOscillation {
+    system: DrivenSystem,
+    amplitude: f64,
+    frequency: f64,
+}
+
+pub struct Populations {
+    pub t: f64,
+    pub lower: f64,
+    pub upper: f64,
+    pub upper_analytic: f64,
+}
+
+impl RabiOscillation {
+    // dipole coupling between the two states, detuning is the drive frequency minus the transition frequency
+    pub fn new(
+        lower: &WaveFunction,
+        upper: &WaveFunction,
+        amplitude: f64,
+        detuning: f64,
+    ) -> RabiOscillation {
+        let system = DrivenSystem::dipole(&[lower.clone(), upper.clone()]);
+        let frequency = system.angular_frequency(1, 0) + detuning;
+        RabiOscillation {
+            system,
+            amplitude,
+            frequency,
+        }
+    }
+
+    pub fn from_coupling(
+        energies: (f64, f64),
+        coupling: Complex64,
+        amplitude: f64,
+        frequency: f64,
+    ) -> RabiOscillation {
+        RabiOscillation {
+            system: DrivenSystem::from_matrix(
+                vec![energies.0, energies.1],
+                vec![
+                    vec![0.0.into(), coupling.conj()],
+                    vec![coupling, 0.0.into()],
+                ],
+            ),
+            amplitude,
+            frequency,
+        }
+    }
+
+    pub fn get_coupling(&self) -> Complex64 {
+        self.system.coupling[1][0]
+    }
+
+    pub fn rabi_frequency(&self) -> f64 {
+        self.get_coupling().norm() * self.amplitude / self.system.hbar
+    }
+
+    pub fn detuning(&self) -> f64 {
+        self.frequency - self.system.angular_frequency(1, 0)
+    }
+
+    pub fn upper_population_analytic(&self, t: f64) -> f64 {
+        let rabi = self.rabi_frequency();
+        let detuning = self.detuning();
+        let generalized = (rabi * rabi + detuning * detuning).sqrt();
+        (rabi / generalized).powi(2) * (generalized * t / 2.0).sin().powi(2)
+    }
+
+    // starts in the lower state at t = 0
+    pub fn populations(&self, duration: f64, steps: usize) -> Vec<Populations> {
+        let drive = |t: f64| self.amplitude * (self.frequency * t).cos();
+        self.system
+            .evolve(&drive, &[1.0.into(), 0.0.into()], (0.0, duration), steps)
+            .iter()
+            .map(|(t, c)| Populations {
+                t: *t,
+                lower: c[0].norm_sqr(),
+                upper: c[1].norm_sqr(),
+                upper_analytic: self.upper_population_analytic(*t),
+            })
+            .collect()
+    }
+
+    // largest difference to the analytic upper state population
+    pub fn max_deviation(populations: &[Populations]) -> f64 {
+        populations
+            .iter()
+            .map(|p| (p.upper - p.upper_analytic).abs())
+            .max_by(cmp_f64)
+            .unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn weak_drive_matches_first_order() {
+        let system = DrivenSystem::from_matrix(
+            vec![0.5, 1.5],
+            vec![
+                vec![0.0.into(), complex(0.7, 0.0)],
+                vec![complex(0.7, 0.0), 0.0.into()],
+            ],
+        );
+        let drive = |t: f64| 1e-3 * (-(t - 5.0).powi(2)).exp() * (1.2 * t).cos();
+
+        let probabilities = system.transition_probabilities(&drive, 0, (0.0, 10.0), 10000);
+        let first_order = system.first_order_amplitude(&drive, 1, 0, (0.0, 10.0), 10000);
+
+        assert!(float_compare(probabilities.iter().sum(), 1.0, 1e-9));
+        let expected = first_order.norm_sqr();
+        assert!(expected > 0.0);
+        assert!((probabilities[1] - expected).abs() / expected < 1e-3);
+    }
+
+    #[test]
+    fn rabi_matches_analytic() {
+        let rabi = RabiOscillation::from_coupling((0.5, 1.5), complex(0.5, 0.0), 0.01, 1.0 + 0.002);
+        let period = 2.0 * f64::consts::PI / rabi.rabi_frequency();
+        let populations = rabi.populations(2.0 * period, 200000);
+
+        assert!(populations
+            .iter()
+            .all(|p| (p.lower + p.upper - 1.0).abs() < 1e-9));
+        assert!(RabiOscillation::max_deviation(&populations) < 1e-2);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::*;
+use std::cmp::Ordering;
+
+struct Decay<'a, F: Fn(f64) -> f64 + Sync + ?Sized> {
+    potential: &'a F,
+    mass: f64,
+    energy: f64,
+}
+
+// imaginary momentum in the classically forbidden regions, zero elsewhere
+impl<F: Fn(f64) -> f64 + Sync + ?Sized> Func<f64, f64> for Decay<'_, F> {
+    fn eval(&self, x: f64) -> f64 {
+        let v = (self.potential)(x);
+        if v.is_finite() && v > self.energy {
+            (2.0 * self.mass * (v - self.energy)).sqrt() / UNITS.hbar
+        } else {
+            0.0
+        }
+    }
+}
+
+// WKB transmission factor exp(-2 theta) with theta the integral of |p| / hbar under the barrier,
+// 1 above the barrier
+pub fn transmission<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    mass: f64,
+    energy: f64,
+    range: (f64, f64),
+) -> f64 {
+    let theta = Grid::uniform(range, INTEG_STEPS).integrate(&Decay {
+        potential,
+        mass,
+        energy,
+    });
+    (-2.0 * theta).exp()
+}
+
+pub fn transmission_table<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    mass: f64,
+    energies: &Grid,
+) -> Vec<Point<f64, f64>> {
+    energies
+        .points()
+        .iter()
+        .map(|e| Point {
+            x: *e,
+            y: transmission(potential, mass, *e, APPROX_INF),
+        })
+        .collect()
+}
+
+// occupation of the incident states, for a simple estimate of the emitted current
+pub fn fermi_dirac(fermi_energy: f64, kt: f64) -> impl Fn(f64) -> f64 {
+    move |e| 1.0 / (1.0 + ((e - fermi_energy) / kt).exp())
+}
+
+// integral of T(E) w(E) over the energies of the table
+pub fn weighted_transmission(table: &[Point<f64, f64>], weight: &dyn Fn(f64) -> f64) -> f64 {
+    integrate(
+        table
+            .iter()
+            .map(|p| Point {
+                x: p.x,
+                y: p.y * weight(p.x),
+            })
+            .collect(),
+        TRAPEZE_PER_THREAD,
+    )
+}
+
+// "a:b:n", n energies from a to b
+pub fn parse_energy_range(range: &str) -> Result<Grid, String> {
+    let parts: Vec<&str> = range.split(':').collect();
+    if parts.len() != 3 {
+        return Err(format!("energy range '{}' isn't of the form a:b:n", range));
+    }
+    let a: f64 = parts[0]
+        .parse()
+        .map_err(|_| format!("invalid lower energy '{}'", parts[0]))?;
+    let b: f64 = parts[1]
+        .parse()
+        .map_err(|_| format!("invalid upper energy '{}'", parts[1]))?;
+    let n: usize = parts[2]
+        .parse()
+        .map_err(|_| format!("invalid number of energies '{}'", parts[2]))?;
+    if n < 2 || a.partial_cmp(&b) != Some(Ordering::Less) {
+        return Err("the energy range needs a < b and at least 2 energies".to_string());
+    }
+    Ok(Grid::uniform((a, b), n))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn triangular_barrier() {
+        // theta = sqrt(2 m) (W - E)^(3/2) / (3/2 F) for V = W - F x
+        let energy = 2.0;
+        let expected = (-2.0 * 2.0f64.sqrt() * 3.0f64.powf(1.5) / 1.5).exp();
+        let t = transmission(&potentials::field_emission, 1.0, energy, APPROX_INF);
+
+        assert!(
+            (t - expected).abs() < 1e-3 * expected,
+            "{} != {}",
+            t,
+            expected
+        );
+        assert_eq!(
+            transmission(&potentials::field_emission, 1.0, 6.0, APPROX_INF),
+            1.0
+        );
+    }
+
+    #[test]
+    fn weighted_by_step() {
+        let table = transmission_table(
+            &potentials::barrier,
+            1.0,
+            &parse_energy_range("0:12:241").unwrap(),
+        );
+        assert!(table.windows(2).all(|w| w[0].y <= w[1].y));
+
+        // a sharp Fermi edge above the barrier lets everything below it through
+        let current = weighted_transmission(&table, &fermi_dirac(11.0, 1e-3));
+        let below_edge: Vec<Point<f64, f64>> = table.into_iter().filter(|p| p.x <= 11.0).collect();
+        assert!((current - integrate(below_edge, TRAPEZE_PER_THREAD)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn invalid_energy_ranges() {
+        assert!(parse_energy_range("0:1").is_err());
+        assert!(parse_energy_range("1:0:10").is_err());
+        assert!(parse_energy_range("0:x:10").is_err());
+        assert_eq!(parse_energy_range("0:1:11").unwrap().len(), 11);
+    }
+}
@@ -1,4 +1,12 @@
+use crate::potentials::SharedPotential;
+use crate::registry;
+use crate::task::Task;
+use crate::*;
 use std::io;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn get_float_from_user(message: &str) -> f64 {
     loop {
@@ -31,3 +39,327 @@ fn ask_user_for_view(lower_bound: Option<f64>, upper_bound: Option<f64>) -> (f64
 
     return get_user_bounds();
 }
+
+// asks for a potential like "morse(d=10,a=1)" until it is found in the registry
+pub fn ask_user_for_potential() -> (String, SharedPotential) {
+    loop {
+        println!("Potential (one of {}): ", registry::names().join(", "));
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .expect("Not a valid string");
+        println!();
+        match registry::resolve(&input) {
+            Ok(potential) => return (input.trim().to_string(), potential),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// rows of the plot pane at the top, the log scrolls below it
+const PLOT_ROWS: usize = 16;
+const PLOT_COLUMNS: usize = 72;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+// a line starting with it cancels the running computation, Esc has to be followed by Enter as the
+// terminal stays line buffered
+const ESCAPE: char = '\u{1b}';
+// the last wave function is plotted here with plot::plot_wavefunction_progressive
+const OUTPUT_DIR: &str = "output/tui";
+const OUTPUT_FILE: &str = "psi.dat";
+
+// Split terminal with a plot pane on top and a scrolling log and progress pane below, drawn with
+// ANSI escape sequences only. Input is read on its own thread so computations can be cancelled
+// while they run.
+pub struct Screen {
+    input: Receiver<String>,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    if sender.send(line.trim_end().to_string()).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Screen::with_input(receiver)
+    }
+
+    fn with_input(input: Receiver<String>) -> Screen {
+        // clear, keep the log below the plot pane and its border, move into the log
+        print!("\x1b[2J\x1b[{}r\x1b[{};1H", PLOT_ROWS + 3, PLOT_ROWS + 3);
+        io::stdout().flush().unwrap();
+        Screen { input }
+    }
+
+    // replaces the plot pane, the cursor stays in the log
+    pub fn show_plot(&mut self, lines: &[String]) {
+        let mut out = String::from("\x1b7");
+        for row in 0..PLOT_ROWS + 2 {
+            out += &format!(
+                "\x1b[{};1H\x1b[2K{}",
+                row + 1,
+                lines.get(row).map_or("", |l| l.as_str())
+            );
+        }
+        print!("{}\x1b8", out);
+        io::stdout().flush().unwrap();
+    }
+
+    pub fn log(&mut self, message: &str) {
+        println!("\r\x1b[2K{}", message);
+    }
+
+    // overwritten by the next progress or log message
+    pub fn progress(&mut self, message: &str) {
+        print!("\r\x1b[2K{}", message);
+        io::stdout().flush().unwrap();
+    }
+
+    // none once stdin is closed
+    pub fn read_line(&mut self) -> Option<String> {
+        self.input.recv().ok()
+    }
+
+    fn ask<T: FromStr>(&mut self, message: &str) -> Option<T> {
+        loop {
+            self.log(message);
+            if let Ok(value) = self.read_line()?.trim().parse() {
+                return Some(value);
+            }
+        }
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Screen {
+        Screen::new()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        // the whole terminal scrolls again
+        print!("\x1b[r");
+        io::stdout().flush().unwrap();
+    }
+}
+
+// bars of the largest value in each column, scaled to the largest value overall, and a line with
+// the range of x
+pub fn render_plot(values: &[Point<f64, f64>], rows: usize, columns: usize) -> Vec<String> {
+    if values.is_empty() {
+        return vec![];
+    }
+    // every column gets at least one value, even with fewer values than columns
+    let heights: Vec<f64> = (0..columns)
+        .map(|c| {
+            let start = (c * values.len() / columns).min(values.len() - 1);
+            let end = ((c + 1) * values.len() / columns).max(start + 1);
+            values[start..end].iter().map(|p| p.y).fold(0.0, f64::max)
+        })
+        .collect();
+    let max = heights.iter().cloned().fold(0.0, f64::max);
+    let max = if max > 0.0 { max } else { 1.0 };
+
+    let mut lines: Vec<String> = (0..rows)
+        .map(|row| {
+            let level = (rows - row) as f64 - 0.5;
+            heights
+                .iter()
+                .map(|h| {
+                    if h / max * rows as f64 >= level {
+                        '#'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    lines.push("-".repeat(columns));
+    let (first, last) = (values[0].x, values.last().unwrap().x);
+    let first = format!("{:.3}", first);
+    lines.push(format!(
+        "{}{:>width$.3}",
+        first,
+        last,
+        width = columns.saturating_sub(first.len())
+    ));
+    lines
+}
+
+// Runs compute as a task while showing its progress, a line starting with Esc cancels it. The
+// task stops at its next cancellation check, a computation without checks finishes in the
+// background and its result is dropped. None if it was cancelled or panicked.
+pub fn run_cancellable<T: Send + 'static>(
+    screen: &mut Screen,
+    label: &str,
+    compute: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    let (sender, receiver) = mpsc::channel();
+    let running = task::spawn(move || {
+        let _ = sender.send(compute());
+    });
+    let started = Instant::now();
+    for spinner in SPINNER.iter().cycle() {
+        match receiver.recv_timeout(PROGRESS_INTERVAL) {
+            Ok(value) => {
+                screen.log(&format!(
+                    "{} done after {:.1} s",
+                    label,
+                    started.elapsed().as_secs_f64()
+                ));
+                return Some(value);
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let message = running.join().err().unwrap_or_default();
+                screen.log(&format!("{} failed: {}", label, message));
+                return None;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        // other input while computing is dropped
+        if screen.input.try_iter().any(|l| l.starts_with(ESCAPE)) {
+            running.cancel();
+            screen.log(&format!("{} cancelled", label));
+            return None;
+        }
+        screen.progress(&format!(
+            "{} {} {:.1} s, Esc and Enter to cancel",
+            spinner,
+            label,
+            started.elapsed().as_secs_f64()
+        ));
+    }
+    unreachable!()
+}
+
+// Asks for a potential, mass and n in the log pane, computes the wave function in the background
+// and plots |psi|^2 above. Its data is written to OUTPUT_DIR as a preview, which is refined in the
+// background until the next wave function replaces it. Ends on "q" or when stdin is closed.
+pub fn run() {
+    let mut screen = Screen::new();
+    let mut refining: Option<Task<()>> = None;
+    loop {
+        screen.log(&format!(
+            "Potential (one of {}), q to quit: ",
+            registry::names().join(", ")
+        ));
+        let input = match screen.read_line() {
+            Some(input) if input.trim() != "q" => input,
+            _ => return finish_refinement(&mut screen, refining),
+        };
+        let potential = match registry::resolve(&input) {
+            Ok(potential) => potential,
+            Err(e) => {
+                screen.log(&e);
+                continue;
+            }
+        };
+        let mass: f64 = match screen.ask("Mass: ") {
+            Some(mass) => mass,
+            None => return finish_refinement(&mut screen, refining),
+        };
+        let n: usize = match screen.ask("n: ") {
+            Some(n) => n,
+            None => return finish_refinement(&mut screen, refining),
+        };
+
+        let label = format!("{} n = {}", input.trim(), n);
+        let computed = run_cancellable(&mut screen, &label, move || {
+            let wave_function = WaveFunction::new(
+                potential,
+                mass,
+                n,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::Renormalize(1.0.into()),
+            );
+            let probability = Grid::uniform(wave_function.get_view(), 4 * PLOT_COLUMNS)
+                .evaluate(&NormSquare { f: &wave_function });
+            (wave_function, probability)
+        });
+        if let Some((wave_function, probability)) = computed {
+            screen.show_plot(&render_plot(&probability, PLOT_ROWS, PLOT_COLUMNS));
+            screen.log(&format!("E = {}", wave_function.get_energy()));
+
+            // the data of the previous wave function would overwrite the new preview
+            if let Some(previous) = refining.take() {
+                previous.cancel();
+                let _ = previous.join();
+            }
+            let output_dir = Path::new(OUTPUT_DIR);
+            if let Err(e) = std::fs::create_dir_all(output_dir) {
+                screen.log(&format!("can't create {}: {}", OUTPUT_DIR, e));
+                continue;
+            }
+            refining = Some(plot::plot_wavefunction_progressive(
+                wave_function,
+                output_dir,
+                OUTPUT_FILE,
+            ));
+            screen.log(&format!(
+                "preview written to {}/{}, refining in the background",
+                OUTPUT_DIR, OUTPUT_FILE
+            ));
+        }
+    }
+}
+
+// waits for the refinement of the last wave function, Esc and Enter keeps its preview
+fn finish_refinement(screen: &mut Screen, refining: Option<Task<()>>) {
+    if let Some(refining) = refining {
+        if !refining.is_finished() {
+            let token = refining.get_token();
+            run_cancellable(screen, "refinement", move || {
+                let _ = refining.join();
+            });
+            token.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plot_of_a_peak() {
+        let values: Vec<Point<f64, f64>> = (0..100)
+            .map(|i| Point {
+                x: i as f64 / 99.0,
+                y: (-((i as f64 - 50.0) / 10.0).powi(2)).exp(),
+            })
+            .collect();
+        let lines = render_plot(&values, 4, 20);
+        assert_eq!(lines.len(), 6);
+        let top = lines[0].trim();
+        assert!(!top.is_empty() && top.len() <= 2 && top.chars().all(|c| c == '#'));
+        assert!(lines[3].chars().filter(|c| *c == '#').count() > 2);
+        assert_eq!(lines[5], format!("0.000{:>15}", "1.000"));
+    }
+
+    #[test]
+    fn cancelling() {
+        let (sender, receiver) = mpsc::channel();
+        let mut screen = Screen::with_input(receiver);
+        assert_eq!(run_cancellable(&mut screen, "quick", || 2), Some(2));
+
+        let started = Instant::now();
+        sender.send(format!("{}", ESCAPE)).unwrap();
+        let slow = run_cancellable(&mut screen, "slow", || {
+            thread::sleep(Duration::from_secs(30));
+            1
+        });
+        assert_eq!(slow, None);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,11 +1,21 @@
 use crate::cmp_f64;
 use crate::newtons_method::*;
+use crate::polynomial::Polynomial;
 use crate::wkb_wave_func::*;
 use crate::*;
 use num::signum;
 
 const MAX_TURNING_POINTS: usize = 2048;
-const ACCURACY: f64 = 1e-9;
+// guesses newtons method tries per zero before find_zeros gives up
+const GUESS_CANDIDATES: usize = 8;
+// the search for a missing edge of an airy range steps its guess by this, sqrt(1e-9)
+const GUESS_STEP: f64 = 3.162_277_660_168_379_5e-5;
+// points of the scan for the outermost turning points in outer_boundaries
+const BOUNDARY_SCAN_POINTS: usize = 4000;
+// points of the scan for the zeros of the validity function of potentials that can't be fitted
+const ZERO_SCAN_POINTS: usize = 4000;
+// jump of V across the bisected crossing, relative to 1 + |E|, above which it is a hard wall
+const HARD_WALL_JUMP: f64 = 1e-6;
 
 pub struct TGroup {
     pub ts: Vec<((f64, f64), f64)>,
@@ -20,109 +30,447 @@ impl TGroup {
     pub fn add_ts(&mut self, new_t: ((f64, f64), f64)) {
         self.ts.push(new_t);
     }
+
+    // Close turning points, e.g. below the top of a shallow barrier, can have overlapping airy
+    // ranges and which airy function describes psi between them would depend on the order of the
+    // parts. Both ranges are cut at the midpoint of the turning points, where the parts of the
+    // wave function meet as well. Returns the number of overlaps.
+    pub fn separate_airy_ranges(&mut self) -> usize {
+        self.ts.sort_by(|a, b| cmp_f64(&a.1, &b.1));
+        let mut overlaps = 0;
+        for i in 1..self.ts.len() {
+            let ((left_start, left_end), left_t) = self.ts[i - 1];
+            let ((right_start, right_end), right_t) = self.ts[i];
+            if left_end <= right_start {
+                continue;
+            }
+            let cut = (left_t + right_t) / 2.0;
+            println!(
+                "Airy ranges ({}, {}) and ({}, {}) of the turning points {} and {} overlap, both \
+                 are cut at {}",
+                left_start, left_end, right_start, right_end, left_t, right_t, cut
+            );
+            self.ts[i - 1].0 .1 = left_end.min(cut);
+            self.ts[i].0 .0 = right_start.max(cut);
+            overlaps += 1;
+        }
+        overlaps
+    }
 }
 
-fn validity_func(phase: Phase) -> Arc<dyn Fn(f64) -> f64> {
+fn validity_func(phase: Phase) -> Arc<dyn Fn(f64) -> f64 + Send + Sync> {
     Arc::new(move |x: f64| {
-        1.0 / (2.0 * phase.mass).sqrt()
+        phase.hbar / (2.0 * phase.mass).sqrt()
             * derivative(&|t| (phase.potential)(t), x).abs()
             * VALIDITY_LL_FACTOR
             - ((phase.potential)(x) - phase.energy).pow(2)
     })
 }
 
-fn group_ts(zeros: &Vec<f64>, phase: &Phase) -> TGroup {
-    let mut zeros = zeros.clone();
+// +1 if the validity function rises through its zero x, -1 if it falls, from its values a step to
+// both sides. The finite differences of derivative are too noisy, the validity function contains
+// a derivative of V itself. 0 if it only touches zero, e.g. at the top of a barrier.
+fn crossing_direction(valid: &dyn Fn(f64) -> f64, x: f64, step: f64) -> f64 {
+    let (before, after) = (valid(x - step), valid(x + step));
+    if before.signum() == after.signum() {
+        return 0.0;
+    }
+    signum(after - before)
+}
+
+// roots are the solutions of V = E if they are known, the turning points are found by newtons
+// method otherwise
+fn group_ts(zeros: &[f64], phase: &Phase, roots: &[f64]) -> Result<TGroup, String> {
+    let mut zeros = zeros.to_vec();
     let valid = validity_func(phase.clone());
+    // the direction of a crossing is taken well within the distance to its neighbours
+    let step = |x: f64, neighbours: &[f64]| {
+        neighbours
+            .iter()
+            .filter(|z| **z != x)
+            .map(|z| (z - x).abs() / 4.0)
+            .fold(GUESS_STEP, f64::min)
+    };
 
     zeros.sort_by(cmp_f64);
     let mut derivatives = zeros
         .iter()
-        .map(|x| derivative(valid.as_ref(), *x))
-        .map(signum)
+        .map(|x| crossing_direction(valid.as_ref(), *x, step(*x, &zeros)))
         .zip(zeros.clone())
+        // a zero the validity function only touches lies within an airy range
+        .filter(|(deriv, _)| *deriv != 0.0)
         .collect::<Vec<(f64, f64)>>();
 
     let mut groups = TGroup { ts: vec![] };
 
     if let Some((deriv, z)) = derivatives.first() {
         if *deriv < 0.0 {
-            let mut guess = z - ACCURACY.sqrt();
+            let mut guess = z - GUESS_STEP;
             let mut new_deriv = *deriv;
             let mut missing_t = *z;
 
             while new_deriv < 0.0 {
                 missing_t =
-                    regula_falsi_bisection(valid.as_ref(), guess, -ACCURACY.sqrt(), ACCURACY);
-                new_deriv = signum(derivative(valid.as_ref(), missing_t));
-                guess -= ACCURACY.sqrt();
+                    regula_falsi_bisection(valid.as_ref(), guess, -GUESS_STEP, BOUNDARY_TOLERANCE);
+                new_deriv = crossing_direction(valid.as_ref(), missing_t, step(missing_t, &[*z]));
+                guess -= GUESS_STEP;
             }
 
-            derivatives.insert(
-                0,
-                (signum(derivative(valid.as_ref(), missing_t)), missing_t),
-            );
+            derivatives.insert(0, (new_deriv, missing_t));
         }
     }
 
     if let Some((deriv, z)) = derivatives.last() {
         if *deriv > 0.0 {
-            let mut guess = z + ACCURACY.sqrt();
+            let mut guess = z + GUESS_STEP;
             let mut new_deriv = *deriv;
             let mut missing_t = *z;
 
             while new_deriv > 0.0 {
                 missing_t =
-                    regula_falsi_bisection(valid.as_ref(), guess, ACCURACY.sqrt(), ACCURACY);
-                new_deriv = signum(derivative(valid.as_ref(), missing_t));
-                guess += ACCURACY.sqrt();
+                    regula_falsi_bisection(valid.as_ref(), guess, GUESS_STEP, BOUNDARY_TOLERANCE);
+                new_deriv = crossing_direction(valid.as_ref(), missing_t, step(missing_t, &[*z]));
+                guess += GUESS_STEP;
             }
 
-            derivatives.push((signum(derivative(valid.as_ref(), missing_t)), missing_t));
+            derivatives.push((new_deriv, missing_t));
         }
     }
 
-    assert_eq!(derivatives.len() % 2, 0);
+    if derivatives.len() % 2 != 0 {
+        return Err(format!(
+            "the zeros {:?} of the validity function don't pair up",
+            derivatives.iter().map(|(_, z)| *z).collect::<Vec<f64>>()
+        ));
+    }
 
     for i in (0..derivatives.len()).step_by(2) {
         let (t1_deriv, t1) = derivatives[i];
         let (t2_deriv, t2) = derivatives[i + 1];
-        assert!(t1_deriv > 0.0);
-        assert!(t2_deriv < 0.0);
+        if t1_deriv <= 0.0 || t2_deriv >= 0.0 {
+            return Err(format!(
+                "the validity function doesn't rise at {} and fall at {}",
+                t1, t2
+            ));
+        }
 
-        let turning_point = newtons_method(
-            &|x| phase.energy - (phase.potential)(x),
-            (t1 + t2) / 2.0,
-            1e-7,
-        );
+        let turning_point = roots
+            .iter()
+            .copied()
+            .find(|t| t1 <= *t && *t <= t2)
+            .unwrap_or_else(|| {
+                newtons_method(
+                    &|x| phase.energy - (phase.potential)(x),
+                    (t1 + t2) / 2.0,
+                    TURNING_POINT_TOLERANCE,
+                )
+            });
         groups.add_ts(((t1, t2), turning_point));
     }
 
-    return groups;
+    Ok(groups)
 }
 
-pub fn calc_ts(phase: &Phase, view: (f64, f64)) -> TGroup {
-    let zeros = find_zeros(phase, view);
-    let groups = group_ts(&zeros, phase);
-    return groups;
+// fails if the zeros of the validity function don't pair up around the turning points
+pub fn calc_ts(phase: &Phase, view: (f64, f64)) -> Result<TGroup, String> {
+    let sorted = (view.0.min(view.1), view.0.max(view.1));
+    let polynomial = if POLYNOMIAL_TURNING_POINTS {
+        Polynomial::detect(phase.potential.as_ref(), sorted)
+    } else {
+        None
+    };
+    let (zeros, roots) = match &polynomial {
+        Some(polynomial) => (
+            polynomial_zeros(phase, polynomial, sorted),
+            polynomial.shifted(phase.energy).real_roots(),
+        ),
+        None => (find_zeros(phase, view), vec![]),
+    };
+    let mut groups = group_ts(&zeros, phase, &roots)?;
+    groups.separate_airy_ranges();
+    Ok(groups)
 }
 
+// outermost points with V(x) = E, the outermost roots of a Chebyshev fit of V - E if V is smooth,
+// searched from both ends of approx_inf otherwise
+pub fn classical_turning_points<F: Fn(f64) -> f64 + Sync + ?Sized>(
+    potential: &F,
+    energy: f64,
+    approx_inf: (f64, f64),
+) -> Option<(f64, f64)> {
+    if GLOBAL_ROOT_TOLERANCE > 0.0 {
+        let roots = chebyshev::find_real_roots(
+            &|x| potential(x) - energy,
+            approx_inf,
+            GLOBAL_ROOT_TOLERANCE,
+            TURNING_POINT_TOLERANCE,
+        );
+        // a fit without a pair of roots, e.g. of a singular potential, falls back to the search
+        if let Some(roots) = roots.filter(|roots| roots.len() >= 2) {
+            return Some((roots[0], roots[roots.len() - 1]));
+        }
+    }
+    let find = |guess| {
+        newtons_method_max_iters(
+            &|x| potential(x) - energy,
+            guess,
+            TURNING_POINT_TOLERANCE,
+            100000,
+        )
+    };
+    let (lower, upper) = (find(approx_inf.0), find(approx_inf.1));
+    if lower.is_some() && upper.is_some() {
+        return lower.zip(upper);
+    }
+    // newtons method runs off the flat tail of e.g. a Coulomb potential, the outermost crossings of
+    // a scan are bisected instead
+    let scanned = scanned_turning_points(potential, energy, approx_inf);
+    lower
+        .or(scanned.map(|t| t.0))
+        .zip(upper.or(scanned.map(|t| t.1)))
+}
+
+fn scanned_turning_points<F: Fn(f64) -> f64 + ?Sized>(
+    potential: &F,
+    energy: f64,
+    approx_inf: (f64, f64),
+) -> Option<(f64, f64)> {
+    let grid = Grid::uniform(approx_inf, BOUNDARY_SCAN_POINTS);
+    let allowed = |i: &usize| potential(grid.x(*i)) < energy;
+    let first = (0..grid.len()).find(allowed).filter(|i| *i > 0)?;
+    let last = (0..grid.len())
+        .rev()
+        .find(allowed)
+        .filter(|i| *i + 1 < grid.len())?;
+    let (lower, _) = bisect_crossing(potential, grid.x(first), grid.x(first - 1), energy);
+    let (upper, _) = bisect_crossing(potential, grid.x(last), grid.x(last + 1), energy);
+    Some((lower, upper))
+}
+
+// How psi is reflected where V crosses E. At a soft turning point psi leaks into the wall and the
+// Airy connection loses a quarter of a period, pi / 4, at a hard wall psi vanishes and it is pi / 2.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Boundary {
+    Soft,
+    HardWall,
+}
+
+impl Boundary {
+    // phase subtracted from the action between the turning point and x
+    pub fn phase_correction(&self) -> f64 {
+        match self {
+            Boundary::Soft => f64::consts::PI / 4.0,
+            Boundary::HardWall => f64::consts::PI / 2.0,
+        }
+    }
+}
+
+// bisects the crossing of V and E between a and b down to neighbouring floats, V jumps there if
+// it is a wall
+fn boundary_between<F: Fn(f64) -> f64 + ?Sized>(
+    potential: &F,
+    a: f64,
+    b: f64,
+    energy: f64,
+) -> Boundary {
+    let (a, b) = bisect_crossing(potential, a, b, energy);
+    let jump = (potential(b) - potential(a)).abs();
+    if !jump.is_finite() || jump > HARD_WALL_JUMP * (1.0 + energy.abs()) {
+        Boundary::HardWall
+    } else {
+        Boundary::Soft
+    }
+}
+
+// neighbouring floats on both sides of the crossing of f and level between a and b, in the order
+// of a and b, e.g. of V and E
+fn bisect_crossing<F: Fn(f64) -> f64 + ?Sized>(
+    f: &F,
+    mut a: f64,
+    mut b: f64,
+    level: f64,
+) -> (f64, f64) {
+    let below_a = f(a) < level;
+    loop {
+        let middle = (a + b) / 2.0;
+        if middle <= a.min(b) || middle >= a.max(b) {
+            return (a, b);
+        }
+        if (f(middle) < level) == below_a {
+            a = middle;
+        } else {
+            b = middle;
+        }
+    }
+}
+
+// type of the turning point t, found by newtons method, soft if V doesn't cross E close to it
+pub fn boundary_at<F: Fn(f64) -> f64 + ?Sized>(potential: &F, t: f64, energy: f64) -> Boundary {
+    let mut width = f64::EPSILON.sqrt() * t.abs().max(1.0);
+    for _ in 0..32 {
+        if (potential(t - width) < energy) != (potential(t + width) < energy) {
+            return boundary_between(potential, t - width, t + width, energy);
+        }
+        width *= 2.0;
+    }
+    Boundary::Soft
+}
+
+// types of the outermost turning points at energy in view, soft where there is none
+pub fn outer_boundaries<F: Fn(f64) -> f64 + ?Sized>(
+    potential: &F,
+    energy: f64,
+    view: (f64, f64),
+) -> (Boundary, Boundary) {
+    let grid = Grid::uniform(view, BOUNDARY_SCAN_POINTS);
+    let allowed = |i: &usize| potential(grid.x(*i)) < energy;
+    let first = (0..grid.len()).find(allowed);
+    let last = (0..grid.len()).rev().find(allowed);
+    match first.zip(last) {
+        Some((first, last)) => (
+            if first == 0 {
+                Boundary::Soft
+            } else {
+                boundary_between(potential, grid.x(first - 1), grid.x(first), energy)
+            },
+            if last + 1 == grid.len() {
+                Boundary::Soft
+            } else {
+                boundary_between(potential, grid.x(last), grid.x(last + 1), energy)
+            },
+        ),
+        None => (Boundary::Soft, Boundary::Soft),
+    }
+}
+
+// uncertainty of the location of turning point t, where newtons method stopped
+pub fn location_error(phase: &Phase, t: f64) -> f64 {
+    TURNING_POINT_TOLERANCE.x_error(t, derivative(phase.potential.as_ref(), t))
+}
+
+// zeros of the validity function in the view, all at once for potentials smooth enough for
+// find_zeros_global, from the sign changes of a scan for those that aren't, e.g. close to the pole
+// of a Coulomb potential, and one by one with newtons method if the scan doesn't see any
 fn find_zeros(phase: &Phase, view: (f64, f64)) -> Vec<f64> {
+    let sorted = (view.0.min(view.1), view.0.max(view.1));
+    if GLOBAL_ROOT_TOLERANCE > 0.0 {
+        if let Some(zeros) = find_zeros_global(phase, sorted) {
+            return zeros
+                .into_iter()
+                .filter(|x| *x > sorted.0 && *x < sorted.1)
+                .collect();
+        }
+    }
+    let scanned = find_zeros_scanned(phase, sorted);
+    if !scanned.is_empty() {
+        return scanned;
+    }
+    find_zeros_deflated(phase, view)
+}
+
+// bisected sign changes of the validity function between neighbouring scan points, zeros closer
+// than the spacing of the scan are missed
+fn find_zeros_scanned(phase: &Phase, view: (f64, f64)) -> Vec<f64> {
+    let valid = validity_func(phase.clone());
+    let grid = Grid::uniform(view, ZERO_SCAN_POINTS);
+    let values: Vec<f64> = grid.points().par_iter().map(|x| valid(*x)).collect();
+    (1..grid.len())
+        .filter(|i| values[i - 1].is_finite() && values[*i].is_finite())
+        .filter(|i| (values[i - 1] < 0.0) != (values[*i] < 0.0))
+        .map(|i| bisect_crossing(valid.as_ref(), grid.x(i - 1), grid.x(i), 0.0).0)
+        .collect()
+}
+
+// The roots of Chebyshev fits of V - E and of
+//     +-c V' - (V - E)^2
+// where V' has that sign, the validity function without the kink of |V'|. The derivative comes
+// from the fit, the finite differences of the validity function itself are too noisy for it.
+fn find_zeros_global(phase: &Phase, view: (f64, f64)) -> Option<Vec<f64>> {
+    let shifted = chebyshev::fit_real(
+        &|x| (phase.potential)(x) - phase.energy,
+        &[view.0, view.1],
+        GLOBAL_ROOT_TOLERANCE,
+    )?;
+    let slope = shifted.derivative();
+    let c = phase.hbar / (2.0 * phase.mass).sqrt() * VALIDITY_LL_FACTOR;
+    let breakpoints = shifted.breakpoints();
+
+    let mut zeros = vec![];
+    for sign in [1.0, -1.0] {
+        let branch = |x: f64| sign * c * slope.eval(x).re - shifted.eval(x).re.powi(2);
+        let proxy = chebyshev::fit_real(&branch, &breakpoints, GLOBAL_ROOT_TOLERANCE)?;
+        zeros.extend(
+            proxy
+                .real_roots(GLOBAL_ROOT_TOLERANCE * (view.1 - view.0))
+                .into_iter()
+                .filter(|x| sign * slope.eval(*x).re >= 0.0),
+        );
+    }
+    zeros.sort_by(cmp_f64);
+    Some(chebyshev::polish_roots(
+        validity_func(phase.clone()).as_ref(),
+        &zeros,
+        BOUNDARY_TOLERANCE,
+    ))
+}
+
+// zeros of the validity function of a polynomial V in the view, the roots of the polynomials
+//     +-c V' - (V - E)^2
+// where V' has that sign
+fn polynomial_zeros(phase: &Phase, polynomial: &Polynomial, view: (f64, f64)) -> Vec<f64> {
+    let slope = polynomial.derivative();
+    let shifted = polynomial.shifted(phase.energy);
+    let square = shifted.product(&shifted).scaled(-1.0);
+    let c = phase.hbar / (2.0 * phase.mass).sqrt() * VALIDITY_LL_FACTOR;
+
+    // the slope is only known to its change over the precision of the roots, a zero where it is
+    // tangent to 0, e.g. at the top of a barrier, is a root of both signs
+    let precision = polynomial.root_precision();
+    let curvature = slope.derivative();
+    let mut zeros = vec![];
+    for sign in [1.0, -1.0] {
+        zeros.extend(
+            slope
+                .scaled(sign * c)
+                .sum(&square)
+                .real_roots()
+                .into_iter()
+                .filter(|x| {
+                    sign * slope.eval(*x) >= -precision * curvature.eval(*x).abs()
+                        && *x > view.0
+                        && *x < view.1
+                }),
+        );
+    }
+    zeros.sort_by(cmp_f64);
+    zeros.dedup_by(|b, a| (*b - *a).abs() <= precision);
+    zeros
+}
+
+fn find_zeros_deflated(phase: &Phase, view: (f64, f64)) -> Vec<f64> {
     let phase_clone = phase.clone();
     let validity_func = Arc::new(move |x: f64| {
-        1.0 / (2.0 * phase_clone.mass).sqrt()
+        phase_clone.hbar / (2.0 * phase_clone.mass).sqrt()
             * derivative(&|t| (phase_clone.potential)(t), x).abs()
             * VALIDITY_LL_FACTOR
             - ((phase_clone.potential)(x) - phase_clone.energy).pow(2)
     });
-    let mut zeros = NewtonsMethodFindNewZero::new(validity_func, ACCURACY, 1e4 as usize);
+    let mut zeros = NewtonsMethodFindNewZero::new(validity_func, BOUNDARY_TOLERANCE, 1e4 as usize);
 
     for _ in 0..MAX_TURNING_POINTS {
         let modified_func = |x| zeros.modified_func(x);
 
-        let guess = make_guess(&modified_func, view, 1000);
-        let result = guess.map(|g| zeros.next_zero(g)).flatten();
-        if result.is_none() {
+        let guesses = make_guesses(
+            &modified_func,
+            view,
+            1000,
+            GUESS_CANDIDATES,
+            &zeros.get_previous_zeros(),
+            BOUNDARY_TOLERANCE,
+        );
+        // a repeated zero means there are no new ones left
+        let found = zeros.get_distinct_zeros().len();
+        if zeros.next_zero_from(&guesses).is_none() || zeros.get_distinct_zeros().len() == found {
             break;
         }
     }
@@ -133,7 +481,7 @@ fn find_zeros(phase: &Phase, view: (f64, f64)) -> Vec<f64> {
         (view.1, view.0)
     };
     let unique_zeros = zeros
-        .get_previous_zeros()
+        .get_distinct_zeros()
         .iter()
         .filter(|x| **x > view.0 && **x < view.1)
         .map(|x| *x)
@@ -145,52 +493,169 @@ fn find_zeros(phase: &Phase, view: (f64, f64)) -> Vec<f64> {
 mod test {
     use super::*;
 
-    extern crate test;
+    use std::sync::Arc;
 
-    use test::Bencher;
+    fn half_square(x: f64) -> f64 {
+        if x < 0.0 {
+            f64::INFINITY
+        } else {
+            x * x
+        }
+    }
 
-    use duplicate::duplicate_item;
-    use paste::paste;
-    use std::sync::Arc;
+    #[test]
+    fn overlapping_airy_ranges() {
+        let mut groups = TGroup::new();
+        groups.add_ts(((0.5, 1.6), 1.0));
+        groups.add_ts(((-1.5, -0.5), -1.0));
+        groups.add_ts(((1.2, 2.5), 2.0));
+        assert_eq!(groups.separate_airy_ranges(), 1);
+        assert_eq!(
+            groups.ts,
+            vec![((-1.5, -0.5), -1.0), ((0.5, 1.5), 1.0), ((1.5, 2.5), 2.0)]
+        );
+        assert_eq!(groups.separate_airy_ranges(), 0);
 
-    #[duplicate_item(
-        num;
-        [1];
-        [2];
-        [3];
-        [4];
-        [5];
-        [6];
-        [7];
-        [8];
-        [9];
-    )]
-    paste! {
-        #[bench]
-        fn [< turning_point_square_nenergy_ num >](b: &mut Bencher){
-            let potential = &potentials::square;
-            let mass = 1.0;
-
-            let energy = energy::nth_energy(num, mass, potential, APPROX_INF);
-            let lower_bound = newtons_method::newtons_method(
-                &|x| potential(x) - energy,
-                APPROX_INF.0,
-                1e-7,
-            );
-            let upper_bound = newtons_method::newtons_method(
-                &|x| potential(x) - energy,
-                APPROX_INF.1,
-                1e-7,
-            );
-            let phase = Arc::new(Phase::new(energy, mass, potential));
-            let view =
-                (
-                    lower_bound - (upper_bound - lower_bound) * VIEW_FACTOR,
-                    upper_bound + (upper_bound - lower_bound) * VIEW_FACTOR,
-                );
-            b.iter(|| {
-                let _result = AiryWaveFunction::new(phase.clone(), test::black_box((view.0, view.1)));
-            });
+        // just below the top of the barrier the two inner turning points are close
+        let phase = Phase::new(255.75, 1.0, UNITS.hbar, Arc::new(potentials::mexican_hat));
+        let ts = calc_ts(&phase, (-10.0, 10.0)).unwrap().ts;
+        assert_eq!(ts.len(), 4);
+        for (left, right) in ts.iter().zip(ts.iter().skip(1)) {
+            assert!(left.0 .1 <= right.0 .0, "{:?} {:?}", left, right);
+            assert!(is_in_range(left.0, left.1) && is_in_range(right.0, right.1));
         }
+
+        // a zero without its partner is reported, not asserted
+        let polynomial = Polynomial::detect(&potentials::mexican_hat, (-10.0, 10.0)).unwrap();
+        let mut zeros = polynomial_zeros(&phase, &polynomial, (-10.0, 10.0));
+        zeros.remove(1);
+        assert!(group_ts(&zeros, &phase, &[]).is_err());
+    }
+
+    #[test]
+    fn global_zeros() {
+        for (potential, energy) in [
+            (
+                Arc::new(potentials::square) as potentials::SharedPotential,
+                2.0,
+            ),
+            (
+                Arc::new(potentials::mexican_hat) as potentials::SharedPotential,
+                100.0,
+            ),
+        ] {
+            let phase = Phase::new(energy, 1.0, UNITS.hbar, potential);
+            let global = find_zeros_global(&phase, (-10.0, 10.0)).unwrap();
+            let mut deflated = find_zeros_deflated(&phase, (-10.0, 10.0));
+            deflated.sort_by(cmp_f64);
+            assert_eq!(global.len(), deflated.len());
+            for (g, d) in global.iter().zip(deflated.iter()) {
+                assert!((g - d).abs() < 1e-8, "{} {}", g, d);
+            }
+        }
+        assert!(find_zeros_global(
+            &Phase::new(2.0, 1.0, UNITS.hbar, Arc::new(half_square)),
+            APPROX_INF
+        )
+        .is_none());
+
+        let (left, right) = classical_turning_points(&potentials::square, 2.0, APPROX_INF).unwrap();
+        assert!((left + 2.0f64.sqrt()).abs() < 1e-7 && (right - 2.0f64.sqrt()).abs() < 1e-7);
+    }
+
+    #[test]
+    fn polynomial_turning_points() {
+        let phase = Phase::new(100.0, 1.0, UNITS.hbar, Arc::new(potentials::mexican_hat));
+        let polynomial = Polynomial::detect(&potentials::mexican_hat, (-10.0, 10.0)).unwrap();
+        let exact = polynomial_zeros(&phase, &polynomial, (-10.0, 10.0));
+        let mut deflated = find_zeros_deflated(&phase, (-10.0, 10.0));
+        deflated.sort_by(cmp_f64);
+        assert_eq!(exact.len(), deflated.len());
+        for (e, d) in exact.iter().zip(deflated.iter()) {
+            assert!((e - d).abs() < 1e-8, "{} {}", e, d);
+        }
+
+        let ts = calc_ts(&phase, (-10.0, 10.0)).unwrap().ts;
+        let expected = [
+            -26.0f64.sqrt(),
+            -6.0f64.sqrt(),
+            6.0f64.sqrt(),
+            26.0f64.sqrt(),
+        ];
+        assert_eq!(ts.len(), 4);
+        for ((range, t), expected) in ts.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-9, "{}", t);
+            assert!(is_in_range(*range, *t));
+        }
+
+        // at the top of the barrier the validity function only touches zero, once
+        let phase = Phase::new(256.0, 1.0, UNITS.hbar, Arc::new(potentials::mexican_hat));
+        let zeros = polynomial_zeros(&phase, &polynomial, (-10.0, 10.0));
+        assert_eq!(
+            zeros.iter().filter(|z| z.abs() < 1e-6).count(),
+            1,
+            "{:?}",
+            zeros
+        );
+        let ts = calc_ts(&phase, (-10.0, 10.0)).unwrap().ts;
+        assert_eq!(ts.len(), 3);
+        assert!(ts[1].1.abs() < 1e-6 && is_in_range(ts[1].0, 0.0));
+    }
+
+    #[test]
+    fn boundary_types() {
+        assert_eq!(
+            outer_boundaries(&potentials::square, 2.0, APPROX_INF),
+            (Boundary::Soft, Boundary::Soft)
+        );
+        assert_eq!(
+            outer_boundaries(&half_square, 2.0, APPROX_INF),
+            (Boundary::HardWall, Boundary::Soft)
+        );
+        let finite_box = |x: f64| if x.abs() < 1.0 { 0.0 } else { 1e6 };
+        assert_eq!(
+            outer_boundaries(&finite_box, 2.0, APPROX_INF),
+            (Boundary::HardWall, Boundary::HardWall)
+        );
+        // no turning points below the well
+        assert_eq!(
+            outer_boundaries(&potentials::square, -1.0, APPROX_INF),
+            (Boundary::Soft, Boundary::Soft)
+        );
+
+        assert_eq!(boundary_at(&half_square, 0.0, 2.0), Boundary::HardWall);
+        assert_eq!(
+            boundary_at(&half_square, 2.0f64.sqrt(), 2.0),
+            Boundary::Soft
+        );
+    }
+
+    #[test]
+    fn coulomb_falls_back_to_scans() {
+        // the fits fail on the pole of 1/|x|, its zeros and turning points are searched by scans
+        let coulomb = |x: f64| -1.0 / x.abs();
+        let phase = Phase::new(-0.5, 1.0, UNITS.hbar, Arc::new(coulomb));
+        assert!(chebyshev::fit_real(
+            &coulomb,
+            &[APPROX_INF.0, APPROX_INF.1],
+            GLOBAL_ROOT_TOLERANCE
+        )
+        .is_none());
+        assert!(find_zeros_global(&phase, APPROX_INF).is_none());
+
+        let zeros = find_zeros(&phase, (-20.0, 20.0));
+        assert_eq!(zeros, find_zeros_scanned(&phase, (-20.0, 20.0)));
+        // the validity function is positive on both sides of the pole
+        assert_eq!(zeros.len(), 2, "{:?}", zeros);
+        assert!((zeros[0] + zeros[1]).abs() < 1e-6, "{:?}", zeros);
+        let valid = validity_func(phase.clone());
+        for z in &zeros {
+            assert!(valid(*z).abs() < 1e-6, "{}", valid(*z));
+        }
+
+        // newtons method runs off the flat tails
+        let (left, right) = classical_turning_points(&coulomb, -0.5, APPROX_INF).unwrap();
+        assert!((left + 2.0).abs() < 1e-7, "{}", left);
+        assert!((right - 2.0).abs() < 1e-7, "{}", right);
     }
 }
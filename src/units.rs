@@ -0,0 +1,212 @@
+use serde::Serialize;
+
+pub const HBAR_SI: f64 = 1.054571817e-34;
+pub const SPEED_OF_LIGHT_SI: f64 = 299792458.0;
+pub const ELECTRON_MASS_SI: f64 = 9.1093837015e-31;
+pub const PROTON_MASS_SI: f64 = 1.67262192369e-27;
+pub const NEUTRON_MASS_SI: f64 = 1.67492749804e-27;
+pub const MUON_MASS_SI: f64 = 1.883531627e-28;
+pub const ATOMIC_MASS_UNIT_SI: f64 = 1.66053906660e-27;
+pub const BOHR_RADIUS_SI: f64 = 5.29177210903e-11;
+pub const HARTREE_SI: f64 = 4.3597447222071e-18;
+pub const ELECTRON_VOLT_SI: f64 = 1.602176634e-19;
+pub const ANGSTROM_SI: f64 = 1e-10;
+
+// Unit system the solver works in. mass, length and energy are the sizes of the units in SI,
+// hbar is the value of the reduced Planck constant expressed in these units.
+// The solver expects the numbers of a problem to be of order one (nth_energy scans the energy
+// in fixed steps), so pick a unit system matching the scale of the problem.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Units {
+    pub mass: f64,
+    pub length: f64,
+    pub energy: f64,
+    pub hbar: f64,
+}
+
+impl Units {
+    pub const SI: Units = Units {
+        mass: 1.0,
+        length: 1.0,
+        energy: 1.0,
+        hbar: HBAR_SI,
+    };
+
+    // electron mass, bohr radius, hartree
+    pub const ATOMIC: Units = Units {
+        mass: ELECTRON_MASS_SI,
+        length: BOHR_RADIUS_SI,
+        energy: HARTREE_SI,
+        hbar: 1.0,
+    };
+
+    // hbar = c = 1 with energies in eV, masses in eV/c^2 and lengths in hbar c / eV
+    pub const NATURAL: Units = Units {
+        mass: ELECTRON_VOLT_SI / (SPEED_OF_LIGHT_SI * SPEED_OF_LIGHT_SI),
+        length: HBAR_SI * SPEED_OF_LIGHT_SI / ELECTRON_VOLT_SI,
+        energy: ELECTRON_VOLT_SI,
+        hbar: 1.0,
+    };
+
+    // atomic mass unit, angstrom, eV
+    pub const MOLECULAR: Units = Units {
+        mass: ATOMIC_MASS_UNIT_SI,
+        length: ANGSTROM_SI,
+        energy: ELECTRON_VOLT_SI,
+        hbar: 0.06465415130134121,
+    };
+
+    pub fn custom(mass: f64, length: f64, energy: f64) -> Units {
+        Units {
+            mass,
+            length,
+            energy,
+            hbar: HBAR_SI / (length * (mass * energy).sqrt()),
+        }
+    }
+
+    // size of the time unit in seconds, follows from the other units
+    pub fn time(&self) -> f64 {
+        HBAR_SI / (self.hbar * self.energy)
+    }
+
+    pub fn mass_to_si(&self, mass: f64) -> f64 {
+        mass * self.mass
+    }
+
+    pub fn mass_from_si(&self, mass: f64) -> f64 {
+        mass / self.mass
+    }
+
+    pub fn length_to_si(&self, length: f64) -> f64 {
+        length * self.length
+    }
+
+    pub fn length_from_si(&self, length: f64) -> f64 {
+        length / self.length
+    }
+
+    pub fn energy_to_si(&self, energy: f64) -> f64 {
+        energy * self.energy
+    }
+
+    pub fn energy_from_si(&self, energy: f64) -> f64 {
+        energy / self.energy
+    }
+
+    pub fn convert_mass(&self, mass: f64, to: &Units) -> f64 {
+        to.mass_from_si(self.mass_to_si(mass))
+    }
+
+    pub fn convert_length(&self, length: f64, to: &Units) -> f64 {
+        to.length_from_si(self.length_to_si(length))
+    }
+
+    pub fn convert_energy(&self, energy: f64, to: &Units) -> f64 {
+        to.energy_from_si(self.energy_to_si(energy))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Particle {
+    Electron,
+    Muon,
+    Proton,
+    Neutron,
+    // mass in atomic mass units, e.g. Atom(1.00782503207) for hydrogen-1
+    Atom(f64),
+}
+
+impl Particle {
+    pub fn mass_si(&self) -> f64 {
+        match self {
+            Particle::Electron => ELECTRON_MASS_SI,
+            Particle::Muon => MUON_MASS_SI,
+            Particle::Proton => PROTON_MASS_SI,
+            Particle::Neutron => NEUTRON_MASS_SI,
+            Particle::Atom(amu) => amu * ATOMIC_MASS_UNIT_SI,
+        }
+    }
+
+    pub fn mass(&self, units: &Units) -> f64 {
+        units.mass_from_si(self.mass_si())
+    }
+}
+
+pub fn reduced_mass(m1: f64, m2: f64) -> f64 {
+    m1 * m2 / (m1 + m2)
+}
+
+pub fn reduced_mass_of(p1: Particle, p2: Particle, units: &Units) -> f64 {
+    reduced_mass(p1.mass(units), p2.mass(units))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn float_compare(expect: f64, actual: f64, epsilon: f64) -> bool {
+        (expect - actual).abs() / expect.abs() < epsilon
+    }
+
+    #[test]
+    fn hbar_of_presets() {
+        for units in [Units::SI, Units::ATOMIC, Units::NATURAL, Units::MOLECULAR] {
+            let custom = Units::custom(units.mass, units.length, units.energy);
+            assert!(float_compare(units.hbar, custom.hbar, 1e-8));
+        }
+    }
+
+    #[test]
+    fn time_units() {
+        assert!(float_compare(Units::SI.time(), 1.0, 1e-12));
+        assert!(float_compare(
+            Units::ATOMIC.time(),
+            2.4188843265857e-17,
+            1e-8
+        ));
+    }
+
+    #[test]
+    fn energy_conversion() {
+        let hartree_in_ev = Units::ATOMIC.convert_energy(1.0, &Units::MOLECULAR);
+        assert!(float_compare(hartree_in_ev, 27.211386245988, 1e-10));
+        assert!(float_compare(
+            Units::MOLECULAR.convert_energy(hartree_in_ev, &Units::ATOMIC),
+            1.0,
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn particle_masses() {
+        assert_eq!(Particle::Electron.mass(&Units::ATOMIC), 1.0);
+        assert!(float_compare(
+            Particle::Proton.mass(&Units::ATOMIC),
+            1836.15267343,
+            1e-10
+        ));
+        assert!(float_compare(
+            Particle::Electron.mass(&Units::NATURAL),
+            510998.95,
+            1e-8
+        ));
+        assert_eq!(Particle::Atom(12.0).mass(&Units::MOLECULAR), 12.0);
+    }
+
+    #[test]
+    fn reduced_masses() {
+        assert_eq!(reduced_mass(2.0, 2.0), 1.0);
+        assert!(float_compare(
+            reduced_mass_of(Particle::Electron, Particle::Proton, &Units::ATOMIC),
+            0.999455679,
+            1e-9
+        ));
+        let hydrogen = Particle::Atom(1.00782503207);
+        assert!(float_compare(
+            reduced_mass_of(hydrogen, hydrogen, &Units::MOLECULAR),
+            0.503912516,
+            1e-9
+        ));
+    }
+}
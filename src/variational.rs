@@ -0,0 +1,89 @@
+use crate::*;
+
+// Variational estimate of the ground state energy. The trial wave function is a real function
+// of the parameters and x, <H> is minimized over the parameters.
+pub struct VariationalResult {
+    pub parameters: Vec<f64>,
+    pub energy: f64,
+    pub wkb_energy: f64,
+}
+
+// <psi|H|psi> / <psi|psi>, the kinetic term is integrated by parts to hbar^2 / (2m) |psi'|^2
+pub fn expectation_energy<F, T>(
+    potential: &F,
+    mass: f64,
+    trial: &T,
+    parameters: &[f64],
+    view: (f64, f64),
+) -> f64
+where
+    F: Fn(f64) -> f64 + Sync,
+    T: Fn(&[f64], f64) -> f64 + Sync,
+{
+    let kinetic_factor = UNITS.hbar.powi(2) / (2.0 * mass);
+    let (energy, norm): (Vec<_>, Vec<_>) = (0..INTEG_STEPS)
+        .into_par_iter()
+        .map(|i| {
+            let x = index_to_range(i as f64, 0.0, (INTEG_STEPS - 1) as f64, view.0, view.1);
+            let psi = trial(parameters, x);
+            let psi_prime = derivative(&|x| trial(parameters, x), x);
+            let density = kinetic_factor * psi_prime * psi_prime + potential(x) * psi * psi;
+            (Point { x, y: density }, Point { x, y: psi * psi })
+        })
+        .unzip();
+
+    integrate(energy, TRAPEZE_PER_THREAD) / integrate(norm, TRAPEZE_PER_THREAD)
+}
+
+pub fn minimize<F, T>(
+    potential: &F,
+    mass: f64,
+    trial: &T,
+    start: &[f64],
+    view: (f64, f64),
+) -> VariationalResult
+where
+    F: Fn(f64) -> f64 + Sync,
+    T: Fn(&[f64], f64) -> f64 + Sync,
+{
+    let (parameters, energy) = optimize::nelder_mead(
+        &|p: &[f64]| expectation_energy(potential, mass, trial, p, view),
+        start,
+        0.1,
+        1e-10,
+        10000,
+    );
+    let wkb_energy = energy::nth_energy(0, mass, UNITS.hbar, potential, view);
+    println!(
+        "Variational energy: {:.9} (WKB: {:.9}) at {:?}",
+        energy, wkb_energy, parameters
+    );
+
+    VariationalResult {
+        parameters,
+        energy,
+        wkb_energy,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gaussian_in_harmonic_oscillator() {
+        // exact for V = x^2 and m = 1: psi = exp(-a x^2) with a = 1 / sqrt(2), E = 1 / sqrt(2)
+        let trial = |p: &[f64], x: f64| (-p[0].abs() * x * x).exp();
+        let view = (-10.0, 10.0);
+        let (parameters, energy) = optimize::nelder_mead(
+            &|p: &[f64]| expectation_energy(&potentials::square, 1.0, &trial, p, view),
+            &[0.3],
+            0.1,
+            1e-12,
+            1000,
+        );
+
+        assert!((parameters[0].abs() - 0.5f64.sqrt()).abs() < 1e-3);
+        assert!((energy - 0.5f64.sqrt()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::*;
+use wasm_bindgen::prelude::*;
+
+fn make_potential(name: &str, parameters: &[f64]) -> Option<SharedPotential> {
+    let parameter = |i: usize, default: f64| *parameters.get(i).unwrap_or(&default);
+    match name {
+        // a * x^2
+        "square" => {
+            let a = parameter(0, 1.0);
+            Some(Arc::new(move |x| a * x * x))
+        }
+        // a * (x - b)^2 * (x + b)^2
+        "mexican_hat" => {
+            let (a, b) = (parameter(0, 1.0), parameter(1, 4.0));
+            Some(Arc::new(move |x| a * (x - b).powi(2) * (x + b).powi(2)))
+        }
+        // a * |x|^p
+        "power" => {
+            let (a, p) = (parameter(0, 1.0), parameter(1, 2.0));
+            Some(Arc::new(move |x: f64| a * x.abs().powf(p)))
+        }
+        _ => None,
+    }
+}
+
+// State of a browser demo, the wave function is recomputed on demand after the inputs change.
+#[wasm_bindgen]
+pub struct WasmSolver {
+    potential: SharedPotential,
+    mass: f64,
+    n_energy: usize,
+    wave_function: Option<WaveFunction>,
+}
+
+#[wasm_bindgen]
+impl WasmSolver {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmSolver {
+        WasmSolver {
+            potential: make_potential("square", &[]).unwrap(),
+            mass: 1.0,
+            n_energy: 0,
+            wave_function: None,
+        }
+    }
+
+    pub fn potentials() -> Vec<JsValue> {
+        ["square", "mexican_hat", "power"]
+            .iter()
+            .map(|name| JsValue::from_str(name))
+            .collect()
+    }
+
+    pub fn set_potential(&mut self, name: &str, parameters: Vec<f64>) -> Result<(), JsValue> {
+        let potential = make_potential(name, &parameters)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown potential '{}'", name)))?;
+        self.potential = potential;
+        self.wave_function = None;
+        Ok(())
+    }
+
+    pub fn set_mass(&mut self, mass: f64) {
+        self.mass = mass;
+        self.wave_function = None;
+    }
+
+    pub fn set_energy_level(&mut self, n_energy: usize) {
+        self.n_energy = n_energy;
+        self.wave_function = None;
+    }
+
+    fn wave_function(&mut self) -> &WaveFunction {
+        if self.wave_function.is_none() {
+            self.wave_function = Some(WaveFunction::new(
+                self.potential.clone(),
+                self.mass,
+                self.n_energy,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::Renormalize(1.0.into()),
+            ));
+        }
+        self.wave_function.as_ref().unwrap()
+    }
+
+    pub fn energy(&mut self) -> f64 {
+        self.wave_function().get_energy()
+    }
+
+    // [start, end] of the region worth plotting
+    pub fn view(&mut self) -> Vec<f64> {
+        let view = self.wave_function().get_view();
+        vec![view.0, view.1]
+    }
+
+    pub fn sample_x(&mut self, n: usize) -> Vec<f64> {
+        let view = self.wave_function().get_view();
+        // a single sample is at the start of the view
+        let last = (n.max(2) - 1) as f64;
+        (0..n)
+            .map(|i| index_to_range(i as f64, 0.0, last, view.0, view.1))
+            .collect()
+    }
+
+    fn sample(&mut self, n: usize) -> Vec<Point<f64, Complex64>> {
+        let xs = self.sample_x(n);
+        let wave_function = self.wave_function();
+        xs.into_par_iter()
+            .map(|x| Point {
+                x,
+                y: wave_function.eval(x),
+            })
+            .collect()
+    }
+
+    pub fn sample_re(&mut self, n: usize) -> Vec<f64> {
+        self.sample(n).iter().map(|p| p.y.re).collect()
+    }
+
+    pub fn sample_im(&mut self, n: usize) -> Vec<f64> {
+        self.sample(n).iter().map(|p| p.y.im).collect()
+    }
+
+    pub fn sample_probability(&mut self, n: usize) -> Vec<f64> {
+        self.sample(n).iter().map(|p| p.y.norm_sqr()).collect()
+    }
+
+    pub fn sample_potential(&mut self, n: usize) -> Vec<f64> {
+        let potential = self.potential;
+        self.sample_x(n).iter().map(|x| potential(*x)).collect()
+    }
+}
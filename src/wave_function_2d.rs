@@ -0,0 +1,122 @@
+use crate::*;
+
+/// Separable product state psi_{n,m}(x, y) = psi_n(x) * psi_m(y) built from two 1D wave functions.
+#[derive(Clone)]
+pub struct WaveFunction2D {
+    psi_x: WaveFunction,
+    psi_y: WaveFunction,
+    scaling: Complex64,
+}
+
+impl WaveFunction2D {
+    pub fn new(psi_x: WaveFunction, psi_y: WaveFunction, scaling: ScalingType) -> WaveFunction2D {
+        match scaling {
+            ScalingType::Mul(s) => WaveFunction2D {
+                psi_x,
+                psi_y,
+                scaling: s,
+            },
+            ScalingType::None => WaveFunction2D {
+                psi_x,
+                psi_y,
+                scaling: complex(1.0, 0.0),
+            },
+            ScalingType::Renormalize(s) => {
+                // the state is separable, so the norm factorizes into the norms along x and y,
+                // integrated over the same domain as in WaveFunction::scale
+                let factor = renormalize_factor(&psi_x, NORMALIZATION_DOMAIN.range(&psi_x))
+                    * renormalize_factor(&psi_y, NORMALIZATION_DOMAIN.range(&psi_y));
+                WaveFunction2D {
+                    psi_x,
+                    psi_y,
+                    scaling: s * factor,
+                }
+            }
+        }
+    }
+
+    pub fn get_energy(&self) -> f64 {
+        self.psi_x.get_energy() + self.psi_y.get_energy()
+    }
+
+    pub fn get_psi_x(&self) -> &WaveFunction {
+        &self.psi_x
+    }
+
+    pub fn get_psi_y(&self) -> &WaveFunction {
+        &self.psi_y
+    }
+
+    pub fn get_view(&self) -> ((f64, f64), (f64, f64)) {
+        (self.psi_x.get_view(), self.psi_y.get_view())
+    }
+
+    pub fn probability_grid(&self, n_x: usize, n_y: usize) -> Vec<Vec<Point<(f64, f64), f64>>> {
+        let (view_x, view_y) = self.get_view();
+        evaluate_function_on_grid(self, view_x, view_y, n_x, n_y)
+            .par_iter()
+            .map(|row| {
+                row.iter()
+                    .map(|p| Point {
+                        x: p.x,
+                        y: p.y.norm_sqr(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Func<(f64, f64), Complex64> for WaveFunction2D {
+    fn eval(&self, (x, y): (f64, f64)) -> Complex64 {
+        self.scaling * self.psi_x.eval(x) * self.psi_y.eval(y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renormalized_over_the_domain() {
+        let state = WaveFunction2D::new(
+            WaveFunction::new(
+                Arc::new(potentials::square),
+                1.0,
+                2,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::None,
+            ),
+            WaveFunction::new(
+                Arc::new(potentials::square),
+                1.0,
+                3,
+                APPROX_INF,
+                VIEW_FACTOR,
+                ScalingType::None,
+            ),
+            ScalingType::Renormalize(1.0.into()),
+        );
+
+        // |psi(x, y)|^2 factorizes, its integral over the plane is the product of the integrals
+        // along two lines through (x0, y0) divided by |psi(x0, y0)|^2
+        let (x0, y0) = (0.3, 0.2);
+        let along = |range: (f64, f64), at: &dyn Fn(f64) -> (f64, f64)| {
+            let grid = Grid::uniform(range, INTEG_STEPS);
+            integrate(
+                (0..grid.len())
+                    .map(|i| Point {
+                        x: grid.x(i),
+                        y: state.eval(at(grid.x(i))).norm_sqr(),
+                    })
+                    .collect(),
+                TRAPEZE_PER_THREAD,
+            )
+        };
+        let norm = along(state.get_psi_x().domain(), &|x| (x, y0))
+            * along(state.get_psi_y().domain(), &|y| (x0, y))
+            / state.eval((x0, y0)).norm_sqr();
+        assert!((norm - 1.0).abs() < 1e-6, "{}", norm);
+    }
+}
@@ -1,8 +1,17 @@
+use crate::chebyshev::ChebyshevProxy;
+use crate::comparison::{BesselMapping, ComparisonLibrary, CylinderMapping};
+use crate::discontinuities::{Discontinuity, DiscontinuityKind, DiscontinuityPolicy};
+use crate::potential_cache::CachedPotential;
+use crate::smoothing::{SmoothedPotential, SmoothingChange};
+use crate::turning_points::Boundary;
 use crate::wkb_wave_func::Phase;
 use crate::*;
 use ordinal::Ordinal;
+use rand::Rng;
+use serde::Serialize;
 use std::sync::*;
 
+#[derive(Clone, Copy)]
 pub enum ScalingType {
     Mul(Complex64),
     Renormalize(Complex64),
@@ -24,6 +33,63 @@ pub fn is_in_range(range: (f64, f64), x: f64) -> bool {
     return range.0 <= x && range.1 > x;
 }
 
+// the mirror image of is_in_range, the right end belongs to the range
+pub fn is_in_range_from_left(range: (f64, f64), x: f64) -> bool {
+    range.0 < x && range.1 >= x
+}
+
+// Which part evaluates psi where one ends and the next starts. HalfOpen leaves it to the part
+// starting there, except at the end of the domain which belongs to the part ending there, Average
+// takes the mean of both parts.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum PartBoundary {
+    HalfOpen,
+    Average,
+}
+
+// Overall phase psi is rotated to after scaling, the solution only fixes it up to a constant.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum PhaseConvention {
+    // as it comes out of the parts
+    None,
+    // psi real and positive at the leftmost turning point, vanishes there at a hard wall
+    LeftTurningPoint,
+    // psi real with a positive slope at its first node, LeftTurningPoint without nodes
+    FirstNode,
+}
+
+// How psi is continued through the soft turning points.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum ApproximationKind {
+    // Airy functions around the turning points joined to the WKB functions between them
+    Patched,
+    // Langer's uniform approximation, one Airy function of the mapped argument from the middle of
+    // one well or barrier to the next, without joints
+    Uniform,
+}
+
+// first of the parts containing x, at a boundary shared by two parts they are combined by policy
+fn eval_parts(
+    parts: &[Arc<dyn WaveFunctionPart>],
+    x: f64,
+    policy: PartBoundary,
+) -> Option<Complex64> {
+    let starting = parts.iter().find(|p| is_in_range(p.range(), x));
+    if policy == PartBoundary::HalfOpen {
+        if let Some(part) = starting {
+            return Some(part.eval(x));
+        }
+    }
+    let ending = parts.iter().find(|p| is_in_range_from_left(p.range(), x));
+    match (starting, ending) {
+        (Some(starting), Some(ending)) if starting.range().0 == x && ending.range().1 == x => {
+            Some((starting.eval(x) + ending.eval(x)) / 2.0)
+        }
+        (Some(part), _) | (None, Some(part)) => Some(part.eval(x)),
+        (None, None) => None,
+    }
+}
+
 #[derive(Clone)]
 pub struct Joint {
     pub left: Arc<dyn Func<f64, Complex64>>,
@@ -103,6 +169,10 @@ impl Func<f64, Complex64> for PureWkb {
 struct ApproxPart {
     airy: Arc<AiryWaveFunction>,
     wkb: Arc<WkbWaveFunction>,
+    // wkb on the forbidden side of the turning point, scaled if the derivatives are matched
+    wkb_exp: Arc<WkbWaveFunction>,
+    // phase shift of wkb at the oscillating edge of the airy range, relaxed to 0 at the end of range
+    phase_shift: f64,
     airy_join_l: Joint,
     airy_join_r: Joint,
     range: (f64, f64),
@@ -127,22 +197,124 @@ impl WaveFunctionPartWithOp for ApproxPart {
     }
 
     fn with_op(&self, op: fn(Complex64) -> Complex64) -> Box<dyn WaveFunctionPartWithOp> {
-        Box::new(ApproxPart::new(
+        Box::new(ApproxPart::from_matched(
             self.airy.with_op(op),
             self.wkb.with_op(op),
+            self.wkb_exp.with_op(op),
+            self.phase_shift,
             self.range,
         ))
     }
 }
 
+// solution of [[a, b], [c, d]] (x, y) = rhs, none if the matrix is singular
+fn solve_2x2(matrix: [[f64; 2]; 2], rhs: [f64; 2]) -> Option<(f64, f64)> {
+    let [[a, b], [c, d]] = matrix;
+    let det = a * d - b * c;
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    Some((
+        (rhs[0] * d - b * rhs[1]) / det,
+        (a * rhs[1] - c * rhs[0]) / det,
+    ))
+}
+
+// Continues the decaying wkb function through the airy range with value and derivative. At the
+// forbidden edge of the airy range alpha Ai + beta Bi takes over from the exponential, at the
+// oscillating edge it is continued by a cos(I + phase_off) + b sin(I + phase_off), which is the wkb
+// function shifted in phase. Everything is scaled to keep the amplitude of the oscillating wkb,
+// returns the scaled airy and exponential wkb functions and the phase shift.
+fn match_derivatives(
+    airy: &AiryWaveFunction,
+    wkb: &WkbWaveFunction,
+) -> Option<(AiryWaveFunction, WkbWaveFunction, f64)> {
+    let forbidden = |x: f64| wkb.phase.energy < (wkb.phase.potential)(x);
+    let (forbidden_edge, oscillating_edge) = match (forbidden(airy.ts.0), forbidden(airy.ts.1)) {
+        (true, false) => (airy.ts.0, airy.ts.1),
+        (false, true) => (airy.ts.1, airy.ts.0),
+        _ => return None,
+    };
+
+    let unit = wkb.with_c(1.0.into());
+    let basis = airy.basis(forbidden_edge);
+    let (alpha, beta) = solve_2x2(
+        [
+            [basis.ai.re, basis.bi.re],
+            [basis.ai_prime.re, basis.bi_prime.re],
+        ],
+        [
+            unit.eval(forbidden_edge).re,
+            unit.derivative(forbidden_edge).re,
+        ],
+    )?;
+
+    let matched = airy.with_c(alpha.into()).with_c_bi(beta.into());
+    let sin = unit.with_phase_off(unit.phase_off - f64::consts::PI / 2.0);
+    let (a, b) = solve_2x2(
+        [
+            [
+                unit.eval(oscillating_edge).re,
+                sin.eval(oscillating_edge).re,
+            ],
+            [
+                unit.derivative(oscillating_edge).re,
+                sin.derivative(oscillating_edge).re,
+            ],
+        ],
+        [
+            matched.eval(oscillating_edge).re,
+            matched.derivative(oscillating_edge).re,
+        ],
+    )?;
+
+    let amplitude = a.hypot(b);
+    let scale = wkb.get_c() / amplitude;
+    if !(amplitude > 0.0 && scale.is_finite() && alpha.is_finite() && beta.is_finite()) {
+        return None;
+    }
+    Some((
+        airy.with_c(scale * alpha).with_c_bi(scale * beta),
+        wkb.with_c(scale),
+        -b.atan2(a),
+    ))
+}
+
 impl ApproxPart {
-    fn new(airy: AiryWaveFunction, wkb: WkbWaveFunction, range: (f64, f64)) -> ApproxPart {
+    fn new(
+        airy: AiryWaveFunction,
+        wkb: WkbWaveFunction,
+        range: (f64, f64),
+        match_edges: bool,
+    ) -> ApproxPart {
+        let matched = if match_edges {
+            match_derivatives(&airy, &wkb)
+        } else {
+            None
+        };
+        match matched {
+            Some((airy, wkb_exp, phase_shift)) => {
+                ApproxPart::from_matched(airy, wkb, wkb_exp, phase_shift, range)
+            }
+            None => ApproxPart::from_matched(airy, wkb.clone(), wkb, 0.0, range),
+        }
+    }
+
+    fn from_matched(
+        airy: AiryWaveFunction,
+        wkb: WkbWaveFunction,
+        wkb_exp: WkbWaveFunction,
+        phase_shift: f64,
+        range: (f64, f64),
+    ) -> ApproxPart {
         let airy_rc = Arc::new(airy);
         let wkb_rc = Arc::new(wkb);
         let delta = (airy_rc.ts.1 - airy_rc.ts.0) * AIRY_TRANSITION_FRACTION;
         ApproxPart {
             airy: airy_rc.clone(),
             wkb: wkb_rc.clone(),
+            wkb_exp: Arc::new(wkb_exp),
+            phase_shift,
             airy_join_l: Joint {
                 left: wkb_rc.clone(),
                 right: airy_rc.clone(),
@@ -160,16 +332,455 @@ impl ApproxPart {
     }
 }
 
+impl ApproxPart {
+    fn eval_with(&self, x: f64, policy: PartBoundary) -> Complex64 {
+        let joints = ENABLE_AIRY_JOINTS && !MATCH_AIRY_DERIVATIVES;
+        if is_in_range(self.airy_join_l.range(), x) && joints {
+            self.airy_join_l.eval(x)
+        } else if is_in_range(self.airy_join_r.range(), x) && joints {
+            self.airy_join_r.eval(x)
+        } else if policy == PartBoundary::Average && (x == self.airy.ts.0 || x == self.airy.ts.1) {
+            (self.airy.eval(x) + self.eval_wkb(x)) / 2.0
+        } else if is_in_range(self.airy.ts, x) {
+            self.airy.eval(x)
+        } else {
+            self.eval_wkb(x)
+        }
+    }
+
+    // psi outside of the airy range
+    fn eval_wkb(&self, x: f64) -> Complex64 {
+        if self.wkb.phase.energy < (self.wkb.phase.potential)(x) {
+            self.wkb_exp.eval(x)
+        } else if self.phase_shift == 0.0 {
+            self.wkb.eval(x)
+        } else {
+            // The shift makes up for the phase error of the linearized airy function at its
+            // oscillating edge, further out the WKB function is accurate on its own. It fades out
+            // within the transition width of the airy range, cos^2 keeps the derivative at both
+            // ends and psi is unchanged where it meets the next part.
+            let delta = (self.airy.ts.1 - self.airy.ts.0) * AIRY_TRANSITION_FRACTION;
+            let (edge, end) = if x < self.airy.ts.0 {
+                (self.airy.ts.0, f64::max(self.range.0, self.airy.ts.0 - delta))
+            } else {
+                (self.airy.ts.1, f64::min(self.range.1, self.airy.ts.1 + delta))
+            };
+            let weight = (((x - edge) / (end - edge)).clamp(0.0, 1.0) * f64::consts::PI / 2.0)
+                .cos()
+                .powi(2);
+            self.wkb
+                .with_phase_off(self.wkb.phase_off + self.phase_shift * weight)
+                .eval(x)
+        }
+    }
+}
+
 impl Func<f64, Complex64> for ApproxPart {
     fn eval(&self, x: f64) -> Complex64 {
-        if is_in_range(self.airy_join_l.range(), x) && ENABLE_AIRY_JOINTS {
-            return self.airy_join_l.eval(x);
-        } else if is_in_range(self.airy_join_r.range(), x) && ENABLE_AIRY_JOINTS {
-            return self.airy_join_r.eval(x);
-        } else if is_in_range(self.airy.ts, x) {
-            return self.airy.eval(x);
+        self.eval_with(x, PART_BOUNDARY)
+    }
+}
+
+// the Airy function of a turning point under Langer's mapping over the whole range of its WKB
+// function, see AiryWaveFunction::eval_uniform
+#[derive(Clone)]
+struct UniformPart {
+    airy: Arc<AiryWaveFunction>,
+    range: (f64, f64),
+}
+
+impl WaveFunctionPart for UniformPart {
+    fn range(&self) -> (f64, f64) {
+        self.range
+    }
+    fn as_func(&self) -> Box<dyn Func<f64, Complex64>> {
+        Box::new(self.clone())
+    }
+}
+
+impl WaveFunctionPartWithOp for UniformPart {
+    fn as_wave_function_part(&self) -> Box<dyn WaveFunctionPart> {
+        Box::new(self.clone())
+    }
+
+    fn get_op(&self) -> Box<fn(Complex64) -> Complex64> {
+        self.airy.get_op()
+    }
+
+    fn with_op(&self, op: fn(Complex64) -> Complex64) -> Box<dyn WaveFunctionPartWithOp> {
+        Box::new(UniformPart {
+            airy: Arc::new(self.airy.with_op(op)),
+            range: self.range,
+        })
+    }
+}
+
+impl Func<f64, Complex64> for UniformPart {
+    fn eval(&self, x: f64) -> Complex64 {
+        self.airy.eval_uniform(x)
+    }
+}
+
+// UniformPart with Bessel's equation in place of Airy's, from a centrifugal singularity through
+// the first turning point
+#[derive(Clone)]
+struct BesselPart {
+    mapping: Arc<BesselMapping>,
+    c: Complex64,
+    op: fn(Complex64) -> Complex64,
+    range: (f64, f64),
+}
+
+impl WaveFunctionPart for BesselPart {
+    fn range(&self) -> (f64, f64) {
+        self.range
+    }
+    fn as_func(&self) -> Box<dyn Func<f64, Complex64>> {
+        Box::new(self.clone())
+    }
+}
+
+impl WaveFunctionPartWithOp for BesselPart {
+    fn as_wave_function_part(&self) -> Box<dyn WaveFunctionPart> {
+        Box::new(self.clone())
+    }
+
+    fn get_op(&self) -> Box<fn(Complex64) -> Complex64> {
+        Box::new(self.op)
+    }
+
+    fn with_op(&self, op: fn(Complex64) -> Complex64) -> Box<dyn WaveFunctionPartWithOp> {
+        Box::new(BesselPart { op, ..self.clone() })
+    }
+}
+
+impl Func<f64, Complex64> for BesselPart {
+    fn eval(&self, x: f64) -> Complex64 {
+        (self.op)(self.c * self.mapping.eval(x))
+    }
+}
+
+// c H_n(xi) e^(-xi^2 / 2) with xi = (x - x0) / length, n = 0 or 1, the eigenfunction of the
+// oscillator fitted to the bottom of a well
+#[derive(Clone)]
+struct HermiteFunction {
+    n: usize,
+    x0: f64,
+    length: f64,
+    c: Complex64,
+}
+
+impl Func<f64, Complex64> for HermiteFunction {
+    fn eval(&self, x: f64) -> Complex64 {
+        let xi = (x - self.x0) / self.length;
+        let hermite = if self.n == 0 { 1.0 } else { 2.0 * xi };
+        self.c * hermite * (-xi * xi / 2.0).exp()
+    }
+}
+
+// Even and odd solution of psi'' + (z^2 + 2 epsilon) psi = 0 with z = (x - x0) / length, the
+// inverted oscillator fitted to the top of a barrier with epsilon = (E - V(x0)) / (hbar omega).
+// They span the same space as the parabolic cylinder functions W(-epsilon, +-sqrt(2) z) and are
+// summed as power series, which converge everywhere. With a mapping z and the prefactor come from
+// it instead.
+#[derive(Clone)]
+struct ParabolicCylinder {
+    epsilon: f64,
+    x0: f64,
+    length: f64,
+    mapping: Option<Arc<CylinderMapping>>,
+    c_even: Complex64,
+    c_odd: Complex64,
+}
+
+impl ParabolicCylinder {
+    const MAX_TERMS: usize = 400;
+
+    // even(0) = 1, even'(0) = 0 and odd(0) = 0, odd'(0) = 1 by z
+    fn basis(&self, x: f64) -> (f64, f64) {
+        let (z, factor) = match &self.mapping {
+            Some(mapping) => mapping.map(x),
+            None => ((x - self.x0) / self.length, 1.0),
+        };
+        // coefficients k - 2, k - 1, k and k + 1 of both series, with
+        // (k + 2) (k + 1) a_(k + 2) = -2 epsilon a_k - a_(k - 2)
+        let mut even = [0.0, 0.0, 1.0, 0.0];
+        let mut odd = [0.0, 0.0, 0.0, 1.0];
+        let (mut even_sum, mut odd_sum) = (1.0, z);
+        let mut power = 1.0;
+        // at epsilon = 0 every other pair of terms vanishes, two negligible ones in a row end it
+        let mut negligible = false;
+        for k in (0..Self::MAX_TERMS).step_by(2) {
+            let next = |a: [f64; 4]| {
+                let k = k as f64;
+                [
+                    a[2],
+                    a[3],
+                    -(2.0 * self.epsilon * a[2] + a[0]) / ((k + 2.0) * (k + 1.0)),
+                    -(2.0 * self.epsilon * a[3] + a[1]) / ((k + 3.0) * (k + 2.0)),
+                ]
+            };
+            even = next(even);
+            odd = next(odd);
+            power *= z * z;
+            let terms = (even[2] + even[3] * z) * power;
+            let odd_terms = (odd[2] + odd[3] * z) * power;
+            even_sum += terms;
+            odd_sum += odd_terms;
+            // the coefficients fall off once k is well above z^2
+            let small = (k as f64) > z * z
+                && terms.abs() <= f64::EPSILON * even_sum.abs()
+                && odd_terms.abs() <= f64::EPSILON * odd_sum.abs();
+            if small && negligible {
+                break;
+            }
+            negligible = small;
+        }
+        (even_sum * factor, odd_sum * factor)
+    }
+}
+
+impl Func<f64, Complex64> for ParabolicCylinder {
+    fn eval(&self, x: f64) -> Complex64 {
+        let (even, odd) = self.basis(x);
+        self.c_even * even + self.c_odd * odd
+    }
+}
+
+// the parts an overlay is blended into
+#[derive(Clone)]
+struct Surrounding {
+    parts: Vec<Arc<dyn WaveFunctionPart>>,
+}
+
+impl Func<f64, Complex64> for Surrounding {
+    fn eval(&self, x: f64) -> Complex64 {
+        eval_parts(&self.parts, x, PART_BOUNDARY).unwrap_or(complex(0.0, 0.0))
+    }
+}
+
+// core in the range of the cuts, blended into the surrounding parts over transition on both sides
+#[derive(Clone)]
+struct Overlay {
+    core: Arc<dyn Func<f64, Complex64>>,
+    join_l: Joint,
+    join_r: Joint,
+}
+
+impl WaveFunctionPart for Overlay {
+    fn range(&self) -> (f64, f64) {
+        (self.join_l.range().0, self.join_r.range().1)
+    }
+    fn as_func(&self) -> Box<dyn Func<f64, Complex64>> {
+        Box::new(self.clone())
+    }
+}
+
+impl Overlay {
+    // points in each transition the core is fitted to the surrounding parts on
+    const FIT_POINTS: usize = 32;
+
+    fn new(
+        core: Arc<dyn Func<f64, Complex64>>,
+        surrounding: Arc<Surrounding>,
+        cuts: (f64, f64),
+        transition: f64,
+    ) -> Overlay {
+        Overlay {
+            core: core.clone(),
+            join_l: Joint {
+                left: surrounding.clone(),
+                right: core.clone(),
+                cut: cuts.0,
+                delta: -transition,
+            },
+            join_r: Joint {
+                left: core,
+                right: surrounding,
+                cut: cuts.1,
+                delta: transition,
+            },
+        }
+    }
+
+    fn fit_points(cuts: (f64, f64), transition: f64) -> Vec<f64> {
+        [
+            Grid::uniform((cuts.0 - transition, cuts.0), Self::FIT_POINTS).points(),
+            Grid::uniform((cuts.1, cuts.1 + transition), Self::FIT_POINTS).points(),
+        ]
+        .concat()
+    }
+
+    // the Hermite function of the oscillator level n fitted to the bottom of a well at x0
+    fn harmonic(n: usize, x0: f64, length: f64, surrounding: Arc<Surrounding>) -> Overlay {
+        let turning_point = length * (2.0 * n as f64 + 1.0).sqrt();
+        let core = turning_point * HARMONIC_CORE_FRACTION;
+        let transition = turning_point - core;
+
+        // least squares fit of c H_n e^(-xi^2 / 2) to the surrounding parts over both transitions
+        let mut hermite = HermiteFunction {
+            n,
+            x0,
+            length,
+            c: complex(1.0, 0.0),
+        };
+        let cuts = (x0 - core, x0 + core);
+        let (overlap, norm) = Self::fit_points(cuts, transition)
+            .par_iter()
+            .map(|x| {
+                let h = hermite.eval(*x).re;
+                (surrounding.eval(*x) * h, h * h)
+            })
+            .reduce(|| (complex(0.0, 0.0), 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+        hermite.c = overlap / norm;
+        Overlay::new(Arc::new(hermite), surrounding, cuts, transition)
+    }
+
+    // Both solutions of the inverted oscillator fitted to the top of a barrier at x0, or of the
+    // equation the mapping leads to, least squares fitted to the surrounding parts. Returns the
+    // relative rms residual of the fit as well.
+    fn barrier_top(
+        epsilon: f64,
+        x0: f64,
+        length: f64,
+        mapping: Option<CylinderMapping>,
+        surrounding: Arc<Surrounding>,
+    ) -> (Overlay, f64) {
+        let cuts = (
+            x0 - BARRIER_TOP_CORE * length,
+            x0 + BARRIER_TOP_CORE * length,
+        );
+        let transition = BARRIER_TOP_TRANSITION * length;
+        let mut cylinder = ParabolicCylinder {
+            epsilon: mapping.as_ref().map_or(epsilon, |m| m.epsilon),
+            x0,
+            length,
+            mapping: mapping.map(Arc::new),
+            c_even: complex(1.0, 0.0),
+            c_odd: complex(0.0, 0.0),
+        };
+        let samples: Vec<((f64, f64), Complex64)> = Self::fit_points(cuts, transition)
+            .par_iter()
+            .map(|x| (cylinder.basis(*x), surrounding.eval(*x)))
+            .collect();
+
+        // normal equations of the two real basis functions with complex coefficients
+        let (mut ee, mut eo, mut oo) = (0.0, 0.0, 0.0);
+        let (mut es, mut os) = (complex(0.0, 0.0), complex(0.0, 0.0));
+        for ((even, odd), psi) in &samples {
+            ee += even * even;
+            eo += even * odd;
+            oo += odd * odd;
+            es += psi * even;
+            os += psi * odd;
+        }
+        let determinant = ee * oo - eo * eo;
+        cylinder.c_even = (es * oo - os * eo) / determinant;
+        cylinder.c_odd = (os * ee - es * eo) / determinant;
+
+        let (residual, norm) = samples
+            .iter()
+            .map(|((even, odd), psi)| {
+                let fit = cylinder.c_even * even + cylinder.c_odd * odd;
+                ((psi - fit).norm_sqr(), psi.norm_sqr())
+            })
+            .fold((0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+        let residual = if norm > 0.0 {
+            (residual / norm).sqrt()
+        } else {
+            0.0
+        };
+        (
+            Overlay::new(Arc::new(cylinder), surrounding, cuts, transition),
+            residual,
+        )
+    }
+}
+
+impl Func<f64, Complex64> for Overlay {
+    fn eval(&self, x: f64) -> Complex64 {
+        if is_in_range(self.join_l.range(), x) {
+            self.join_l.eval(x)
+        } else if is_in_range(self.join_r.range(), x) {
+            self.join_r.eval(x)
         } else {
-            return self.wkb.eval(x);
+            self.core.eval(x)
+        }
+    }
+}
+
+// printed once, evaluating psi on a grid beyond its domain would repeat it for every point
+static OUT_OF_DOMAIN_WARNING: std::sync::Once = std::sync::Once::new();
+// the ends of the outermost parts are computed from mirrored turning points and may miss
+// approx_inf by a few ulps, relative to its width
+const RANGE_TOLERANCE: f64 = 1e-12;
+
+// The parts, sorted by their ranges, have to cover approx_inf without gaps or overlaps and every
+// airy range, inside of which the joints of its part are, has to lie in the range of its part.
+fn check_part_ranges(
+    part_ranges: &[(f64, f64)],
+    airy_ranges: &[(f64, f64)],
+    approx_inf: (f64, f64),
+) -> Result<(), String> {
+    let tolerance = (approx_inf.1 - approx_inf.0) * RANGE_TOLERANCE;
+    let mut ranges = part_ranges.to_vec();
+    ranges.sort_by(|a, b| cmp_f64(&a.0, &b.0));
+    let (first, last) = match (ranges.first(), ranges.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err("the wave function has no parts".to_string()),
+    };
+    if let Some(range) = ranges
+        .iter()
+        .find(|r| r.0 >= r.1 || r.0.is_nan() || r.1.is_nan())
+    {
+        return Err(format!("the part range {:?} is empty", range));
+    }
+    if (first.0 - approx_inf.0).abs() > tolerance || (last.1 - approx_inf.1).abs() > tolerance {
+        return Err(format!(
+            "the parts cover ({}, {}) instead of approx_inf {:?}",
+            first.0, last.1, approx_inf
+        ));
+    }
+    for (left, right) in ranges.iter().zip(ranges.iter().skip(1)) {
+        if left.1 < right.0 {
+            return Err(format!(
+                "gap ({}, {}) between the parts {:?} and {:?}",
+                left.1, right.0, left, right
+            ));
+        }
+        if left.1 > right.0 {
+            return Err(format!(
+                "the parts {:?} and {:?} overlap in ({}, {})",
+                left, right, right.0, left.1
+            ));
+        }
+    }
+    for (airy, part) in airy_ranges.iter().zip(part_ranges.iter()) {
+        if airy.0 < part.0 || airy.1 > part.1 {
+            return Err(format!(
+                "the airy range {:?} and its joints stick out of the part {:?}",
+                airy, part
+            ));
+        }
+    }
+    Ok(())
+}
+
+// approximation psi is evaluated with, the joints only exist without matched airy derivatives
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Region {
+    Wkb,
+    Airy,
+    Joint,
+}
+
+impl Region {
+    // column value in the plot data
+    pub fn code(&self) -> u8 {
+        match self {
+            Region::Wkb => 0,
+            Region::Airy => 1,
+            Region::Joint => 2,
         }
     }
 }
@@ -181,7 +792,11 @@ pub struct WaveFunction {
     parts: Vec<Arc<dyn WaveFunctionPart>>,
     airy_ranges: Vec<(f64, f64)>,
     wkb_ranges: Vec<(f64, f64)>,
+    // amplitude of each well over the one left of it, from the connection across the barrier
+    amplitude_ratios: Vec<f64>,
     scaling: Complex64,
+    // range psi was renormalized over, none if it was scaled by a fixed factor
+    normalization: Option<(f64, f64)>,
 }
 
 fn sign_match(f1: f64, f2: f64) -> bool {
@@ -213,88 +828,845 @@ impl WaveFunction {
         self.phase.energy
     }
 
-    pub fn new<F: Fn(f64) -> f64 + Sync + Send>(
-        potential: &'static F,
+    // panics where the builder fails, see find_turning_points and build_parts
+    pub fn new(
+        potential: SharedPotential,
         mass: f64,
         n_energy: usize,
         approx_inf: (f64, f64),
         view_factor: f64,
         scaling: ScalingType,
     ) -> WaveFunction {
-        let energy = energy::nth_energy(n_energy, mass, &potential, approx_inf);
-        println!("{} Energy: {:.9}", Ordinal(n_energy).to_string(), energy);
-
-        let lower_bound = newtons_method::newtons_method_max_iters(
-            &|x| potential(x) - energy,
-            approx_inf.0,
-            1e-7,
-            100000,
-        );
-        let upper_bound = newtons_method::newtons_method_max_iters(
-            &|x| potential(x) - energy,
-            approx_inf.1,
-            1e-7,
-            100000,
-        );
+        WaveFunctionBuilder::new(potential, mass)
+            .with_approx_inf(approx_inf)
+            .with_view_factor(view_factor)
+            .with_scaling(scaling)
+            .solve_energy(n_energy)
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale()
+    }
 
-        let view = if lower_bound.is_some() && upper_bound.is_some() {
-            (
-                lower_bound.unwrap() - (upper_bound.unwrap() - lower_bound.unwrap()) * view_factor,
-                upper_bound.unwrap() + (upper_bound.unwrap() - lower_bound.unwrap()) * view_factor,
-            )
-        } else {
-            println!("Failed to determine view automatically, using APPROX_INF as view");
-            (
-                approx_inf.0 - f64::EPSILON.sqrt(),
-                approx_inf.1 + f64::EPSILON.sqrt(),
-            )
-        };
+    // fails if the energy isn't above the minimum of the potential in approx_inf or the builder
+    // fails
+    pub fn from_energy(
+        potential: SharedPotential,
+        mass: f64,
+        energy: f64,
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> Result<WaveFunction, String> {
+        potentials::check_energy(potential.as_ref(), energy, approx_inf)?;
+        let phase = Arc::new(Phase::new(energy, mass, UNITS.hbar, potential));
+        Ok(EnergyStage::from_phase(phase, approx_inf, view_factor, scaling)
+            .find_turning_points()?
+            .build_parts()?
+            .scale())
+    }
 
-        let phase = Arc::new(Phase::new(energy, mass, potential));
+    // the phase can be shared with other wave functions of the same potential, see Phase::with_energy,
+    // panics where the builder fails like new
+    pub fn from_phase(
+        phase: Arc<Phase>,
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> WaveFunction {
+        EnergyStage::from_phase(phase, approx_inf, view_factor, scaling)
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale()
+    }
 
-        let (airy_wave_funcs, boundaries) = AiryWaveFunction::new(phase.clone(), (view.0, view.1));
-        let (parts, airy_ranges, wkb_ranges): (
-            Vec<Arc<dyn WaveFunctionPart>>,
-            Vec<(f64, f64)>,
-            Vec<(f64, f64)>,
-        ) = if boundaries.ts.len() == 0 {
-            println!("No turning points found in view! Results might be in accurate");
-            let wkb1 = WkbWaveFunction::new(
-                phase.clone(),
-                1.0.into(),
-                INTEG_STEPS,
-                approx_inf.0,
-                approx_inf.0,
-                f64::consts::PI / 4.0,
-            );
-            let wkb2 = WkbWaveFunction::new(
-                phase.clone(),
-                1.0.into(),
-                INTEG_STEPS,
-                approx_inf.0,
-                approx_inf.1,
-                f64::consts::PI / 4.0,
-            );
+    // every eigenstate with window.0 <= E <= window.1 and its n, for when the energies of
+    // interest are known but not the indices
+    pub fn all_in_energy_window(
+        potential: SharedPotential,
+        mass: f64,
+        window: (f64, f64),
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> Vec<(usize, WaveFunction)> {
+        let phase = shared_phase(potential, mass);
+        let levels = energy::energies_in_window(
+            mass,
+            phase.hbar,
+            &|x| (phase.potential)(x),
+            approx_inf,
+            window,
+        )
+        .unwrap();
+        levels
+            .into_par_iter()
+            .map(|(n, energy)| {
+                let wave_function = WaveFunction::from_phase(
+                    Arc::new(phase.with_energy(energy)),
+                    approx_inf,
+                    view_factor,
+                    scaling,
+                );
+                (n, wave_function)
+            })
+            .collect()
+    }
 
-            let center = (view.0 + view.1) / 2.0;
-            let wkb1 = Box::new(PureWkb {
-                wkb: Arc::new(wkb1),
-                range: (approx_inf.0, center),
-            });
+    // outside of the domain the outermost part is continued, it is 0 where that isn't finite
+    pub fn calc_psi(&self, x: f64) -> Complex64 {
+        if let Some(psi) = eval_parts(&self.parts, x, PART_BOUNDARY) {
+            return psi;
+        }
 
-            let wkb2 = Box::new(PureWkb {
-                wkb: Arc::new(wkb2),
-                range: (center, approx_inf.1),
+        let outermost = if x < self.domain().0 {
+            self.parts
+                .iter()
+                .min_by(|a, b| cmp_f64(&a.range().0, &b.range().0))
+        } else {
+            self.parts
+                .iter()
+                .max_by(|a, b| cmp_f64(&a.range().1, &b.range().1))
+        };
+        let tail = outermost.unwrap().eval(x);
+        if tail.is_finite() {
+            tail
+        } else {
+            OUT_OF_DOMAIN_WARNING.call_once(|| {
+                println!(
+                    "[WaveFunction::calc_psi] psi isn't finite outside of its domain {:?} (x = {}), using 0",
+                    self.domain(),
+                    x
+                )
             });
+            complex(0.0, 0.0)
+        }
+    }
 
-            let wkb1_range = wkb1.range();
-            (
+    // interval covered by the parts, psi is only an extrapolation outside of it
+    pub fn domain(&self) -> (f64, f64) {
+        let lower = self
+            .parts
+            .iter()
+            .map(|p| p.range().0)
+            .fold(f64::INFINITY, f64::min);
+        let upper = self
+            .parts
+            .iter()
+            .map(|p| p.range().1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        (lower, upper)
+    }
+
+    // Smallest interval outside of which |psi| is below epsilon times its value at the outermost
+    // turning points. Beyond the outermost airy ranges psi decays like exp(-integral kappa dx / hbar)
+    // with kappa = sqrt(2 m (V - E)), the prefactor 1 / sqrt(kappa) is left out. The integral is
+    // taken outwards from the ranges until it reaches ln(1 / epsilon), an infinite wall ends it at
+    // once. All of the domain without turning points.
+    pub fn effective_support(&self, epsilon: f64) -> (f64, f64) {
+        // steps of the decay integral per distance between the outermost airy ranges
+        const STEPS: usize = 1000;
+
+        let domain = self.domain();
+        if self.airy_ranges.is_empty() || !(epsilon > 0.0 && epsilon < 1.0) {
+            return domain;
+        }
+        let lower = self
+            .airy_ranges
+            .iter()
+            .map(|r| r.0)
+            .fold(f64::INFINITY, f64::min);
+        let upper = self
+            .airy_ranges
+            .iter()
+            .map(|r| r.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let phase = &self.phase;
+        let kappa = |x: f64| {
+            (2.0 * phase.mass * ((phase.potential)(x) - phase.energy))
+                .max(0.0)
+                .sqrt()
+                / phase.hbar
+        };
+        let step = (upper - lower).max(f64::EPSILON.sqrt()) / STEPS as f64;
+        let decay = -epsilon.ln();
+        let walk = |start: f64, end: f64, direction: f64| {
+            let (mut x, mut integral, mut previous) = (start, 0.0, kappa(start));
+            while integral < decay && (end - x) * direction > 0.0 {
+                let next = x + direction * step;
+                let current = kappa(next);
+                if !current.is_finite() {
+                    return next;
+                }
+                integral += (previous + current) / 2.0 * step;
+                previous = current;
+                x = next;
+            }
+            x
+        };
+        (
+            walk(lower, domain.0, -1.0).max(domain.0),
+            walk(upper, domain.1, 1.0).min(domain.1),
+        )
+    }
+
+    pub fn get_airy_ranges(&self) -> &[(f64, f64)] {
+        self.airy_ranges.as_slice()
+    }
+
+    pub fn get_wkb_ranges(&self) -> &[(f64, f64)] {
+        self.wkb_ranges.as_slice()
+    }
+
+    pub fn get_amplitude_ratios(&self) -> &[f64] {
+        self.amplitude_ratios.as_slice()
+    }
+
+    pub fn get_wkb_ranges_in_view(&self) -> Vec<(f64, f64)> {
+        self.wkb_ranges
+            .iter()
+            .map(|range| {
+                (
+                    f64::max(self.get_view().0, range.0),
+                    f64::min(self.get_view().1, range.1),
+                )
+            })
+            .collect::<Vec<(f64, f64)>>()
+    }
+
+    // boundaries between the airy and wkb parts, psi may jump slightly across them
+    pub fn get_joints(&self) -> Vec<f64> {
+        let mut joints: Vec<f64> = self
+            .airy_ranges
+            .iter()
+            .chain(self.wkb_ranges.iter())
+            .flat_map(|range| [range.0, range.1])
+            .filter(|b| b.is_finite())
+            .collect();
+        joints.sort_by(cmp_f64);
+        joints.dedup();
+        joints
+    }
+
+    pub fn is_wkb(&self, x: f64) -> bool {
+        self.wkb_ranges
+            .iter()
+            .map(|r| is_in_range(*r, x))
+            .collect::<Vec<bool>>()
+            .contains(&true)
+    }
+
+    pub fn is_airy(&self, x: f64) -> bool {
+        self.airy_ranges
+            .iter()
+            .map(|r| is_in_range(*r, x))
+            .collect::<Vec<bool>>()
+            .contains(&true)
+    }
+
+    pub fn get_view(&self) -> (f64, f64) {
+        self.view
+    }
+
+    // psi is this times the unscaled wave function
+    pub fn get_scaling(&self) -> Complex64 {
+        self.scaling
+    }
+
+    pub fn get_normalization(&self) -> Option<(f64, f64)> {
+        self.normalization
+    }
+
+    pub fn set_view(&mut self, view: (f64, f64)) {
+        self.view = view
+    }
+
+    // the joints blend over AIRY_TRANSITION_FRACTION of the airy range around each of its edges,
+    // like in ApproxPart
+    pub fn region(&self, x: f64) -> Region {
+        let joints = ENABLE_AIRY_JOINTS && !MATCH_AIRY_DERIVATIVES;
+        let in_joint = |range: &(f64, f64)| {
+            let delta = (range.1 - range.0) * AIRY_TRANSITION_FRACTION;
+            [range.0, range.1]
+                .iter()
+                .any(|edge| is_in_range((edge - delta / 2.0, edge + delta / 2.0), x))
+        };
+        if joints && self.airy_ranges.iter().any(in_joint) {
+            Region::Joint
+        } else if self.is_airy(x) {
+            Region::Airy
+        } else {
+            Region::Wkb
+        }
+    }
+
+    pub fn get_phase(&self) -> Arc<Phase> {
+        self.phase.clone()
+    }
+
+    // piecewise Chebyshev fit of psi in the view, split at the joints, tolerance relative to max |psi|
+    pub fn compress(&self, tolerance: f64) -> ChebyshevProxy {
+        let max = evaluate_function_between(self, self.view.0, self.view.1, 1000)
+            .iter()
+            .map(|p| p.y.norm())
+            .fold(0.0, f64::max);
+
+        let mut breakpoints = vec![self.view.0];
+        breakpoints.extend(
+            self.get_joints()
+                .into_iter()
+                .filter(|j| self.view.0 < *j && *j < self.view.1),
+        );
+        breakpoints.push(self.view.1);
+
+        ChebyshevProxy::fit(self, &breakpoints, tolerance * max)
+    }
+
+    // n positions distributed like |psi|^2 in the view, by inverse transform sampling of its CDF
+    pub fn sample_positions<R: Rng + ?Sized>(&self, rng: &mut R, n: usize) -> Vec<f64> {
+        let probability: Vec<Point<f64, f64>> =
+            evaluate_function_between(self, self.view.0, self.view.1, INTEG_STEPS)
+                .par_iter()
+                .map(|p| Point {
+                    x: p.x,
+                    y: p.y.norm_sqr(),
+                })
+                .collect();
+        let cdf = cumulative_integral(&probability);
+        let total = *cdf.last().unwrap();
+
+        (0..n)
+            .map(|_| inverse_cumulative(&probability, &cdf, rng.gen::<f64>() * total))
+            .collect()
+    }
+
+    // unit factor psi is multiplied with to follow the convention, 1 where it can't be applied
+    fn convention_factor(&self, convention: PhaseConvention) -> Complex64 {
+        // points of the scan for the first node, sign changes below this fraction of the largest
+        // |psi| are noise in the tails
+        const NODE_SCAN_POINTS: usize = 1000;
+        const NODE_THRESHOLD: f64 = 1e-3;
+
+        let unit = |psi: Complex64| {
+            if psi.norm() > 0.0 && psi.is_finite() {
+                psi.conj() / psi.norm()
+            } else {
+                complex(1.0, 0.0)
+            }
+        };
+        let at_turning_point = || {
+            turning_points::classical_turning_points(
+                self.phase.potential.as_ref(),
+                self.phase.energy,
+                self.domain(),
+            )
+            .map_or(complex(1.0, 0.0), |(t, _)| unit(self.eval(t)))
+        };
+        match convention {
+            PhaseConvention::None => complex(1.0, 0.0),
+            PhaseConvention::LeftTurningPoint => at_turning_point(),
+            PhaseConvention::FirstNode => {
+                let rotation = at_turning_point();
+                let values = Grid::uniform(self.view, NODE_SCAN_POINTS).evaluate(self);
+                let max = values.iter().map(|p| p.y.norm()).fold(0.0, f64::max);
+                // the sign of the real part after the rotation onto the turning point
+                let signs: Vec<f64> = values
+                    .iter()
+                    .map(|p| (p.y * rotation).re)
+                    .filter(|re| re.abs() > NODE_THRESHOLD * max)
+                    .map(f64::signum)
+                    .collect();
+                match signs.windows(2).find(|s| s[0] != s[1]) {
+                    Some(s) => rotation * s[1],
+                    None => rotation,
+                }
+            }
+        }
+    }
+
+    pub fn apply_phase_convention(&mut self, convention: PhaseConvention) {
+        self.scaling *= self.convention_factor(convention);
+    }
+
+    // rotates psi so that its overlap with other on OBSERVABLE_DOMAIN is real and positive
+    pub fn align_phase_to(&mut self, other: &WaveFunction) {
+        let range = OBSERVABLE_DOMAIN.common_range(&[self.clone(), other.clone()]);
+        let overlap = matrix_elements::matrix_element(
+            other,
+            &|_| 1.0,
+            self,
+            &Grid::uniform(range, INTEG_STEPS),
+        );
+        if overlap.norm() > 0.0 {
+            self.scaling *= overlap.conj() / overlap.norm();
+        }
+    }
+}
+
+fn describe_discontinuity(discontinuity: &Discontinuity) -> String {
+    let kind = match discontinuity.kind {
+        DiscontinuityKind::Jump => "jump",
+        DiscontinuityKind::Kink => "kink",
+    };
+    format!(
+        "{} of {:.3e} at {:.6}",
+        kind, discontinuity.size, discontinuity.x
+    )
+}
+
+// Staged construction of a WaveFunction: energy, turning points, parts and scaling. Each stage can
+// be inspected before going on, and a known energy can be injected instead of solving for it.
+pub struct WaveFunctionBuilder {
+    // as given, potential is what is solved for, smoothed if smoothing_width isn't 0 or the
+    // discontinuity policy asks for it
+    raw_potential: SharedPotential,
+    potential: SharedPotential,
+    smoothing_width: f64,
+    smoothing_changes: Vec<SmoothingChange>,
+    discontinuity_policy: DiscontinuityPolicy,
+    discontinuities: Vec<Discontinuity>,
+    mass: f64,
+    units: Units,
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    scaling: ScalingType,
+    boundaries: Option<(Boundary, Boundary)>,
+    normalization_domain: IntegrationDomain,
+    phase_convention: PhaseConvention,
+    approximation: ApproximationKind,
+    comparison_library: ComparisonLibrary,
+    trace_root_finds: bool,
+}
+
+impl WaveFunctionBuilder {
+    pub fn new(potential: SharedPotential, mass: f64) -> WaveFunctionBuilder {
+        let potential: SharedPotential = if CACHE_POTENTIAL {
+            let cache = CachedPotential::new(move |x| potential(x));
+            Arc::new(move |x| cache.eval(x))
+        } else {
+            potential
+        };
+
+        WaveFunctionBuilder {
+            raw_potential: potential.clone(),
+            potential,
+            smoothing_width: SMOOTHING_WIDTH,
+            smoothing_changes: vec![],
+            discontinuity_policy: DISCONTINUITY_POLICY,
+            discontinuities: vec![],
+            mass,
+            units: UNITS,
+            approx_inf: APPROX_INF,
+            view_factor: VIEW_FACTOR,
+            scaling: ScalingType::None,
+            boundaries: None,
+            normalization_domain: NORMALIZATION_DOMAIN,
+            phase_convention: PHASE_CONVENTION,
+            approximation: APPROXIMATION,
+            comparison_library: COMPARISON_LIBRARY,
+            trace_root_finds: TRACE_ROOT_FINDS,
+        }
+        .preprocess()
+    }
+
+    // the potential is scanned and smoothed again over the new approx_inf
+    pub fn with_approx_inf(mut self, approx_inf: (f64, f64)) -> WaveFunctionBuilder {
+        self.approx_inf = approx_inf;
+        self.preprocess()
+    }
+
+    // convolves V with a Gaussian of this standard deviation, for potentials with kinks or steps,
+    // 0 solves for V as given
+    pub fn with_smoothing(mut self, width: f64) -> WaveFunctionBuilder {
+        self.smoothing_width = width;
+        self.preprocess()
+    }
+
+    // what is done about jumps and kinks of V in approx_inf that aren't hard walls
+    pub fn with_discontinuity_policy(mut self, policy: DiscontinuityPolicy) -> WaveFunctionBuilder {
+        self.discontinuity_policy = policy;
+        self.preprocess()
+    }
+
+    fn preprocess(mut self) -> WaveFunctionBuilder {
+        self.discontinuities = if self.discontinuity_policy == DiscontinuityPolicy::Ignore {
+            vec![]
+        } else {
+            discontinuities::find_discontinuities(self.raw_potential.as_ref(), self.approx_inf)
+        };
+        if self.discontinuity_policy == DiscontinuityPolicy::Warn {
+            for discontinuity in &self.discontinuities {
+                println!("Warning: V has a {}", describe_discontinuity(discontinuity));
+            }
+        }
+
+        let width = match self.discontinuity_policy {
+            DiscontinuityPolicy::Smooth(width)
+                if self.smoothing_width == 0.0 && !self.discontinuities.is_empty() =>
+            {
+                width
+            }
+            _ => self.smoothing_width,
+        };
+        if width == 0.0 {
+            self.potential = self.raw_potential.clone();
+            self.smoothing_changes = vec![];
+            return self;
+        }
+
+        let smoothed = Arc::new(SmoothedPotential::new(
+            self.raw_potential.as_ref(),
+            self.approx_inf,
+            width,
+        ));
+        self.smoothing_changes = smoothed.changes(SMOOTHING_TOLERANCE);
+        for change in &self.smoothing_changes {
+            println!(
+                "Smoothing changed V by up to {:.3e} in [{:.4}, {:.4}]",
+                change.max_deviation, change.range.0, change.range.1
+            );
+        }
+        self.potential = Arc::new(move |x| smoothed.eval(x));
+        self
+    }
+
+    // where the smoothing moved V by more than SMOOTHING_TOLERANCE
+    pub fn get_smoothing_changes(&self) -> &[SmoothingChange] {
+        &self.smoothing_changes
+    }
+
+    // of V as given, empty if the discontinuity policy is Ignore
+    pub fn get_discontinuities(&self) -> &[Discontinuity] {
+        &self.discontinuities
+    }
+
+    pub fn with_view_factor(mut self, view_factor: f64) -> WaveFunctionBuilder {
+        self.view_factor = view_factor;
+        self
+    }
+
+    pub fn with_scaling(mut self, scaling: ScalingType) -> WaveFunctionBuilder {
+        self.scaling = scaling;
+        self
+    }
+
+    // what ScalingType::Renormalize integrates over
+    pub fn with_normalization_domain(mut self, domain: IntegrationDomain) -> WaveFunctionBuilder {
+        self.normalization_domain = domain;
+        self
+    }
+
+    // applied after the scaling
+    pub fn with_phase_convention(mut self, convention: PhaseConvention) -> WaveFunctionBuilder {
+        self.phase_convention = convention;
+        self
+    }
+
+    // how psi is continued through the soft turning points, hard walls are always patched
+    pub fn with_approximation(mut self, approximation: ApproximationKind) -> WaveFunctionBuilder {
+        self.approximation = approximation;
+        self
+    }
+
+    // the comparison equations besides Airy's the uniform approximation may map psi onto
+    pub fn with_comparison_library(mut self, library: ComparisonLibrary) -> WaveFunctionBuilder {
+        self.comparison_library = library;
+        self
+    }
+
+    // records the iterates of the energy solution, see EnergyStage::get_energy_trace
+    pub fn with_root_find_trace(mut self, enabled: bool) -> WaveFunctionBuilder {
+        self.trace_root_finds = enabled;
+        self
+    }
+
+    // units of the problem, only hbar enters the solution, mass and potential are already in them
+    pub fn with_units(mut self, units: Units) -> WaveFunctionBuilder {
+        self.units = units;
+        self
+    }
+
+    // types of the outermost turning points, otherwise they are detected from the potential
+    pub fn with_boundaries(mut self, left: Boundary, right: Boundary) -> WaveFunctionBuilder {
+        self.boundaries = Some((left, right));
+        self
+    }
+
+    // the error carries the iterates of the energy solution if they are recorded
+    pub fn solve_energy(&self, n: usize) -> Result<EnergyStage, RootFindError> {
+        let potential = |x| (self.potential)(x);
+        let mut trace = Trace::new(self.trace_root_finds);
+        let energy = energy::nth_energy_traced(
+            n,
+            self.mass,
+            self.units.hbar,
+            &potential,
+            self.approx_inf,
+            self.boundaries,
+            &mut trace,
+        )
+        .map_err(|err| RootFindError {
+            message: format!("no {} energy: {}", Ordinal(n), err.message),
+            trace: err.trace,
+        })?;
+        if trace.is_enabled() {
+            println!("Iterates of the {} energy:\n{}", Ordinal(n), trace);
+        }
+        let energy = energy::report_energy(
+            n,
+            self.mass,
+            self.units.hbar,
+            &potential,
+            self.approx_inf,
+            energy,
+        );
+        let mut stage = match self.with_energy(energy) {
+            Ok(stage) => stage,
+            Err(err) => {
+                return Err(RootFindError {
+                    message: format!("no wave function for the {} energy: {}", Ordinal(n), err),
+                    trace,
+                })
+            }
+        };
+        stage.energy_trace = trace;
+        Ok(stage)
+    }
+
+    // fails if the energy isn't above the minimum of the potential in approx_inf
+    pub fn with_energy(&self, energy: f64) -> Result<EnergyStage, String> {
+        potentials::check_energy(self.potential.as_ref(), energy, self.approx_inf)?;
+        if self.discontinuity_policy == DiscontinuityPolicy::Refuse {
+            if let Some(discontinuity) = self.discontinuities.iter().find(|d| !d.is_wall(energy)) {
+                return Err(format!(
+                    "V has a {}, which isn't a hard wall at this energy",
+                    describe_discontinuity(discontinuity)
+                ));
+            }
+        }
+        let phase = Phase {
+            energy,
+            mass: self.mass,
+            hbar: self.units.hbar,
+            potential: self.potential.clone(),
+        };
+        let mut stage = EnergyStage::from_phase(
+            Arc::new(phase),
+            self.approx_inf,
+            self.view_factor,
+            self.scaling,
+        );
+        stage.boundaries = self.boundaries;
+        stage.normalization_domain = self.normalization_domain;
+        stage.phase_convention = self.phase_convention;
+        stage.approximation = self.approximation;
+        stage.comparison_library = self.comparison_library;
+        Ok(stage)
+    }
+}
+
+pub struct EnergyStage {
+    phase: Arc<Phase>,
+    // of solve_energy, empty unless traced
+    energy_trace: Trace,
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    scaling: ScalingType,
+    boundaries: Option<(Boundary, Boundary)>,
+    normalization_domain: IntegrationDomain,
+    phase_convention: PhaseConvention,
+    approximation: ApproximationKind,
+    comparison_library: ComparisonLibrary,
+}
+
+impl EnergyStage {
+    pub fn from_phase(
+        phase: Arc<Phase>,
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> EnergyStage {
+        EnergyStage {
+            phase,
+            energy_trace: Trace::new(false),
+            approx_inf,
+            view_factor,
+            scaling,
+            boundaries: None,
+            normalization_domain: NORMALIZATION_DOMAIN,
+            phase_convention: PHASE_CONVENTION,
+            approximation: APPROXIMATION,
+            comparison_library: COMPARISON_LIBRARY,
+        }
+    }
+
+    pub fn get_energy(&self) -> f64 {
+        self.phase.energy
+    }
+
+    pub fn get_phase(&self) -> Arc<Phase> {
+        self.phase.clone()
+    }
+
+    pub fn get_energy_trace(&self) -> &Trace {
+        &self.energy_trace
+    }
+
+    // fails if the zeros of the validity function don't pair up around the turning points
+    pub fn find_turning_points(self) -> Result<TurningPointStage, String> {
+        let EnergyStage {
+            phase,
+            energy_trace: _,
+            approx_inf,
+            view_factor,
+            scaling,
+            boundaries: outer_boundaries,
+            normalization_domain,
+            phase_convention,
+            approximation,
+            comparison_library,
+        } = self;
+
+        let view = if let Some((lower_bound, upper_bound)) =
+            turning_points::classical_turning_points(phase.potential.as_ref(), phase.energy, approx_inf)
+        {
+            // not beyond approx_inf, V might not even be defined there, e.g. at r < 0
+            (
+                f64::max(
+                    lower_bound - (upper_bound - lower_bound) * view_factor,
+                    approx_inf.0,
+                ),
+                f64::min(
+                    upper_bound + (upper_bound - lower_bound) * view_factor,
+                    approx_inf.1,
+                ),
+            )
+        } else {
+            println!("Failed to determine view automatically, using APPROX_INF as view");
+            (
+                approx_inf.0 - f64::EPSILON.sqrt(),
+                approx_inf.1 + f64::EPSILON.sqrt(),
+            )
+        };
+
+        let (airy_wave_funcs, boundaries) =
+            AiryWaveFunction::new(phase.clone(), (view.0, view.1))?;
+        Ok(TurningPointStage {
+            phase,
+            approx_inf,
+            scaling,
+            view,
+            airy_wave_funcs,
+            boundaries,
+            outer_boundaries,
+            normalization_domain,
+            phase_convention,
+            approximation,
+            comparison_library,
+        })
+    }
+}
+
+// the parts of psi, the ranges of the airy and of the WKB parts and the amplitude ratios of the
+// wells across the barriers
+type PartsLayout = (
+    Vec<Arc<dyn WaveFunctionPart>>,
+    Vec<(f64, f64)>,
+    Vec<(f64, f64)>,
+    Vec<f64>,
+);
+
+pub struct TurningPointStage {
+    phase: Arc<Phase>,
+    approx_inf: (f64, f64),
+    scaling: ScalingType,
+    normalization_domain: IntegrationDomain,
+    phase_convention: PhaseConvention,
+    approximation: ApproximationKind,
+    comparison_library: ComparisonLibrary,
+    view: (f64, f64),
+    airy_wave_funcs: Vec<AiryWaveFunction>,
+    boundaries: turning_points::TGroup,
+    outer_boundaries: Option<(Boundary, Boundary)>,
+}
+
+impl TurningPointStage {
+    pub fn get_energy(&self) -> f64 {
+        self.phase.energy
+    }
+
+    pub fn get_view(&self) -> (f64, f64) {
+        self.view
+    }
+
+    // turning points in the view, each with the range of its airy function
+    pub fn get_turning_points(&self) -> &[((f64, f64), f64)] {
+        &self.boundaries.ts
+    }
+
+    // fails if the parts don't cover approx_inf consistently, see check_part_ranges
+    pub fn build_parts(self) -> Result<PartsStage, String> {
+        let TurningPointStage {
+            phase,
+            approx_inf,
+            scaling,
+            view,
+            airy_wave_funcs,
+            boundaries,
+            outer_boundaries,
+            normalization_domain,
+            phase_convention,
+            approximation,
+            comparison_library,
+        } = self;
+
+        let (parts, airy_ranges, wkb_ranges, amplitude_ratios): PartsLayout = if boundaries
+            .ts
+            .is_empty()
+        {
+            println!("No turning points found in view! Results might be in accurate");
+            let wkb1 = WkbWaveFunction::new(
+                phase.clone(),
+                1.0.into(),
+                INTEG_STEPS,
+                approx_inf.0,
+                approx_inf.0,
+                f64::consts::PI / 4.0,
+            );
+            let wkb2 = WkbWaveFunction::new(
+                phase.clone(),
+                1.0.into(),
+                INTEG_STEPS,
+                approx_inf.0,
+                approx_inf.1,
+                f64::consts::PI / 4.0,
+            );
+
+            let center = (view.0 + view.1) / 2.0;
+            let wkb1 = Box::new(PureWkb {
+                wkb: Arc::new(wkb1),
+                range: (approx_inf.0, center),
+            });
+
+            let wkb2 = Box::new(PureWkb {
+                wkb: Arc::new(wkb2),
+                range: (center, approx_inf.1),
+            });
+
+            let wkb1_range = wkb1.range();
+            (
                 vec![
                     Arc::from(wkb1.as_wave_function_part()),
                     Arc::from(wkb2.as_wave_function_part()),
                 ],
                 vec![],
                 vec![wkb1_range, wkb2.range()],
+                vec![],
             )
         } else {
             let turning_points: Vec<f64> = [
@@ -303,34 +1675,77 @@ impl WaveFunction {
                 vec![2.0 * approx_inf.1 - boundaries.ts.last().unwrap().1],
             ]
             .concat();
+            // the two turning points of a well share its amplitude
+            let amplitudes = wkb_wave_func::well_amplitudes(
+                phase.as_ref(),
+                &turning_points[1..turning_points.len() - 1],
+            );
+            // phase correction of the real turning points, the virtual ones at the ends are soft
+            let mut kinds: Vec<Boundary> = boundaries
+                .ts
+                .iter()
+                .map(|p| turning_points::boundary_at(phase.potential.as_ref(), p.1, phase.energy))
+                .collect();
+            if let Some((left, right)) = outer_boundaries {
+                kinds[0] = left;
+                *kinds.last_mut().unwrap() = right;
+            }
+
+            // the airy function of a uniform part is used in all of it, there are no joints
+            let uniform: Vec<bool> = kinds
+                .iter()
+                .map(|kind| approximation == ApproximationKind::Uniform && *kind == Boundary::Soft)
+                .collect();
+            // the parts meet halfway between neighbouring turning points, or halfway between their
+            // airy ranges if the joints of one of them reach past that, e.g. in the wells of a
+            // Coulomb potential
+            let edges: Vec<f64> = turning_points
+                .windows(2)
+                .enumerate()
+                .map(|(i, t)| {
+                    let middle = (t[0] + t[1]) / 2.0;
+                    match (i.checked_sub(1).map(|j| boundaries.ts[j].0), boundaries.ts.get(i)) {
+                        (Some((_, left)), Some(((right, _), _)))
+                            if (middle < left || middle > *right)
+                                && !(uniform[i - 1] && uniform[i]) =>
+                        {
+                            (left + right) / 2.0
+                        }
+                        _ => middle,
+                    }
+                })
+                .collect();
 
             let wave_funcs = turning_points
                 .iter()
                 .zip(turning_points.iter().skip(1))
                 .zip(turning_points.iter().skip(2))
+                .enumerate()
                 .map(
-                    |((previous, boundary), next)| -> (WkbWaveFunction, (f64, f64)) {
+                    |(i, ((previous, boundary), _))| -> (WkbWaveFunction, (f64, f64)) {
+                        let amplitude = amplitudes[i / 2];
                         (
                             if derivative(phase.potential.as_ref(), *boundary) > 0.0 {
+                                let kind = if i == 0 { Boundary::Soft } else { kinds[i - 1] };
                                 WkbWaveFunction::new(
                                     phase.clone(),
-                                    1.0.into(),
+                                    amplitude.into(),
                                     INTEG_STEPS,
                                     *boundary,
                                     *previous,
-                                    f64::consts::PI / 4.0,
+                                    kind.phase_correction(),
                                 )
                             } else {
                                 WkbWaveFunction::new(
                                     phase.clone(),
-                                    1.0.into(),
+                                    amplitude.into(),
                                     INTEG_STEPS,
                                     *boundary,
                                     *boundary,
-                                    f64::consts::PI / 4.0,
+                                    kinds[i].phase_correction(),
                                 )
                             },
-                            ((boundary + previous) / 2.0, (next + boundary) / 2.0),
+                            (edges[i], edges[i + 1]),
                         )
                     },
                 )
@@ -343,7 +1758,7 @@ impl WaveFunction {
                     (
                         w,
                         a.with_phase_off(w.0.phase_off)
-                            .with_c(w.0.get_exp_sign().into()),
+                            .with_c(w.0.get_c() * w.0.get_exp_sign()),
                     )
                 })
                 .collect();
@@ -352,13 +1767,70 @@ impl WaveFunction {
                 .iter()
                 .map(|((_, wkb_range), _)| *wkb_range)
                 .collect();
-            let airy_ranges = wkb_airy_pair.iter().map(|(_, airy)| airy.ts).collect();
+            // a uniform part has no joints that could stick out of it
+            let airy_ranges = wkb_airy_pair
+                .iter()
+                .zip(uniform.iter())
+                .map(|(((_, range), airy), uniform)| {
+                    if *uniform {
+                        (f64::max(airy.ts.0, range.0), f64::min(airy.ts.1, range.1))
+                    } else {
+                        airy.ts
+                    }
+                })
+                .collect();
 
+            // a potential rising like a centrifugal term towards the left end of the domain is
+            // mapped onto Bessel's equation through the first turning point instead
+            let singularity = if approximation == ApproximationKind::Uniform
+                && comparison_library.bessel
+                && derivative(phase.potential.as_ref(), boundaries.ts[0].1) < 0.0
+            {
+                comparison::centrifugal_singularity(phase.as_ref(), approx_inf)
+            } else {
+                None
+            };
+
+            // the airy function doesn't describe psi at a hard wall, its phase is kept there
             let approx_parts: Vec<Arc<dyn WaveFunctionPartWithOp>> = wkb_airy_pair
                 .iter()
-                .map(|((wkb, range), airy)| -> Arc<dyn WaveFunctionPartWithOp> {
-                    Arc::new(ApproxPart::new(airy.clone(), wkb.clone(), *range))
-                })
+                .zip(kinds.iter())
+                .enumerate()
+                .map(
+                    |(i, (((wkb, range), airy), kind))| -> Arc<dyn WaveFunctionPartWithOp> {
+                        if approximation == ApproximationKind::Uniform && *kind == Boundary::Soft {
+                            if let (0, Some((nu, x_s))) = (i, singularity) {
+                                println!(
+                                    "V rises like a centrifugal term with nu = {:.6} left of x = {}, \
+                                     psi is mapped onto Bessel's equation in ({}, {})",
+                                    nu, approx_inf.0, range.0, range.1
+                                );
+                                return Arc::new(BesselPart {
+                                    mapping: Arc::new(BesselMapping::new(
+                                        phase.clone(),
+                                        nu,
+                                        x_s,
+                                        airy.turning_point,
+                                        *range,
+                                    )),
+                                    c: airy.get_c(),
+                                    op: identity,
+                                    range: *range,
+                                });
+                            }
+                            return Arc::new(UniformPart {
+                                airy: Arc::new(airy.with_action_table(*range)),
+                                range: *range,
+                            });
+                        }
+                        Arc::new(ApproxPart::new(
+                            airy.clone(),
+                            wkb.clone(),
+                            *range,
+                            MATCH_AIRY_DERIVATIVES && *kind == Boundary::Soft,
+                        ))
+                    },
+                )
                 .collect();
 
             (
@@ -368,193 +1840,777 @@ impl WaveFunction {
                     .collect(),
                 airy_ranges,
                 wkb_ranges,
+                amplitudes.windows(2).map(|a| a[1] / a[0]).collect(),
             )
         };
 
-        match scaling {
-            ScalingType::Mul(s) => WaveFunction {
-                phase,
-                view,
-                parts,
-                airy_ranges,
-                wkb_ranges,
-                scaling: s,
-            },
-            ScalingType::None => WaveFunction {
+        let stage = PartsStage {
+            wave_function: WaveFunction {
                 phase,
                 view,
                 parts,
                 airy_ranges,
                 wkb_ranges,
+                amplitude_ratios,
                 scaling: complex(1.0, 0.0),
+                normalization: None,
             },
+            approx_inf,
+            scaling,
+            normalization_domain,
+            phase_convention,
+            approximation,
+            comparison_library,
+        };
+        stage
+            .validate()
+            .map_err(|err| format!("inconsistent parts of the wave function: {}", err))?;
+        let stage = if HARMONIC_BOTTOMS {
+            stage.with_harmonic_bottoms()
+        } else {
+            stage
+        };
+        // the uniform approximation keeps the airy functions without the parabolic cylinder
+        if BARRIER_TOP_MARGIN > 0.0
+            && (approximation == ApproximationKind::Patched
+                || comparison_library.parabolic_cylinder)
+        {
+            Ok(stage.with_barrier_tops())
+        } else {
+            Ok(stage)
+        }
+    }
+}
+
+pub struct PartsStage {
+    wave_function: WaveFunction,
+    approx_inf: (f64, f64),
+    scaling: ScalingType,
+    normalization_domain: IntegrationDomain,
+    phase_convention: PhaseConvention,
+    approximation: ApproximationKind,
+    comparison_library: ComparisonLibrary,
+}
+
+impl PartsStage {
+    pub fn get_unscaled(&self) -> &WaveFunction {
+        &self.wave_function
+    }
+
+    // checks the parts as built, before any harmonic bottoms are laid over them
+    pub fn validate(&self) -> Result<(), String> {
+        check_part_ranges(
+            &self.wave_function.wkb_ranges,
+            &self.wave_function.airy_ranges,
+            self.approx_inf,
+        )
+    }
+
+    // Replaces psi around the bottom of every well, whose level is the n = 0 or 1 of the oscillator
+    // fitted there, with the Hermite function. WKB is least accurate for the lowest levels.
+    pub fn with_harmonic_bottoms(mut self) -> PartsStage {
+        // the level may be this far from the oscillator's, in units of hbar omega
+        const DETUNING: f64 = 0.25;
+
+        let wave_function = &mut self.wave_function;
+        let phase = wave_function.phase.clone();
+        let potential = phase.potential.as_ref();
+        let surrounding = Arc::new(Surrounding {
+            parts: wave_function.parts.clone(),
+        });
+        // step of the second difference, as in energy::harmonic_estimates
+        let h = f64::EPSILON.powf(0.25);
+
+        let harmonic_parts: Vec<Arc<dyn WaveFunctionPart>> =
+            analysis::find_minima(potential, wave_function.view)
+                .iter()
+                .filter_map(|m| -> Option<Arc<dyn WaveFunctionPart>> {
+                    let curvature = (potential(m.x + h) - 2.0 * m.y + potential(m.x - h)) / (h * h);
+                    if !curvature.is_finite() || curvature <= 0.0 {
+                        return None;
+                    }
+                    let omega = (curvature / phase.mass).sqrt();
+                    let level = (phase.energy - m.y) / (phase.hbar * omega) - 0.5;
+                    let n = level.round();
+                    if (n != 0.0 && n != 1.0) || (level - n).abs() > DETUNING {
+                        return None;
+                    }
+                    let length = (phase.hbar / (phase.mass * omega)).sqrt();
+                    Some(Arc::new(Overlay::harmonic(
+                        n as usize,
+                        m.x,
+                        length,
+                        surrounding.clone(),
+                    )))
+                })
+                .collect();
+
+        // calc_psi takes the first part containing x
+        wave_function.parts = [harmonic_parts, wave_function.parts.clone()].concat();
+        self
+    }
+
+    // Replaces psi around the top of every barrier within BARRIER_TOP_MARGIN hbar omega of the
+    // energy by the solutions of the inverted oscillator fitted there. The airy functions of the two
+    // close turning points below the top, or of none above it, don't connect the sides there. The
+    // uniform approximation with the parabolic cylinder in its library maps the barrier onto the
+    // comparison equation instead, which doesn't need it to be a parabola.
+    pub fn with_barrier_tops(mut self) -> PartsStage {
+        let mapped = self.approximation == ApproximationKind::Uniform
+            && self.comparison_library.parabolic_cylinder;
+        let wave_function = &mut self.wave_function;
+        let phase = wave_function.phase.clone();
+        let potential = phase.potential.as_ref();
+        let surrounding = Arc::new(Surrounding {
+            parts: wave_function.parts.clone(),
+        });
+        // step of the second difference, as in with_harmonic_bottoms
+        let h = f64::EPSILON.powf(0.25);
+
+        let barrier_parts: Vec<Arc<dyn WaveFunctionPart>> =
+            analysis::find_maxima(potential, wave_function.view)
+                .iter()
+                .filter_map(|m| -> Option<Arc<dyn WaveFunctionPart>> {
+                    let curvature = (potential(m.x + h) - 2.0 * m.y + potential(m.x - h)) / (h * h);
+                    if !curvature.is_finite() || curvature >= 0.0 {
+                        return None;
+                    }
+                    let omega = (-curvature / phase.mass).sqrt();
+                    let epsilon = (phase.energy - m.y) / (phase.hbar * omega);
+                    if epsilon.abs() > BARRIER_TOP_MARGIN {
+                        return None;
+                    }
+                    let length = (phase.hbar / (phase.mass * omega)).sqrt();
+
+                    let reach = (BARRIER_TOP_CORE + BARRIER_TOP_TRANSITION) * length;
+                    let mapping = if mapped {
+                        CylinderMapping::new(phase.clone(), m.x, epsilon, length, reach)
+                    } else {
+                        None
+                    };
+
+                    // without a mapping the barrier has to be close to a parabola where the
+                    // solutions are fitted
+                    let anharmonicity = [m.x - reach, m.x + reach]
+                        .iter()
+                        .map(|x| {
+                            let parabola = m.y + curvature / 2.0 * (x - m.x).powi(2);
+                            (potential(*x) - parabola).abs() / (phase.hbar * omega)
+                        })
+                        .fold(0.0, f64::max);
+                    if mapping.is_none()
+                        && (anharmonicity.is_nan() || anharmonicity > BARRIER_TOP_ANHARMONICITY)
+                    {
+                        println!(
+                            "E = {} is {:.3} hbar omega from the top of the barrier at x = {}, but \
+                             it deviates {:.3} hbar omega from a parabola within {} of it, the \
+                             airy functions are kept",
+                            phase.energy, epsilon, m.x, anharmonicity, reach
+                        );
+                        return None;
+                    }
+
+                    let kind = if mapping.is_some() {
+                        "mapped parabolic cylinder functions"
+                    } else {
+                        "parabolic cylinder functions"
+                    };
+                    let (overlay, residual) =
+                        Overlay::barrier_top(epsilon, m.x, length, mapping, surrounding.clone());
+                    let range = overlay.range();
+                    println!(
+                        "E = {} is {:.3} hbar omega from the top of the barrier V = {} at x = {}, \
+                         psi is replaced by {} in ({}, {}), they deviate {:.3e} from the airy and \
+                         WKB parts where they are blended",
+                        phase.energy, epsilon, m.y, m.x, kind, range.0, range.1, residual
+                    );
+                    Some(Arc::new(overlay))
+                })
+                .collect();
+
+        // calc_psi takes the first part containing x
+        wave_function.parts = [barrier_parts, wave_function.parts.clone()].concat();
+        self
+    }
+
+    pub fn scale(self) -> WaveFunction {
+        let mut wave_function = self.wave_function;
+        match self.scaling {
+            ScalingType::Mul(s) => wave_function.scaling = s,
+            ScalingType::None => {}
             ScalingType::Renormalize(s) => {
-                let unscaled = WaveFunction {
-                    phase: phase.clone(),
-                    view,
-                    parts: parts.clone(),
-                    airy_ranges: airy_ranges.clone(),
-                    wkb_ranges: wkb_ranges.clone(),
-                    scaling: s,
-                };
-                let factor = renormalize_factor(&unscaled, approx_inf);
-                WaveFunction {
-                    phase,
-                    view,
-                    parts,
-                    airy_ranges,
-                    wkb_ranges,
-                    scaling: s * factor,
-                }
+                wave_function.scaling = s;
+                let range = self.normalization_domain.range(&wave_function);
+                wave_function.scaling = s * renormalize_factor(&wave_function, range);
+                wave_function.normalization = Some(range);
             }
         }
+        wave_function.apply_phase_convention(self.phase_convention);
+        wave_function
     }
+}
 
-    pub fn calc_psi(&self, x: f64) -> Complex64 {
-        for part in self.parts.as_slice() {
-            if is_in_range(part.range(), x) {
-                return part.eval(x);
-            }
+impl Func<f64, Complex64> for WaveFunction {
+    fn eval(&self, x: f64) -> Complex64 {
+        self.scaling * self.calc_psi(x)
+    }
+}
+
+struct Component {
+    // none for members of other potentials or masses, added with push_wave_function
+    n: Option<usize>,
+    coefficient: Complex64,
+    wave_func: WaveFunction,
+    // integral of |psi|^2 over NORMALIZATION_DOMAIN, computed once for the analytic renormalization
+    norm: OnceLock<f64>,
+}
+
+impl Component {
+    // e^(-i E t / hbar) with the energy of the member in its own potential
+    fn time_phase(&self, time: f64) -> Complex64 {
+        let phase = self.wave_func.get_phase();
+        Complex64::from_polar(1.0, -phase.energy * time / phase.hbar)
+    }
+}
+
+// Members are kept unscaled, so changing a coefficient doesn't recompute anything.
+// The renormalization factor is computed on the next evaluation after a change.
+// The eigenstates share the energy scan and the potential, cached with CACHE_POTENTIAL. Their turning
+// points and validity zeros depend on the energy and are searched per member.
+// Members of other potentials aren't orthogonal to the eigenstates, the norm changes over time then.
+pub struct Superposition {
+    phase: Phase,
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    energies: Vec<f64>,
+    components: Vec<Component>,
+    scaling: ScalingType,
+    time: f64,
+    factor: OnceLock<Complex64>,
+}
+
+// sum of the members without the overall scaling
+struct Unscaled<'a> {
+    superposition: &'a Superposition,
+}
+
+impl Func<f64, Complex64> for Unscaled<'_> {
+    fn eval(&self, x: f64) -> Complex64 {
+        self.superposition
+            .components
+            .iter()
+            .map(|c| c.coefficient * c.time_phase(self.superposition.time) * c.wave_func.eval(x))
+            .sum::<Complex64>()
+    }
+}
+
+// c_n e^(-i E_n t / hbar) psi_n of one member with the scaling of the superposition, the terms of
+// all members add up to it
+pub struct MemberTerm<'a> {
+    superposition: &'a Superposition,
+    index: usize,
+}
+
+impl Func<f64, Complex64> for MemberTerm<'_> {
+    fn eval(&self, x: f64) -> Complex64 {
+        let component = &self.superposition.components[self.index];
+        self.superposition.get_scaling()
+            * component.coefficient
+            * component.time_phase(self.superposition.time)
+            * component.wave_func.eval(x)
+    }
+}
+
+impl Superposition {
+    // panics where try_new fails
+    pub fn new(
+        potential: SharedPotential,
+        mass: f64,
+        n_energies_scaling: &[(usize, Complex64)],
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> Superposition {
+        Superposition::try_new(
+            potential,
+            mass,
+            n_energies_scaling,
+            approx_inf,
+            view_factor,
+            scaling,
+        )
+        .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // fails if the potential binds less states than asked for or a member can't be built, see
+    // make_component
+    pub fn try_new(
+        potential: SharedPotential,
+        mass: f64,
+        n_energies_scaling: &[(usize, Complex64)],
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> Result<Superposition, String> {
+        let mut superposition = Superposition {
+            phase: shared_phase(potential, mass),
+            approx_inf,
+            view_factor,
+            energies: vec![],
+            components: vec![],
+            scaling,
+            time: 0.0,
+            factor: OnceLock::new(),
+        };
+
+        // all members share the energy scan and the potential
+        let max_n = n_energies_scaling.iter().map(|(n, _)| *n).max().unwrap_or(0);
+        superposition.scan_energies(max_n)?;
+
+        let components = n_energies_scaling
+            .par_iter()
+            .map(|(n, coefficient)| superposition.make_component(*n, *coefficient))
+            .collect::<Result<Vec<Component>, String>>()?;
+        superposition.components = components;
+        Ok(superposition)
+    }
+
+    // Coherent state with the weights e^(-|alpha|^2 / 2) alpha^n / sqrt(n!) of the harmonic oscillator,
+    // truncated after n_max. Only close to a coherent state for nearly harmonic wells.
+    pub fn coherent(
+        potential: SharedPotential,
+        mass: f64,
+        alpha: Complex64,
+        n_max: usize,
+        approx_inf: (f64, f64),
+        view_factor: f64,
+        scaling: ScalingType,
+    ) -> Superposition {
+        Superposition::new(
+            potential,
+            mass,
+            &coherent_coefficients(alpha, n_max),
+            approx_inf,
+            view_factor,
+            scaling,
+        )
+    }
+
+    fn scan_energies(&mut self, n: usize) -> Result<(), String> {
+        if self.energies.len() <= n {
+            let potential = self.phase.potential.clone();
+            self.energies = energy::first_energies(
+                n + 1,
+                self.phase.mass,
+                self.phase.hbar,
+                &|x| potential(x),
+                self.approx_inf,
+            )?;
         }
-        panic!(
-            "[WkbWaveFunction::calc_psi] x out of range (x = {}, ranges: {:#?})",
-            x,
-            self.parts
-                .iter()
-                .map(|p| p.range())
-                .collect::<Vec<(f64, f64)>>()
+        Ok(())
+    }
+
+    // fails if the builder fails for the nth state or it doesn't cover approx_inf
+    fn make_component(&self, n: usize, coefficient: Complex64) -> Result<Component, String> {
+        let potential = self.phase.potential.clone();
+        let energy = energy::report_energy(
+            n,
+            self.phase.mass,
+            self.phase.hbar,
+            &|x| potential(x),
+            self.approx_inf,
+            self.energies[n],
         );
+        let wave_func = EnergyStage::from_phase(
+            Arc::new(self.phase.with_energy(energy)),
+            self.approx_inf,
+            self.view_factor,
+            ScalingType::None,
+        )
+        .find_turning_points()?
+        .build_parts()?
+        .scale();
+        println!("Calculated {} Energy\n", Ordinal(n));
+
+        // members covering less than approx_inf would only be extrapolated in parts of it
+        let domain = wave_func.domain();
+        if domain.0 > self.approx_inf.0 || domain.1 < self.approx_inf.1 {
+            return Err(format!(
+                "the {} state covers {:?}, not all of approx_inf {:?}",
+                Ordinal(n),
+                domain,
+                self.approx_inf
+            ));
+        }
+        Ok(Component {
+            n: Some(n),
+            coefficient,
+            wave_func,
+            norm: OnceLock::new(),
+        })
     }
 
-    pub fn get_airy_ranges(&self) -> &[(f64, f64)] {
-        self.airy_ranges.as_slice()
+    fn component_mut(&mut self, n: usize) -> Option<&mut Component> {
+        self.components.iter_mut().find(|c| c.n == Some(n))
     }
 
-    pub fn get_wkb_ranges(&self) -> &[(f64, f64)] {
-        self.wkb_ranges.as_slice()
+    // Adds a wave function of any potential and mass, e.g. a state transferred to another surface.
+    // Returns its index in get_members, it can't be addressed by n.
+    pub fn push_wave_function(&mut self, wave_func: WaveFunction, coefficient: Complex64) -> usize {
+        self.components.push(Component {
+            n: None,
+            coefficient,
+            wave_func,
+            norm: OnceLock::new(),
+        });
+        self.factor = OnceLock::new();
+        self.components.len() - 1
     }
 
-    pub fn get_wkb_ranges_in_view(&self) -> Vec<(f64, f64)> {
-        self.wkb_ranges
+    // n of the eigenstates of the potential of the superposition, none for other members
+    pub fn get_members(&self) -> Vec<(Option<usize>, Complex64)> {
+        self.components
             .iter()
-            .map(|range| {
-                (
-                    f64::max(self.get_view().0, range.0),
-                    f64::min(self.get_view().1, range.1),
-                )
+            .map(|c| (c.n, c.coefficient))
+            .collect()
+    }
+
+    // the term of the member at index in get_members
+    pub fn member_term(&self, index: usize) -> Option<MemberTerm<'_>> {
+        if index < self.components.len() {
+            Some(MemberTerm {
+                superposition: self,
+                index,
             })
-            .collect::<Vec<(f64, f64)>>()
+        } else {
+            None
+        }
     }
 
-    pub fn is_wkb(&self, x: f64) -> bool {
-        self.wkb_ranges
+    // returns false if there is no member at index
+    pub fn set_member_coefficient(&mut self, index: usize, coefficient: Complex64) -> bool {
+        match self.components.get_mut(index) {
+            Some(component) => {
+                component.coefficient = coefficient;
+                self.factor = OnceLock::new();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    // every member evolves with the energy of its own potential
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+        self.factor = OnceLock::new();
+    }
+
+    // energy of every member in its own potential, in the order of get_members
+    pub fn get_member_energies(&self) -> Vec<f64> {
+        self.components
             .iter()
-            .map(|r| is_in_range(*r, x))
-            .collect::<Vec<bool>>()
-            .contains(&true)
+            .map(|c| c.wave_func.get_energy())
+            .collect()
     }
 
-    pub fn is_airy(&self, x: f64) -> bool {
-        self.airy_ranges
+    pub fn get_potential(&self) -> SharedPotential {
+        self.phase.potential.clone()
+    }
+
+    // mass of the eigenstates, members added with push_wave_function may have another one
+    pub fn get_mass(&self) -> f64 {
+        self.phase.mass
+    }
+
+    pub fn get_hbar(&self) -> f64 {
+        self.phase.hbar
+    }
+
+    // psi on the grid at each of the times, the members are evaluated only once. The time of the
+    // superposition is restored afterwards.
+    pub fn sample_evolution(
+        &mut self,
+        grid: &Grid,
+        times: &[f64],
+    ) -> Vec<Vec<Point<f64, Complex64>>> {
+        let members: Vec<Vec<Point<f64, Complex64>>> = self
+            .components
             .iter()
-            .map(|r| is_in_range(*r, x))
-            .collect::<Vec<bool>>()
-            .contains(&true)
+            .map(|c| grid.evaluate(&c.wave_func))
+            .collect();
+        let time = self.time;
+        let mut frames = vec![];
+        for t in times {
+            self.set_time(*t);
+            let scaling = self.get_scaling();
+            let factors: Vec<Complex64> = self
+                .components
+                .iter()
+                .map(|c| scaling * c.coefficient * c.time_phase(*t))
+                .collect();
+            frames.push(
+                (0..grid.len())
+                    .map(|i| Point {
+                        x: grid.x(i),
+                        y: members
+                            .iter()
+                            .zip(factors.iter())
+                            .map(|(member, factor)| factor * member[i].y)
+                            .sum(),
+                    })
+                    .collect(),
+            );
+        }
+        self.set_time(time);
+        frames
+    }
+
+    // adds the nth eigenstate, if it's already part of the superposition only the coefficient is
+    // changed. Fails like try_new, the superposition is left as it was then.
+    pub fn push_state(&mut self, n: usize, coefficient: Complex64) -> Result<(), String> {
+        if !self.set_coefficient(n, coefficient) {
+            self.scan_energies(n)?;
+            let component = self.make_component(n, coefficient)?;
+            self.components.push(component);
+            self.factor = OnceLock::new();
+        }
+        Ok(())
+    }
+
+    // returns false if the nth eigenstate isn't part of the superposition
+    pub fn set_coefficient(&mut self, n: usize, coefficient: Complex64) -> bool {
+        match self.component_mut(n) {
+            Some(component) => {
+                component.coefficient = coefficient;
+                self.factor = OnceLock::new();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // keeps the absolute value of the coefficient
+    pub fn set_phase(&mut self, n: usize, phase: f64) -> bool {
+        match self.component_mut(n) {
+            Some(component) => {
+                let coefficient = Complex64::from_polar(component.coefficient.norm(), phase);
+                self.set_coefficient(n, coefficient)
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_state(&mut self, n: usize) -> bool {
+        let len = self.components.len();
+        self.components.retain(|c| c.n != Some(n));
+        self.factor = OnceLock::new();
+        self.components.len() != len
+    }
+
+    // coefficients of the eigenstates of the potential of the superposition
+    pub fn get_coefficients(&self) -> Vec<(usize, Complex64)> {
+        self.components
+            .iter()
+            .filter_map(|c| c.n.map(|n| (n, c.coefficient)))
+            .collect()
+    }
+
+    // energies of the eigenstates in the order of get_coefficients
+    pub fn get_energies(&self) -> Vec<(usize, f64)> {
+        self.components
+            .iter()
+            .filter_map(|c| c.n.map(|n| (n, c.wave_func.get_energy())))
+            .collect()
+    }
+
+    // range the members are renormalized over, none for a fixed factor
+    pub fn get_normalization(&self) -> Option<(f64, f64)> {
+        match self.scaling {
+            ScalingType::Renormalize(_) => Some(self.extent()),
+            _ => None,
+        }
+    }
+
+    pub fn get_scaling(&self) -> Complex64 {
+        *self.factor.get_or_init(|| match self.scaling {
+            ScalingType::Mul(s) => s,
+            ScalingType::None => 1.0.into(),
+            ScalingType::Renormalize(s) => {
+                let factor = match self.analytic_factor() {
+                    Some(factor) if CHECK_SUPERPOSITION_NORM => {
+                        let integrated = self.integrated_factor();
+                        let deviation = (factor - integrated).abs() / integrated;
+                        if deviation > SUPERPOSITION_NORM_TOLERANCE {
+                            println!(
+                                "the analytic renormalization factor {} deviates by {:.3e} from the \
+                                 integrated {}, the members aren't orthogonal enough",
+                                factor, deviation, integrated
+                            );
+                        }
+                        integrated
+                    }
+                    Some(factor) => factor,
+                    None => self.integrated_factor(),
+                };
+                println!("factor: {}", factor);
+                s * factor
+            }
+        })
+    }
+
+    fn integrated_factor(&self) -> f64 {
+        renormalize_factor(&Unscaled { superposition: self }, self.extent())
+    }
+
+    // The eigenstates are orthogonal, so the integral of |psi|^2 is sum |c_n|^2 N_n with the norms
+    // of the members, which don't change with the coefficients or the time. None if a member of
+    // another potential takes part.
+    fn analytic_factor(&self) -> Option<f64> {
+        if !ANALYTIC_SUPERPOSITION_NORM || self.components.iter().any(|c| c.n.is_none()) {
+            return None;
+        }
+        let area: f64 = self
+            .components
+            .par_iter()
+            .map(|c| {
+                let norm = c.norm.get_or_init(|| {
+                    let range = NORMALIZATION_DOMAIN.range(&c.wave_func);
+                    renormalize_factor(&c.wave_func, range).powi(-2)
+                });
+                c.coefficient.norm_sqr() * norm
+            })
+            .sum();
+        if area == 0.0 {
+            return None;
+        }
+        Some(1.0 / area.sqrt())
     }
 
-    pub fn get_view(&self) -> (f64, f64) {
-        self.view
+    pub fn get_joints(&self) -> Vec<f64> {
+        let mut joints: Vec<f64> = self
+            .components
+            .iter()
+            .flat_map(|c| c.wave_func.get_joints())
+            .collect();
+        joints.sort_by(cmp_f64);
+        joints.dedup();
+        joints
     }
 
-    pub fn set_view(&mut self, view: (f64, f64)) {
-        self.view = view
+    // the eigenstates cover approx_inf, other members their own approx_inf
+    fn member_domain(&self, component: &Component) -> (f64, f64) {
+        let domain = component.wave_func.domain();
+        match component.n {
+            Some(_) => (
+                domain.0.max(self.approx_inf.0),
+                domain.1.min(self.approx_inf.1),
+            ),
+            None => domain,
+        }
     }
 
-    pub fn get_phase(&self) -> Arc<Phase> {
-        self.phase.clone()
+    // interval covered by any member, the superposition is normalized on it
+    fn extent(&self) -> (f64, f64) {
+        self.components
+            .iter()
+            .map(|c| self.member_domain(c))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .unwrap_or(self.approx_inf)
     }
-}
 
-impl Func<f64, Complex64> for WaveFunction {
-    fn eval(&self, x: f64) -> Complex64 {
-        self.scaling * self.calc_psi(x)
+    // interval covered by all members, outside of it they are continued like WaveFunction::calc_psi
+    pub fn domain(&self) -> (f64, f64) {
+        self.components
+            .iter()
+            .map(|c| self.member_domain(c))
+            .reduce(|a, b| (a.0.max(b.0), a.1.min(b.1)))
+            .unwrap_or(self.approx_inf)
     }
-}
-
-pub struct Superposition {
-    wave_funcs: Vec<WaveFunction>,
-    scaling: Complex64,
-}
 
-impl Superposition {
-    pub fn new<F: Fn(f64) -> f64 + Send + Sync>(
-        potential: &'static F,
-        mass: f64,
-        n_energies_scaling: &[(usize, Complex64)],
-        approx_inf: (f64, f64),
-        view_factor: f64,
-        scaling: ScalingType,
-    ) -> Superposition {
-        let wave_funcs = n_energies_scaling
-            .par_iter()
-            .map(|(e, scale)| {
-                let wave = WaveFunction::new(
-                    potential,
-                    mass,
-                    *e,
-                    approx_inf,
-                    view_factor,
-                    ScalingType::Mul(*scale),
-                );
-                println!("Calculated {} Energy\n", Ordinal(*e).to_string());
-                return wave;
+    // union of the views of the members inside of their domains
+    pub fn get_view(&self) -> (f64, f64) {
+        self.components
+            .iter()
+            .map(|c| {
+                let (view, domain) = (c.wave_func.get_view(), self.member_domain(c));
+                (view.0.max(domain.0), view.1.min(domain.1))
             })
-            .collect();
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+            .unwrap_or(self.approx_inf)
+    }
+}
 
-        match scaling {
-            ScalingType::Mul(s) => Superposition {
-                wave_funcs,
-                scaling: s,
-            },
-            ScalingType::None => Superposition {
-                wave_funcs,
-                scaling: 1.0.into(),
-            },
-            ScalingType::Renormalize(s) => {
-                let unscaled = Superposition {
-                    wave_funcs: wave_funcs.clone(),
-                    scaling: s,
-                };
-                let factor = renormalize_factor(&unscaled, approx_inf);
-                println!("factor: {}", factor);
-                Superposition {
-                    wave_funcs,
-                    scaling: s * factor,
-                }
-            }
+// phase at energy 0 for the states of one potential, with_energy gives the phase of each state
+fn shared_phase(potential: SharedPotential, mass: f64) -> Phase {
+    if CACHE_POTENTIAL {
+        let cache = CachedPotential::new(move |x| potential(x));
+        Phase {
+            energy: 0.0,
+            mass,
+            hbar: UNITS.hbar,
+            potential: Arc::new(move |x| cache.eval(x)),
         }
+    } else {
+        Phase::new(0.0, mass, UNITS.hbar, potential)
     }
+}
 
-    pub fn get_view(&self) -> (f64, f64) {
-        let view_a = self
-            .wave_funcs
-            .iter()
-            .map(|w| w.get_view().0)
-            .min_by(cmp_f64)
-            .unwrap();
-        let view_b = self
-            .wave_funcs
-            .iter()
-            .map(|w| w.get_view().1)
-            .max_by(cmp_f64)
+// the eigenstates n_range of a potential, sharing one energy scan and the potential between them
+pub fn eigenstates(
+    potential: SharedPotential,
+    mass: f64,
+    n_range: std::ops::Range<usize>,
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    scaling: ScalingType,
+) -> Vec<WaveFunction> {
+    let ns = n_range.collect::<Vec<usize>>();
+    eigenstates_of(potential, mass, &ns, approx_inf, view_factor, scaling)
+}
+
+// the eigenstates ns, in this order
+pub fn eigenstates_of(
+    potential: SharedPotential,
+    mass: f64,
+    ns: &[usize],
+    approx_inf: (f64, f64),
+    view_factor: f64,
+    scaling: ScalingType,
+) -> Vec<WaveFunction> {
+    let phase = shared_phase(potential, mass);
+    let count = ns.iter().max().map_or(0, |n| n + 1);
+    let energies =
+        energy::first_energies(count, mass, phase.hbar, &|x| (phase.potential)(x), approx_inf)
             .unwrap();
-        (view_a, view_b)
+    ns.par_iter()
+        .map(|n| {
+            WaveFunction::from_phase(
+                Arc::new(phase.with_energy(energies[*n])),
+                approx_inf,
+                view_factor,
+                scaling,
+            )
+        })
+        .collect()
+}
+
+pub fn coherent_coefficients(alpha: Complex64, n_max: usize) -> Vec<(usize, Complex64)> {
+    let mut coefficient: Complex64 = (-alpha.norm_sqr() / 2.0).exp().into();
+    let mut coefficients = vec![(0, coefficient)];
+    for n in 1..=n_max {
+        coefficient = coefficient * alpha / (n as f64).sqrt();
+        coefficients.push((n, coefficient));
     }
+    coefficients
 }
 
 impl Func<f64, Complex64> for Superposition {
     fn eval(&self, x: f64) -> Complex64 {
-        self.scaling * self.wave_funcs.iter().map(|w| w.eval(x)).sum::<Complex64>()
+        self.get_scaling() * Unscaled { superposition: self }.eval(x)
     }
 }
 
@@ -575,7 +2631,7 @@ where
     }
 }
 
-fn renormalize_factor(wave_func: &dyn Func<f64, Complex64>, approx_inf: (f64, f64)) -> f64 {
+pub fn renormalize_factor(wave_func: &dyn Func<f64, Complex64>, approx_inf: (f64, f64)) -> f64 {
     let area = integrate(
         evaluate_function_between(
             wave_func,
@@ -599,7 +2655,8 @@ fn renormalize_factor(wave_func: &dyn Func<f64, Complex64>, approx_inf: (f64, f6
         area
     };
 
-    1.0 / area
+    // psi is scaled, so the integral of |psi|^2 takes the square of the factor
+    1.0 / area.sqrt()
 }
 
 pub fn renormalize(
@@ -617,8 +2674,871 @@ pub fn renormalize(
 mod test {
     use super::*;
 
-    extern crate test;
-    use test::Bencher;
+    #[test]
+    fn renormalize_factor_of_gaussian() {
+        // the integral of exp(-2 x^2) is sqrt(pi / 2)
+        let gaussian = Function::new(|x: f64| complex((-x * x).exp(), 0.0));
+        let factor = renormalize_factor(&gaussian, (-10.0, 10.0));
+        let norm = factor * factor * (f64::consts::PI / 2.0).sqrt();
+        assert!((norm - 1.0).abs() < 1e-6, "{}", norm);
+    }
+
+    #[test]
+    fn states_in_energy_window() {
+        let states = WaveFunction::all_in_energy_window(
+            Arc::new(potentials::square),
+            1.0,
+            (2.0, 4.0),
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        assert_eq!(states.len(), 2);
+        for (n, state) in &states {
+            let energy = energy::nth_energy(*n, 1.0, 1.0, &potentials::square, APPROX_INF);
+            assert!(float_compare(state.get_energy(), energy, 1e-3));
+        }
+        assert_eq!((states[0].0, states[1].0), (1, 2));
+    }
+
+    #[test]
+    fn phase_conventions() {
+        let builder = |scaling, convention| {
+            WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0)
+                .with_scaling(scaling)
+                .with_phase_convention(convention)
+                .solve_energy(2)
+                .unwrap()
+                .find_turning_points()
+                .unwrap()
+                .build_parts()
+                .unwrap()
+                .scale()
+        };
+        let rotated = ScalingType::Mul(complex(0.0, 0.7).exp() * -2.0);
+        let mut aligned = builder(rotated, PhaseConvention::None);
+        let (t, _) = turning_points::classical_turning_points(
+            &potentials::square,
+            aligned.get_energy(),
+            APPROX_INF,
+        )
+        .unwrap();
+
+        let left = builder(rotated, PhaseConvention::LeftTurningPoint);
+        let psi = left.eval(t);
+        assert!(psi.re > 0.0 && psi.im.abs() < 1e-12 * psi.re);
+
+        // the first lobe is positive, so psi falls through the first node
+        let first_node = builder(rotated, PhaseConvention::FirstNode);
+        for x in [t, 0.0, 0.5] {
+            assert!(complex_compare(first_node.eval(x), -left.eval(x), 1e-12));
+        }
+
+        aligned.align_phase_to(&left);
+        for x in [t, 0.0, 0.5] {
+            assert!(complex_compare(aligned.eval(x), left.eval(x), 1e-9));
+        }
+    }
+
+    #[test]
+    fn effective_support() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            0,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let airy = wave_func.get_airy_ranges();
+        let edges = (airy[0].0, airy.last().unwrap().1);
+        let mut previous = edges;
+        for epsilon in [1e-2, 1e-4, 1e-8] {
+            let support = wave_func.effective_support(epsilon);
+            assert!(support.0 < previous.0 && previous.1 < support.1);
+            // kappa grows outwards, leaving out the prefactor overestimates psi
+            assert!(wave_func.eval(support.0).norm() < epsilon * wave_func.eval(edges.0).norm());
+            assert!(wave_func.eval(support.1).norm() < epsilon * wave_func.eval(edges.1).norm());
+            previous = support;
+        }
+        assert_eq!(wave_func.effective_support(0.0), wave_func.domain());
+    }
+
+    #[test]
+    fn regions() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            1,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let airy = wave_func.get_airy_ranges()[0];
+        let middle = (airy.0 + airy.1) / 2.0;
+        let expected = if ENABLE_AIRY_JOINTS && !MATCH_AIRY_DERIVATIVES {
+            Region::Joint
+        } else {
+            Region::Airy
+        };
+        assert_eq!(wave_func.region(airy.0), expected);
+        assert_eq!(wave_func.region(middle), Region::Airy);
+        assert_eq!(wave_func.region(0.0), Region::Wkb);
+        assert_eq!(wave_func.region(APPROX_INF.0), Region::Wkb);
+    }
+
+    #[test]
+    fn member_terms_add_up() {
+        let superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (2, complex(0.5, -1.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Mul(complex(2.0, 0.0)),
+        );
+        assert!(superposition.member_term(2).is_none());
+        for x in [-1.5, 0.0, 0.3, 2.0] {
+            let sum: Complex64 = (0..2)
+                .map(|i| superposition.member_term(i).unwrap().eval(x))
+                .sum();
+            assert!((sum - superposition.eval(x)).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn analytic_superposition_norm() {
+        let mut superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (1, complex(0.0, 2.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let analytic = superposition.analytic_factor().unwrap();
+        let integrated = superposition.integrated_factor();
+        assert!(
+            (analytic - integrated).abs() < 1e-2 * integrated,
+            "{} {}",
+            analytic,
+            integrated
+        );
+
+        // the norms of the members are kept, only the coefficients enter again
+        superposition.set_coefficient(1, complex(0.0, 0.0));
+        let norm = *superposition.components[0].norm.get().unwrap();
+        assert!(float_compare(
+            superposition.analytic_factor().unwrap(),
+            1.0 / norm.sqrt(),
+            1e-12
+        ));
+
+        let other = WaveFunction::new(
+            Arc::new(potentials::square),
+            2.0,
+            0,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        superposition.push_wave_function(other, complex(1.0, 0.0));
+        assert!(superposition.analytic_factor().is_none());
+    }
+
+    #[test]
+    fn boundaries_between_parts() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let boundary = wave_func.get_wkb_ranges()[0].1;
+        let (left, right) = (&wave_func.parts[0], &wave_func.parts[1]);
+        let average = (left.eval(boundary) + right.eval(boundary)) / 2.0;
+        assert_eq!(
+            eval_parts(&wave_func.parts, boundary, PartBoundary::HalfOpen),
+            Some(right.eval(boundary))
+        );
+        assert_eq!(
+            eval_parts(&wave_func.parts, boundary, PartBoundary::Average),
+            Some(average)
+        );
+
+        // the end of the domain belongs to the last part with either policy
+        let end = wave_func.domain().1;
+        let last = wave_func.parts.last().unwrap();
+        for policy in [PartBoundary::HalfOpen, PartBoundary::Average] {
+            assert_eq!(
+                eval_parts(&wave_func.parts, end, policy),
+                Some(last.eval(end))
+            );
+        }
+        assert_eq!(
+            eval_parts(&wave_func.parts, end + 1.0, PartBoundary::HalfOpen),
+            None
+        );
+    }
+
+    #[test]
+    fn part_range_validation() {
+        let approx_inf = (-10.0, 10.0);
+        let parts = [(-10.0, -1.0), (-1.0, 2.0), (2.0, 10.0)];
+        let airy = [(-3.0, -2.0), (0.0, 1.0), (3.0, 4.0)];
+        assert!(check_part_ranges(&parts, &airy, approx_inf).is_ok());
+
+        let gap = [(-10.0, -1.0), (-0.5, 10.0)];
+        assert!(check_part_ranges(&gap, &[], approx_inf)
+            .unwrap_err()
+            .contains("gap"));
+        let overlap = [(-10.0, 1.0), (-0.5, 10.0)];
+        assert!(check_part_ranges(&overlap, &[], approx_inf)
+            .unwrap_err()
+            .contains("overlap"));
+        let short = [(-10.0, 9.0)];
+        assert!(check_part_ranges(&short, &[], approx_inf).is_err());
+        let outside = [(-1.5, -0.5), (0.0, 1.0), (3.0, 4.0)];
+        assert!(check_part_ranges(&parts, &outside, approx_inf)
+            .unwrap_err()
+            .contains("airy range"));
+
+        let stage = WaveFunctionBuilder::new(Arc::new(potentials::mexican_hat), 1.0)
+            .solve_energy(2)
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap();
+        assert!(stage.validate().is_ok());
+    }
+
+    #[test]
+    fn sampled_positions_follow_probability() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            0,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let positions = wave_func.sample_positions(&mut StdRng::seed_from_u64(0), 20000);
+        let view = wave_func.get_view();
+        assert!(positions.iter().all(|x| *x >= view.0 && *x <= view.1));
+
+        // ground state of V = x^2 with m = 1: <x> = 0, <x^2> = 1 / (2 sqrt(2))
+        let mean = positions.iter().sum::<f64>() / positions.len() as f64;
+        let variance = positions.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / positions.len() as f64;
+        assert!(mean.abs() < 0.02);
+        assert!((variance - 0.125f64.sqrt()).abs() < 0.1 * 0.125f64.sqrt());
+    }
+
+    #[test]
+    fn compressed_matches_wave_function() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            5,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let proxy = wave_func.compress(1e-6);
+        let view = wave_func.get_view();
+        let max = evaluate_function_between(&wave_func, view.0, view.1, 1000)
+            .iter()
+            .map(|p| p.y.norm())
+            .fold(0.0, f64::max);
+
+        // away from the joints, psi isn't continuous across them
+        let joints = wave_func.get_joints();
+        for p in evaluate_function_between(&wave_func, view.0, view.1, 997) {
+            if joints.iter().all(|j| (p.x - j).abs() > 1e-6) {
+                assert!((proxy.eval(p.x) - p.y).norm() < 1e-5 * max);
+            }
+        }
+    }
+
+    #[test]
+    fn staged_construction() {
+        let builder = WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0);
+        let energy = builder.solve_energy(3).unwrap();
+        // E_n = sqrt(2) (n + 1/2) for V = x^2 and m = 1
+        assert!(float_compare(energy.get_energy(), 3.5 * 2.0f64.sqrt(), 1e-2));
+
+        let turning_points = energy.find_turning_points().unwrap();
+        let view = turning_points.get_view();
+        assert_eq!(turning_points.get_turning_points().len(), 2);
+        for (_, t) in turning_points.get_turning_points() {
+            assert!(view.0 < *t && *t < view.1);
+            assert!(float_compare(t * t, turning_points.get_energy(), 1e-6));
+        }
+
+        let wave_func = turning_points.build_parts().unwrap().scale();
+        let injected = builder
+            .with_energy(wave_func.get_energy())
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale();
+        for x in [-3.0, -1.2, 0.0, 0.7, 2.5] {
+            assert_eq!(wave_func.eval(x), injected.eval(x));
+        }
+    }
+
+    #[test]
+    fn oscillator_with_other_units() {
+        // E_n = hbar omega (n + 1/2) with omega = sqrt(2) for V = x^2 and m = 1
+        let units = Units {
+            hbar: 0.5,
+            ..Units::ATOMIC
+        };
+        let builder = WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0).with_units(units);
+        for n in 0..4 {
+            let energy = builder.solve_energy(n).unwrap().get_energy();
+            let exact = 0.5 * 2.0f64.sqrt() * (n as f64 + 0.5);
+            assert!((energy - exact).abs() < 1e-4 * exact, "{} != {}", energy, exact);
+        }
+
+        // <x^2> = hbar (n + 1/2) / (m omega) halves with hbar, the WKB states of n = 1 are about 8%
+        // high for both
+        let square = |builder: WaveFunctionBuilder| {
+            let wave_func = builder
+                .with_scaling(ScalingType::Renormalize(1.0.into()))
+                .solve_energy(1)
+                .unwrap()
+                .find_turning_points()
+                .unwrap()
+                .build_parts()
+                .unwrap()
+                .scale();
+            observables::expectation_of(&wave_func, &|x: f64| x * x).value
+        };
+        let atomic = WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0);
+        let ratio = square(builder) / square(atomic);
+        assert!((ratio - 0.5).abs() < 0.02, "{}", ratio);
+    }
+
+    #[test]
+    fn evaluation_outside_of_domain() {
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        assert_eq!(wave_func.domain(), APPROX_INF);
+
+        // the upper end isn't part of the last range any more
+        for x in [APPROX_INF.0, APPROX_INF.1, APPROX_INF.1 + 1.0, APPROX_INF.0 - 1.0] {
+            let psi = wave_func.eval(x);
+            assert!(psi.is_finite() && psi.norm() < 1e-10, "psi({}) = {}", x, psi);
+        }
+    }
+
+    #[test]
+    fn energy_below_potential_minimum() {
+        fn shifted(x: f64) -> f64 {
+            x * x + 2.0
+        }
+        let builder = WaveFunctionBuilder::new(Arc::new(shifted), 1.0);
+        let err = builder.with_energy(1.5).err().unwrap();
+        assert!(err.contains("minimum"), "{}", err);
+
+        assert!(builder.with_energy(2.0).is_err());
+        assert!(builder.with_energy(2.5).is_ok());
+    }
+
+    #[test]
+    fn poissonian_coherent_weights() {
+        let alpha = complex(1.5, 0.5);
+        let coefficients = coherent_coefficients(alpha, 30);
+        assert_eq!(coefficients.len(), 31);
+        assert!((coefficients.iter().map(|(_, c)| c.norm_sqr()).sum::<f64>() - 1.0).abs() < 1e-12);
+
+        let mean_n = alpha.norm_sqr();
+        let poisson = |n: i32| (-mean_n).exp() * mean_n.powi(n) / (1..=n).product::<i32>() as f64;
+        for (n, c) in coefficients.iter().take(6) {
+            assert!((c.norm_sqr() - poisson(*n as i32)).abs() < 1e-12);
+        }
+        assert!(complex_compare(coefficients[1].1 * alpha / 2.0f64.sqrt(), coefficients[2].1, 1e-12));
+    }
+
+    #[test]
+    fn superposition_editing() {
+        let mut superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (1, complex(1.0, 0.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Mul(complex(2.0, 0.0)),
+        );
+        let psi_1 = superposition.eval(0.5);
+
+        superposition.push_state(1, complex(0.0, 1.0)).unwrap();
+        assert_eq!(superposition.get_coefficients().len(), 2);
+        assert!(superposition.set_phase(1, 0.0));
+        assert_eq!(superposition.get_coefficients()[1], (1, complex(1.0, 0.0)));
+        assert!((superposition.eval(0.5) - psi_1).norm() < 1e-12);
+
+        superposition.push_state(2, complex(0.5, 0.0)).unwrap();
+        assert_eq!(superposition.get_coefficients().len(), 3);
+        assert!(superposition.remove_state(2));
+        assert!(!superposition.remove_state(2));
+        assert!(!superposition.set_coefficient(2, complex(1.0, 0.0)));
+        assert!((superposition.eval(0.5) - psi_1).norm() < 1e-12);
+        assert_eq!(superposition.get_scaling(), complex(2.0, 0.0));
+    }
+
+    #[test]
+    fn amplitudes_of_tilted_double_well() {
+        // the right well is 4 higher, the levels alternate between the wells
+        fn tilted(x: f64) -> f64 {
+            potentials::mexican_hat(x) + 0.5 * x + 10.0
+        }
+        let ratios: Vec<f64> = (0..2)
+            .map(|n| {
+                let wave_func = WaveFunction::new(
+                    Arc::new(tilted),
+                    1.0,
+                    n,
+                    APPROX_INF,
+                    VIEW_FACTOR,
+                    ScalingType::None,
+                );
+                assert_eq!(wave_func.get_amplitude_ratios().len(), 1);
+                wave_func.get_amplitude_ratios()[0]
+            })
+            .collect();
+        assert!(ratios[0] < 0.75, "{:?}", ratios);
+        assert!(ratios[1] > 1.5, "{:?}", ratios);
+
+        let symmetric = WaveFunction::new(
+            Arc::new(potentials::mexican_hat),
+            1.0,
+            1,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        assert!((symmetric.get_amplitude_ratios()[0] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parabolic_cylinder_basis() {
+        for epsilon in [0.0, -0.5, 0.8] {
+            let cylinder = ParabolicCylinder {
+                epsilon,
+                x0: 1.0,
+                length: 0.5,
+                mapping: None,
+                c_even: complex(1.0, 0.0),
+                c_odd: complex(0.0, 0.0),
+            };
+            assert_eq!(cylinder.basis(1.0), (1.0, 0.0));
+            // psi'' = -(z^2 + 2 epsilon) psi by z and the Wronskian stays 1
+            let h = 1e-4;
+            for z in [-4.0, -1.5, 0.5, 2.0, 4.0] {
+                let at = |dz: f64| cylinder.basis(1.0 + (z + dz) * 0.5);
+                let (even, odd) = at(0.0);
+                let second = |f: fn((f64, f64)) -> f64| {
+                    (f(at(h)) - 2.0 * f(at(0.0)) + f(at(-h))) / (h * h)
+                };
+                let first = |f: fn((f64, f64)) -> f64| (f(at(h)) - f(at(-h))) / (2.0 * h);
+                let factor = z * z + 2.0 * epsilon;
+                assert!((second(|b| b.0) + factor * even).abs() < 1e-4 * (1.0 + factor.abs()));
+                assert!((second(|b| b.1) + factor * odd).abs() < 1e-4 * (1.0 + factor.abs()));
+                let wronskian = even * first(|b| b.1) - odd * first(|b| b.0);
+                assert!((wronskian - 1.0).abs() < 1e-6, "{}", wronskian);
+            }
+        }
+    }
+
+    #[test]
+    fn barrier_top() {
+        // V = (x^2 - 16)^2 has its barrier top V = 256 at x = 0 with omega = 8 for m = 1
+        let wave_func = WaveFunctionBuilder::new(Arc::new(potentials::mexican_hat), 1.0)
+            .with_energy(255.75)
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale();
+        let (lower, upper) = wave_func.parts[0].range();
+        let reach = (BARRIER_TOP_CORE + BARRIER_TOP_TRANSITION) / 8.0f64.sqrt();
+        assert!((lower + reach).abs() < 1e-6 && (upper - reach).abs() < 1e-6);
+
+        // the airy functions of the close turning points don't solve the equation at the top
+        let error = check::SchroedingerError {
+            wave_func: &wave_func,
+        };
+        let max = Grid::uniform((-5.0, 5.0), 200)
+            .evaluate(&wave_func)
+            .iter()
+            .map(|p| p.y.norm())
+            .fold(0.0, f64::max);
+        for x in [-0.2, 0.0, 0.3] {
+            assert!(error.eval(x).norm() < 0.1 * 8.0 * max);
+        }
+
+        // continuous where the blending ends
+        for x in [lower, upper] {
+            let inside = wave_func.calc_psi(x + if x < 0.0 { 1e-9 } else { -1e-9 });
+            let outside = wave_func.calc_psi(x + if x < 0.0 { -1e-9 } else { 1e-9 });
+            assert!((inside - outside).norm() < 1e-6 * outside.norm().max(1.0));
+        }
+    }
+
+    #[test]
+    fn uniform_approximation() {
+        let build = |approximation| {
+            WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0)
+                .with_scaling(ScalingType::Renormalize(1.0.into()))
+                .with_approximation(approximation)
+                .solve_energy(3)
+                .unwrap()
+                .find_turning_points()
+                .unwrap()
+                .build_parts()
+                .unwrap()
+                .scale()
+        };
+        let patched = build(ApproximationKind::Patched);
+        let uniform = build(ApproximationKind::Uniform);
+        assert_eq!(uniform.parts.len(), patched.parts.len());
+
+        // close to the patched construction in the middle of the well and at the turning points
+        let t = uniform.airy_ranges[0];
+        let max = Grid::uniform(uniform.get_view(), 400)
+            .evaluate(&patched)
+            .iter()
+            .map(|p| p.y.norm())
+            .fold(0.0, f64::max);
+        for x in [(t.0 + t.1) / 2.0, 0.0, -(t.0 + t.1) / 2.0] {
+            let deviation = (uniform.calc_psi(x) - patched.calc_psi(x)).norm();
+            assert!(deviation < 5e-2 * max, "{}: {}", x, deviation);
+        }
+
+        // smooth through the turning point, where the patched parts would meet
+        let h = (t.1 - t.0) / 100.0;
+        for x in Grid::uniform(t, 20).points() {
+            let psi = |x| uniform.calc_psi(x);
+            let second = psi(x + h) - 2.0 * psi(x) + psi(x - h);
+            assert!(second.norm() < 1e-2 * max, "{}", x);
+        }
+    }
+
+    #[test]
+    fn smoothed_kink() {
+        let potential: SharedPotential = Arc::new(|x: f64| x.abs());
+        let builder = WaveFunctionBuilder::new(potential.clone(), 1.0)
+            .with_approx_inf((-20.0, 20.0))
+            .with_smoothing(0.05);
+        let changes = builder.get_smoothing_changes();
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].range.0 < 0.0 && changes[0].range.1 > 0.0);
+
+        // the kink is at the bottom, where it hardly moves the ground state
+        let unsmoothed = WaveFunctionBuilder::new(potential, 1.0)
+            .with_approx_inf((-20.0, 20.0))
+            .solve_energy(0)
+            .unwrap()
+            .get_energy();
+        assert!((builder.solve_energy(0).unwrap().get_energy() - unsmoothed).abs() < 1e-2);
+    }
+
+    #[test]
+    fn refused_discontinuities() {
+        let kink = WaveFunctionBuilder::new(Arc::new(|x: f64| x.abs()), 1.0)
+            .with_approx_inf((-20.0, 20.0))
+            .with_discontinuity_policy(DiscontinuityPolicy::Refuse);
+        assert_eq!(kink.get_discontinuities().len(), 1);
+        assert!(kink.with_energy(1.0).is_err());
+
+        // the walls of a box are handled as hard walls
+        let finite_box = |x: f64| if x.abs() < 1.0 { 0.0 } else { 1e6 };
+        let finite_box = WaveFunctionBuilder::new(Arc::new(finite_box), 1.0)
+            .with_approx_inf((-20.0, 20.0))
+            .with_discontinuity_policy(DiscontinuityPolicy::Refuse);
+        assert_eq!(finite_box.get_discontinuities().len(), 2);
+        assert!(finite_box.with_energy(2.0).is_ok());
+    }
+
+    #[test]
+    fn refused_energy_keeps_trace() {
+        // the lowest energy of |x| is found, the kink at 0 is refused afterwards
+        let err = WaveFunctionBuilder::new(Arc::new(|x: f64| x.abs()), 1.0)
+            .with_approx_inf((-20.0, 20.0))
+            .with_discontinuity_policy(DiscontinuityPolicy::Refuse)
+            .with_root_find_trace(true)
+            .solve_energy(0)
+            .err()
+            .unwrap();
+        assert!(err.message.contains("isn't a hard wall"));
+        assert!(!err.trace.get_iterates().is_empty());
+    }
+
+    #[test]
+    fn bessel_at_centrifugal_singularity() {
+        // the radial 2p state of hydrogen is r^2 e^(-r / 2), its turning points are at 4 -+ sqrt(7)
+        let hydrogen = hydrogen::HydrogenLike::new(1.0, 1.0, 2, 1);
+        let wave_func = WaveFunctionBuilder::new(hydrogen.effective_potential(), 1.0)
+            .with_approx_inf((1e-6, 200.0))
+            .with_approximation(ApproximationKind::Uniform)
+            .with_comparison_library(ComparisonLibrary::ALL)
+            .solve_energy(hydrogen.radial_quantum_number())
+            .unwrap()
+            .find_turning_points()
+            .unwrap()
+            .build_parts()
+            .unwrap()
+            .scale();
+        let exact = |r: f64| r * r * (-r / 2.0).exp();
+        let reference = 3.0;
+        for r in [1e-3, 0.5, 4.0 - 7.0f64.sqrt(), 2.5] {
+            let ratio = (wave_func.calc_psi(r) / wave_func.calc_psi(reference)).re;
+            let expected = exact(r) / exact(reference);
+            assert!((ratio - expected).abs() < 5e-2 * expected, "{}: {}", r, ratio);
+        }
+    }
+
+    #[test]
+    fn harmonic_bottoms() {
+        // V = x^2 with m = 1, omega = sqrt(2): psi_0 ~ e^(-x^2 / sqrt(2)), psi_1 ~ x e^(-x^2 / sqrt(2))
+        let build = |n: usize| {
+            WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0)
+                .solve_energy(n)
+                .unwrap()
+                .find_turning_points()
+                .unwrap()
+                .build_parts()
+                .unwrap()
+        };
+        let gaussian = |x: f64| (-x * x / 2.0f64.sqrt()).exp();
+
+        let ground_state = build(0);
+        let parts = ground_state.get_unscaled().parts.len();
+        let ground_state = ground_state.with_harmonic_bottoms().scale();
+        assert_eq!(ground_state.parts.len(), parts + 1);
+        let ratio = ground_state.calc_psi(0.3) / ground_state.calc_psi(0.0);
+        assert!((ratio.re - gaussian(0.3)).abs() < 1e-9, "{}", ratio);
+
+        // continuous where the blending ends
+        let (lower, upper) = ground_state.parts[0].range();
+        for x in [lower, upper] {
+            let inside = ground_state.calc_psi(x + if x < 0.0 { 1e-9 } else { -1e-9 });
+            let outside = ground_state.calc_psi(x + if x < 0.0 { -1e-9 } else { 1e-9 });
+            assert!((inside - outside).norm() < 1e-6 * outside.norm().max(1.0));
+        }
+
+        let first_excited = build(1).with_harmonic_bottoms().scale();
+        let ratio = first_excited.calc_psi(0.3) / first_excited.calc_psi(0.1);
+        assert!((ratio.re - 3.0 * gaussian(0.3) / gaussian(0.1)).abs() < 1e-9);
+        assert!(first_excited.calc_psi(0.0).norm() < 1e-12);
+
+        // no replacement above the lowest two levels
+        let second_excited = build(2);
+        let parts = second_excited.get_unscaled().parts.len();
+        assert_eq!(second_excited.with_harmonic_bottoms().scale().parts.len(), parts);
+    }
+
+    #[test]
+    fn given_boundaries() {
+        fn half_square(x: f64) -> f64 {
+            if x < 0.0 {
+                1e6
+            } else {
+                x * x
+            }
+        }
+        // the wall at 0 is detected, the lowest level is the first odd one of x^2
+        let detected = WaveFunctionBuilder::new(Arc::new(half_square), 1.0)
+            .solve_energy(0)
+            .unwrap();
+        assert!((detected.get_energy() - 1.5 * 2.0f64.sqrt()).abs() < 1e-3);
+
+        let soft = WaveFunctionBuilder::new(Arc::new(half_square), 1.0)
+            .with_boundaries(Boundary::Soft, Boundary::Soft)
+            .solve_energy(0)
+            .unwrap();
+        assert!((soft.get_energy() - 2.0f64.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn smooth_airy_edges() {
+        for n in [2, 3] {
+            let wave_function = WaveFunctionBuilder::new(Arc::new(potentials::square), 1.0)
+                .solve_energy(n)
+                .unwrap()
+                .find_turning_points()
+                .unwrap()
+                .build_parts()
+                .unwrap()
+                .scale();
+            let psi = |x: f64| wave_function.calc_psi(x).re;
+            let airy_ranges = wave_function.get_airy_ranges().to_vec();
+            let h = (airy_ranges[0].1 - airy_ranges[0].0) * 1e-4;
+            // the edges of the airy ranges and the middle of the well, where two parts meet
+            let edges = airy_ranges.iter().flat_map(|(a, b)| [*a, *b]).chain([0.0]);
+            for edge in edges {
+                // second order one sided derivatives at the edge
+                let side = |s: f64| {
+                    let at = |i: f64| psi(edge + s * (1e-3 + i) * h);
+                    s * (-3.0 * at(0.0) + 4.0 * at(1.0) - at(2.0)) / (2.0 * h)
+                };
+                let (left, right) = (side(-1.0), side(1.0));
+                assert!(
+                    (left - right).abs() < 1e-4 * (1.0 + left.abs()),
+                    "n = {}, x = {}: {} != {}",
+                    n,
+                    edge,
+                    left,
+                    right
+                );
+                assert!((psi(edge + 1e-3 * h) - psi(edge - 1e-3 * h)).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn phase_shift_fades_out() {
+        // psi_3 of V = x^2 with m = 1 is H_3(x / l) e^(-x^2 / (2 l^2)) with l = 2^(-1/4), away from
+        // the airy ranges the WKB function is accurate without the phase shift of the matching
+        let wave_function = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            3,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let l = 2.0f64.powf(-0.25);
+        let exact = |x: f64| {
+            let y = x / l;
+            (8.0 * y.powi(3) - 12.0 * y) * (-y * y / 2.0).exp()
+                / (48.0 * f64::consts::PI.sqrt() * l).sqrt()
+        };
+        for x in [-0.75, 0.5, 0.75] {
+            let psi = wave_function.eval(x).re;
+            assert!((psi.abs() - exact(x).abs()).abs() < 1e-2, "{}: {} != {}", x, psi, exact(x));
+        }
+    }
+
+    #[test]
+    fn superposition_of_different_potentials() {
+        let mut superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let psi_own = superposition.eval(0.5);
+
+        // the ground state of a heavier particle in the well shifted by 1
+        let shifted_inf = (APPROX_INF.0 + 1.0, APPROX_INF.1 + 1.0);
+        let shifted = WaveFunction::new(
+            Arc::new(|x: f64| potentials::square(x - 1.0)),
+            2.0,
+            0,
+            shifted_inf,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        let (psi_shifted, energy) = (shifted.calc_psi(0.5), shifted.get_energy());
+        assert_eq!(
+            superposition.push_wave_function(shifted, complex(0.0, 2.0)),
+            1
+        );
+        assert_eq!(superposition.get_coefficients().len(), 1);
+        assert_eq!(superposition.get_members()[1], (None, complex(0.0, 2.0)));
+        assert!(
+            (superposition.eval(0.5) - psi_own - complex(0.0, 2.0) * psi_shifted).norm() < 1e-12
+        );
+
+        // covered by both
+        let domain = superposition.domain();
+        assert!((domain.0 - shifted_inf.0).abs() < 1e-6 && domain.1 == APPROX_INF.1);
+
+        // the shifted member turns with its own energy
+        assert!(superposition.set_coefficient(0, complex(0.0, 0.0)));
+        assert!(superposition.set_member_coefficient(1, complex(1.0, 0.0)));
+        assert!(!superposition.set_member_coefficient(2, complex(1.0, 0.0)));
+        superposition.set_time(1.3);
+        let expected = psi_shifted * Complex64::from_polar(1.0, -energy * 1.3 / UNITS.hbar);
+        assert!((superposition.eval(0.5) - expected).norm() < 1e-12);
+    }
+
+    #[test]
+    fn superposition_outside_of_domain() {
+        let superposition = Superposition::new(
+            Arc::new(potentials::square),
+            1.0,
+            &[(0, complex(1.0, 0.0)), (3, complex(0.0, 1.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(complex(1.0, 0.0)),
+        );
+        assert_eq!(superposition.domain(), APPROX_INF);
+
+        for x in [APPROX_INF.0, APPROX_INF.1, APPROX_INF.1 + 1.0, APPROX_INF.0 - 1.0] {
+            let psi = superposition.eval(x);
+            assert!(psi.is_finite() && psi.norm() < 1e-10, "psi({}) = {}", x, psi);
+        }
+    }
+
+    #[test]
+    fn superposition_of_missing_level() {
+        // binds a single state below the continuum at 1
+        let well: SharedPotential = Arc::new(|x: f64| if x.abs() < 1.0 { 0.0 } else { 1.0 });
+        let result = Superposition::try_new(
+            well,
+            1.0,
+            &[(0, complex(1.0, 0.0)), (3, complex(1.0, 0.0))],
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::None,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn renormalized_to_unit_norm() {
+        // the factor multiplies psi, |psi|^2 takes its square
+        let wave_func = WaveFunction::new(
+            Arc::new(potentials::square),
+            1.0,
+            2,
+            APPROX_INF,
+            VIEW_FACTOR,
+            ScalingType::Renormalize(1.0.into()),
+        );
+        let range = wave_func.get_normalization().unwrap();
+        let norm = integrate(
+            evaluate_function_between(&wave_func, range.0, range.1, INTEG_STEPS)
+                .iter()
+                .map(|p| Point {
+                    x: p.x,
+                    y: p.y.norm_sqr(),
+                })
+                .collect(),
+            TRAPEZE_PER_THREAD,
+        );
+        assert!((norm - 1.0).abs() < 1e-6, "{}", norm);
+    }
 
     #[test]
     fn sign_check_complex_test() {
@@ -642,14 +3562,4 @@ mod test {
             }
         }
     }
-
-    #[bench]
-    fn renormalize_square(b: &mut Bencher) {
-        let square = Function::new(|x: f64| complex(x*x, 0.0));
-
-        b.iter(||{
-            let bounds = test::black_box((-10.0, 10.0));
-            let _ = test::black_box(renormalize_factor(&square, bounds));
-        });
-    }
 }
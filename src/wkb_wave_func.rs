@@ -6,15 +6,16 @@ use std::sync::Arc;
 pub struct Phase {
     pub energy: f64,
     pub mass: f64,
-    pub potential: Arc<dyn Fn(f64) -> f64 + Send + Sync>,
+    pub hbar: f64,
+    pub potential: SharedPotential,
 }
 
 impl Display for Phase {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Phase {{energy: {}, mass: {}, potential: [func]}}",
-            self.energy, self.mass
+            "Phase {{energy: {}, mass: {}, hbar: {}, potential: [func]}}",
+            self.energy, self.mass, self.hbar
         )
     }
 }
@@ -24,35 +25,167 @@ impl Phase {
         Phase {
             energy: 0.0,
             mass: 0.0,
+            hbar: UNITS.hbar,
             potential: Arc::new(|_x| 0.0),
         }
     }
 
-    pub fn new<F: Fn(f64) -> f64 + Sync + Send>(
-        energy: f64,
-        mass: f64,
-        potential: &'static F,
-    ) -> Phase {
-        return Phase {
+    pub fn new(energy: f64, mass: f64, hbar: f64, potential: SharedPotential) -> Phase {
+        Phase {
             energy,
             mass,
-            potential: Arc::new(potential),
-        };
+            hbar,
+            potential,
+        }
+    }
+
+    // same potential and mass, the potential is not copied
+    pub fn with_energy(&self, energy: f64) -> Phase {
+        Phase {
+            energy,
+            mass: self.mass,
+            hbar: self.hbar,
+            potential: self.potential.clone(),
+        }
+    }
+
+    fn momentum(&self, x: f64) -> f64 {
+        (2.0 * self.mass * ((self.potential)(x) - self.energy))
+            .abs()
+            .sqrt()
     }
 
     fn sqrt_momentum(&self, x: f64) -> f64 {
-        self.eval(x).abs().sqrt()
+        self.momentum(x).sqrt()
     }
 }
 
+// integrand of the phase p(x) / hbar
 impl Func<f64, f64> for Phase {
     fn eval(&self, x: f64) -> f64 {
-        (2.0 * self.mass * ((self.potential)(x) - self.energy))
-            .abs()
-            .sqrt()
+        self.momentum(x) / self.hbar
     }
 }
 
+pub struct SemiclassicalPoint {
+    pub x: f64,
+    pub momentum: f64,
+    pub wavelength: f64,
+    pub phase: f64,
+}
+
+// p(x), the local de Broglie wavelength 2 pi hbar / p and the phase, the integral of p / hbar from
+// view.0, on a uniform grid. In forbidden regions p is 0, the wavelength infinite and the
+// phase doesn't change.
+pub fn semiclassical_quantities(
+    phase: &Phase,
+    view: (f64, f64),
+    points: usize,
+) -> Vec<SemiclassicalPoint> {
+    let grid = Grid::uniform(view, points);
+    let momentum: Vec<f64> = grid
+        .points()
+        .iter()
+        .map(|x| {
+            if (phase.potential)(*x) < phase.energy {
+                phase.momentum(*x)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let mut accumulated = 0.0;
+    (0..grid.len())
+        .map(|i| {
+            if i > 0 {
+                accumulated += (grid.x(i) - grid.x(i - 1)) * (momentum[i] + momentum[i - 1])
+                    / (2.0 * phase.hbar);
+            }
+            SemiclassicalPoint {
+                x: grid.x(i),
+                momentum: momentum[i],
+                wavelength: 2.0 * f64::consts::PI * phase.hbar / momentum[i],
+                phase: accumulated,
+            }
+        })
+        .collect()
+}
+
+// Logs of the amplitudes of k^(-1/2) [P cos(phi - pi/4) + Q sin(phi - pi/4)] in every well, phi
+// counted from the left turning point of the well, starting with a solution decaying to the left.
+// Across a barrier with the action theta the connection matrix of two linear turning points gives
+//     P' = -2 e^theta S,  Q' = -1/2 e^(-theta) R
+// with R and S the cos and sin coefficients at the right turning point of the well, the common
+// factor e^theta is kept in the log to avoid overflows under thick barriers.
+fn propagate_amplitudes(wells: &[f64], barriers: &[f64]) -> Vec<f64> {
+    let (mut p, mut q) = (2.0, 0.0);
+    let mut log_scale = 0.0;
+    let mut log_amplitudes = vec![];
+    for (j, w) in wells.iter().enumerate() {
+        let amplitude = f64::hypot(p, q).max(f64::MIN_POSITIVE);
+        log_scale += amplitude.ln();
+        log_amplitudes.push(log_scale);
+        (p, q) = (p / amplitude, q / amplitude);
+
+        if let Some(theta) = barriers.get(j) {
+            let r = p * w.sin() - q * w.cos();
+            let s = -p * w.cos() - q * w.sin();
+            (p, q) = (-2.0 * s, -0.5 * r * (-2.0 * theta).exp());
+            log_scale += theta;
+        }
+    }
+    log_amplitudes
+}
+
+// Amplitudes of psi in the wells between the turning points relative to the first well. The
+// turning points have to alternate between falling and rising potential, starting with a falling
+// one, otherwise every well gets the amplitude 1. Propagating from the left only is unstable if
+// the energy misses an eigenvalue slightly, the ratios of neighbouring wells are the geometric mean
+// of the propagation from the left and from the right.
+pub fn well_amplitudes(phase: &Phase, turning_points: &[f64]) -> Vec<f64> {
+    let rising: Vec<bool> = turning_points
+        .iter()
+        .map(|t| derivative(phase.potential.as_ref(), *t) > 0.0)
+        .collect();
+    let alternating = turning_points.len().is_multiple_of(2)
+        && rising.iter().enumerate().all(|(i, r)| *r == (i % 2 == 1));
+    if !alternating {
+        return vec![1.0; turning_points.len().div_ceil(2)];
+    }
+
+    let action = |a: f64, b: f64| {
+        integrate(
+            evaluate_function_between(phase, a, b, INTEG_STEPS),
+            TRAPEZE_PER_THREAD,
+        )
+    };
+    let wells: Vec<f64> = turning_points
+        .chunks(2)
+        .map(|t| action(t[0], t[1]))
+        .collect();
+    let barriers: Vec<f64> = turning_points[1..]
+        .chunks(2)
+        .filter(|t| t.len() == 2)
+        .map(|t| action(t[0], t[1]))
+        .collect();
+
+    let from_left = propagate_amplitudes(&wells, &barriers);
+    let mut from_right = propagate_amplitudes(
+        &wells.iter().rev().cloned().collect::<Vec<f64>>(),
+        &barriers.iter().rev().cloned().collect::<Vec<f64>>(),
+    );
+    from_right.reverse();
+
+    let mut amplitudes = vec![1.0];
+    for j in 1..wells.len() {
+        let log_ratio =
+            0.5 * ((from_left[j] - from_left[j - 1]) + (from_right[j] - from_right[j - 1]));
+        amplitudes.push(amplitudes[j - 1] * log_ratio.exp());
+    }
+    amplitudes
+}
+
 #[derive(Clone)]
 pub struct WkbWaveFunction {
     pub c: Complex64,
@@ -81,6 +214,13 @@ impl WkbWaveFunction {
         }
     }
 
+    pub fn with_phase_off(&self, phase_off: f64) -> WkbWaveFunction {
+        WkbWaveFunction {
+            phase_off,
+            ..self.clone()
+        }
+    }
+
     pub fn new(
         phase: Arc<Phase>,
         c: Complex64,
@@ -128,32 +268,58 @@ impl WkbWaveFunction {
             .signum()
     }
 
-    fn psi_osc(&self, x: f64) -> Complex64 {
-        let integral = integrate(
+    fn action_to(&self, x: f64, turning_point: f64) -> f64 {
+        integrate(
             evaluate_function_between(
                 self.phase.as_ref(),
                 x,
-                self.turning_point_osc,
+                turning_point,
                 self.integration_steps,
             ),
             TRAPEZE_PER_THREAD,
-        );
+        )
+    }
+
+    fn psi_osc(&self, x: f64) -> Complex64 {
+        let integral = self.action_to(x, self.turning_point_osc);
         self.c * complex((integral + self.phase_off).cos(), 0.0) / self.phase.sqrt_momentum(x)
     }
 
     fn psi_exp(&self, x: f64) -> Complex64 {
-        let integral = integrate(
-            evaluate_function_between(
-                self.phase.as_ref(),
-                x,
-                self.turning_point_exp,
-                self.integration_steps,
-            ),
-            TRAPEZE_PER_THREAD,
-        );
+        let integral = self.action_to(x, self.turning_point_exp);
         let exp_sign = self.get_exp_sign();
 
-        exp_sign * (self.c * 0.5 * (-integral.abs()).exp())
+        exp_sign * (self.c * 0.5 * (-integral.abs()).exp()) / self.phase.sqrt_momentum(x)
+    }
+
+    // d/dx of c cos(I + phase_off) / sqrt(p) with dI/dx = -p / hbar
+    fn psi_osc_derivative(&self, x: f64) -> Complex64 {
+        let angle = self.action_to(x, self.turning_point_osc) + self.phase_off;
+        let momentum = self.phase.momentum(x);
+        let momentum_derivative =
+            -self.phase.mass * derivative(self.phase.potential.as_ref(), x) / momentum;
+        self.c
+            * (angle.sin() * momentum.sqrt() / self.phase.hbar
+                - 0.5 * angle.cos() * momentum_derivative / momentum.powf(1.5))
+    }
+
+    // d/dx of c / 2 e^(-|I|) / sqrt(p) with dp/dx = m V' / p
+    fn psi_exp_derivative(&self, x: f64) -> Complex64 {
+        let side = (self.turning_point_exp - x).signum();
+        let momentum = self.phase.momentum(x);
+        let momentum_derivative =
+            self.phase.mass * derivative(self.phase.potential.as_ref(), x) / momentum;
+        self.psi_exp(x) * (side * self.phase.eval(x) - 0.5 * momentum_derivative / momentum)
+    }
+
+    pub fn derivative(&self, x: f64) -> Complex64 {
+        let val = if self.phase.energy < (self.phase.potential)(x) {
+            self.psi_exp_derivative(x)
+        } else {
+            self.psi_osc_derivative(x)
+        };
+
+        (self.op)(val)
     }
 }
 
@@ -182,6 +348,75 @@ mod test {
         1.0 / x.sqrt()
     }
 
+    #[test]
+    fn amplitudes_of_two_wells() {
+        // at an eigenvalue of two wells, cot W_L cot W_R = e^(-2 theta) / 4, the amplitudes follow
+        // (A_R / A_L)^2 = sin(2 W_L) / sin(2 W_R)
+        let w_r: f64 = 1.7;
+        for theta in [0.5f64, 2.0, 5.0] {
+            let w_l = (4.0 * (2.0 * theta).exp() / w_r.tan()).atan();
+            let from_left = propagate_amplitudes(&[w_l, w_r], &[theta]);
+            let from_right = propagate_amplitudes(&[w_r, w_l], &[theta]);
+            let log_ratio = 0.5 * ((from_left[1] - from_left[0]) + (from_right[0] - from_right[1]));
+            let expected = ((2.0 * w_l).sin() / (2.0 * w_r).sin()).abs().sqrt();
+            assert!(
+                (log_ratio.exp() - expected).abs() < 1e-9 * expected,
+                "theta = {}",
+                theta
+            );
+        }
+    }
+
+    #[test]
+    fn semiclassical_phase_of_oscillator() {
+        // the action of a level of x^2 with m = 1 is pi E / sqrt(2) over the whole well
+        let phase = Phase::new(3.0, 1.0, UNITS.hbar, Arc::new(potentials::square));
+        let values = semiclassical_quantities(&phase, (-4.0, 4.0), 20001);
+        let expected = f64::consts::PI * 3.0 / 2.0f64.sqrt() / phase.hbar;
+        assert!((values.last().unwrap().phase - expected).abs() < 1e-4 * expected);
+
+        let center = &values[10000];
+        assert!(float_compare(center.momentum, 6.0f64.sqrt(), 1e-12));
+        assert!(float_compare(
+            center.momentum * center.wavelength,
+            2.0 * f64::consts::PI * phase.hbar,
+            1e-12
+        ));
+        assert_eq!(values[0].momentum, 0.0);
+        assert!(values[0].wavelength.is_infinite());
+    }
+
+    #[test]
+    fn decaying_tail_of_oscillator() {
+        // the ground state of x^2 with m = 1 decays as e^(-x^2 / sqrt(2)), the WKB tail
+        // e^(-|I|) / sqrt(p) approaches it up to a constant
+        let energy = 2.0f64.sqrt() / 2.0;
+        let phase = Arc::new(Phase::new(
+            energy,
+            1.0,
+            UNITS.hbar,
+            Arc::new(potentials::square),
+        ));
+        let t = energy.sqrt();
+        let wkb = WkbWaveFunction::new(phase, complex(1.0, 0.0), INTEG_STEPS, t, t, 0.0);
+        let ratio = |x: f64| wkb.eval(x).re / (-x * x / 2.0f64.sqrt()).exp();
+        assert!((ratio(3.0) / ratio(4.0) - 1.0).abs() < 2e-2);
+
+        let h = 1e-5;
+        let x = 3.0;
+        let difference = (wkb.eval(x + h) - wkb.eval(x - h)) / (2.0 * h);
+        assert!((wkb.derivative(x) - difference).norm() < 1e-6 * difference.norm());
+    }
+
+    #[test]
+    fn with_energy_shares_potential() {
+        let phase = Phase::new(1.0, 2.0, UNITS.hbar, Arc::new(pot));
+        let other = phase.with_energy(3.0);
+        assert_eq!(other.energy, 3.0);
+        assert_eq!(other.mass, 2.0);
+        assert!(Arc::ptr_eq(&phase.potential, &other.potential));
+    }
+
     #[test]
     fn phase_off() {
         let energy_cond = |e: f64| -> f64 { (0.5 * (e - 0.5)) % 1.0 };
@@ -197,7 +432,8 @@ mod test {
         let data_str: String = values
             .par_iter()
             .map(|p| -> String { format!("{} {}\n", p.x, p.y) })
-            .reduce(|| String::new(), |s: String, current: String| s + &*current);
+            .collect::<Vec<String>>()
+            .concat();
 
         data_file.write_all((data_str).as_ref()).unwrap()
     }